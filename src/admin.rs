@@ -0,0 +1,2729 @@
+//! Typed access to the `SHOW` statements used to administer a cluster.
+
+use crate::{
+    data_types::Region,
+    jobs::{JobHandle, JobStatus, JobType},
+    locality::NodeLocality,
+    quote,
+    version::{ClusterVersion, Unsupported, VersionCheck},
+};
+use diesel::{
+    expression::{AsExpression, Expression},
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::{QuerySource, Table},
+    result::QueryResult,
+    sql_types::{Array, BigInt, Binary, Bool, Double, Nullable, Text, Timestamp},
+    Queryable,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+/// Creates a `SHOW JOBS` statement, listing the jobs of the last 12 hours,
+/// and any unfinished jobs.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{
+///     admin::{show_jobs, JobDetails},
+///     jobs::{JobStatus, JobType},
+/// };
+///
+/// let failed_imports: Vec<JobDetails> = show_jobs()
+///     .job_type(JobType::Import)
+///     .status(JobStatus::Failed)
+///     .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_jobs() -> ShowJobsStatement {
+    ShowJobsStatement {
+        automatic: false,
+        job_types: Vec::new(),
+        statuses: Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW JOBS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-jobs.html
+pub struct ShowJobsStatement {
+    automatic: bool,
+    job_types: Vec<JobType>,
+    statuses: Vec<JobStatus>,
+}
+
+impl ShowJobsStatement {
+    /// Show jobs started automatically, such as collecting statistics,
+    /// instead of those started by users.
+    ///
+    /// ```sql
+    /// SHOW AUTOMATIC JOBS;
+    /// ```
+    pub fn automatic(mut self) -> Self {
+        self.automatic = true;
+        self
+    }
+
+    /// Only show jobs of the given type. May be called more than once, to
+    /// show jobs of any of the types.
+    pub fn job_type(mut self, job_type: JobType) -> Self {
+        self.job_types.push(job_type);
+        self
+    }
+
+    /// Only show jobs with the given status. May be called more than once,
+    /// to show jobs with any of the statuses.
+    pub fn status(mut self, status: JobStatus) -> Self {
+        self.statuses.push(status);
+        self
+    }
+}
+
+fn push_in_list(out: &mut AstPass<Pg>, column: &str, values: &[&str]) {
+    out.push_sql(column);
+    out.push_sql(" IN (");
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push_sql(", ");
+        }
+        out.push_sql(&quote::string_literal(value));
+    }
+    out.push_sql(")");
+}
+
+impl QueryFragment<Pg> for ShowJobsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT job_id, job_type, description, user_name, status, running_status, \
+             created, started, finished, fraction_completed, error FROM [SHOW ",
+        );
+        if self.automatic {
+            out.push_sql("AUTOMATIC ");
+        }
+        out.push_sql("JOBS]");
+        let job_types: Vec<_> = self.job_types.iter().map(JobType::as_str).collect();
+        let statuses: Vec<_> = self.statuses.iter().map(JobStatus::as_str).collect();
+        let mut keyword = " WHERE ";
+        for (column, values) in [("job_type", job_types), ("status", statuses)] {
+            if !values.is_empty() {
+                out.push_sql(keyword);
+                push_in_list(&mut out, column, &values);
+                keyword = " AND ";
+            }
+        }
+        out.push_sql(" ORDER BY created");
+        Ok(())
+    }
+}
+
+impl Query for ShowJobsStatement {
+    type SqlType = JobDetailsSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for ShowJobsStatement {}
+
+impl QueryId for ShowJobsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A job, as returned by [`show_jobs`].
+///
+/// [`show_jobs`]: fn.show_jobs.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobDetails {
+    /// The ID of the job.
+    pub job_id: i64,
+    /// The type of the job.
+    pub job_type: JobType,
+    /// A description of the job, usually the statement that started it.
+    pub description: String,
+    /// The user who started the job.
+    pub user_name: String,
+    /// The current status of the job.
+    pub status: JobStatus,
+    /// A description of what a running job is currently doing.
+    pub running_status: Option<String>,
+    /// When the job was created.
+    pub created: SystemTime,
+    /// When the job started running.
+    pub started: Option<SystemTime>,
+    /// When the job finished.
+    pub finished: Option<SystemTime>,
+    /// The fraction of the job completed, between `0.0` and `1.0`, if known.
+    pub fraction_completed: Option<f64>,
+    /// The error the job failed with, if any.
+    pub error: Option<String>,
+}
+
+impl JobDetails {
+    /// A handle to the job, to pause, resume or cancel it.
+    pub const fn job(&self) -> JobHandle {
+        JobHandle::new(self.job_id)
+    }
+}
+
+impl Queryable<JobDetailsSqlType, Pg> for JobDetails {
+    type Row = (
+        i64,
+        String,
+        String,
+        String,
+        String,
+        Option<String>,
+        SystemTime,
+        Option<SystemTime>,
+        Option<SystemTime>,
+        Option<f64>,
+        Option<String>,
+    );
+
+    fn build(
+        (
+            job_id,
+            job_type,
+            description,
+            user_name,
+            status,
+            running_status,
+            created,
+            started,
+            finished,
+            fraction_completed,
+            error,
+        ): Self::Row,
+    ) -> Self {
+        JobDetails {
+            job_id,
+            job_type: job_type.parse().unwrap_or_else(|never| match never {}),
+            description,
+            user_name,
+            status: status.parse().unwrap_or_else(|never| match never {}),
+            running_status,
+            created,
+            started,
+            finished,
+            fraction_completed,
+            error: error.filter(|error| !error.is_empty()),
+        }
+    }
+}
+
+/// The SQL type of a [`JobDetails`].
+///
+/// [`JobDetails`]: struct.JobDetails.html
+pub type JobDetailsSqlType = (
+    BigInt,
+    Text,
+    Text,
+    Text,
+    Text,
+    Nullable<Text>,
+    Timestamp,
+    Nullable<Timestamp>,
+    Nullable<Timestamp>,
+    Nullable<Double>,
+    Nullable<Text>,
+);
+
+/// Creates a `SHOW RANGES` statement, listing the ranges of the target
+/// table.
+///
+/// From CockroachDB v23.1, the size and leaseholder of each range are only
+/// listed `WITH DETAILS`, so the [`ClusterVersion`] of the cluster must be
+/// given.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{
+///     admin::{ranges_per_node, show_ranges_from_table, Range},
+///     version::ClusterVersion,
+/// };
+///
+/// let version = ClusterVersion::detect(&connection)?;
+/// let ranges: Vec<Range> = show_ranges_from_table(&version, books::table).load(&connection)?;
+/// for (node_id, node) in ranges_per_node(&ranges) {
+///     println!("node {} holds {} leases", node_id, node.leases);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ClusterVersion`]: ../version/struct.ClusterVersion.html
+pub fn show_ranges_from_table<T>(version: &ClusterVersion, target: T) -> ShowRangesStatement<T> {
+    ShowRangesStatement {
+        target,
+        index: None,
+        details: version.show_ranges_needs_details(),
+    }
+}
+
+/// Creates a `SHOW RANGES` statement, listing the ranges of the index called
+/// `name` on the target table.
+///
+/// As for [`show_ranges_from_table`], the [`ClusterVersion`] of the cluster
+/// must be given.
+///
+/// ```sql
+/// SHOW RANGES FROM INDEX books@books_title_idx;
+/// ```
+///
+/// [`show_ranges_from_table`]: fn.show_ranges_from_table.html
+/// [`ClusterVersion`]: ../version/struct.ClusterVersion.html
+pub fn show_ranges_from_index<T>(
+    version: &ClusterVersion,
+    target: T,
+    name: impl Into<String>,
+) -> ShowRangesStatement<T> {
+    ShowRangesStatement {
+        target,
+        index: Some(name.into()),
+        details: version.show_ranges_needs_details(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW RANGES` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table whose ranges are shown
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-ranges.html
+pub struct ShowRangesStatement<T> {
+    target: T,
+    index: Option<String>,
+    details: bool,
+}
+
+impl<T> QueryFragment<Pg> for ShowRangesStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT start_key, end_key, range_id, range_size_mb::FLOAT8, lease_holder, \
+             lease_holder_locality, replicas, replica_localities FROM [SHOW RANGES FROM ",
+        );
+        match &self.index {
+            Some(index) => {
+                out.push_sql("INDEX ");
+                self.target.from_clause().walk_ast(out.reborrow())?;
+                out.push_sql("@");
+                out.push_identifier(index)?;
+            }
+            None => {
+                out.push_sql("TABLE ");
+                self.target.from_clause().walk_ast(out.reborrow())?;
+            }
+        }
+        if self.details {
+            out.push_sql(" WITH DETAILS");
+        }
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl<T> Query for ShowRangesStatement<T> {
+    type SqlType = (
+        Nullable<Text>,
+        Nullable<Text>,
+        BigInt,
+        Double,
+        BigInt,
+        Nullable<Text>,
+        Array<BigInt>,
+        Array<Text>,
+    );
+}
+
+impl<T> RunQueryDsl<PgConnection> for ShowRangesStatement<T> {}
+
+impl<T> QueryId for ShowRangesStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A range, as returned by [`show_ranges_from_table`] or
+/// [`show_ranges_from_index`].
+///
+/// [`show_ranges_from_table`]: fn.show_ranges_from_table.html
+/// [`show_ranges_from_index`]: fn.show_ranges_from_index.html
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct Range {
+    /// The first key of the range, or `None` for the start of the table.
+    pub start_key: Option<String>,
+    /// The key after the end of the range, or `None` for the end of the
+    /// table.
+    pub end_key: Option<String>,
+    /// The ID of the range.
+    pub range_id: i64,
+    /// The size of the range, in MiB.
+    pub size_mb: f64,
+    /// The node holding the lease of the range.
+    pub lease_holder: i64,
+    /// The locality of the leaseholder, such as `region=us-east1,az=b`.
+    pub lease_holder_locality: Option<String>,
+    /// The nodes holding replicas of the range.
+    pub replicas: Vec<i64>,
+    /// The localities of the replicas, in the same order as `replicas`.
+    pub replica_localities: Vec<String>,
+}
+
+/// The ranges held by a node, as counted by [`ranges_per_node`].
+///
+/// [`ranges_per_node`]: fn.ranges_per_node.html
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeRanges {
+    /// The number of ranges the node holds a replica of.
+    pub replicas: usize,
+    /// The number of ranges the node holds the lease of.
+    pub leases: usize,
+    /// The total size of the replicas held by the node, in MiB.
+    pub size_mb: f64,
+}
+
+/// Counts the replicas and leases held by each node, by node ID.
+pub fn ranges_per_node(ranges: &[Range]) -> BTreeMap<i64, NodeRanges> {
+    let mut nodes = BTreeMap::<i64, NodeRanges>::new();
+    for range in ranges {
+        for node_id in &range.replicas {
+            let node = nodes.entry(*node_id).or_default();
+            node.replicas += 1;
+            node.size_mb += range.size_mb;
+        }
+        nodes.entry(range.lease_holder).or_default().leases += 1;
+    }
+    nodes
+}
+
+/// Creates a `SHOW RANGE FOR ROW` statement, finding the range holding the
+/// row of the target table with the given primary key.
+///
+/// Values are sent as binds. Composite primary keys take a tuple of
+/// expressions, such as `(1.into_sql::<BigInt>(), "a".into_sql::<Text>())`.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{show_range_for_row, RangeForRow};
+///
+/// let range: RangeForRow = show_range_for_row(books::table, 42).get_result(&connection)?;
+/// println!("row is served by node {}", range.lease_holder);
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_range_for_row<T, V>(
+    target: T,
+    primary_key: V,
+) -> ShowRangeForRowStatement<T, V::Expression>
+where
+    T: Table,
+    V: AsExpression<<T::PrimaryKey as Expression>::SqlType>,
+{
+    ShowRangeForRowStatement {
+        target,
+        primary_key: primary_key.as_expression(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW RANGE FOR ROW` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table the row belongs to
+/// - `E`: The values of the primary key of the row
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-range-for-row.html
+pub struct ShowRangeForRowStatement<T, E> {
+    target: T,
+    primary_key: E,
+}
+
+impl<T, E> QueryFragment<Pg> for ShowRangeForRowStatement<T, E>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+    E: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT start_key, end_key, range_id, lease_holder, lease_holder_locality, \
+             replicas, replica_localities FROM [SHOW RANGE FROM TABLE ",
+        );
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(" FOR ROW (");
+        self.primary_key.walk_ast(out.reborrow())?;
+        out.push_sql(")]");
+        Ok(())
+    }
+}
+
+impl<T, E> Query for ShowRangeForRowStatement<T, E> {
+    type SqlType = (
+        Nullable<Text>,
+        Nullable<Text>,
+        BigInt,
+        BigInt,
+        Nullable<Text>,
+        Array<BigInt>,
+        Array<Text>,
+    );
+}
+
+impl<T, E> RunQueryDsl<PgConnection> for ShowRangeForRowStatement<T, E> {}
+
+impl<T, E> QueryId for ShowRangeForRowStatement<T, E> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The range holding a row, as returned by [`show_range_for_row`].
+///
+/// [`show_range_for_row`]: fn.show_range_for_row.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct RangeForRow {
+    /// The first key of the range, or `None` for the start of the table.
+    pub start_key: Option<String>,
+    /// The key after the end of the range, or `None` for the end of the
+    /// table.
+    pub end_key: Option<String>,
+    /// The ID of the range.
+    pub range_id: i64,
+    /// The node holding the lease of the range, which serves the row.
+    pub lease_holder: i64,
+    /// The locality of the leaseholder.
+    pub lease_holder_locality: Option<String>,
+    /// The nodes holding replicas of the range.
+    pub replicas: Vec<i64>,
+    /// The localities of the replicas, in the same order as `replicas`.
+    pub replica_localities: Vec<String>,
+}
+
+/// Creates a `SHOW HOT RANGES` statement, listing the ranges receiving the
+/// most queries per second, hottest first.
+///
+/// Requires CockroachDB v22.2 or later.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{show_hot_ranges, HotRange};
+///
+/// let hottest: Vec<HotRange> = show_hot_ranges().limit(10).load(&connection)?;
+/// for range in hottest {
+///     println!("{}@{}: {} qps", range.table_name, range.index_name, range.qps);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_hot_ranges() -> ShowHotRangesStatement {
+    ShowHotRangesStatement { limit: None }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW HOT RANGES` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-hot-ranges.html
+pub struct ShowHotRangesStatement {
+    limit: Option<u64>,
+}
+
+impl ShowHotRangesStatement {
+    /// Only list the `n` hottest ranges.
+    pub fn limit(mut self, n: u64) -> Self {
+        self.limit = Some(n);
+        self
+    }
+}
+
+impl VersionCheck for ShowHotRangesStatement {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        version.require("SHOW HOT RANGES", 22, 2)
+    }
+}
+
+impl QueryFragment<Pg> for ShowHotRangesStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT range_id, qps, database_name, table_name, index_name \
+             FROM [SHOW HOT RANGES] ORDER BY qps DESC",
+        );
+        if let Some(limit) = self.limit {
+            out.push_sql(&format!(" LIMIT {}", limit));
+        }
+        Ok(())
+    }
+}
+
+impl Query for ShowHotRangesStatement {
+    type SqlType = (BigInt, Double, Text, Text, Text);
+}
+
+impl RunQueryDsl<PgConnection> for ShowHotRangesStatement {}
+
+impl QueryId for ShowHotRangesStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A range receiving queries, as returned by [`show_hot_ranges`].
+///
+/// The names are empty for ranges which do not belong to a table, such as
+/// system ranges.
+///
+/// [`show_hot_ranges`]: fn.show_hot_ranges.html
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct HotRange {
+    /// The ID of the range.
+    pub range_id: i64,
+    /// The number of queries per second the range is receiving, averaged
+    /// over the last 30 minutes.
+    pub qps: f64,
+    /// The database the range belongs to.
+    pub database_name: String,
+    /// The table the range belongs to.
+    pub table_name: String,
+    /// The index the range belongs to.
+    pub index_name: String,
+}
+
+/// Creates a `SHOW REGIONS` statement, listing the regions of the cluster
+/// and the databases using them.
+///
+/// ```sql
+/// SHOW REGIONS;
+/// ```
+pub fn show_regions() -> ShowRegionsStatement {
+    ShowRegionsStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW REGIONS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-regions.html
+pub struct ShowRegionsStatement;
+
+impl QueryFragment<Pg> for ShowRegionsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT region, zones, database_names, primary_region_of, secondary_region_of \
+             FROM [SHOW REGIONS]",
+        );
+        Ok(())
+    }
+}
+
+impl Query for ShowRegionsStatement {
+    type SqlType = (Text, Array<Text>, Array<Text>, Array<Text>, Array<Text>);
+}
+
+impl RunQueryDsl<PgConnection> for ShowRegionsStatement {}
+
+impl QueryId for ShowRegionsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A region of the cluster, as returned by [`show_regions`].
+///
+/// [`show_regions`]: fn.show_regions.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct RegionUsage {
+    /// The region.
+    pub region: Region,
+    /// The availability zones of the region.
+    pub zones: Vec<String>,
+    /// The databases with the region.
+    pub database_names: Vec<String>,
+    /// The databases with the region as their primary region.
+    pub primary_region_of: Vec<String>,
+    /// The databases with the region as their secondary region.
+    pub secondary_region_of: Vec<String>,
+}
+
+/// Creates a `SHOW REGIONS FROM CLUSTER` statement, listing the regions
+/// available to databases in the cluster.
+///
+/// ```sql
+/// SHOW REGIONS FROM CLUSTER;
+/// ```
+pub fn show_regions_from_cluster() -> ShowClusterRegionsStatement {
+    ShowClusterRegionsStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW REGIONS FROM CLUSTER` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-regions.html
+pub struct ShowClusterRegionsStatement;
+
+impl QueryFragment<Pg> for ShowClusterRegionsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT region, zones FROM [SHOW REGIONS FROM CLUSTER]");
+        Ok(())
+    }
+}
+
+impl Query for ShowClusterRegionsStatement {
+    type SqlType = (Text, Array<Text>);
+}
+
+impl RunQueryDsl<PgConnection> for ShowClusterRegionsStatement {}
+
+impl QueryId for ShowClusterRegionsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A region available to the cluster, as returned by
+/// [`show_regions_from_cluster`].
+///
+/// [`show_regions_from_cluster`]: fn.show_regions_from_cluster.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct ClusterRegion {
+    /// The region.
+    pub region: Region,
+    /// The availability zones of the region.
+    pub zones: Vec<String>,
+}
+
+/// Creates a `SHOW REGIONS FROM DATABASE` statement, listing the regions of
+/// the database called `name`.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{show_regions_from_database, DatabaseRegion};
+///
+/// let regions: Vec<DatabaseRegion> = show_regions_from_database("library").load(&connection)?;
+/// let primary = regions.iter().find(|region| region.primary);
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_regions_from_database(name: impl Into<String>) -> ShowDatabaseRegionsStatement {
+    ShowDatabaseRegionsStatement { name: name.into() }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW REGIONS FROM DATABASE` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-regions.html
+pub struct ShowDatabaseRegionsStatement {
+    name: String,
+}
+
+impl QueryFragment<Pg> for ShowDatabaseRegionsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT database, region, \"primary\", secondary, zones \
+             FROM [SHOW REGIONS FROM DATABASE ",
+        );
+        out.push_identifier(&self.name)?;
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl Query for ShowDatabaseRegionsStatement {
+    type SqlType = (Text, Text, Bool, Bool, Array<Text>);
+}
+
+impl RunQueryDsl<PgConnection> for ShowDatabaseRegionsStatement {}
+
+impl QueryId for ShowDatabaseRegionsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A region of a database, as returned by [`show_regions_from_database`].
+///
+/// [`show_regions_from_database`]: fn.show_regions_from_database.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct DatabaseRegion {
+    /// The database.
+    pub database: String,
+    /// The region.
+    pub region: Region,
+    /// Whether the region is the primary region of the database.
+    pub primary: bool,
+    /// Whether the region is the secondary region of the database.
+    pub secondary: bool,
+    /// The availability zones of the region.
+    pub zones: Vec<String>,
+}
+
+/// Creates a `SHOW SESSIONS` statement, listing the active sessions on
+/// every node of the cluster.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{cancel_session, show_sessions, Session};
+///
+/// let sessions: Vec<Session> = show_sessions().load(&connection)?;
+/// for session in sessions.iter().filter(|session| session.user_name == "reports") {
+///     cancel_session(&session.session_id).execute(&connection)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_sessions() -> ShowSessionsStatement {
+    ShowSessionsStatement { local: false }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW SESSIONS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-sessions.html
+pub struct ShowSessionsStatement {
+    local: bool,
+}
+
+impl ShowSessionsStatement {
+    /// Only show the sessions on the node the connection is to.
+    ///
+    /// ```sql
+    /// SHOW LOCAL SESSIONS;
+    /// ```
+    pub fn local(mut self) -> Self {
+        self.local = true;
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowSessionsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT node_id, session_id, user_name, client_address, application_name, \
+             active_queries, last_active_query, session_start FROM [SHOW ",
+        );
+        out.push_sql(if self.local { "LOCAL" } else { "CLUSTER" });
+        out.push_sql(" SESSIONS]");
+        Ok(())
+    }
+}
+
+// `session_start` is a `TIMESTAMPTZ`, which has the same binary
+// representation as a `TIMESTAMP` in UTC.
+impl Query for ShowSessionsStatement {
+    type SqlType = (
+        BigInt,
+        Text,
+        Text,
+        Nullable<Text>,
+        Nullable<Text>,
+        Nullable<Text>,
+        Nullable<Text>,
+        Nullable<Timestamp>,
+    );
+}
+
+impl RunQueryDsl<PgConnection> for ShowSessionsStatement {}
+
+impl QueryId for ShowSessionsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// An active session, as returned by [`show_sessions`].
+///
+/// [`show_sessions`]: fn.show_sessions.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct Session {
+    /// The node the session is connected to.
+    pub node_id: i64,
+    /// The ID of the session, used to cancel it.
+    pub session_id: String,
+    /// The user the session is authenticated as.
+    pub user_name: String,
+    /// The address of the client.
+    pub client_address: Option<String>,
+    /// The `application_name` set by the client.
+    pub application_name: Option<String>,
+    /// The queries currently running in the session, separated by
+    /// semicolons.
+    pub active_queries: Option<String>,
+    /// The most recently completed query in the session.
+    pub last_active_query: Option<String>,
+    /// When the session started.
+    pub session_start: Option<SystemTime>,
+}
+
+/// Creates a `CANCEL SESSION` statement, cancelling the session with the
+/// given ID, along with any query it is running.
+///
+/// ```sql
+/// CANCEL SESSION '1530fe0e46d2692e0000000000000001';
+/// ```
+pub fn cancel_session(session_id: impl Into<String>) -> CancelSessionStatement {
+    CancelSessionStatement {
+        session_id: session_id.into(),
+        if_exists: false,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CANCEL SESSION` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/cancel-session.html
+pub struct CancelSessionStatement {
+    session_id: String,
+    if_exists: bool,
+}
+
+impl CancelSessionStatement {
+    /// Do nothing if the session has already ended.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+}
+
+impl QueryFragment<Pg> for CancelSessionStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CANCEL SESSION ");
+        if self.if_exists {
+            out.push_sql("IF EXISTS ");
+        }
+        out.push_sql(&quote::string_literal(&self.session_id));
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for CancelSessionStatement {}
+
+impl QueryId for CancelSessionStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW STATEMENTS` statement, listing the statements running on
+/// every node of the cluster.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{cancel_query, show_statements, RunningStatement};
+/// use std::time::{Duration, SystemTime};
+///
+/// let statements: Vec<RunningStatement> = show_statements().load(&connection)?;
+/// let cutoff = SystemTime::now() - Duration::from_secs(300);
+/// for statement in statements.iter().filter(|statement| statement.start < cutoff) {
+///     cancel_query(&statement.query_id).if_exists().execute(&connection)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_statements() -> ShowStatementsStatement {
+    ShowStatementsStatement { local: false }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW STATEMENTS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-statements.html
+pub struct ShowStatementsStatement {
+    local: bool,
+}
+
+impl ShowStatementsStatement {
+    /// Only show the statements running on the node the connection is to.
+    ///
+    /// ```sql
+    /// SHOW LOCAL STATEMENTS;
+    /// ```
+    pub fn local(mut self) -> Self {
+        self.local = true;
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowStatementsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT query_id, node_id, session_id, user_name, \"start\", query, \
+             client_address, application_name, distributed, phase FROM [SHOW ",
+        );
+        out.push_sql(if self.local { "LOCAL" } else { "CLUSTER" });
+        out.push_sql(" STATEMENTS]");
+        Ok(())
+    }
+}
+
+// `start` is a `TIMESTAMPTZ`, which has the same binary representation as a
+// `TIMESTAMP` in UTC.
+impl Query for ShowStatementsStatement {
+    type SqlType = (
+        Text,
+        BigInt,
+        Text,
+        Text,
+        Timestamp,
+        Text,
+        Nullable<Text>,
+        Nullable<Text>,
+        Nullable<Bool>,
+        Nullable<Text>,
+    );
+}
+
+impl RunQueryDsl<PgConnection> for ShowStatementsStatement {}
+
+impl QueryId for ShowStatementsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A running statement, as returned by [`show_statements`].
+///
+/// [`show_statements`]: fn.show_statements.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct RunningStatement {
+    /// The ID of the query, used to cancel it.
+    pub query_id: String,
+    /// The node the statement is running on.
+    pub node_id: i64,
+    /// The ID of the session running the statement.
+    pub session_id: String,
+    /// The user running the statement.
+    pub user_name: String,
+    /// When the statement started.
+    pub start: SystemTime,
+    /// The SQL of the statement, with constants redacted.
+    pub query: String,
+    /// The address of the client.
+    pub client_address: Option<String>,
+    /// The `application_name` set by the client.
+    pub application_name: Option<String>,
+    /// Whether the statement is being executed across several nodes.
+    pub distributed: Option<bool>,
+    /// Whether the statement is `preparing` or `executing`.
+    pub phase: Option<String>,
+}
+
+/// Creates a `CANCEL QUERY` statement, cancelling the running query with the
+/// given ID.
+///
+/// ```sql
+/// CANCEL QUERY '1530fe0e46d2692e0000000000000001';
+/// ```
+pub fn cancel_query(query_id: impl Into<String>) -> CancelQueryStatement {
+    CancelQueryStatement {
+        query_id: query_id.into(),
+        if_exists: false,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CANCEL QUERY` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/cancel-query.html
+pub struct CancelQueryStatement {
+    query_id: String,
+    if_exists: bool,
+}
+
+impl CancelQueryStatement {
+    /// Do nothing if the query has already finished.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+}
+
+impl QueryFragment<Pg> for CancelQueryStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CANCEL QUERY ");
+        if self.if_exists {
+            out.push_sql("IF EXISTS ");
+        }
+        out.push_sql(&quote::string_literal(&self.query_id));
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for CancelQueryStatement {}
+
+impl QueryId for CancelQueryStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a query listing the open transactions of the cluster, as shown
+/// by `SHOW TRANSACTIONS`.
+///
+/// The transactions are read from `crdb_internal.cluster_transactions`, the
+/// table behind `SHOW TRANSACTIONS`, to include when each one started.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # use std::time::Duration;
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{show_transactions, Transaction};
+///
+/// let long_running: Vec<Transaction> = show_transactions()
+///     .application_name("bookshop")
+///     .older_than(Duration::from_secs(60))
+///     .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_transactions() -> ShowTransactionsStatement {
+    ShowTransactionsStatement {
+        local: false,
+        application_name: None,
+        older_than: None,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW TRANSACTIONS` query.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-transactions.html
+pub struct ShowTransactionsStatement {
+    local: bool,
+    application_name: Option<String>,
+    older_than: Option<Duration>,
+}
+
+impl ShowTransactionsStatement {
+    /// Only show the transactions on the node the connection is to, as with
+    /// `SHOW LOCAL TRANSACTIONS`.
+    pub fn local(mut self) -> Self {
+        self.local = true;
+        self
+    }
+
+    /// Only show transactions of sessions with the given
+    /// `application_name`.
+    pub fn application_name(mut self, application_name: impl Into<String>) -> Self {
+        self.application_name = Some(application_name.into());
+        self
+    }
+
+    /// Only show transactions which have been open for longer than the given
+    /// duration.
+    pub fn older_than(mut self, duration: Duration) -> Self {
+        self.older_than = Some(duration);
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowTransactionsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT id::STRING, node_id, session_id, start, application_name, num_stmts, \
+             num_retries, num_auto_retries FROM crdb_internal.",
+        );
+        out.push_sql(if self.local {
+            "node_transactions"
+        } else {
+            "cluster_transactions"
+        });
+        let mut conjunction = " WHERE ";
+        if let Some(application_name) = &self.application_name {
+            out.push_sql(conjunction);
+            out.push_sql("application_name = ");
+            out.push_bind_param::<Text, _>(application_name)?;
+            conjunction = " AND ";
+        }
+        if let Some(duration) = self.older_than {
+            out.push_sql(conjunction);
+            out.push_sql("start < now()::TIMESTAMP - INTERVAL ");
+            out.push_sql(&quote::string_literal(&quote::interval(duration)));
+        }
+        out.push_sql(" ORDER BY start");
+        Ok(())
+    }
+}
+
+impl Query for ShowTransactionsStatement {
+    type SqlType = (
+        Text,
+        BigInt,
+        Nullable<Text>,
+        Timestamp,
+        Nullable<Text>,
+        BigInt,
+        BigInt,
+        BigInt,
+    );
+}
+
+impl RunQueryDsl<PgConnection> for ShowTransactionsStatement {}
+
+impl QueryId for ShowTransactionsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// An open transaction, as returned by [`show_transactions`].
+///
+/// [`show_transactions`]: fn.show_transactions.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct Transaction {
+    /// The ID of the transaction.
+    pub id: String,
+    /// The node coordinating the transaction.
+    pub node_id: i64,
+    /// The session running the transaction, which can be cancelled to abort
+    /// it.
+    pub session_id: Option<String>,
+    /// When the transaction started.
+    pub start: SystemTime,
+    /// The `application_name` of the session.
+    pub application_name: Option<String>,
+    /// The number of statements run in the transaction.
+    pub num_statements: i64,
+    /// The number of times the client retried the transaction.
+    pub num_retries: i64,
+    /// The number of times CockroachDB automatically retried the
+    /// transaction.
+    pub num_auto_retries: i64,
+}
+
+/// Creates a query reading the status of each node of the cluster, from
+/// `crdb_internal.gossip_nodes`.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{node_status, NodeStatus};
+///
+/// let nodes: Vec<NodeStatus> = node_status().load(&connection)?;
+/// let dead: Vec<i64> = nodes
+///     .iter()
+///     .filter(|node| !node.is_live)
+///     .map(|node| node.node_id)
+///     .collect();
+/// # Ok(())
+/// # }
+/// ```
+pub fn node_status() -> NodeStatusQuery {
+    NodeStatusQuery { live: false }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query reading the status of nodes.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/crdb-internal.html
+pub struct NodeStatusQuery {
+    live: bool,
+}
+
+impl NodeStatusQuery {
+    /// Only read the nodes which are currently live.
+    pub fn live(mut self) -> Self {
+        self.live = true;
+        self
+    }
+}
+
+impl QueryFragment<Pg> for NodeStatusQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT node_id, address, sql_address, locality, server_version, build_tag, \
+             started_at, is_live, ranges, leases FROM crdb_internal.gossip_nodes",
+        );
+        if self.live {
+            out.push_sql(" WHERE is_live");
+        }
+        out.push_sql(" ORDER BY node_id");
+        Ok(())
+    }
+}
+
+impl Query for NodeStatusQuery {
+    type SqlType = (
+        BigInt,
+        Text,
+        Nullable<Text>,
+        Text,
+        Text,
+        Text,
+        Timestamp,
+        Bool,
+        BigInt,
+        BigInt,
+    );
+}
+
+impl RunQueryDsl<PgConnection> for NodeStatusQuery {}
+
+impl QueryId for NodeStatusQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The status of a node, as returned by [`node_status`].
+///
+/// [`node_status`]: fn.node_status.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct NodeStatus {
+    /// The ID of the node.
+    pub node_id: i64,
+    /// The address the node serves its RPC traffic on.
+    pub address: String,
+    /// The address the node serves SQL connections on, if different.
+    pub sql_address: Option<String>,
+    /// The locality of the node.
+    pub locality: NodeLocality,
+    /// The cluster version the node is running at, such as `22.2`.
+    pub server_version: String,
+    /// The release of CockroachDB the node was built from, such as
+    /// `v22.2.3`.
+    pub build_tag: String,
+    /// When the node started.
+    pub started_at: SystemTime,
+    /// Whether the node is live, according to its liveness record.
+    pub is_live: bool,
+    /// The number of ranges the node holds replicas of.
+    pub ranges: i64,
+    /// The number of ranges the node holds the lease of.
+    pub leases: i64,
+}
+
+impl NodeStatus {
+    /// The release of CockroachDB the node is running, if its build tag can
+    /// be parsed.
+    pub fn version(&self) -> Option<ClusterVersion> {
+        ClusterVersion::parse(&self.build_tag)
+    }
+}
+
+/// Creates a query reading the statement statistics of the cluster, from
+/// `crdb_internal.statement_statistics`.
+///
+/// Each row aggregates the executions of one statement fingerprint, by one
+/// application, over one aggregation interval (an hour by default). Combine
+/// the rows of each fingerprint with [`slowest_fingerprints`].
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{slowest_fingerprints, statement_statistics, StatementStatistics};
+///
+/// let statistics: Vec<StatementStatistics> = statement_statistics()
+///     .app_name("bookshop")
+///     .load(&connection)?;
+/// for fingerprint in slowest_fingerprints(&statistics, 10) {
+///     println!("{:?}: {}", fingerprint.mean_service_latency, fingerprint.query);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`slowest_fingerprints`]: fn.slowest_fingerprints.html
+pub fn statement_statistics() -> StatementStatisticsQuery {
+    StatementStatisticsQuery {
+        app_name: None,
+        since: None,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query reading statement statistics.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/crdb-internal.html#statement_statistics
+pub struct StatementStatisticsQuery {
+    app_name: Option<String>,
+    since: Option<SystemTime>,
+}
+
+impl StatementStatisticsQuery {
+    /// Only read statistics of statements run by the given application.
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Only read statistics aggregated since the given time.
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+}
+
+impl QueryFragment<Pg> for StatementStatisticsQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT encode(fingerprint_id, 'hex'), app_name, metadata->>'query', \
+             aggregated_ts, (statistics->'statistics'->>'cnt')::INT8, \
+             (statistics->'statistics'->'runLat'->>'mean')::FLOAT8, \
+             (statistics->'statistics'->'svcLat'->>'mean')::FLOAT8, \
+             (statistics->'statistics'->'latencyInfo'->>'p50')::FLOAT8, \
+             (statistics->'statistics'->'latencyInfo'->>'p90')::FLOAT8, \
+             (statistics->'statistics'->'latencyInfo'->>'p99')::FLOAT8, \
+             (statistics->'statistics'->'latencyInfo'->>'max')::FLOAT8, \
+             (statistics->'execution_statistics'->'contentionTime'->>'mean')::FLOAT8 \
+             FROM crdb_internal.statement_statistics",
+        );
+        let mut conjunction = " WHERE ";
+        if let Some(app_name) = &self.app_name {
+            out.push_sql(conjunction);
+            out.push_sql("app_name = ");
+            out.push_bind_param::<Text, _>(app_name)?;
+            conjunction = " AND ";
+        }
+        if let Some(since) = &self.since {
+            out.push_sql(conjunction);
+            out.push_sql("aggregated_ts >= ");
+            out.push_bind_param::<Timestamp, _>(since)?;
+        }
+        Ok(())
+    }
+}
+
+// `aggregated_ts` is a `TIMESTAMPTZ`, which has the same binary
+// representation as a `TIMESTAMP` in UTC.
+impl Query for StatementStatisticsQuery {
+    type SqlType = StatementStatisticsSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for StatementStatisticsQuery {}
+
+impl QueryId for StatementStatisticsQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The statistics of a statement fingerprint over an aggregation interval, as
+/// returned by [`statement_statistics`].
+///
+/// Latency percentiles are only recorded by CockroachDB v22.2 and later.
+///
+/// [`statement_statistics`]: fn.statement_statistics.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementStatistics {
+    /// The ID of the statement fingerprint, in hex.
+    pub fingerprint_id: String,
+    /// The application that ran the statement.
+    pub app_name: String,
+    /// The statement fingerprint, with constants replaced by placeholders.
+    pub query: String,
+    /// The start of the aggregation interval.
+    pub aggregated_at: SystemTime,
+    /// The number of times the statement was executed.
+    pub count: i64,
+    /// The mean time taken to run the statement, excluding planning.
+    pub mean_run_latency: Duration,
+    /// The mean time taken to serve the statement, including planning.
+    pub mean_service_latency: Duration,
+    /// The median service latency.
+    pub p50_latency: Option<Duration>,
+    /// The 90th percentile service latency.
+    pub p90_latency: Option<Duration>,
+    /// The 99th percentile service latency.
+    pub p99_latency: Option<Duration>,
+    /// The highest service latency.
+    pub max_latency: Option<Duration>,
+    /// The mean time spent waiting for contended rows, if sampled.
+    pub mean_contention_time: Option<Duration>,
+}
+
+fn seconds(seconds: f64) -> Duration {
+    if seconds.is_finite() && seconds > 0.0 {
+        Duration::from_secs_f64(seconds)
+    } else {
+        Duration::from_secs(0)
+    }
+}
+
+impl Queryable<StatementStatisticsSqlType, Pg> for StatementStatistics {
+    type Row = (
+        String,
+        String,
+        String,
+        SystemTime,
+        i64,
+        f64,
+        f64,
+        Option<f64>,
+        Option<f64>,
+        Option<f64>,
+        Option<f64>,
+        Option<f64>,
+    );
+
+    fn build(
+        (
+            fingerprint_id,
+            app_name,
+            query,
+            aggregated_at,
+            count,
+            mean_run_latency,
+            mean_service_latency,
+            p50_latency,
+            p90_latency,
+            p99_latency,
+            max_latency,
+            mean_contention_time,
+        ): Self::Row,
+    ) -> Self {
+        StatementStatistics {
+            fingerprint_id,
+            app_name,
+            query,
+            aggregated_at,
+            count,
+            mean_run_latency: seconds(mean_run_latency),
+            mean_service_latency: seconds(mean_service_latency),
+            p50_latency: p50_latency.map(seconds),
+            p90_latency: p90_latency.map(seconds),
+            p99_latency: p99_latency.map(seconds),
+            max_latency: max_latency.map(seconds),
+            mean_contention_time: mean_contention_time.map(seconds),
+        }
+    }
+}
+
+/// The SQL type of a [`StatementStatistics`].
+///
+/// [`StatementStatistics`]: struct.StatementStatistics.html
+pub type StatementStatisticsSqlType = (
+    Text,
+    Text,
+    Text,
+    Timestamp,
+    BigInt,
+    Double,
+    Double,
+    Nullable<Double>,
+    Nullable<Double>,
+    Nullable<Double>,
+    Nullable<Double>,
+    Nullable<Double>,
+);
+
+/// The statistics of a statement fingerprint, combined across applications
+/// and aggregation intervals by [`slowest_fingerprints`].
+///
+/// [`slowest_fingerprints`]: fn.slowest_fingerprints.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FingerprintStatistics {
+    /// The ID of the statement fingerprint, in hex.
+    pub fingerprint_id: String,
+    /// The statement fingerprint, with constants replaced by placeholders.
+    pub query: String,
+    /// The number of times the statement was executed.
+    pub count: i64,
+    /// The mean time taken to serve the statement, weighted by executions.
+    pub mean_service_latency: Duration,
+    /// The highest service latency, if recorded.
+    pub max_latency: Option<Duration>,
+}
+
+/// Combine statement statistics by fingerprint, returning the `n`
+/// fingerprints with the highest mean service latency, slowest first.
+pub fn slowest_fingerprints(
+    statistics: &[StatementStatistics],
+    n: usize,
+) -> Vec<FingerprintStatistics> {
+    let mut fingerprints: HashMap<&str, (FingerprintStatistics, f64)> = HashMap::new();
+    for row in statistics {
+        let (fingerprint, total_seconds) = fingerprints
+            .entry(row.fingerprint_id.as_str())
+            .or_insert_with(|| {
+                (
+                    FingerprintStatistics {
+                        fingerprint_id: row.fingerprint_id.clone(),
+                        query: row.query.clone(),
+                        count: 0,
+                        mean_service_latency: Duration::from_secs(0),
+                        max_latency: None,
+                    },
+                    0.0,
+                )
+            });
+        fingerprint.count += row.count;
+        *total_seconds += row.mean_service_latency.as_secs_f64() * row.count as f64;
+        fingerprint.max_latency = fingerprint.max_latency.max(row.max_latency);
+    }
+    let mut fingerprints: Vec<FingerprintStatistics> = fingerprints
+        .into_iter()
+        .map(|(_, (mut fingerprint, total_seconds))| {
+            if fingerprint.count > 0 {
+                fingerprint.mean_service_latency =
+                    seconds(total_seconds / fingerprint.count as f64);
+            }
+            fingerprint
+        })
+        .collect();
+    fingerprints.sort_by(|a, b| {
+        b.mean_service_latency
+            .cmp(&a.mean_service_latency)
+            .then_with(|| a.fingerprint_id.cmp(&b.fingerprint_id))
+    });
+    fingerprints.truncate(n);
+    fingerprints
+}
+
+/// Creates a query listing the statement fingerprints which performed full
+/// table or index scans, from `crdb_internal.statement_statistics`.
+///
+/// This lists the same statements as `SHOW FULL TABLE SCANS`, but combined
+/// by fingerprint and with their execution counts, so a test suite can fail
+/// when a change introduces an unindexed query. Statements run internally by
+/// CockroachDB are excluded.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{full_table_scans, FullTableScan};
+///
+/// let scans: Vec<FullTableScan> = full_table_scans()
+///     .app_name("bookshop")
+///     .load(&connection)?;
+/// assert!(scans.is_empty(), "unindexed queries: {:#?}", scans);
+/// # Ok(())
+/// # }
+/// ```
+pub fn full_table_scans() -> FullTableScansQuery {
+    FullTableScansQuery {
+        app_name: None,
+        since: None,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query listing statements which performed full scans.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-full-table-scans.html
+pub struct FullTableScansQuery {
+    app_name: Option<String>,
+    since: Option<SystemTime>,
+}
+
+impl FullTableScansQuery {
+    /// Only list statements run by the given application.
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Only list statements run in aggregation intervals since the given
+    /// time.
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+}
+
+impl QueryFragment<Pg> for FullTableScansQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT encode(fingerprint_id, 'hex'), app_name, metadata->>'query', \
+             metadata->>'db', sum((statistics->'statistics'->>'cnt')::INT8)::INT8, \
+             max(aggregated_ts) FROM crdb_internal.statement_statistics \
+             WHERE (metadata->>'fullScan')::BOOL AND app_name NOT LIKE '$ internal%'",
+        );
+        if let Some(app_name) = &self.app_name {
+            out.push_sql(" AND app_name = ");
+            out.push_bind_param::<Text, _>(app_name)?;
+        }
+        if let Some(since) = &self.since {
+            out.push_sql(" AND aggregated_ts >= ");
+            out.push_bind_param::<Timestamp, _>(since)?;
+        }
+        out.push_sql(" GROUP BY 1, 2, 3, 4 ORDER BY 5 DESC, 1");
+        Ok(())
+    }
+}
+
+// `aggregated_ts` is a `TIMESTAMPTZ`, which has the same binary
+// representation as a `TIMESTAMP` in UTC.
+impl Query for FullTableScansQuery {
+    type SqlType = (Text, Text, Text, Nullable<Text>, BigInt, Timestamp);
+}
+
+impl RunQueryDsl<PgConnection> for FullTableScansQuery {}
+
+impl QueryId for FullTableScansQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A statement fingerprint which performed full scans, as returned by
+/// [`full_table_scans`].
+///
+/// [`full_table_scans`]: fn.full_table_scans.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct FullTableScan {
+    /// The ID of the statement fingerprint, in hex.
+    pub fingerprint_id: String,
+    /// The application that ran the statement.
+    pub app_name: String,
+    /// The statement fingerprint, with constants replaced by placeholders.
+    pub query: String,
+    /// The database the statement was run in.
+    pub database: Option<String>,
+    /// The number of times the statement was executed.
+    pub count: i64,
+    /// The start of the latest aggregation interval the statement was run in.
+    pub last_seen: SystemTime,
+}
+
+/// Creates a query reading the contention events recorded between
+/// transactions, from `crdb_internal.transaction_contention_events`.
+///
+/// Each event records a transaction which waited on a key locked by another
+/// transaction. Combine the events of each table with
+/// [`contention_by_table`].
+///
+/// Requires CockroachDB v22.2 or later.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # use std::time::{Duration, SystemTime};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{contention_by_table, transaction_contention_events, ContentionEvent};
+///
+/// let hour_ago = SystemTime::now() - Duration::from_secs(60 * 60);
+/// let events: Vec<ContentionEvent> = transaction_contention_events()
+///     .since(hour_ago)
+///     .load(&connection)?;
+/// for table in contention_by_table(&events) {
+///     println!("{:?}: {:?}", table.table_name, table.total_duration);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`contention_by_table`]: fn.contention_by_table.html
+pub fn transaction_contention_events() -> TransactionContentionEventsQuery {
+    TransactionContentionEventsQuery { since: None }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query reading transaction contention events.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/crdb-internal.html#transaction_contention_events
+pub struct TransactionContentionEventsQuery {
+    since: Option<SystemTime>,
+}
+
+impl TransactionContentionEventsQuery {
+    /// Only read events collected since the given time.
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+}
+
+impl VersionCheck for TransactionContentionEventsQuery {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        version.require("crdb_internal.transaction_contention_events", 22, 2)
+    }
+}
+
+impl QueryFragment<Pg> for TransactionContentionEventsQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        // Keys of tables start with `/Table/<table_id>`, after a
+        // `/Tenant/<tenant_id>` prefix for keys of secondary tenants.
+        out.push_sql(
+            "SELECT e.collection_ts, e.blocking_txn_id::STRING, \
+             encode(e.blocking_txn_fingerprint_id, 'hex'), e.waiting_txn_id::STRING, \
+             encode(e.waiting_txn_fingerprint_id, 'hex'), \
+             (extract(epoch FROM e.contention_duration) * 1000000)::INT8, e.contending_key, \
+             crdb_internal.pretty_key(e.contending_key, 0), t.database_name, t.name \
+             FROM crdb_internal.transaction_contention_events AS e \
+             LEFT JOIN crdb_internal.tables AS t ON t.table_id::STRING = \
+             substring(crdb_internal.pretty_key(e.contending_key, 0), \
+             '^(?:/Tenant/\\d+)?/Table/(\\d+)')",
+        );
+        if let Some(since) = &self.since {
+            out.push_sql(" WHERE e.collection_ts >= ");
+            out.push_bind_param::<Timestamp, _>(since)?;
+        }
+        out.push_sql(" ORDER BY e.collection_ts");
+        Ok(())
+    }
+}
+
+// `collection_ts` is a `TIMESTAMPTZ`, which has the same binary
+// representation as a `TIMESTAMP` in UTC.
+impl Query for TransactionContentionEventsQuery {
+    type SqlType = ContentionEventSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for TransactionContentionEventsQuery {}
+
+impl QueryId for TransactionContentionEventsQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A transaction waiting on a key locked by another transaction, as returned
+/// by [`transaction_contention_events`].
+///
+/// [`transaction_contention_events`]: fn.transaction_contention_events.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentionEvent {
+    /// When the event was collected.
+    pub collected_at: SystemTime,
+    /// The ID of the transaction holding the lock.
+    pub blocking_txn_id: String,
+    /// The fingerprint ID of the transaction holding the lock, in hex.
+    pub blocking_fingerprint_id: String,
+    /// The ID of the transaction which waited.
+    pub waiting_txn_id: String,
+    /// The fingerprint ID of the transaction which waited, in hex.
+    pub waiting_fingerprint_id: String,
+    /// How long the transaction waited for.
+    pub duration: Duration,
+    /// The contended key.
+    pub key: Vec<u8>,
+    /// The contended key, in a readable form.
+    pub pretty_key: String,
+    /// The database of the table of the contended key, if known.
+    pub database_name: Option<String>,
+    /// The table of the contended key, if known. Keys with a
+    /// `/Tenant/<tenant_id>` prefix are looked up in the tables of the current
+    /// tenant.
+    pub table_name: Option<String>,
+}
+
+impl Queryable<ContentionEventSqlType, Pg> for ContentionEvent {
+    type Row = (
+        SystemTime,
+        String,
+        String,
+        String,
+        String,
+        i64,
+        Vec<u8>,
+        String,
+        Option<String>,
+        Option<String>,
+    );
+
+    fn build(
+        (
+            collected_at,
+            blocking_txn_id,
+            blocking_fingerprint_id,
+            waiting_txn_id,
+            waiting_fingerprint_id,
+            duration,
+            key,
+            pretty_key,
+            database_name,
+            table_name,
+        ): Self::Row,
+    ) -> Self {
+        ContentionEvent {
+            collected_at,
+            blocking_txn_id,
+            blocking_fingerprint_id,
+            waiting_txn_id,
+            waiting_fingerprint_id,
+            duration: Duration::from_micros(duration.max(0) as u64),
+            key,
+            pretty_key,
+            database_name,
+            table_name,
+        }
+    }
+}
+
+/// The SQL type of a [`ContentionEvent`].
+///
+/// [`ContentionEvent`]: struct.ContentionEvent.html
+pub type ContentionEventSqlType = (
+    Timestamp,
+    Text,
+    Text,
+    Text,
+    Text,
+    BigInt,
+    Binary,
+    Text,
+    Nullable<Text>,
+    Nullable<Text>,
+);
+
+/// The contention on a table, combined from its contention events by
+/// [`contention_by_table`].
+///
+/// [`contention_by_table`]: fn.contention_by_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableContention {
+    /// The database of the table, if known.
+    pub database_name: Option<String>,
+    /// The name of the table, or `None` for keys outside of any known table.
+    pub table_name: Option<String>,
+    /// The number of contention events.
+    pub events: usize,
+    /// The total time transactions waited for.
+    pub total_duration: Duration,
+    /// The fingerprint IDs of the waiting transactions, with the time each
+    /// waited for in total, longest first.
+    pub waiting_fingerprints: Vec<(String, Duration)>,
+}
+
+/// Combine contention events by table, returning the tables with the longest
+/// total contention first.
+pub fn contention_by_table(events: &[ContentionEvent]) -> Vec<TableContention> {
+    type Key<'a> = (Option<&'a str>, Option<&'a str>);
+    let mut tables: HashMap<Key, (TableContention, HashMap<&str, Duration>)> = HashMap::new();
+    for event in events {
+        let key = (event.database_name.as_deref(), event.table_name.as_deref());
+        let (table, fingerprints) = tables.entry(key).or_insert_with(|| {
+            (
+                TableContention {
+                    database_name: event.database_name.clone(),
+                    table_name: event.table_name.clone(),
+                    events: 0,
+                    total_duration: Duration::from_secs(0),
+                    waiting_fingerprints: Vec::new(),
+                },
+                HashMap::new(),
+            )
+        });
+        table.events += 1;
+        table.total_duration += event.duration;
+        *fingerprints
+            .entry(event.waiting_fingerprint_id.as_str())
+            .or_insert_with(|| Duration::from_secs(0)) += event.duration;
+    }
+    let mut tables: Vec<TableContention> = tables
+        .into_iter()
+        .map(|(_, (mut table, fingerprints))| {
+            table.waiting_fingerprints = fingerprints
+                .into_iter()
+                .map(|(fingerprint, duration)| (fingerprint.to_owned(), duration))
+                .collect();
+            table
+                .waiting_fingerprints
+                .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            table
+        })
+        .collect();
+    tables.sort_by(|a, b| {
+        b.total_duration
+            .cmp(&a.total_duration)
+            .then_with(|| a.table_name.cmp(&b.table_name))
+    });
+    tables
+}
+
+/// Creates a query reading how often each index of the current database has
+/// been read, from `crdb_internal.index_usage_statistics`.
+///
+/// Indexes never read since statistics were last reset have a
+/// `total_reads` of zero.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # use std::time::{Duration, SystemTime};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{index_usage_statistics, IndexUsage};
+///
+/// let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+/// let unused: Vec<IndexUsage> = index_usage_statistics()
+///     .unused_since(month_ago)
+///     .load(&connection)?;
+/// for index in unused {
+///     println!("{}@{} is unused", index.table_name, index.index_name);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn index_usage_statistics() -> IndexUsageStatisticsQuery {
+    IndexUsageStatisticsQuery { unused_since: None }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query reading index usage statistics.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/crdb-internal.html#index_usage_statistics
+pub struct IndexUsageStatisticsQuery {
+    unused_since: Option<SystemTime>,
+}
+
+impl IndexUsageStatisticsQuery {
+    /// Only read secondary indexes which have not been read since the given
+    /// time, and so are candidates for dropping.
+    pub fn unused_since(mut self, time: SystemTime) -> Self {
+        self.unused_since = Some(time);
+        self
+    }
+}
+
+impl QueryFragment<Pg> for IndexUsageStatisticsQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT ti.descriptor_id, ti.descriptor_name, ti.index_id, ti.index_name, \
+             ti.index_type, ti.is_unique, COALESCE(us.total_reads, 0), us.last_read \
+             FROM crdb_internal.table_indexes AS ti \
+             LEFT JOIN crdb_internal.index_usage_statistics AS us \
+             ON us.table_id = ti.descriptor_id AND us.index_id = ti.index_id",
+        );
+        if let Some(since) = &self.unused_since {
+            out.push_sql(
+                " WHERE ti.index_type = 'secondary' AND (us.last_read IS NULL OR us.last_read < ",
+            );
+            out.push_bind_param::<Timestamp, _>(since)?;
+            out.push_sql(")");
+        }
+        out.push_sql(" ORDER BY ti.descriptor_name, ti.index_id");
+        Ok(())
+    }
+}
+
+// `last_read` is a `TIMESTAMPTZ`, which has the same binary representation
+// as a `TIMESTAMP` in UTC.
+impl Query for IndexUsageStatisticsQuery {
+    type SqlType = (
+        BigInt,
+        Text,
+        BigInt,
+        Text,
+        Text,
+        Bool,
+        BigInt,
+        Nullable<Timestamp>,
+    );
+}
+
+impl RunQueryDsl<PgConnection> for IndexUsageStatisticsQuery {}
+
+impl QueryId for IndexUsageStatisticsQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// How often an index has been read, as returned by
+/// [`index_usage_statistics`].
+///
+/// [`index_usage_statistics`]: fn.index_usage_statistics.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct IndexUsage {
+    /// The ID of the table.
+    pub table_id: i64,
+    /// The name of the table.
+    pub table_name: String,
+    /// The ID of the index within the table.
+    pub index_id: i64,
+    /// The name of the index.
+    pub index_name: String,
+    /// The type of the index, either `primary` or `secondary`.
+    pub index_type: String,
+    /// Whether the index enforces uniqueness, in which case dropping it
+    /// changes the constraints of the table.
+    pub is_unique: bool,
+    /// The number of times the index has been read.
+    pub total_reads: i64,
+    /// When the index was last read, if ever.
+    pub last_read: Option<SystemTime>,
+}
+
+/// Creates a query running a consistency check of the replicas of every
+/// range of the cluster, using `crdb_internal.check_consistency`.
+///
+/// The check compares the data of every replica of each range, which is
+/// expensive, so should be scheduled outside of peak hours, or limited with
+/// [`keys`] or [`stats_only`].
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::admin::{check_consistency, ConsistencyCheck};
+///
+/// let results: Vec<ConsistencyCheck> = check_consistency().load(&connection)?;
+/// for result in results.iter().filter(|result| !result.status.is_consistent()) {
+///     eprintln!("range {} is {}: {}", result.range_id, result.status, result.detail);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`keys`]: struct.CheckConsistencyQuery.html#method.keys
+/// [`stats_only`]: struct.CheckConsistencyQuery.html#method.stats_only
+pub fn check_consistency() -> CheckConsistencyQuery {
+    CheckConsistencyQuery {
+        stats_only: false,
+        start_key: vec![0x02],
+        end_key: vec![0xff, 0xff],
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed consistency check.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/crdb-internal.html
+pub struct CheckConsistencyQuery {
+    stats_only: bool,
+    start_key: Vec<u8>,
+    end_key: Vec<u8>,
+}
+
+impl CheckConsistencyQuery {
+    /// Only check the ranges overlapping the keys from `start_key` up to
+    /// `end_key`, such as the keys of a [`RangesNoLeasesRow`].
+    ///
+    /// [`RangesNoLeasesRow`]: ../crdb_internal/struct.RangesNoLeasesRow.html
+    pub fn keys(mut self, start_key: impl Into<Vec<u8>>, end_key: impl Into<Vec<u8>>) -> Self {
+        self.start_key = start_key.into();
+        self.end_key = end_key.into();
+        self
+    }
+
+    /// Only compare the statistics of the replicas, rather than their data,
+    /// which is much cheaper.
+    pub fn stats_only(mut self) -> Self {
+        self.stats_only = true;
+        self
+    }
+}
+
+impl QueryFragment<Pg> for CheckConsistencyQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT range_id, start_key, start_key_pretty, status, detail \
+             FROM crdb_internal.check_consistency(",
+        );
+        out.push_bind_param::<Bool, _>(&self.stats_only)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Binary, _>(&self.start_key)?;
+        out.push_sql(", ");
+        out.push_bind_param::<Binary, _>(&self.end_key)?;
+        out.push_sql(")");
+        Ok(())
+    }
+}
+
+impl Query for CheckConsistencyQuery {
+    type SqlType = ConsistencyCheckSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for CheckConsistencyQuery {}
+
+impl QueryId for CheckConsistencyQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The outcome of checking the consistency of a range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConsistencyStatus {
+    /// `RANGE_CONSISTENT`: the replicas hold the same data.
+    Consistent,
+    /// `RANGE_CONSISTENT_STATS_ESTIMATED`: the replicas hold the same data,
+    /// and the statistics of the range are estimates.
+    ConsistentStatsEstimated,
+    /// `RANGE_CONSISTENT_STATS_INCORRECT`: the replicas hold the same data,
+    /// but the statistics of the range are incorrect.
+    ConsistentStatsIncorrect,
+    /// `RANGE_INCONSISTENT`: the replicas hold different data.
+    Inconsistent,
+    /// `RANGE_INDETERMINATE`: the check could not be completed.
+    Indeterminate,
+    /// Any other status, by name.
+    Other(String),
+}
+
+impl ConsistencyStatus {
+    /// The name of this status, as returned by CockroachDB.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ConsistencyStatus::Consistent => "RANGE_CONSISTENT",
+            ConsistencyStatus::ConsistentStatsEstimated => "RANGE_CONSISTENT_STATS_ESTIMATED",
+            ConsistencyStatus::ConsistentStatsIncorrect => "RANGE_CONSISTENT_STATS_INCORRECT",
+            ConsistencyStatus::Inconsistent => "RANGE_INCONSISTENT",
+            ConsistencyStatus::Indeterminate => "RANGE_INDETERMINATE",
+            ConsistencyStatus::Other(name) => name,
+        }
+    }
+
+    /// Whether the replicas of the range were found to hold the same data.
+    pub fn is_consistent(&self) -> bool {
+        matches!(
+            self,
+            ConsistencyStatus::Consistent
+                | ConsistencyStatus::ConsistentStatsEstimated
+                | ConsistencyStatus::ConsistentStatsIncorrect
+        )
+    }
+}
+
+impl fmt::Display for ConsistencyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ConsistencyStatus {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "RANGE_CONSISTENT" => ConsistencyStatus::Consistent,
+            "RANGE_CONSISTENT_STATS_ESTIMATED" => ConsistencyStatus::ConsistentStatsEstimated,
+            "RANGE_CONSISTENT_STATS_INCORRECT" => ConsistencyStatus::ConsistentStatsIncorrect,
+            "RANGE_INCONSISTENT" => ConsistencyStatus::Inconsistent,
+            "RANGE_INDETERMINATE" => ConsistencyStatus::Indeterminate,
+            other => ConsistencyStatus::Other(other.to_owned()),
+        })
+    }
+}
+
+/// The result of checking the consistency of a range, as returned by
+/// [`check_consistency`].
+///
+/// [`check_consistency`]: fn.check_consistency.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyCheck {
+    /// The ID of the range.
+    pub range_id: i64,
+    /// The first key of the range.
+    pub start_key: Vec<u8>,
+    /// The first key of the range, in a readable form.
+    pub start_key_pretty: String,
+    /// The outcome of the check.
+    pub status: ConsistencyStatus,
+    /// More detail about the outcome, such as the differing replicas.
+    pub detail: String,
+}
+
+impl Queryable<ConsistencyCheckSqlType, Pg> for ConsistencyCheck {
+    type Row = (i64, Vec<u8>, String, String, String);
+
+    fn build((range_id, start_key, start_key_pretty, status, detail): Self::Row) -> Self {
+        ConsistencyCheck {
+            range_id,
+            start_key,
+            start_key_pretty,
+            status: status.parse().unwrap_or_else(|never| match never {}),
+            detail,
+        }
+    }
+}
+
+/// The SQL type of a [`ConsistencyCheck`].
+///
+/// [`ConsistencyCheck`]: struct.ConsistencyCheck.html
+pub type ConsistencyCheckSqlType = (BigInt, Binary, Text, Text, Text);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    table! {
+        reviews (book_id, reviewer) {
+            book_id -> Int8,
+            reviewer -> Text,
+        }
+    }
+
+    #[test]
+    fn jobs() {
+        assert_eq!(
+            diesel::debug_query(&show_jobs()).to_string(),
+            r#"SELECT job_id, job_type, description, user_name, status, running_status, created, started, finished, fraction_completed, error FROM [SHOW JOBS] ORDER BY created -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn filtered() {
+        let statement = show_jobs()
+            .automatic()
+            .job_type(JobType::AutoCreateStats)
+            .status(JobStatus::Running)
+            .status(JobStatus::Paused);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"SELECT job_id, job_type, description, user_name, status, running_status, created, started, finished, fraction_completed, error FROM [SHOW AUTOMATIC JOBS] WHERE job_type IN ('AUTO CREATE STATS') AND status IN ('running', 'paused') ORDER BY created -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_jobs().status(JobStatus::Failed)).to_string(),
+            r#"SELECT job_id, job_type, description, user_name, status, running_status, created, started, finished, fraction_completed, error FROM [SHOW JOBS] WHERE status IN ('failed') ORDER BY created -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let version = ClusterVersion::new(22, 2, 0);
+        assert_eq!(
+            diesel::debug_query(&show_ranges_from_table(&version, books::table)).to_string(),
+            r#"SELECT start_key, end_key, range_id, range_size_mb::FLOAT8, lease_holder, lease_holder_locality, replicas, replica_localities FROM [SHOW RANGES FROM TABLE "books"] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_ranges_from_index(
+                &version,
+                books::table,
+                "books_title_idx"
+            ))
+            .to_string(),
+            r#"SELECT start_key, end_key, range_id, range_size_mb::FLOAT8, lease_holder, lease_holder_locality, replicas, replica_localities FROM [SHOW RANGES FROM INDEX "books"@"books_title_idx"] -- binds: []"#
+        );
+
+        let version = ClusterVersion::new(23, 1, 0);
+        assert_eq!(
+            diesel::debug_query(&show_ranges_from_table(&version, books::table)).to_string(),
+            r#"SELECT start_key, end_key, range_id, range_size_mb::FLOAT8, lease_holder, lease_holder_locality, replicas, replica_localities FROM [SHOW RANGES FROM TABLE "books" WITH DETAILS] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_ranges_from_index(
+                &version,
+                books::table,
+                "books_title_idx"
+            ))
+            .to_string(),
+            r#"SELECT start_key, end_key, range_id, range_size_mb::FLOAT8, lease_holder, lease_holder_locality, replicas, replica_localities FROM [SHOW RANGES FROM INDEX "books"@"books_title_idx" WITH DETAILS] -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn per_node() {
+        let range = |range_id, lease_holder, replicas: &[i64]| Range {
+            start_key: None,
+            end_key: None,
+            range_id,
+            size_mb: 1.5,
+            lease_holder,
+            lease_holder_locality: None,
+            replicas: replicas.to_vec(),
+            replica_localities: Vec::new(),
+        };
+        let nodes = ranges_per_node(&[range(1, 1, &[1, 2, 3]), range(2, 2, &[2, 3, 4])]);
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(
+            nodes[&2],
+            NodeRanges {
+                replicas: 2,
+                leases: 1,
+                size_mb: 3.0,
+            }
+        );
+        assert_eq!(
+            nodes[&4],
+            NodeRanges {
+                replicas: 1,
+                leases: 0,
+                size_mb: 1.5,
+            }
+        );
+    }
+
+    #[test]
+    fn range_for_row() {
+        use diesel::{dsl::sql, sql_types::Text, IntoSql};
+
+        assert_eq!(
+            diesel::debug_query(&show_range_for_row(books::table, 42)).to_string(),
+            r#"SELECT start_key, end_key, range_id, lease_holder, lease_holder_locality, replicas, replica_localities FROM [SHOW RANGE FROM TABLE "books" FOR ROW ($1)] -- binds: [42]"#
+        );
+        let primary_key = (sql::<BigInt>("1"), "alice".into_sql::<Text>());
+        assert_eq!(
+            diesel::debug_query(&show_range_for_row(reviews::table, primary_key)).to_string(),
+            r#"SELECT start_key, end_key, range_id, lease_holder, lease_holder_locality, replicas, replica_localities FROM [SHOW RANGE FROM TABLE "reviews" FOR ROW (1, $1)] -- binds: ["alice"]"#
+        );
+    }
+
+    #[test]
+    fn regions() {
+        assert_eq!(
+            diesel::debug_query(&show_regions()).to_string(),
+            r#"SELECT region, zones, database_names, primary_region_of, secondary_region_of FROM [SHOW REGIONS] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_regions_from_cluster()).to_string(),
+            r#"SELECT region, zones FROM [SHOW REGIONS FROM CLUSTER] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_regions_from_database("library")).to_string(),
+            r#"SELECT database, region, "primary", secondary, zones FROM [SHOW REGIONS FROM DATABASE "library"] -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn sessions() {
+        assert_eq!(
+            diesel::debug_query(&show_sessions()).to_string(),
+            r#"SELECT node_id, session_id, user_name, client_address, application_name, active_queries, last_active_query, session_start FROM [SHOW CLUSTER SESSIONS] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_sessions().local()).to_string(),
+            r#"SELECT node_id, session_id, user_name, client_address, application_name, active_queries, last_active_query, session_start FROM [SHOW LOCAL SESSIONS] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&cancel_session("1530fe0e46d2692e0000000000000001").if_exists())
+                .to_string(),
+            r#"CANCEL SESSION IF EXISTS '1530fe0e46d2692e0000000000000001' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            diesel::debug_query(&show_statements().local()).to_string(),
+            r#"SELECT query_id, node_id, session_id, user_name, "start", query, client_address, application_name, distributed, phase FROM [SHOW LOCAL STATEMENTS] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&cancel_query("1530fe0e46d2692e0000000000000001")).to_string(),
+            r#"CANCEL QUERY '1530fe0e46d2692e0000000000000001' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn statement_statistics_query() {
+        assert_eq!(
+            diesel::debug_query(&statement_statistics().app_name("bookshop")).to_string(),
+            r#"SELECT encode(fingerprint_id, 'hex'), app_name, metadata->>'query', aggregated_ts, (statistics->'statistics'->>'cnt')::INT8, (statistics->'statistics'->'runLat'->>'mean')::FLOAT8, (statistics->'statistics'->'svcLat'->>'mean')::FLOAT8, (statistics->'statistics'->'latencyInfo'->>'p50')::FLOAT8, (statistics->'statistics'->'latencyInfo'->>'p90')::FLOAT8, (statistics->'statistics'->'latencyInfo'->>'p99')::FLOAT8, (statistics->'statistics'->'latencyInfo'->>'max')::FLOAT8, (statistics->'execution_statistics'->'contentionTime'->>'mean')::FLOAT8 FROM crdb_internal.statement_statistics WHERE app_name = $1 -- binds: ["bookshop"]"#
+        );
+    }
+
+    #[test]
+    fn slowest() {
+        let row = |fingerprint_id: &str, count, millis, max_millis| StatementStatistics {
+            fingerprint_id: fingerprint_id.to_owned(),
+            app_name: "bookshop".to_owned(),
+            query: format!("SELECT {}", fingerprint_id),
+            aggregated_at: SystemTime::UNIX_EPOCH,
+            count,
+            mean_run_latency: Duration::from_millis(millis),
+            mean_service_latency: Duration::from_millis(millis),
+            p50_latency: None,
+            p90_latency: None,
+            p99_latency: None,
+            max_latency: Some(Duration::from_millis(max_millis)),
+            mean_contention_time: None,
+        };
+        let statistics = [
+            row("a", 1, 10, 10),
+            row("b", 3, 20, 30),
+            row("a", 3, 30, 50),
+            row("c", 1, 5, 5),
+        ];
+        assert_eq!(
+            slowest_fingerprints(&statistics, 2),
+            [
+                FingerprintStatistics {
+                    fingerprint_id: "a".to_owned(),
+                    query: "SELECT a".to_owned(),
+                    count: 4,
+                    mean_service_latency: Duration::from_millis(25),
+                    max_latency: Some(Duration::from_millis(50)),
+                },
+                FingerprintStatistics {
+                    fingerprint_id: "b".to_owned(),
+                    query: "SELECT b".to_owned(),
+                    count: 3,
+                    mean_service_latency: Duration::from_millis(20),
+                    max_latency: Some(Duration::from_millis(30)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn index_usage() {
+        let base = "SELECT ti.descriptor_id, ti.descriptor_name, ti.index_id, ti.index_name, ti.index_type, ti.is_unique, COALESCE(us.total_reads, 0), us.last_read FROM crdb_internal.table_indexes AS ti LEFT JOIN crdb_internal.index_usage_statistics AS us ON us.table_id = ti.descriptor_id AND us.index_id = ti.index_id";
+        assert_eq!(
+            diesel::debug_query(&index_usage_statistics()).to_string(),
+            format!(
+                "{} ORDER BY ti.descriptor_name, ti.index_id -- binds: []",
+                base
+            )
+        );
+        let since = SystemTime::UNIX_EPOCH;
+        assert_eq!(
+            diesel::debug_query(&index_usage_statistics().unused_since(since)).to_string(),
+            format!(
+                "{} WHERE ti.index_type = 'secondary' AND (us.last_read IS NULL OR us.last_read < $1) ORDER BY ti.descriptor_name, ti.index_id -- binds: [{:?}]",
+                base, since
+            )
+        );
+    }
+
+    #[test]
+    fn show_transactions() {
+        assert_eq!(
+            diesel::debug_query(&super::show_transactions().local()).to_string(),
+            r#"SELECT id::STRING, node_id, session_id, start, application_name, num_stmts, num_retries, num_auto_retries FROM crdb_internal.node_transactions ORDER BY start -- binds: []"#
+        );
+        let statement = super::show_transactions()
+            .application_name("bookshop")
+            .older_than(Duration::from_secs(60));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"SELECT id::STRING, node_id, session_id, start, application_name, num_stmts, num_retries, num_auto_retries FROM crdb_internal.cluster_transactions WHERE application_name = $1 AND start < now()::TIMESTAMP - INTERVAL '60s' ORDER BY start -- binds: ["bookshop"]"#
+        );
+    }
+
+    #[test]
+    fn node_status() {
+        assert_eq!(
+            diesel::debug_query(&super::node_status().live()).to_string(),
+            r#"SELECT node_id, address, sql_address, locality, server_version, build_tag, started_at, is_live, ranges, leases FROM crdb_internal.gossip_nodes WHERE is_live ORDER BY node_id -- binds: []"#
+        );
+        let node = NodeStatus {
+            node_id: 1,
+            address: "localhost:26257".to_owned(),
+            sql_address: None,
+            locality: "region=us-east1".parse().unwrap(),
+            server_version: "22.2".to_owned(),
+            build_tag: "v22.2.3".to_owned(),
+            started_at: SystemTime::UNIX_EPOCH,
+            is_live: true,
+            ranges: 40,
+            leases: 12,
+        };
+        assert_eq!(node.version(), Some(ClusterVersion::new(22, 2, 3)));
+    }
+
+    #[test]
+    fn show_hot_ranges() {
+        assert_eq!(
+            diesel::debug_query(&super::show_hot_ranges().limit(10)).to_string(),
+            r#"SELECT range_id, qps, database_name, table_name, index_name FROM [SHOW HOT RANGES] ORDER BY qps DESC LIMIT 10 -- binds: []"#
+        );
+        assert!(ClusterVersion::new(22, 2, 0)
+            .check(&super::show_hot_ranges())
+            .is_ok());
+        assert_eq!(
+            ClusterVersion::new(22, 1, 0)
+                .check(&super::show_hot_ranges())
+                .unwrap_err()
+                .to_string(),
+            "SHOW HOT RANGES is not supported on CockroachDB v22.1.0, and requires v22.2 or later"
+        );
+    }
+
+    #[test]
+    fn consistency() {
+        assert_eq!(
+            diesel::debug_query(&check_consistency()).to_string(),
+            r#"SELECT range_id, start_key, start_key_pretty, status, detail FROM crdb_internal.check_consistency($1, $2, $3) -- binds: [false, [2], [255, 255]]"#
+        );
+        assert_eq!(
+            diesel::debug_query(
+                &check_consistency()
+                    .stats_only()
+                    .keys(vec![0xf0], vec![0xf1])
+            )
+            .to_string(),
+            r#"SELECT range_id, start_key, start_key_pretty, status, detail FROM crdb_internal.check_consistency($1, $2, $3) -- binds: [true, [240], [241]]"#
+        );
+        assert!("RANGE_CONSISTENT_STATS_ESTIMATED"
+            .parse::<ConsistencyStatus>()
+            .unwrap()
+            .is_consistent());
+        assert!(!ConsistencyStatus::Indeterminate.is_consistent());
+    }
+
+    #[test]
+    fn full_scans() {
+        assert_eq!(
+            diesel::debug_query(&full_table_scans().app_name("bookshop")).to_string(),
+            r#"SELECT encode(fingerprint_id, 'hex'), app_name, metadata->>'query', metadata->>'db', sum((statistics->'statistics'->>'cnt')::INT8)::INT8, max(aggregated_ts) FROM crdb_internal.statement_statistics WHERE (metadata->>'fullScan')::BOOL AND app_name NOT LIKE '$ internal%' AND app_name = $1 GROUP BY 1, 2, 3, 4 ORDER BY 5 DESC, 1 -- binds: ["bookshop"]"#
+        );
+    }
+
+    #[test]
+    fn contention_events() {
+        assert_eq!(
+            diesel::debug_query(&transaction_contention_events()).to_string(),
+            r#"SELECT e.collection_ts, e.blocking_txn_id::STRING, encode(e.blocking_txn_fingerprint_id, 'hex'), e.waiting_txn_id::STRING, encode(e.waiting_txn_fingerprint_id, 'hex'), (extract(epoch FROM e.contention_duration) * 1000000)::INT8, e.contending_key, crdb_internal.pretty_key(e.contending_key, 0), t.database_name, t.name FROM crdb_internal.transaction_contention_events AS e LEFT JOIN crdb_internal.tables AS t ON t.table_id::STRING = substring(crdb_internal.pretty_key(e.contending_key, 0), '^(?:/Tenant/\d+)?/Table/(\d+)') ORDER BY e.collection_ts -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn contention_events_version() {
+        let query = transaction_contention_events();
+        assert!(ClusterVersion::new(23, 1, 0).check(&query).is_ok());
+        assert_eq!(
+            ClusterVersion::new(22, 1, 12).check(&query).unwrap_err(),
+            Unsupported {
+                feature: "crdb_internal.transaction_contention_events",
+                required: ClusterVersion::new(22, 2, 0),
+                version: ClusterVersion::new(22, 1, 12),
+            }
+        );
+    }
+
+    #[test]
+    fn contention_tables() {
+        let event = |table: &str, fingerprint: &str, millis: i64| {
+            ContentionEvent::build((
+                SystemTime::UNIX_EPOCH,
+                "blocking".to_owned(),
+                "aa".to_owned(),
+                "waiting".to_owned(),
+                fingerprint.to_owned(),
+                millis * 1000,
+                vec![],
+                format!("/Table/104/1/{}", table),
+                Some("bookshop".to_owned()),
+                Some(table.to_owned()),
+            ))
+        };
+        let events = [
+            event("books", "01", 10),
+            event("authors", "02", 50),
+            event("books", "03", 30),
+            event("books", "01", 30),
+        ];
+        let tables = contention_by_table(&events);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].table_name.as_deref(), Some("books"));
+        assert_eq!(tables[0].events, 3);
+        assert_eq!(tables[0].total_duration, Duration::from_millis(70));
+        assert_eq!(
+            tables[0].waiting_fingerprints,
+            vec![
+                ("01".to_owned(), Duration::from_millis(40)),
+                ("03".to_owned(), Duration::from_millis(30)),
+            ]
+        );
+        assert_eq!(tables[1].table_name.as_deref(), Some("authors"));
+    }
+}
@@ -0,0 +1,637 @@
+//! Support for `ALTER TABLE` SQL queries.
+
+use crate::{
+    column_list::{push_identifiers, ColumnList},
+    migrations::Reversible,
+    quote,
+    storage_params::{self, StorageParam, StorageParams},
+};
+use diesel::{
+    expression::AsExpression,
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::{Column, QuerySource},
+    result::QueryResult,
+};
+
+/// Creates an `ALTER TABLE` statement for the target table.
+///
+/// The alteration to make is chosen by calling one of the methods of
+/// [`IncompleteAlterTableStatement`].
+///
+/// [`IncompleteAlterTableStatement`]: struct.IncompleteAlterTableStatement.html
+pub fn alter_table<T>(target: T) -> IncompleteAlterTableStatement<T> {
+    IncompleteAlterTableStatement::new(target)
+}
+
+/// The structure returned by [`alter_table`].
+///
+/// Each method chooses an alteration to make, and returns the complete
+/// statement.
+///
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+pub struct IncompleteAlterTableStatement<T> {
+    target: T,
+    if_exists: bool,
+}
+
+impl<T> IncompleteAlterTableStatement<T> {
+    fn new(target: T) -> Self {
+        IncompleteAlterTableStatement {
+            target,
+            if_exists: false,
+        }
+    }
+
+    /// Do nothing if the table does not exist.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+
+    /// Change the primary key of the table to the given columns.
+    ///
+    /// The previous primary key is kept as a secondary unique index. Existing
+    /// data is rewritten in the background, as an online schema change.
+    ///
+    /// ```sql
+    /// ALTER TABLE books ALTER PRIMARY KEY USING COLUMNS (author, id);
+    /// ```
+    pub fn alter_primary_key<C, K>(self, columns: C) -> AlterTableStatement<T, AlterPrimaryKey>
+    where
+        C: ColumnList<K, Table = T>,
+    {
+        AlterTableStatement::new(
+            self,
+            AlterPrimaryKey {
+                columns: columns.names(),
+                hash: None,
+            },
+        )
+    }
+
+    /// Set storage parameters on the table.
+    ///
+    /// ```sql
+    /// ALTER TABLE events SET (ttl_expire_after = '2592000s');
+    /// ```
+    pub fn set_storage_params(
+        self,
+        storage_params: StorageParams,
+    ) -> AlterTableStatement<T, SetStorageParams> {
+        AlterTableStatement::new(self, SetStorageParams(storage_params))
+    }
+
+    /// Add a `UNIQUE WITHOUT INDEX` constraint on the given columns.
+    ///
+    /// Uniqueness is enforced without creating an index, which is mostly
+    /// useful for `REGIONAL BY ROW` tables, where columns such as a UUID
+    /// primary key are unique without being unique per region.
+    ///
+    /// ```sql
+    /// ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE WITHOUT INDEX (email);
+    /// ```
+    pub fn add_unique_without_index<C, K>(
+        self,
+        name: impl Into<String>,
+        columns: C,
+    ) -> AlterTableStatement<T, AddUniqueWithoutIndex>
+    where
+        C: ColumnList<K, Table = T>,
+    {
+        AlterTableStatement::new(
+            self,
+            AddUniqueWithoutIndex {
+                name: name.into(),
+                columns: columns.names(),
+                not_valid: false,
+            },
+        )
+    }
+
+    /// Validate a constraint which was added as `NOT VALID`, checking that
+    /// existing rows satisfy it.
+    ///
+    /// ```sql
+    /// ALTER TABLE users VALIDATE CONSTRAINT users_email_key;
+    /// ```
+    pub fn validate_constraint(
+        self,
+        name: impl Into<String>,
+    ) -> AlterTableStatement<T, ValidateConstraint> {
+        AlterTableStatement::new(self, ValidateConstraint(name.into()))
+    }
+
+    /// Set an expression to assign to the column whenever a row is updated
+    /// without explicitly setting the column, such as to track when each row
+    /// was last modified.
+    ///
+    /// The expression is embedded in the statement, so must not contain any
+    /// bind parameters, such as a plain Rust value. Expressions with binds
+    /// are an error when the statement is built.
+    ///
+    /// ```sql
+    /// ALTER TABLE books ALTER COLUMN updated_at SET ON UPDATE CURRENT_TIMESTAMP;
+    /// ```
+    pub fn set_on_update<C, E>(
+        self,
+        column: C,
+        expression: E,
+    ) -> AlterTableStatement<T, SetOnUpdate<E::Expression>>
+    where
+        C: Column<Table = T>,
+        E: AsExpression<C::SqlType>,
+    {
+        let _ = column;
+        AlterTableStatement::new(
+            self,
+            SetOnUpdate {
+                column: C::NAME,
+                expression: expression.as_expression(),
+            },
+        )
+    }
+
+    /// Remove any `ON UPDATE` expression from the column.
+    ///
+    /// ```sql
+    /// ALTER TABLE books ALTER COLUMN updated_at DROP ON UPDATE;
+    /// ```
+    pub fn drop_on_update<C>(self, column: C) -> AlterTableStatement<T, DropOnUpdate>
+    where
+        C: Column<Table = T>,
+    {
+        let _ = column;
+        AlterTableStatement::new(self, DropOnUpdate { column: C::NAME })
+    }
+
+    /// Enable or disable auditing of the table.
+    ///
+    /// With auditing enabled, every read and write of the table is logged to
+    /// the `SENSITIVE_ACCESS` logging channel.
+    ///
+    /// ```sql
+    /// ALTER TABLE customers EXPERIMENTAL_AUDIT SET READ WRITE;
+    /// ```
+    pub fn experimental_audit(self, mode: AuditMode) -> AlterTableStatement<T, ExperimentalAudit> {
+        AlterTableStatement::new(self, ExperimentalAudit(mode))
+    }
+
+    /// Reset storage parameters on the table to their defaults.
+    ///
+    /// ```sql
+    /// ALTER TABLE events RESET (ttl_expire_after);
+    /// ```
+    pub fn reset_storage_params(
+        self,
+        params: Vec<StorageParam>,
+    ) -> AlterTableStatement<T, ResetStorageParams> {
+        AlterTableStatement::new(self, ResetStorageParams(params))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `ALTER TABLE` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table we are altering
+/// - `A`: The alteration being made
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-table.html
+pub struct AlterTableStatement<T, A> {
+    target: T,
+    if_exists: bool,
+    action: A,
+}
+
+impl<T, A> AlterTableStatement<T, A> {
+    pub(crate) fn new(incomplete: IncompleteAlterTableStatement<T>, action: A) -> Self {
+        AlterTableStatement {
+            target: incomplete.target,
+            if_exists: incomplete.if_exists,
+            action,
+        }
+    }
+}
+
+/// The `ALTER PRIMARY KEY` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`alter_primary_key`] on the result of [`alter_table`].
+///
+/// [`alter_primary_key`]: struct.IncompleteAlterTableStatement.html#method.alter_primary_key
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlterPrimaryKey {
+    columns: Vec<&'static str>,
+    hash: Option<Option<u32>>,
+}
+
+impl<T> AlterTableStatement<T, AlterPrimaryKey> {
+    /// Make the new primary key hash-sharded, spreading sequential keys
+    /// across ranges to avoid a single hot range.
+    ///
+    /// ```sql
+    /// ALTER TABLE events ALTER PRIMARY KEY USING COLUMNS (ts) USING HASH;
+    /// ```
+    pub fn using_hash(mut self) -> Self {
+        self.action.hash = Some(None);
+        self
+    }
+
+    /// Make the new primary key hash-sharded into the given number of buckets.
+    ///
+    /// ```sql
+    /// ALTER TABLE events ALTER PRIMARY KEY USING COLUMNS (ts) USING HASH WITH (bucket_count = 8);
+    /// ```
+    pub fn using_hash_with_bucket_count(mut self, bucket_count: u32) -> Self {
+        self.action.hash = Some(Some(bucket_count));
+        self
+    }
+}
+
+impl QueryFragment<Pg> for AlterPrimaryKey {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("ALTER PRIMARY KEY USING COLUMNS (");
+        push_identifiers(&mut out, &self.columns)?;
+        out.push_sql(")");
+        match self.hash {
+            Some(Some(bucket_count)) => out.push_sql(&format!(
+                " USING HASH WITH (bucket_count = {})",
+                bucket_count
+            )),
+            Some(None) => out.push_sql(" USING HASH"),
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// The `SET (...)` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`set_storage_params`] on the result of [`alter_table`].
+///
+/// [`set_storage_params`]: struct.IncompleteAlterTableStatement.html#method.set_storage_params
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetStorageParams(StorageParams);
+
+impl QueryFragment<Pg> for SetStorageParams {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("SET ");
+        self.0.walk_ast(out.reborrow())
+    }
+}
+
+/// The `RESET (...)` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`reset_storage_params`] on the result of [`alter_table`].
+///
+/// [`reset_storage_params`]: struct.IncompleteAlterTableStatement.html#method.reset_storage_params
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResetStorageParams(Vec<StorageParam>);
+
+impl QueryFragment<Pg> for ResetStorageParams {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("RESET ");
+        storage_params::push_names(&mut out, &self.0)
+    }
+}
+
+/// The `ADD CONSTRAINT ... UNIQUE WITHOUT INDEX` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`add_unique_without_index`] on the result of [`alter_table`].
+///
+/// [`add_unique_without_index`]: struct.IncompleteAlterTableStatement.html#method.add_unique_without_index
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddUniqueWithoutIndex {
+    name: String,
+    columns: Vec<&'static str>,
+    not_valid: bool,
+}
+
+impl<T> AlterTableStatement<T, AddUniqueWithoutIndex> {
+    /// Only enforce the constraint for new writes, without checking existing
+    /// rows. The constraint can later be checked with [`validate_constraint`].
+    ///
+    /// [`validate_constraint`]: struct.IncompleteAlterTableStatement.html#method.validate_constraint
+    pub fn not_valid(mut self) -> Self {
+        self.action.not_valid = true;
+        self
+    }
+}
+
+impl QueryFragment<Pg> for AddUniqueWithoutIndex {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("ADD CONSTRAINT ");
+        out.push_identifier(&self.name)?;
+        out.push_sql(" UNIQUE WITHOUT INDEX (");
+        push_identifiers(&mut out, &self.columns)?;
+        out.push_sql(")");
+        if self.not_valid {
+            out.push_sql(" NOT VALID");
+        }
+        Ok(())
+    }
+}
+
+/// The `VALIDATE CONSTRAINT` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`validate_constraint`] on the result of [`alter_table`].
+///
+/// [`validate_constraint`]: struct.IncompleteAlterTableStatement.html#method.validate_constraint
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidateConstraint(String);
+
+impl QueryFragment<Pg> for ValidateConstraint {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("VALIDATE CONSTRAINT ");
+        out.push_identifier(&self.0)
+    }
+}
+
+/// The `ALTER COLUMN ... SET ON UPDATE` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`set_on_update`] on the result of [`alter_table`].
+///
+/// [`set_on_update`]: struct.IncompleteAlterTableStatement.html#method.set_on_update
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, Copy)]
+pub struct SetOnUpdate<E> {
+    column: &'static str,
+    expression: E,
+}
+
+impl<E> QueryFragment<Pg> for SetOnUpdate<E>
+where
+    E: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("ALTER COLUMN ");
+        out.push_identifier(self.column)?;
+        out.push_sql(" SET ON UPDATE ");
+        out.push_sql(&quote::render_unbound(&self.expression)?);
+        Ok(())
+    }
+}
+
+/// The `ALTER COLUMN ... DROP ON UPDATE` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`drop_on_update`] on the result of [`alter_table`].
+///
+/// [`drop_on_update`]: struct.IncompleteAlterTableStatement.html#method.drop_on_update
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DropOnUpdate {
+    column: &'static str,
+}
+
+impl QueryFragment<Pg> for DropOnUpdate {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("ALTER COLUMN ");
+        out.push_identifier(self.column)?;
+        out.push_sql(" DROP ON UPDATE");
+        Ok(())
+    }
+}
+
+/// The auditing mode set with [`experimental_audit`].
+///
+/// [`experimental_audit`]: struct.IncompleteAlterTableStatement.html#method.experimental_audit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditMode {
+    /// `READ WRITE`: log all reads and writes of the table.
+    ReadWrite,
+    /// `OFF`: disable auditing of the table.
+    Off,
+}
+
+/// The `EXPERIMENTAL_AUDIT` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`experimental_audit`] on the result of [`alter_table`].
+///
+/// [`experimental_audit`]: struct.IncompleteAlterTableStatement.html#method.experimental_audit
+/// [`alter_table`]: fn.alter_table.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExperimentalAudit(AuditMode);
+
+impl QueryFragment<Pg> for ExperimentalAudit {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("EXPERIMENTAL_AUDIT SET ");
+        out.push_sql(match self.0 {
+            AuditMode::ReadWrite => "READ WRITE",
+            AuditMode::Off => "OFF",
+        });
+        Ok(())
+    }
+}
+
+impl<T, A> QueryFragment<Pg> for AlterTableStatement<T, A>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+    A: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("ALTER TABLE ");
+        if self.if_exists {
+            out.push_sql("IF EXISTS ");
+        }
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(" ");
+        self.action.walk_ast(out.reborrow())?;
+        Ok(())
+    }
+}
+
+impl<T, A> AlterTableStatement<T, A>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    /// Renders an `ALTER TABLE` statement on the same table, making the
+    /// given alteration instead.
+    fn render_with<B: QueryFragment<Pg>>(&self, action: B) -> QueryResult<String> {
+        Ok(format!(
+            "ALTER TABLE {}{} {}",
+            if self.if_exists { "IF EXISTS " } else { "" },
+            quote::render(&self.target.from_clause())?,
+            quote::render(&action)?
+        ))
+    }
+}
+
+impl<T> Reversible for AlterTableStatement<T, SetStorageParams>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    /// Resets the storage parameters which were set.
+    fn reverse_sql(&self) -> QueryResult<Option<String>> {
+        self.render_with(ResetStorageParams(self.action.0.names()))
+            .map(Some)
+    }
+}
+
+impl<T> Reversible for AlterTableStatement<T, AddUniqueWithoutIndex>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    /// Drops the constraint.
+    fn reverse_sql(&self) -> QueryResult<Option<String>> {
+        self.render_with(DropConstraint(&self.action.name))
+            .map(Some)
+    }
+}
+
+/// Renders `DROP CONSTRAINT`, when reversing a statement adding a constraint.
+struct DropConstraint<'a>(&'a str);
+
+impl<'a> QueryFragment<Pg> for DropConstraint<'a> {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("DROP CONSTRAINT ");
+        out.push_identifier(self.0)
+    }
+}
+
+impl<T, A> RunQueryDsl<PgConnection> for AlterTableStatement<T, A> {}
+
+impl<T, A> QueryId for AlterTableStatement<T, A> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        events (id) {
+            id -> Int8,
+            ts -> Timestamptz,
+            kind -> Text,
+            updated_at -> Nullable<Timestamptz>,
+        }
+    }
+
+    #[test]
+    fn alter_primary_key() {
+        let statement = alter_table(events::table).alter_primary_key((events::kind, events::id));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE "events" ALTER PRIMARY KEY USING COLUMNS ("kind", "id") -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn alter_primary_key_using_hash() {
+        let statement = alter_table(events::table)
+            .if_exists()
+            .alter_primary_key(events::ts)
+            .using_hash();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE IF EXISTS "events" ALTER PRIMARY KEY USING COLUMNS ("ts") USING HASH -- binds: []"#
+        );
+        let statement = alter_table(events::table)
+            .alter_primary_key(events::ts)
+            .using_hash_with_bucket_count(8);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE "events" ALTER PRIMARY KEY USING COLUMNS ("ts") USING HASH WITH (bucket_count = 8) -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn storage_params() {
+        let statement = alter_table(events::table).set_storage_params(
+            StorageParams::new()
+                .ttl_expire_after(std::time::Duration::from_secs(2_592_000))
+                .ttl_pause(false),
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE "events" SET (ttl_expire_after = '2592000s', ttl_pause = false) -- binds: []"#
+        );
+        let statement = alter_table(events::table)
+            .reset_storage_params(vec![StorageParam::TtlExpireAfter, StorageParam::TtlPause]);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE "events" RESET (ttl_expire_after, ttl_pause) -- binds: []"#
+        );
+        let statement = alter_table(events::table)
+            .reset_storage_params(vec![StorageParam::Other("ttl_pause); --".to_owned())]);
+        assert!(quote::render(&statement).is_err());
+        let statement = alter_table(events::table).reset_storage_params(Vec::new());
+        assert!(quote::render(&statement).is_err());
+    }
+
+    #[test]
+    fn experimental_audit() {
+        assert_eq!(
+            diesel::debug_query(
+                &alter_table(events::table).experimental_audit(AuditMode::ReadWrite)
+            )
+            .to_string(),
+            r#"ALTER TABLE "events" EXPERIMENTAL_AUDIT SET READ WRITE -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&alter_table(events::table).experimental_audit(AuditMode::Off))
+                .to_string(),
+            r#"ALTER TABLE "events" EXPERIMENTAL_AUDIT SET OFF -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn unique_without_index() {
+        let statement = alter_table(events::table)
+            .add_unique_without_index("events_kind_ts_key", (events::kind, events::ts));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE "events" ADD CONSTRAINT "events_kind_ts_key" UNIQUE WITHOUT INDEX ("kind", "ts") -- binds: []"#
+        );
+        let statement = alter_table(events::table)
+            .add_unique_without_index("events_kind_key", events::kind)
+            .not_valid();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE "events" ADD CONSTRAINT "events_kind_key" UNIQUE WITHOUT INDEX ("kind") NOT VALID -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&alter_table(events::table).validate_constraint("events_kind_key"))
+                .to_string(),
+            r#"ALTER TABLE "events" VALIDATE CONSTRAINT "events_kind_key" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn on_update() {
+        let statement =
+            alter_table(events::table).set_on_update(events::updated_at, diesel::dsl::now);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER TABLE "events" ALTER COLUMN "updated_at" SET ON UPDATE CURRENT_TIMESTAMP -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&alter_table(events::table).drop_on_update(events::updated_at))
+                .to_string(),
+            r#"ALTER TABLE "events" ALTER COLUMN "updated_at" DROP ON UPDATE -- binds: []"#
+        );
+        let statement = alter_table(events::table).set_on_update(events::kind, "updated");
+        assert!(quote::render(&statement).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Support for querying `ARRAY` columns.
+//!
+//! Diesel's `PgArrayExpressionMethods` only applies to `NOT NULL` arrays.
+//! [`ArrayExpressionMethods`] provides the same operators for nullable and
+//! non-nullable arrays, under names which do not clash when both traits are
+//! imported.
+//!
+//! CockroachDB can answer containment and overlap filters from an inverted
+//! index, created with `CREATE INVERTED INDEX ON posts (tags)`. Filters on
+//! [`array_position`], or on the elements of an array by subscript, need a
+//! full scan of the table.
+//!
+//! ```no_run
+//! # #[macro_use] extern crate diesel;
+//! # use diesel::{pg::PgConnection, Connection, QueryDsl, RunQueryDsl};
+//! # table! { posts (id) { id -> Int8, tags -> Nullable<Array<Text>>, } }
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::array::ArrayExpressionMethods;
+//!
+//! let ids: Vec<i64> = posts::table
+//!     .select(posts::id)
+//!     .filter(posts::tags.array_overlaps(vec!["rust", "sql"]))
+//!     .load(&connection)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! See the [CockroachDB docs].
+//!
+//! [`ArrayExpressionMethods`]: trait.ArrayExpressionMethods.html
+//! [`array_position`]: fn.array_position.html
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/inverted-indexes.html
+
+use diesel::{
+    expression::{AsExpression, Expression},
+    pg::Pg,
+    sql_types::{Array, Integer, Nullable},
+};
+
+diesel_infix_operator!(Contains, " @> ", backend: Pg);
+diesel_infix_operator!(ContainedBy, " <@ ", backend: Pg);
+diesel_infix_operator!(Overlaps, " && ", backend: Pg);
+
+sql_function! {
+    /// The `array_position()` function, returning the index of the first
+    /// occurrence of `element` in `array`, starting from `1`, or `NULL` if it
+    /// does not occur.
+    fn array_position<ST>(array: Array<ST>, element: ST) -> Nullable<Integer>;
+}
+
+sql_function! {
+    /// The `array_remove()` function, returning `array` with every occurrence
+    /// of `element` removed.
+    fn array_remove<ST>(array: Array<ST>, element: ST) -> Array<ST>;
+}
+
+/// The SQL types of arrays, which may be `NULL`.
+pub trait ArrayType {}
+
+impl<ST> ArrayType for Array<ST> {}
+
+impl<ST> ArrayType for Nullable<Array<ST>> {}
+
+/// Adds array operators to array expressions.
+///
+/// `other` must have the same SQL type as this expression, so for nullable
+/// columns may be an `Option<Vec<T>>` or a `Vec<T>`.
+pub trait ArrayExpressionMethods: Expression + Sized
+where
+    Self::SqlType: ArrayType,
+{
+    /// Creates a `@>` expression, which is true when this array contains
+    /// every element of `other`. May use an inverted index.
+    ///
+    /// ```sql
+    /// tags @> ARRAY['rust']
+    /// ```
+    fn array_contains<T>(self, other: T) -> Contains<Self, T::Expression>
+    where
+        T: AsExpression<Self::SqlType>,
+    {
+        Contains::new(self, other.as_expression())
+    }
+
+    /// Creates a `<@` expression, which is true when every element of this
+    /// array is in `other`. May use an inverted index.
+    ///
+    /// ```sql
+    /// tags <@ ARRAY['rust', 'sql']
+    /// ```
+    fn array_contained_by<T>(self, other: T) -> ContainedBy<Self, T::Expression>
+    where
+        T: AsExpression<Self::SqlType>,
+    {
+        ContainedBy::new(self, other.as_expression())
+    }
+
+    /// Creates a `&&` expression, which is true when this array and `other`
+    /// have any element in common. May use an inverted index.
+    ///
+    /// ```sql
+    /// tags && ARRAY['rust', 'sql']
+    /// ```
+    fn array_overlaps<T>(self, other: T) -> Overlaps<Self, T::Expression>
+    where
+        T: AsExpression<Self::SqlType>,
+    {
+        Overlaps::new(self, other.as_expression())
+    }
+}
+
+impl<T> ArrayExpressionMethods for T
+where
+    T: Expression,
+    T::SqlType: ArrayType,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        posts (id) {
+            id -> Int8,
+            tags -> Array<Text>,
+            labels -> Nullable<Array<Text>>,
+        }
+    }
+
+    #[test]
+    fn operators() {
+        let query = posts::table
+            .select(posts::id)
+            .filter(posts::tags.array_contains(vec!["rust"]))
+            .filter(posts::labels.array_contained_by(vec!["a", "b"]))
+            .filter(posts::labels.array_overlaps(None::<Vec<String>>));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "posts"."id" FROM "posts" WHERE "posts"."tags" @> $1 AND "posts"."labels" <@ $2 AND "posts"."labels" && $3 -- binds: [["rust"], ["a", "b"], None]"#
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let query = posts::table
+            .select(array_remove(posts::tags, "draft"))
+            .filter(array_position(posts::tags, "rust").eq(1));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT array_remove("posts"."tags", $1) FROM "posts" WHERE array_position("posts"."tags", $2) = $3 -- binds: ["draft", "rust", 1]"#
+        );
+    }
+}
@@ -0,0 +1,628 @@
+//! Support for the `AS OF SYSTEM TIME` clause, for reading historical data.
+
+use crate::{
+    data_types::Interval,
+    quote,
+    sql_types::HlcDecimal,
+    version::{ClusterVersion, Unsupported, VersionCheck},
+};
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{
+        data_types::{PgNumeric, PgTimestamp},
+        Pg, PgConnection,
+    },
+    query_builder::{AsQuery, AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    serialize::{self, Output, ToSql},
+    sql_types::{Numeric, Text},
+    AsExpression, FromSqlRow,
+};
+use std::{
+    convert::TryFrom,
+    error, fmt,
+    io::Write,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The timestamp of an `AS OF SYSTEM TIME` clause.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/as-of-system-time.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsOfSystemTime {
+    /// A fixed time in the past, relative to the statement time.
+    ///
+    /// ```sql
+    /// AS OF SYSTEM TIME '-10s'
+    /// ```
+    Ago(Duration),
+    /// An exact point in time.
+    ///
+    /// ```sql
+    /// AS OF SYSTEM TIME 1453716610555555000
+    /// ```
+    At(SystemTime),
+    /// An exact hybrid logical clock timestamp, such as the resolved
+    /// timestamp of a changefeed.
+    ///
+    /// ```sql
+    /// AS OF SYSTEM TIME 1453716610555555000.0000000001
+    /// ```
+    Hlc(HlcTimestamp),
+    /// The most recent time at which a follower read can be served locally.
+    ///
+    /// ```sql
+    /// AS OF SYSTEM TIME follower_read_timestamp()
+    /// ```
+    FollowerReadTimestamp,
+    /// A bounded staleness read, at the most recent time no more than the
+    /// given duration ago at which the rows can be read locally.
+    ///
+    /// Bounded staleness reads are only supported for queries reading single
+    /// rows outside of an explicit transaction.
+    ///
+    /// Requires CockroachDB v21.2 or later.
+    ///
+    /// ```sql
+    /// AS OF SYSTEM TIME with_max_staleness('10s')
+    /// ```
+    MaxStaleness(Duration),
+}
+
+impl AsOfSystemTime {
+    /// A fixed time in the past, as [`AsOfSystemTime::Ago`], from a `Duration`
+    /// or an [`Interval`] parsed from configuration.
+    ///
+    /// ```
+    /// use diesel_cockroach::{as_of_system_time::AsOfSystemTime, data_types::Interval};
+    ///
+    /// let interval: Interval = "10s".parse().unwrap();
+    /// let timestamp = AsOfSystemTime::ago(interval);
+    /// ```
+    ///
+    /// [`AsOfSystemTime::Ago`]: #variant.Ago
+    /// [`Interval`]: ../data_types/struct.Interval.html
+    pub fn ago(interval: impl Into<Interval>) -> Self {
+        AsOfSystemTime::Ago(interval.into().into())
+    }
+
+    fn walk_timestamp(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        match self {
+            AsOfSystemTime::Ago(duration) => {
+                out.push_sql(&format!("'-{}'", quote::interval(*duration)))
+            }
+            AsOfSystemTime::At(time) => {
+                let since_epoch = time
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|err| diesel::result::Error::QueryBuilderError(Box::new(err)))?;
+                out.push_sql(&since_epoch.as_nanos().to_string())
+            }
+            AsOfSystemTime::Hlc(timestamp) => out.push_sql(&timestamp.to_string()),
+            AsOfSystemTime::FollowerReadTimestamp => out.push_sql("follower_read_timestamp()"),
+            AsOfSystemTime::MaxStaleness(duration) => out.push_sql(&format!(
+                "with_max_staleness('{}')",
+                quote::interval(*duration)
+            )),
+        }
+        Ok(())
+    }
+}
+
+impl VersionCheck for AsOfSystemTime {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        match self {
+            AsOfSystemTime::MaxStaleness(_) => version.require("bounded staleness reads", 21, 2),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl QueryFragment<Pg> for AsOfSystemTime {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("AS OF SYSTEM TIME ");
+        self.walk_timestamp(out)
+    }
+}
+
+/// A hybrid logical clock timestamp, as used by CockroachDB for transaction
+/// timestamps and changefeed resolved timestamps.
+///
+/// Timestamps are written as a decimal, with the wall time in nanoseconds
+/// since the Unix epoch before the point, and ten digits of logical counter
+/// after it. They order by wall time, then logical counter.
+///
+/// Timestamps are sent and received as a `DECIMAL` with the [`HlcDecimal`]
+/// or `Numeric` SQL types, or as text. They convert to and from
+/// `SystemTime` and `PgTimestamp` by their wall time, and may be used as an
+/// [`AsOfSystemTime`] or a changefeed cursor directly.
+///
+/// There is no `chrono` feature, as this crate does not depend on `chrono`.
+/// A `chrono::DateTime<Utc>` converts through `SystemTime` instead, with
+/// `HlcTimestamp::from(SystemTime::from(date_time))`.
+///
+/// ```
+/// use diesel_cockroach::as_of_system_time::HlcTimestamp;
+///
+/// let timestamp: HlcTimestamp = "1591300372587669851.0000000002".parse().unwrap();
+/// assert_eq!(timestamp, HlcTimestamp::new(1_591_300_372_587_669_851, 2));
+/// assert!(timestamp > HlcTimestamp::new(1_591_300_372_587_669_851, 1));
+/// ```
+///
+/// [`HlcDecimal`]: ../sql_types/struct.HlcDecimal.html
+/// [`AsOfSystemTime`]: enum.AsOfSystemTime.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, AsExpression, FromSqlRow)]
+#[sql_type = "HlcDecimal"]
+pub struct HlcTimestamp {
+    /// The wall time, in nanoseconds since the Unix epoch.
+    pub wall_time: i64,
+    /// The logical counter, ordering events with the same wall time.
+    pub logical: u32,
+}
+
+impl HlcTimestamp {
+    /// Create a timestamp from its parts.
+    pub const fn new(wall_time: i64, logical: u32) -> Self {
+        HlcTimestamp { wall_time, logical }
+    }
+
+    /// The wall time of this timestamp.
+    ///
+    /// Returns `None` for wall times before the Unix epoch.
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        if self.wall_time < 0 {
+            return None;
+        }
+        Some(UNIX_EPOCH + Duration::from_nanos(self.wall_time as u64))
+    }
+
+    /// A timestamp with the given wall time, and a logical counter of zero.
+    ///
+    /// Returns `None` for times before the Unix epoch, or too far in the
+    /// future to be represented.
+    pub fn from_system_time(time: SystemTime) -> Option<Self> {
+        let since_epoch = time.duration_since(UNIX_EPOCH).ok()?;
+        let wall_time = i64::try_from(since_epoch.as_nanos()).ok()?;
+        Some(HlcTimestamp::new(wall_time, 0))
+    }
+
+    /// The wall time of this timestamp, truncated to the microsecond
+    /// precision of a `TIMESTAMP`.
+    pub const fn to_pg_timestamp(self) -> PgTimestamp {
+        PgTimestamp(self.wall_time.div_euclid(1000) - PG_EPOCH_MICROS)
+    }
+
+    /// A timestamp with the wall time of a `TIMESTAMP`, and a logical counter
+    /// of zero.
+    ///
+    /// Returns `None` for times too far in the future to be represented.
+    pub fn from_pg_timestamp(timestamp: PgTimestamp) -> Option<Self> {
+        let wall_time = timestamp
+            .0
+            .checked_add(PG_EPOCH_MICROS)?
+            .checked_mul(1000)?;
+        Some(HlcTimestamp::new(wall_time, 0))
+    }
+
+    fn from_numeric(numeric: PgNumeric) -> Result<Self, InvalidHlcTimestamp> {
+        let (weight, digits) = match &numeric {
+            PgNumeric::Positive { weight, digits, .. } => (i32::from(*weight), digits),
+            _ => return Err(InvalidHlcTimestamp(format!("{:?}", numeric))),
+        };
+        // Digits are stored in base 10000, with `weight` the exponent of the
+        // first digit. Missing digits either side are zero.
+        let digit = |i: i32| {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| digits.get(i))
+                .copied()
+                .unwrap_or(0)
+        };
+        let mut s = digit(0).to_string();
+        for i in 1..=weight {
+            s.push_str(&format!("{:04}", digit(i)));
+        }
+        if weight < 0 {
+            s = "0".to_owned();
+        }
+        let mut fraction = String::new();
+        for i in (weight + 1)..(digits.len() as i32) {
+            fraction.push_str(&format!("{:04}", digit(i)));
+        }
+        s.push('.');
+        s.push_str(fraction.trim_end_matches('0'));
+        s.parse()
+    }
+
+    fn to_numeric(self) -> Result<PgNumeric, InvalidHlcTimestamp> {
+        if self.wall_time < 0 {
+            return Err(InvalidHlcTimestamp(self.to_string()));
+        }
+        let wall_time = self.wall_time.to_string();
+        let padding = (4 - wall_time.len() % 4) % 4;
+        let s = format!(
+            "{:0>2$}{:010}00",
+            wall_time,
+            self.logical,
+            wall_time.len() + padding
+        );
+        let mut digits: Vec<i16> = s
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0, |n, byte| n * 10 + i16::from(byte - b'0'))
+            })
+            .collect();
+        let mut weight = ((wall_time.len() + padding) / 4) as i16 - 1;
+        while digits.first() == Some(&0) {
+            digits.remove(0);
+            weight -= 1;
+        }
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            weight = 0;
+        }
+        Ok(PgNumeric::Positive {
+            weight,
+            scale: 10,
+            digits,
+        })
+    }
+}
+
+/// The Postgres epoch of 2000-01-01, in microseconds since the Unix epoch.
+const PG_EPOCH_MICROS: i64 = 946_684_800_000_000;
+
+impl From<HlcTimestamp> for AsOfSystemTime {
+    fn from(timestamp: HlcTimestamp) -> Self {
+        AsOfSystemTime::Hlc(timestamp)
+    }
+}
+
+impl fmt::Display for HlcTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:010}", self.wall_time, self.logical)
+    }
+}
+
+impl FromStr for HlcTimestamp {
+    type Err = InvalidHlcTimestamp;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidHlcTimestamp(s.to_owned());
+        let (wall_time, logical) = match s.find('.') {
+            Some(point) => (&s[..point], &s[point + 1..]),
+            None => (s, ""),
+        };
+        if logical.len() > 10 || !logical.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let wall_time = wall_time.parse().map_err(|_| invalid())?;
+        let logical = if logical.is_empty() {
+            0
+        } else {
+            // Shorter fractions are missing trailing zeros.
+            format!("{:0<10}", logical).parse().map_err(|_| invalid())?
+        };
+        Ok(HlcTimestamp::new(wall_time, logical))
+    }
+}
+
+/// Decodes the decimal rendered as a string, such as
+/// `high_water_timestamp::STRING`.
+impl FromSql<Text, Pg> for HlcTimestamp {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+impl FromSql<HlcDecimal, Pg> for HlcTimestamp {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let numeric = <PgNumeric as FromSql<Numeric, Pg>>::from_sql(bytes)?;
+        Ok(HlcTimestamp::from_numeric(numeric)?)
+    }
+}
+
+/// Timestamps with a wall time before the Unix epoch cannot be sent.
+impl ToSql<HlcDecimal, Pg> for HlcTimestamp {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <PgNumeric as ToSql<Numeric, Pg>>::to_sql(&self.to_numeric()?, out)
+    }
+}
+
+impl FromSql<Numeric, Pg> for HlcTimestamp {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <HlcTimestamp as FromSql<HlcDecimal, Pg>>::from_sql(bytes)
+    }
+}
+
+/// The error returned when parsing an invalid [`HlcTimestamp`].
+///
+/// [`HlcTimestamp`]: struct.HlcTimestamp.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidHlcTimestamp(pub String);
+
+impl fmt::Display for InvalidHlcTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid HLC timestamp: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidHlcTimestamp {}
+
+/// Serializes as the decimal string used by CockroachDB.
+///
+/// Requires the `json` feature.
+#[cfg(feature = "json")]
+impl serde::Serialize for HlcTimestamp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes from the decimal string used by CockroachDB.
+///
+/// Requires the `json` feature.
+#[cfg(feature = "json")]
+impl<'de> serde::Deserialize<'de> for HlcTimestamp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Adds the [`as_of_system_time`] method to all queries.
+///
+/// [`as_of_system_time`]: #method.as_of_system_time
+pub trait AsOfSystemTimeDsl: AsQuery + Sized {
+    /// Read the results of the query as of a historical timestamp.
+    ///
+    /// Since CockroachDB only accepts `AS OF SYSTEM TIME` directly after a
+    /// `FROM` clause, the query is wrapped as a subquery.
+    ///
+    /// ```sql
+    /// SELECT * FROM (SELECT id, title FROM books) AS OF SYSTEM TIME '-10s';
+    /// ```
+    #[allow(clippy::wrong_self_convention)]
+    fn as_of_system_time(
+        self,
+        as_of_system_time: AsOfSystemTime,
+    ) -> AsOfSystemTimeQuery<Self::Query> {
+        AsOfSystemTimeQuery {
+            query: self.as_query(),
+            as_of_system_time,
+        }
+    }
+}
+
+impl<Q: AsQuery> AsOfSystemTimeDsl for Q {}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A query reading historical data, created by [`as_of_system_time`].
+///
+/// The parameters of this struct represent:
+///
+/// - `Q`: The query being read as of a historical timestamp
+///
+/// [`as_of_system_time`]: trait.AsOfSystemTimeDsl.html#method.as_of_system_time
+pub struct AsOfSystemTimeQuery<Q> {
+    query: Q,
+    as_of_system_time: AsOfSystemTime,
+}
+
+impl<Q> QueryFragment<Pg> for AsOfSystemTimeQuery<Q>
+where
+    Q: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT * FROM (");
+        self.query.walk_ast(out.reborrow())?;
+        out.push_sql(") ");
+        self.as_of_system_time.walk_ast(out.reborrow())
+    }
+}
+
+impl<Q> VersionCheck for AsOfSystemTimeQuery<Q> {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        self.as_of_system_time.check_version(version)
+    }
+}
+
+impl<Q> Query for AsOfSystemTimeQuery<Q>
+where
+    Q: Query,
+{
+    type SqlType = Q::SqlType;
+}
+
+impl<Q> RunQueryDsl<PgConnection> for AsOfSystemTimeQuery<Q> {}
+
+impl<Q> QueryId for AsOfSystemTimeQuery<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn ago() {
+        assert_eq!(
+            diesel::debug_query(&AsOfSystemTime::Ago(Duration::from_secs(10))).to_string(),
+            r#"AS OF SYSTEM TIME '-10s' -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&AsOfSystemTime::Ago(Duration::from_micros(1500))).to_string(),
+            r#"AS OF SYSTEM TIME '-1500us' -- binds: []"#
+        );
+        assert_eq!(
+            AsOfSystemTime::ago("1m30s".parse::<Interval>().unwrap()),
+            AsOfSystemTime::Ago(Duration::from_secs(90))
+        );
+    }
+
+    #[test]
+    fn max_staleness() {
+        let as_of_system_time = AsOfSystemTime::MaxStaleness(Duration::from_secs(10));
+        assert_eq!(
+            diesel::debug_query(&as_of_system_time).to_string(),
+            r#"AS OF SYSTEM TIME with_max_staleness('10s') -- binds: []"#
+        );
+        let query = books::table.find(1).as_of_system_time(as_of_system_time);
+        assert!(ClusterVersion::new(21, 2, 0).check(&query).is_ok());
+        assert_eq!(
+            ClusterVersion::new(21, 1, 5)
+                .check(&query)
+                .unwrap_err()
+                .to_string(),
+            "bounded staleness reads is not supported on CockroachDB v21.1.5, and requires v21.2 or later"
+        );
+        assert!(ClusterVersion::new(21, 1, 5)
+            .check(&AsOfSystemTime::FollowerReadTimestamp)
+            .is_ok());
+    }
+
+    #[test]
+    fn at() {
+        let time = UNIX_EPOCH + Duration::from_nanos(1_453_716_610_555_555_000);
+        assert_eq!(
+            diesel::debug_query(&AsOfSystemTime::At(time)).to_string(),
+            r#"AS OF SYSTEM TIME 1453716610555555000 -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn hlc_conversions() {
+        let time = UNIX_EPOCH + Duration::from_nanos(1_591_300_372_587_669_851);
+        let timestamp = HlcTimestamp::from_system_time(time).unwrap();
+        assert_eq!(timestamp, HlcTimestamp::new(1_591_300_372_587_669_851, 0));
+        assert_eq!(timestamp.to_system_time(), Some(time));
+        assert_eq!(
+            HlcTimestamp::from_system_time(UNIX_EPOCH - Duration::from_secs(1)),
+            None
+        );
+
+        let pg_timestamp = HlcTimestamp::new(1_591_300_372_587_669_851, 2).to_pg_timestamp();
+        assert_eq!(pg_timestamp, PgTimestamp(644_615_572_587_669));
+        assert_eq!(
+            HlcTimestamp::from_pg_timestamp(pg_timestamp),
+            Some(HlcTimestamp::new(1_591_300_372_587_669_000, 0))
+        );
+        assert_eq!(
+            HlcTimestamp::new(0, 0).to_pg_timestamp(),
+            PgTimestamp(-PG_EPOCH_MICROS)
+        );
+        assert_eq!(HlcTimestamp::from_pg_timestamp(PgTimestamp(i64::MAX)), None);
+
+        assert_eq!(
+            AsOfSystemTime::from(HlcTimestamp::new(1, 2)),
+            AsOfSystemTime::Hlc(HlcTimestamp::new(1, 2))
+        );
+    }
+
+    #[test]
+    fn hlc_numeric() {
+        let numeric = |weight, digits: &[i16]| PgNumeric::Positive {
+            weight,
+            scale: 10,
+            digits: digits.to_vec(),
+        };
+        assert_eq!(
+            HlcTimestamp::from_numeric(numeric(4, &[159, 1300, 3725, 8766, 9851, 0, 0, 200])),
+            Ok(HlcTimestamp::new(1_591_300_372_587_669_851, 2))
+        );
+        assert_eq!(
+            HlcTimestamp::from_numeric(numeric(4, &[100])),
+            Ok(HlcTimestamp::new(1_000_000_000_000_000_000, 0))
+        );
+        assert_eq!(
+            HlcTimestamp::from_numeric(numeric(-2, &[1])),
+            Ok(HlcTimestamp::new(0, 100))
+        );
+        assert!(HlcTimestamp::from_numeric(PgNumeric::NaN).is_err());
+
+        for timestamp in &[
+            HlcTimestamp::new(1_591_300_372_587_669_851, 2),
+            HlcTimestamp::new(1_000_000_000_000_000_000, 0),
+            HlcTimestamp::new(12_345, 4_000_000_000),
+            HlcTimestamp::new(0, 100),
+            HlcTimestamp::new(0, 0),
+        ] {
+            let numeric = timestamp.to_numeric().unwrap();
+            assert_eq!(HlcTimestamp::from_numeric(numeric), Ok(*timestamp));
+        }
+        assert_eq!(
+            HlcTimestamp::new(1_000_000_000_000_000_000, 0).to_numeric(),
+            Ok(numeric(4, &[100]))
+        );
+        assert!(HlcTimestamp::new(-1, 0).to_numeric().is_err());
+    }
+
+    #[test]
+    fn hlc() {
+        let timestamp = HlcTimestamp::new(1_453_716_610_555_555_000, 1);
+        assert_eq!(
+            diesel::debug_query(&AsOfSystemTime::Hlc(timestamp)).to_string(),
+            r#"AS OF SYSTEM TIME 1453716610555555000.0000000001 -- binds: []"#
+        );
+        assert_eq!(timestamp.to_string().parse(), Ok(timestamp));
+        assert_eq!(
+            "1453716610555555000".parse(),
+            Ok(HlcTimestamp::new(1_453_716_610_555_555_000, 0))
+        );
+        assert_eq!(
+            "1453716610555555000.05".parse(),
+            Ok(HlcTimestamp::new(1_453_716_610_555_555_000, 500_000_000))
+        );
+        assert!("1453716610555555000.00000000001"
+            .parse::<HlcTimestamp>()
+            .is_err());
+        assert!("now".parse::<HlcTimestamp>().is_err());
+        assert_eq!(
+            timestamp.to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_nanos(1_453_716_610_555_555_000))
+        );
+    }
+
+    #[test]
+    fn follower_read() {
+        assert_eq!(
+            diesel::debug_query(&AsOfSystemTime::FollowerReadTimestamp).to_string(),
+            r#"AS OF SYSTEM TIME follower_read_timestamp() -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn query() {
+        let query = books::table
+            .select(books::title)
+            .filter(books::id.gt(10))
+            .as_of_system_time(AsOfSystemTime::FollowerReadTimestamp);
+        assert_eq!(
+            diesel::debug_query(&query).to_string(),
+            r#"SELECT * FROM (SELECT "books"."title" FROM "books" WHERE "books"."id" > $1) AS OF SYSTEM TIME follower_read_timestamp() -- binds: [10]"#
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//! Support for `BACKUP` SQL queries.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/backup.html
+
+use crate::{
+    as_of_system_time::AsOfSystemTime,
+    external_storage::ExternalStorageUri,
+    jobs::{BulkJobResultSqlType, Detached},
+    quote,
+};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{BigInt, Bool, Nullable, Text, Timestamp},
+    Queryable,
+};
+use std::time::SystemTime;
+
+/// Creates a `BACKUP` statement, backing up the whole cluster into a new
+/// backup in the collection at `uri`.
+///
+/// ```sql
+/// BACKUP INTO 's3://bucket/backups?AUTH=implicit';
+/// ```
+pub fn backup_cluster(uri: impl Into<ExternalStorageUri>) -> BackupStatement<ClusterTarget> {
+    BackupStatement::new(ClusterTarget, uri.into())
+}
+
+/// Creates a `BACKUP DATABASE` statement, backing up the named databases
+/// into a new backup in the collection at `uri`.
+///
+/// ```sql
+/// BACKUP DATABASE movr INTO 's3://bucket/backups?AUTH=implicit';
+/// ```
+pub fn backup_databases(
+    names: Vec<String>,
+    uri: impl Into<ExternalStorageUri>,
+) -> BackupStatement<DatabasesTarget> {
+    BackupStatement::new(DatabasesTarget(names), uri.into())
+}
+
+/// Creates a `BACKUP TABLE` statement, backing up the target table into a
+/// new backup in the collection at `uri`.
+///
+/// Once the backup job finishes, the statement returns a [`BulkJobResult`]:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{
+///     as_of_system_time::AsOfSystemTime, backup::backup_table, jobs::BulkJobResult,
+/// };
+/// use std::time::Duration;
+///
+/// let result: BulkJobResult = backup_table(books::table, "nodelocal://1/backups")
+///     .as_of_system_time(AsOfSystemTime::Ago(Duration::from_secs(10)))
+///     .revision_history()
+///     .get_result(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`BulkJobResult`]: ../jobs/struct.BulkJobResult.html
+pub fn backup_table<T>(
+    target: T,
+    uri: impl Into<ExternalStorageUri>,
+) -> BackupStatement<TableTarget<T>> {
+    BackupStatement::new(TableTarget(target), uri.into())
+}
+
+/// The whole cluster, for [`backup_cluster`] and [`restore_cluster`].
+///
+/// [`backup_cluster`]: fn.backup_cluster.html
+/// [`restore_cluster`]: ../restore/fn.restore_cluster.html
+#[derive(Debug, Clone, Copy)]
+pub struct ClusterTarget;
+
+impl QueryFragment<Pg> for ClusterTarget {
+    fn walk_ast(&self, _out: AstPass<Pg>) -> QueryResult<()> {
+        Ok(())
+    }
+}
+
+/// Databases, for [`backup_databases`] and [`restore_databases`].
+///
+/// [`backup_databases`]: fn.backup_databases.html
+/// [`restore_databases`]: ../restore/fn.restore_databases.html
+#[derive(Debug, Clone)]
+pub struct DatabasesTarget(pub(crate) Vec<String>);
+
+impl QueryFragment<Pg> for DatabasesTarget {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("DATABASE ");
+        for (i, name) in self.0.iter().enumerate() {
+            if i > 0 {
+                out.push_sql(", ");
+            }
+            out.push_identifier(name)?;
+        }
+        out.push_sql(" ");
+        Ok(())
+    }
+}
+
+/// A table, for [`backup_table`] and [`restore_table`].
+///
+/// [`backup_table`]: fn.backup_table.html
+/// [`restore_table`]: ../restore/fn.restore_table.html
+#[derive(Debug, Clone, Copy)]
+pub struct TableTarget<T>(pub(crate) T);
+
+impl<T> QueryFragment<Pg> for TableTarget<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("TABLE ");
+        self.0.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(" ");
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `BACKUP` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `O`: The objects being backed up
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/backup.html
+pub struct BackupStatement<O> {
+    target: O,
+    destination: BackupDestination,
+    uri: ExternalStorageUri,
+    as_of_system_time: Option<AsOfSystemTime>,
+    revision_history: bool,
+    incremental_location: Option<ExternalStorageUri>,
+    detached: bool,
+}
+
+/// Which backup in the collection a `BACKUP` statement writes to.
+#[derive(Debug, Clone)]
+enum BackupDestination {
+    /// A new full backup.
+    Full,
+    /// An incremental backup on top of the most recent full backup.
+    Latest,
+    /// An incremental backup on top of the full backup in a subdirectory.
+    Subdirectory(String),
+}
+
+impl<O> BackupStatement<O> {
+    fn new(target: O, uri: ExternalStorageUri) -> Self {
+        BackupStatement {
+            target,
+            destination: BackupDestination::Full,
+            uri,
+            as_of_system_time: None,
+            revision_history: false,
+            incremental_location: None,
+            detached: false,
+        }
+    }
+
+    /// Take an incremental backup, containing only the changes since the
+    /// previous backup in the most recent backup chain of the collection.
+    ///
+    /// ```sql
+    /// BACKUP INTO LATEST IN 'nodelocal://1/backups';
+    /// ```
+    pub fn into_latest(mut self) -> Self {
+        self.destination = BackupDestination::Latest;
+        self
+    }
+
+    /// Take an incremental backup on top of the full backup in the given
+    /// subdirectory of the collection, as listed by [`show_backups_in`].
+    ///
+    /// ```sql
+    /// BACKUP INTO '2022/10/14-120000.00' IN 'nodelocal://1/backups';
+    /// ```
+    ///
+    /// [`show_backups_in`]: fn.show_backups_in.html
+    pub fn into_subdirectory(mut self, subdirectory: impl Into<String>) -> Self {
+        self.destination = BackupDestination::Subdirectory(subdirectory.into());
+        self
+    }
+
+    /// Store incremental backups at a different URI to the full backups they
+    /// build on, such as a bucket with a shorter retention policy.
+    ///
+    /// The same location must be given when restoring from, or taking further
+    /// incremental backups in, the chain.
+    pub fn incremental_location(mut self, uri: impl Into<ExternalStorageUri>) -> Self {
+        self.incremental_location = Some(uri.into());
+        self
+    }
+
+    /// Back up data as of a historical timestamp, reducing contention with
+    /// foreground traffic.
+    ///
+    /// ```sql
+    /// BACKUP INTO 'nodelocal://1/backups' AS OF SYSTEM TIME '-10s';
+    /// ```
+    pub fn as_of_system_time(mut self, as_of_system_time: AsOfSystemTime) -> Self {
+        self.as_of_system_time = Some(as_of_system_time);
+        self
+    }
+
+    /// Store every revision of the data since the previous backup, allowing
+    /// restores to any point in time covered by the backup.
+    pub fn revision_history(mut self) -> Self {
+        self.revision_history = true;
+        self
+    }
+
+    /// Return the ID of the backup job as soon as it is created, rather than
+    /// waiting for it to finish.
+    pub fn detached(mut self) -> Detached<Self> {
+        self.detached = true;
+        Detached(self)
+    }
+}
+
+impl<O> QueryFragment<Pg> for BackupStatement<O>
+where
+    O: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("BACKUP ");
+        self.target.walk_ast(out.reborrow())?;
+        out.push_sql("INTO ");
+        match &self.destination {
+            BackupDestination::Full => {}
+            BackupDestination::Latest => out.push_sql("LATEST IN "),
+            BackupDestination::Subdirectory(subdirectory) => {
+                out.push_sql(&quote::string_literal(subdirectory));
+                out.push_sql(" IN ");
+            }
+        }
+        out.push_sql(&quote::string_literal(&self.uri.expose()));
+        if let Some(as_of_system_time) = &self.as_of_system_time {
+            out.push_sql(" ");
+            as_of_system_time.walk_ast(out.reborrow())?;
+        }
+        let mut options = Vec::new();
+        if self.revision_history {
+            options.push(("revision_history", None));
+        }
+        if let Some(incremental_location) = &self.incremental_location {
+            options.push(("incremental_location", Some(incremental_location.expose())));
+        }
+        if self.detached {
+            options.push(("detached", None));
+        }
+        quote::push_options(&mut out, &options)?;
+        Ok(())
+    }
+}
+
+impl<O> Query for BackupStatement<O> {
+    type SqlType = BulkJobResultSqlType;
+}
+
+impl<O> RunQueryDsl<PgConnection> for BackupStatement<O> {}
+
+impl<O> QueryId for BackupStatement<O> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW BACKUPS IN` statement, listing the subdirectories of the
+/// backups in the collection at `uri`, oldest first.
+///
+/// ```sql
+/// SHOW BACKUPS IN 'nodelocal://1/backups';
+/// ```
+pub fn show_backups_in(uri: impl Into<ExternalStorageUri>) -> ShowBackupsStatement {
+    ShowBackupsStatement { uri: uri.into() }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW BACKUPS IN` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-backup.html
+pub struct ShowBackupsStatement {
+    uri: ExternalStorageUri,
+}
+
+impl QueryFragment<Pg> for ShowBackupsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW BACKUPS IN ");
+        out.push_sql(&quote::string_literal(&self.uri.expose()));
+        Ok(())
+    }
+}
+
+impl Query for ShowBackupsStatement {
+    type SqlType = Text;
+}
+
+impl RunQueryDsl<PgConnection> for ShowBackupsStatement {}
+
+impl QueryId for ShowBackupsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW BACKUP` statement, listing the objects in the most recent
+/// backup in the collection at `uri`.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::backup::{show_backup, BackupObject};
+///
+/// let objects: Vec<BackupObject> = show_backup("nodelocal://1/backups").load(&connection)?;
+/// let total_bytes: i64 = objects.iter().filter_map(|object| object.size_bytes).sum();
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_backup(uri: impl Into<ExternalStorageUri>) -> ShowBackupStatement {
+    ShowBackupStatement {
+        subdirectory: None,
+        uri: uri.into(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW BACKUP` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-backup.html
+pub struct ShowBackupStatement {
+    subdirectory: Option<String>,
+    uri: ExternalStorageUri,
+}
+
+impl ShowBackupStatement {
+    /// Show the backup in the given subdirectory of the collection, as listed
+    /// by [`show_backups_in`], rather than the most recent backup.
+    ///
+    /// ```sql
+    /// SHOW BACKUP FROM '2022/10/14-120000.00' IN 'nodelocal://1/backups';
+    /// ```
+    ///
+    /// [`show_backups_in`]: fn.show_backups_in.html
+    pub fn from_subdirectory(mut self, subdirectory: impl Into<String>) -> Self {
+        self.subdirectory = Some(subdirectory.into());
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowBackupStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT database_name, parent_schema_name, object_name, object_type, backup_type, \
+             start_time, end_time, size_bytes, rows, is_full_cluster \
+             FROM [SHOW BACKUP FROM ",
+        );
+        match &self.subdirectory {
+            Some(subdirectory) => out.push_sql(&quote::string_literal(subdirectory)),
+            None => out.push_sql("LATEST"),
+        }
+        out.push_sql(" IN ");
+        out.push_sql(&quote::string_literal(&self.uri.expose()));
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl Query for ShowBackupStatement {
+    type SqlType = (
+        Nullable<Text>,
+        Nullable<Text>,
+        Text,
+        Text,
+        Text,
+        Nullable<Timestamp>,
+        Timestamp,
+        Nullable<BigInt>,
+        Nullable<BigInt>,
+        Bool,
+    );
+}
+
+impl RunQueryDsl<PgConnection> for ShowBackupStatement {}
+
+impl QueryId for ShowBackupStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// An object contained in a backup, as returned by [`show_backup`].
+///
+/// [`show_backup`]: fn.show_backup.html
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct BackupObject {
+    /// The database containing the object, or `None` for databases.
+    pub database_name: Option<String>,
+    /// The schema containing the object, or `None` for databases and schemas.
+    pub parent_schema_name: Option<String>,
+    /// The name of the object.
+    pub object_name: String,
+    /// The type of the object, such as `database`, `schema` or `table`.
+    pub object_type: String,
+    /// Whether the backup is `full` or `incremental`.
+    pub backup_type: String,
+    /// The start of the time covered by an incremental backup, or `None` for
+    /// full backups.
+    pub start_time: Option<SystemTime>,
+    /// The time as of which the data was backed up.
+    pub end_time: SystemTime,
+    /// The size of the backed up data, or `None` for objects without data.
+    pub size_bytes: Option<i64>,
+    /// The number of backed up rows, or `None` for objects without data.
+    pub rows: Option<i64>,
+    /// Whether the backup is of the whole cluster.
+    pub is_full_cluster: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn cluster() {
+        assert_eq!(
+            diesel::debug_query(&backup_cluster("nodelocal://1/backups")).to_string(),
+            r#"BACKUP INTO 'nodelocal://1/backups' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn redacted() {
+        use crate::external_storage::{ExternalStorageUri, S3Auth};
+
+        let auth = S3Auth::Specified {
+            access_key_id: "AKIAEXAMPLE".to_owned(),
+            secret_access_key: "top-secret".to_owned(),
+            session_token: None,
+        };
+        let statement = backup_table(books::table, ExternalStorageUri::s3("bucket", "backups", auth))
+            .incremental_location(
+                "s3://bucket/incrementals?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&AWS_SECRET_ACCESS_KEY=top-secret",
+            );
+        let debug = format!("{:?}", statement);
+        assert!(!debug.contains("top-secret"), "{}", debug);
+        assert!(debug.contains("AWS_SECRET_ACCESS_KEY=redacted"));
+        assert!(!format!(
+            "{:?}",
+            show_backup("azure://c/b?AZURE_ACCOUNT_KEY=top-secret")
+        )
+        .contains("top-secret"));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"BACKUP TABLE "books" INTO 's3://bucket/backups?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&AWS_SECRET_ACCESS_KEY=top-secret' WITH incremental_location = 's3://bucket/incrementals?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&AWS_SECRET_ACCESS_KEY=top-secret' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn databases() {
+        let statement = backup_databases(
+            vec!["movr".to_owned(), "bank".to_owned()],
+            "nodelocal://1/backups",
+        )
+        .as_of_system_time(AsOfSystemTime::FollowerReadTimestamp)
+        .detached();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"BACKUP DATABASE "movr", "bank" INTO 'nodelocal://1/backups' AS OF SYSTEM TIME follower_read_timestamp() WITH detached -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn table() {
+        let statement = backup_table(books::table, "nodelocal://1/backups")
+            .as_of_system_time(AsOfSystemTime::Ago(Duration::from_secs(10)))
+            .revision_history();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"BACKUP TABLE "books" INTO 'nodelocal://1/backups' AS OF SYSTEM TIME '-10s' WITH revision_history -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn incremental() {
+        let statement = backup_cluster("nodelocal://1/backups")
+            .into_latest()
+            .incremental_location("nodelocal://1/incrementals");
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"BACKUP INTO LATEST IN 'nodelocal://1/backups' WITH incremental_location = 'nodelocal://1/incrementals' -- binds: []"#
+        );
+        let statement = backup_table(books::table, "nodelocal://1/backups")
+            .into_subdirectory("2022/10/14-120000.00")
+            .revision_history()
+            .detached();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"BACKUP TABLE "books" INTO '2022/10/14-120000.00' IN 'nodelocal://1/backups' WITH revision_history, detached -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show_backups() {
+        assert_eq!(
+            diesel::debug_query(&show_backups_in("nodelocal://1/backups")).to_string(),
+            r#"SHOW BACKUPS IN 'nodelocal://1/backups' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show() {
+        assert_eq!(
+            diesel::debug_query(&show_backup("nodelocal://1/backups")).to_string(),
+            r#"SELECT database_name, parent_schema_name, object_name, object_type, backup_type, start_time, end_time, size_bytes, rows, is_full_cluster FROM [SHOW BACKUP FROM LATEST IN 'nodelocal://1/backups'] -- binds: []"#
+        );
+        let statement =
+            show_backup("nodelocal://1/backups").from_subdirectory("2022/10/14-120000.00");
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"SELECT database_name, parent_schema_name, object_name, object_type, backup_type, start_time, end_time, size_bytes, rows, is_full_cluster FROM [SHOW BACKUP FROM '2022/10/14-120000.00' IN 'nodelocal://1/backups'] -- binds: []"#
+        );
+    }
+}
@@ -0,0 +1,517 @@
+//! Helpers for deleting or updating many rows in small batches, rather than
+//! in a single large transaction.
+//!
+//! CockroachDB limits the size of transactions, and large transactions
+//! contend with foreground traffic for longer. These helpers repeatedly run a
+//! statement limited to a batch of rows, in primary key order, until no rows
+//! are left.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/bulk-delete-data.html
+
+use crate::as_of_system_time::AsOfSystemTime;
+use diesel::{
+    expression::{AppearsOnTable, Expression},
+    pg::{Pg, PgConnection},
+    query_builder::{AsChangeset, AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::Table,
+    result::QueryResult,
+    sql_types::{BigInt, Bool},
+};
+
+/// The default number of rows changed by each batch.
+const DEFAULT_BATCH_SIZE: u64 = 1000;
+
+/// The progress of a batched operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// The number of batches run so far.
+    pub batches: u64,
+    /// The number of rows changed so far.
+    pub rows: u64,
+    /// The number of matching rows counted before starting, if requested.
+    pub total_rows: Option<u64>,
+}
+
+/// Creates a batched delete of the rows of the target table matching
+/// `predicate`.
+///
+/// Each batch runs `DELETE FROM t WHERE <predicate> ORDER BY <pk> LIMIT n`,
+/// until a batch deletes no rows. Each batch is its own transaction, so
+/// rows matching `predicate` may be deleted while others are still left, if a
+/// later batch fails.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, ExpressionMethods};
+/// # table! { events (id) { id -> Int8, created_at -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{as_of_system_time::AsOfSystemTime, batch::delete_in_batches};
+/// use std::time::Duration;
+///
+/// let progress = delete_in_batches(events::table, events::created_at.lt(1_600_000_000))
+///     .batch_size(10_000)
+///     .count_first(AsOfSystemTime::Ago(Duration::from_secs(10)))
+///     .run(&connection, |progress| {
+///         println!("deleted {} of {:?} rows", progress.rows, progress.total_rows);
+///     })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn delete_in_batches<T, P>(target: T, predicate: P) -> BatchedDelete<T, P> {
+    BatchedDelete {
+        target,
+        predicate,
+        batch_size: DEFAULT_BATCH_SIZE,
+        count_first: None,
+    }
+}
+
+/// A batched delete, created by [`delete_in_batches`].
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table rows are deleted from
+/// - `P`: The predicate selecting the rows to delete
+///
+/// [`delete_in_batches`]: fn.delete_in_batches.html
+#[derive(Debug, Clone)]
+#[must_use = "Batches are only executed when calling `run`."]
+pub struct BatchedDelete<T, P> {
+    target: T,
+    predicate: P,
+    batch_size: u64,
+    count_first: Option<AsOfSystemTime>,
+}
+
+/// Builder methods shared by batched deletes and updates.
+macro_rules! batch_options {
+    () => {
+        /// Change at most this many rows in each batch. Defaults to 1000.
+        ///
+        /// The batch size must not be 0, or [`run`] returns an error.
+        ///
+        /// [`run`]: #method.run
+        pub fn batch_size(mut self, batch_size: u64) -> Self {
+            self.batch_size = batch_size;
+            self
+        }
+
+        /// Count the matching rows as of a historical timestamp before
+        /// starting, reported as [`BatchProgress::total_rows`]. Reading
+        /// historical data avoids contending with the batches, at the expense
+        /// of a slightly stale count.
+        ///
+        /// [`BatchProgress::total_rows`]: struct.BatchProgress.html#structfield.total_rows
+        pub fn count_first(mut self, as_of_system_time: AsOfSystemTime) -> Self {
+            self.count_first = Some(as_of_system_time);
+            self
+        }
+    };
+}
+
+impl<T, P> BatchedDelete<T, P>
+where
+    T: Table + Clone,
+    T::FromClause: QueryFragment<Pg>,
+    T::PrimaryKey: QueryFragment<Pg>,
+    P: Expression<SqlType = Bool> + AppearsOnTable<T> + QueryFragment<Pg> + Clone,
+{
+    batch_options!();
+
+    /// Deletes batches of rows until none are left, calling `on_progress`
+    /// after each batch. Returns the final progress.
+    pub fn run<F>(&self, conn: &PgConnection, on_progress: F) -> QueryResult<BatchProgress>
+    where
+        F: FnMut(&BatchProgress),
+    {
+        check_batch_size(self.batch_size)?;
+        let total_rows = count_first(conn, &self.target, &self.predicate, self.count_first)?;
+        let statement = BatchDeleteStatement {
+            target: self.target.clone(),
+            predicate: self.predicate.clone(),
+            batch_size: self.batch_size,
+        };
+        run_batches(|| statement.clone().execute(conn), total_rows, on_progress)
+    }
+}
+
+/// Creates a batched update, applying `changeset` to the rows of the target
+/// table matching `predicate`.
+///
+/// Each batch runs `UPDATE t SET <changeset> WHERE <predicate> ORDER BY <pk>
+/// LIMIT n`, until a batch updates no rows. The changeset must stop updated
+/// rows from matching `predicate`, or the batches would never finish. Each
+/// batch is its own transaction, so some matching rows may be updated while
+/// others are not, if a later batch fails.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, ExpressionMethods};
+/// # table! { books (id) { id -> Int8, title -> Text, title_lower -> Nullable<Text>, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel::dsl::sql;
+/// use diesel::sql_types::{Nullable, Text};
+/// use diesel_cockroach::batch::update_in_batches;
+///
+/// update_in_batches(
+///     books::table,
+///     books::title_lower.is_null(),
+///     books::title_lower.eq(sql::<Nullable<Text>>("lower(title)")),
+/// )
+/// .batch_size(5000)
+/// .run(&connection, |progress| println!("backfilled {} rows", progress.rows))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn update_in_batches<T, P, C>(
+    target: T,
+    predicate: P,
+    changeset: C,
+) -> BatchedUpdate<T, P, C::Changeset>
+where
+    C: AsChangeset<Target = T>,
+{
+    BatchedUpdate {
+        target,
+        predicate,
+        changeset: changeset.as_changeset(),
+        batch_size: DEFAULT_BATCH_SIZE,
+        count_first: None,
+    }
+}
+
+/// A batched update, created by [`update_in_batches`].
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table rows are updated in
+/// - `P`: The predicate selecting the rows to update
+/// - `C`: The changes made to each row
+///
+/// [`update_in_batches`]: fn.update_in_batches.html
+#[derive(Debug, Clone)]
+#[must_use = "Batches are only executed when calling `run`."]
+pub struct BatchedUpdate<T, P, C> {
+    target: T,
+    predicate: P,
+    changeset: C,
+    batch_size: u64,
+    count_first: Option<AsOfSystemTime>,
+}
+
+impl<T, P, C> BatchedUpdate<T, P, C>
+where
+    T: Table + Clone,
+    T::FromClause: QueryFragment<Pg>,
+    T::PrimaryKey: QueryFragment<Pg>,
+    P: Expression<SqlType = Bool> + AppearsOnTable<T> + QueryFragment<Pg> + Clone,
+    C: QueryFragment<Pg> + Clone,
+{
+    batch_options!();
+
+    /// Updates batches of rows until none are left, calling `on_progress`
+    /// after each batch. Returns the final progress.
+    pub fn run<F>(&self, conn: &PgConnection, on_progress: F) -> QueryResult<BatchProgress>
+    where
+        F: FnMut(&BatchProgress),
+    {
+        check_batch_size(self.batch_size)?;
+        let total_rows = count_first(conn, &self.target, &self.predicate, self.count_first)?;
+        let statement = BatchUpdateStatement {
+            target: self.target.clone(),
+            predicate: self.predicate.clone(),
+            changeset: self.changeset.clone(),
+            batch_size: self.batch_size,
+        };
+        run_batches(|| statement.clone().execute(conn), total_rows, on_progress)
+    }
+}
+
+/// Counts the rows matching `predicate`, if requested.
+fn count_first<T, P>(
+    conn: &PgConnection,
+    target: &T,
+    predicate: &P,
+    as_of_system_time: Option<AsOfSystemTime>,
+) -> QueryResult<Option<i64>>
+where
+    T: Table + Clone,
+    T::FromClause: QueryFragment<Pg>,
+    P: QueryFragment<Pg> + Clone,
+{
+    match as_of_system_time {
+        Some(as_of_system_time) => count_rows(target, predicate, as_of_system_time)
+            .get_result(conn)
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Checks that each batch can change at least one row, so the batches make
+/// progress.
+fn check_batch_size(batch_size: u64) -> QueryResult<()> {
+    if batch_size == 0 {
+        Err(diesel::result::Error::QueryBuilderError(
+            "Batch size must be greater than 0".into(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Renders the `LIMIT` clause of a batch.
+fn push_limit(out: &mut AstPass<Pg>, batch_size: u64) -> QueryResult<()> {
+    check_batch_size(batch_size)?;
+    out.push_sql(" LIMIT ");
+    out.push_sql(&batch_size.to_string());
+    Ok(())
+}
+
+/// Runs batches until one changes no rows.
+fn run_batches<B, F>(
+    mut run_batch: B,
+    total_rows: Option<i64>,
+    mut on_progress: F,
+) -> QueryResult<BatchProgress>
+where
+    B: FnMut() -> QueryResult<usize>,
+    F: FnMut(&BatchProgress),
+{
+    let mut progress = BatchProgress {
+        total_rows: total_rows.map(|total_rows| total_rows as u64),
+        ..BatchProgress::default()
+    };
+    loop {
+        let rows = run_batch()?;
+        if rows == 0 {
+            return Ok(progress);
+        }
+        progress.batches += 1;
+        progress.rows += rows as u64;
+        on_progress(&progress);
+    }
+}
+
+/// Creates a query counting the rows matching `predicate`.
+fn count_rows<T, P>(
+    target: &T,
+    predicate: &P,
+    as_of_system_time: AsOfSystemTime,
+) -> CountQuery<T, P>
+where
+    T: Clone,
+    P: Clone,
+{
+    CountQuery {
+        target: target.clone(),
+        predicate: predicate.clone(),
+        as_of_system_time,
+    }
+}
+
+/// `SELECT count(*) FROM t AS OF SYSTEM TIME ... WHERE <predicate>`
+#[derive(Debug, Clone)]
+struct CountQuery<T, P> {
+    target: T,
+    predicate: P,
+    as_of_system_time: AsOfSystemTime,
+}
+
+impl<T, P> QueryFragment<Pg> for CountQuery<T, P>
+where
+    T: Table,
+    T::FromClause: QueryFragment<Pg>,
+    P: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT count(*) FROM ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(" ");
+        self.as_of_system_time.walk_ast(out.reborrow())?;
+        out.push_sql(" WHERE ");
+        self.predicate.walk_ast(out.reborrow())
+    }
+}
+
+impl<T, P> Query for CountQuery<T, P> {
+    type SqlType = BigInt;
+}
+
+impl<T, P> RunQueryDsl<PgConnection> for CountQuery<T, P> {}
+
+impl<T, P> QueryId for CountQuery<T, P> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// `DELETE FROM t WHERE <predicate> ORDER BY <pk> LIMIT n`
+#[derive(Debug, Clone)]
+struct BatchDeleteStatement<T, P> {
+    target: T,
+    predicate: P,
+    batch_size: u64,
+}
+
+impl<T, P> QueryFragment<Pg> for BatchDeleteStatement<T, P>
+where
+    T: Table,
+    T::FromClause: QueryFragment<Pg>,
+    T::PrimaryKey: QueryFragment<Pg>,
+    P: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("DELETE FROM ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(" WHERE ");
+        self.predicate.walk_ast(out.reborrow())?;
+        out.push_sql(" ORDER BY ");
+        self.target.primary_key().walk_ast(out.reborrow())?;
+        push_limit(&mut out, self.batch_size)
+    }
+}
+
+impl<T, P> RunQueryDsl<PgConnection> for BatchDeleteStatement<T, P> {}
+
+impl<T, P> QueryId for BatchDeleteStatement<T, P> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// `UPDATE t SET <changeset> WHERE <predicate> ORDER BY <pk> LIMIT n`
+#[derive(Debug, Clone)]
+struct BatchUpdateStatement<T, P, C> {
+    target: T,
+    predicate: P,
+    changeset: C,
+    batch_size: u64,
+}
+
+impl<T, P, C> QueryFragment<Pg> for BatchUpdateStatement<T, P, C>
+where
+    T: Table,
+    T::FromClause: QueryFragment<Pg>,
+    T::PrimaryKey: QueryFragment<Pg>,
+    P: QueryFragment<Pg>,
+    C: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("UPDATE ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(" SET ");
+        self.changeset.walk_ast(out.reborrow())?;
+        out.push_sql(" WHERE ");
+        self.predicate.walk_ast(out.reborrow())?;
+        out.push_sql(" ORDER BY ");
+        self.target.primary_key().walk_ast(out.reborrow())?;
+        push_limit(&mut out, self.batch_size)
+    }
+}
+
+impl<T, P, C> RunQueryDsl<PgConnection> for BatchUpdateStatement<T, P, C> {}
+
+impl<T, P, C> QueryId for BatchUpdateStatement<T, P, C> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::ExpressionMethods;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    table! {
+        events (tenant_id, id) {
+            tenant_id -> Int8,
+            id -> Int8,
+            created_at -> Int8,
+        }
+    }
+
+    #[test]
+    fn delete() {
+        let statement = BatchDeleteStatement {
+            target: events::table,
+            predicate: events::created_at.lt(100),
+            batch_size: 500,
+        };
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"DELETE FROM "events" WHERE "events"."created_at" < $1 ORDER BY "events"."tenant_id", "events"."id" LIMIT 500 -- binds: [100]"#
+        );
+        let statement = BatchDeleteStatement {
+            batch_size: 0,
+            ..statement
+        };
+        assert!(crate::quote::render(&statement).is_err());
+        assert!(check_batch_size(0).is_err());
+    }
+
+    #[test]
+    fn update() {
+        let statement = BatchUpdateStatement {
+            target: events::table,
+            predicate: events::created_at.lt(100),
+            changeset: events::created_at.eq(100).as_changeset(),
+            batch_size: 500,
+        };
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"UPDATE "events" SET "created_at" = $1 WHERE "events"."created_at" < $2 ORDER BY "events"."tenant_id", "events"."id" LIMIT 500 -- binds: [100, 100]"#
+        );
+        let statement = BatchUpdateStatement {
+            batch_size: 0,
+            ..statement
+        };
+        assert!(crate::quote::render(&statement).is_err());
+    }
+
+    #[test]
+    fn count() {
+        let query = count_rows(
+            &events::table,
+            &events::created_at.lt(100),
+            AsOfSystemTime::Ago(Duration::from_secs(10)),
+        );
+        assert_eq!(
+            diesel::debug_query(&query).to_string(),
+            r#"SELECT count(*) FROM "events" AS OF SYSTEM TIME '-10s' WHERE "events"."created_at" < $1 -- binds: [100]"#
+        );
+    }
+
+    #[test]
+    fn batches() {
+        let mut batches = vec![0, 3, 10, 10];
+        let mut seen = Vec::new();
+        let progress = run_batches(
+            || Ok(batches.pop().unwrap()),
+            Some(23),
+            |progress| seen.push(progress.rows),
+        )
+        .unwrap();
+        assert_eq!(
+            progress,
+            BatchProgress {
+                batches: 3,
+                rows: 23,
+                total_rows: Some(23),
+            }
+        );
+        assert_eq!(seen, vec![10, 20, 23]);
+    }
+}
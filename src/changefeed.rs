@@ -0,0 +1,1645 @@
+//! Support for `CREATE CHANGEFEED` SQL queries, streaming changes to rows
+//! into a sink such as Kafka, a webhook or cloud storage, or back to the client.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-changefeed.html
+
+use crate::{
+    as_of_system_time::HlcTimestamp,
+    external_storage::ExternalStorageUri,
+    jobs::{JobHandle, JobStatus},
+    quote,
+    version::{ClusterVersion, Unsupported, VersionCheck},
+};
+use diesel::{
+    dsl::sql,
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::Table,
+    result::QueryResult,
+    sql_types::{Array, BigInt, Binary, Nullable, Text},
+    Queryable,
+};
+use std::{
+    collections::BTreeMap, convert::Infallible, error, fmt, marker::PhantomData, time::Duration,
+};
+
+/// Represents the tables watched by a changefeed.
+///
+/// This trait is implemented by tables and tuples of tables. The `Kind`
+/// parameter only exists to keep these implementations apart, and never needs
+/// to be specified by hand.
+pub trait ChangefeedTargets<Kind> {
+    /// Pushes the comma separated list of tables.
+    fn walk_targets(&self, out: AstPass<Pg>) -> QueryResult<()>;
+}
+
+/// Marks the [`ChangefeedTargets`] implementation for a single table.
+///
+/// [`ChangefeedTargets`]: trait.ChangefeedTargets.html
+#[derive(Debug, Clone, Copy)]
+pub struct SingleTable;
+
+/// Marks the [`ChangefeedTargets`] implementation for a tuple of tables.
+///
+/// [`ChangefeedTargets`]: trait.ChangefeedTargets.html
+#[derive(Debug, Clone, Copy)]
+pub struct TupleOfTables;
+
+impl<T> ChangefeedTargets<SingleTable> for T
+where
+    T: Table,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_targets(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        self.from_clause().walk_ast(out.reborrow())
+    }
+}
+
+macro_rules! tuple_changefeed_targets {
+    ($first:ident $(, $rest:ident)*) => {
+        #[allow(non_snake_case)]
+        impl<$first: Table, $($rest: Table,)*> ChangefeedTargets<TupleOfTables>
+            for ($first, $($rest,)*)
+        where
+            $first::FromClause: QueryFragment<Pg>,
+            $($rest::FromClause: QueryFragment<Pg>,)*
+        {
+            fn walk_targets(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+                let ($first, $($rest,)*) = self;
+                $first.from_clause().walk_ast(out.reborrow())?;
+                $(
+                    out.push_sql(", ");
+                    $rest.from_clause().walk_ast(out.reborrow())?;
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+tuple_changefeed_targets!(A);
+tuple_changefeed_targets!(A, B);
+tuple_changefeed_targets!(A, B, C);
+tuple_changefeed_targets!(A, B, C, D);
+tuple_changefeed_targets!(A, B, C, D, E);
+tuple_changefeed_targets!(A, B, C, D, E, F);
+tuple_changefeed_targets!(A, B, C, D, E, F, G);
+tuple_changefeed_targets!(A, B, C, D, E, F, G, H);
+
+/// Creates a `CREATE CHANGEFEED` statement, emitting changes to the target
+/// tables into the sink at `sink_uri`.
+///
+/// Running the statement starts the changefeed job, and returns its ID:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # table! { authors (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::changefeed::{create_changefeed, ChangefeedOptions};
+///
+/// use std::time::Duration;
+///
+/// let job = create_changefeed((books::table, authors::table), "kafka://broker:9092")
+///     .options(
+///         ChangefeedOptions::new()
+///             .updated()
+///             .resolved(Some(Duration::from_secs(10))),
+///     )
+///     .start(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_changefeed<T, K>(
+    targets: T,
+    sink_uri: impl Into<ExternalStorageUri>,
+) -> CreateChangefeedStatement<T, K>
+where
+    T: ChangefeedTargets<K>,
+{
+    CreateChangefeedStatement {
+        targets,
+        sink_uri: sink_uri.into(),
+        options: ChangefeedOptions::new(),
+        kind: PhantomData,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CREATE CHANGEFEED` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The tables being watched
+/// - `K`: The kind of [`ChangefeedTargets`] implementation
+///
+/// See the [CockroachDB docs].
+///
+/// [`ChangefeedTargets`]: trait.ChangefeedTargets.html
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-changefeed.html
+pub struct CreateChangefeedStatement<T, K> {
+    targets: T,
+    sink_uri: ExternalStorageUri,
+    options: ChangefeedOptions,
+    kind: PhantomData<K>,
+}
+
+impl<T, K> CreateChangefeedStatement<T, K> {
+    /// Set the options of the changefeed.
+    ///
+    /// ```sql
+    /// CREATE CHANGEFEED FOR TABLE books INTO 'kafka://broker:9092' WITH updated, resolved = '10s';
+    /// ```
+    pub fn options(mut self, options: ChangefeedOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set any other changefeed option, with an optional value.
+    ///
+    /// The name must be a lower case identifier, such as `kafka_sink_config`,
+    /// or the statement fails to build.
+    pub fn with_option(mut self, name: impl Into<String>, value: Option<String>) -> Self {
+        self.options = self.options.with_option(name, value);
+        self
+    }
+
+    /// Start the changefeed, returning a handle to manage its job with.
+    pub fn start(self, conn: &PgConnection) -> QueryResult<ChangefeedJob>
+    where
+        T: ChangefeedTargets<K>,
+    {
+        let job_id = self.get_result(conn)?;
+        Ok(ChangefeedJob::new(job_id))
+    }
+}
+
+impl<T, K> VersionCheck for CreateChangefeedStatement<T, K> {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        if self.sink_uri.base().starts_with("webhook-") {
+            version.require("webhook sinks", 21, 2)?;
+        }
+        self.options.check_version(version)
+    }
+}
+
+impl<T, K> QueryFragment<Pg> for CreateChangefeedStatement<T, K>
+where
+    T: ChangefeedTargets<K>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CREATE CHANGEFEED FOR TABLE ");
+        self.targets.walk_targets(out.reborrow())?;
+        out.push_sql(" INTO ");
+        out.push_sql(&quote::string_literal(&self.sink_uri.expose()));
+        quote::push_options(&mut out, &self.options.to_vec())?;
+        Ok(())
+    }
+}
+
+impl<T, K> Query for CreateChangefeedStatement<T, K> {
+    type SqlType = BigInt;
+}
+
+impl<T, K> RunQueryDsl<PgConnection> for CreateChangefeedStatement<T, K> {}
+
+impl<T, K> QueryId for CreateChangefeedStatement<T, K> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The format of the messages emitted by a changefeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangefeedFormat {
+    /// `format = 'json'`, the default.
+    Json,
+    /// `format = 'avro'`, registering schemas with the given Confluent schema
+    /// registry.
+    Avro {
+        /// `confluent_schema_registry`.
+        confluent_schema_registry: String,
+    },
+}
+
+/// The structure of the messages emitted by a changefeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Envelope {
+    /// The new row under `after`, the default. With `diff`, the previous row
+    /// is also emitted under `before`.
+    Wrapped {
+        /// `diff`.
+        diff: bool,
+    },
+    /// `envelope = 'key_only'`: only the primary key of the changed row.
+    KeyOnly,
+    /// `envelope = 'row'`: the new row, without any wrapping.
+    Row,
+    /// `envelope = 'bare'`: the new row, with metadata under `__crdb__`.
+    Bare,
+}
+
+/// Whether a changefeed starts by emitting every existing row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialScan {
+    /// Emit existing rows, then changes. The default, unless a cursor is set.
+    Yes,
+    /// Only emit changes.
+    No,
+    /// Only emit existing rows, then finish.
+    Only,
+}
+
+impl InitialScan {
+    fn as_str(self) -> &'static str {
+        match self {
+            InitialScan::Yes => "yes",
+            InitialScan::No => "no",
+            InitialScan::Only => "only",
+        }
+    }
+}
+
+/// The schema changes a changefeed reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaChangeEvents {
+    /// Only schema changes which rewrite rows, such as adding a column with
+    /// a default, or dropping a column. The default.
+    Default,
+    /// Any column being added or dropped, even without rewriting rows.
+    ColumnChanges,
+}
+
+impl SchemaChangeEvents {
+    fn as_str(self) -> &'static str {
+        match self {
+            SchemaChangeEvents::Default => "default",
+            SchemaChangeEvents::ColumnChanges => "column_changes",
+        }
+    }
+}
+
+/// How a changefeed reacts to schema changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaChangePolicy {
+    /// Emit every row again with the new schema. The default.
+    Backfill,
+    /// Carry on with the new schema, without emitting rows again.
+    NoBackfill,
+    /// Stop the changefeed with an error at the schema change.
+    Stop,
+}
+
+impl SchemaChangePolicy {
+    fn as_str(self) -> &'static str {
+        match self {
+            SchemaChangePolicy::Backfill => "backfill",
+            SchemaChangePolicy::NoBackfill => "nobackfill",
+            SchemaChangePolicy::Stop => "stop",
+        }
+    }
+}
+
+/// Options for a changefeed.
+///
+/// Options which are only valid together are combined in the same type, such
+/// as [`ChangefeedFormat::Avro`] and its schema registry, or `diff` and the
+/// wrapped [`Envelope`].
+///
+/// [`ChangefeedFormat::Avro`]: enum.ChangefeedFormat.html#variant.Avro
+/// [`Envelope`]: enum.Envelope.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangefeedOptions {
+    format: Option<ChangefeedFormat>,
+    envelope: Option<Envelope>,
+    resolved: Option<Option<Duration>>,
+    updated: bool,
+    initial_scan: Option<InitialScan>,
+    cursor: Option<HlcTimestamp>,
+    end_time: Option<HlcTimestamp>,
+    schema_change_events: Option<SchemaChangeEvents>,
+    schema_change_policy: Option<SchemaChangePolicy>,
+    full_table_name: bool,
+    other: Vec<(String, OptionValue)>,
+}
+
+/// The value of an option set by name, which is redacted when displayed if
+/// it holds a secret.
+#[derive(Clone, PartialEq, Eq)]
+struct OptionValue {
+    value: Option<String>,
+    secret: bool,
+}
+
+/// Redacts secrets.
+impl fmt::Debug for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.value {
+            Some(_) if self.secret => f.write_str("Some(\"redacted\")"),
+            value => value.fmt(f),
+        }
+    }
+}
+
+impl VersionCheck for ChangefeedOptions {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        if self.end_time.is_some() {
+            version.require("the changefeed end_time option", 22, 1)?;
+        }
+        if self.initial_scan.is_some() {
+            version.require("the changefeed initial_scan option", 22, 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChangefeedOptions {
+    /// The default options, emitting wrapped JSON messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the format of the messages.
+    pub fn format(mut self, format: ChangefeedFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set the structure of the messages.
+    pub fn envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = Some(envelope);
+        self
+    }
+
+    /// Emit resolved timestamp messages, at most as often as the given
+    /// interval, or by default every few seconds.
+    ///
+    /// An [`Interval`] parsed from configuration converts into the interval
+    /// with `Some(interval.into())`.
+    ///
+    /// [`Interval`]: ../data_types/struct.Interval.html
+    pub fn resolved(mut self, interval: Option<Duration>) -> Self {
+        self.resolved = Some(interval);
+        self
+    }
+
+    /// Include the commit timestamp of each change in its message.
+    pub fn updated(mut self) -> Self {
+        self.updated = true;
+        self
+    }
+
+    /// Set whether existing rows are emitted first.
+    ///
+    /// Requires CockroachDB v22.1 or later.
+    pub fn initial_scan(mut self, initial_scan: InitialScan) -> Self {
+        self.initial_scan = Some(initial_scan);
+        self
+    }
+
+    /// Start emitting changes from the given timestamp, such as a resolved
+    /// timestamp stored by a previous consumer, instead of from now.
+    ///
+    /// Existing rows are not emitted when a cursor is set, unless requested
+    /// with `initial_scan`.
+    pub fn cursor(mut self, cursor: HlcTimestamp) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Stop the changefeed once every change up to the given timestamp has
+    /// been emitted.
+    pub fn end_time(mut self, end_time: HlcTimestamp) -> Self {
+        self.end_time = Some(end_time);
+        self
+    }
+
+    /// Set which schema changes the changefeed reacts to.
+    pub fn schema_change_events(mut self, events: SchemaChangeEvents) -> Self {
+        self.schema_change_events = Some(events);
+        self
+    }
+
+    /// Set how the changefeed reacts to schema changes.
+    pub fn schema_change_policy(mut self, policy: SchemaChangePolicy) -> Self {
+        self.schema_change_policy = Some(policy);
+        self
+    }
+
+    /// Use fully qualified table names, such as `library.public.books`, for
+    /// topics and messages.
+    pub fn full_table_name(mut self) -> Self {
+        self.full_table_name = true;
+        self
+    }
+
+    /// Send the given `Authorization` header with each request to a webhook
+    /// sink, such as `Basic <credentials>`. The header is redacted when
+    /// displayed.
+    pub fn webhook_auth_header(mut self, header: impl Into<String>) -> Self {
+        self.other.push((
+            "webhook_auth_header".to_owned(),
+            OptionValue {
+                value: Some(header.into()),
+                secret: true,
+            },
+        ));
+        self
+    }
+
+    /// Set any other option, with an optional value.
+    ///
+    /// The name must be a lower case identifier, such as `kafka_sink_config`,
+    /// or the statement fails to build.
+    pub fn with_option(mut self, name: impl Into<String>, value: Option<String>) -> Self {
+        self.other.push((
+            name.into(),
+            OptionValue {
+                value,
+                secret: false,
+            },
+        ));
+        self
+    }
+
+    fn to_vec(&self) -> Vec<(&str, Option<String>)> {
+        let mut options = Vec::new();
+        if let Some(ChangefeedFormat::Avro {
+            confluent_schema_registry,
+        }) = &self.format
+        {
+            options.push(("format", Some("avro".to_owned())));
+            options.push((
+                "confluent_schema_registry",
+                Some(confluent_schema_registry.clone()),
+            ));
+        }
+        match self.envelope {
+            Some(Envelope::Wrapped { diff: true }) => options.push(("diff", None)),
+            Some(Envelope::Wrapped { diff: false }) | None => {}
+            Some(Envelope::KeyOnly) => options.push(("envelope", Some("key_only".to_owned()))),
+            Some(Envelope::Row) => options.push(("envelope", Some("row".to_owned()))),
+            Some(Envelope::Bare) => options.push(("envelope", Some("bare".to_owned()))),
+        }
+        match self.resolved {
+            Some(Some(interval)) => options.push(("resolved", Some(quote::interval(interval)))),
+            Some(None) => options.push(("resolved", None)),
+            None => {}
+        }
+        if self.updated {
+            options.push(("updated", None));
+        }
+        if let Some(initial_scan) = self.initial_scan {
+            options.push(("initial_scan", Some(initial_scan.as_str().to_owned())));
+        }
+        if let Some(cursor) = self.cursor {
+            options.push(("cursor", Some(cursor.to_string())));
+        }
+        if let Some(end_time) = self.end_time {
+            options.push(("end_time", Some(end_time.to_string())));
+        }
+        if let Some(events) = self.schema_change_events {
+            options.push(("schema_change_events", Some(events.as_str().to_owned())));
+        }
+        if let Some(policy) = self.schema_change_policy {
+            options.push(("schema_change_policy", Some(policy.as_str().to_owned())));
+        }
+        if self.full_table_name {
+            options.push(("full_table_name", None));
+        }
+        for (name, value) in &self.other {
+            options.push((name.as_str(), value.value.clone()));
+        }
+        options
+    }
+}
+
+/// A handle to a changefeed job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChangefeedJob {
+    job: JobHandle,
+}
+
+impl ChangefeedJob {
+    /// A handle to the changefeed job with the given ID.
+    pub const fn new(job_id: i64) -> Self {
+        ChangefeedJob {
+            job: JobHandle::new(job_id),
+        }
+    }
+
+    /// The ID of the changefeed job.
+    pub const fn job_id(&self) -> i64 {
+        self.job.job_id()
+    }
+
+    /// The handle to the underlying job.
+    pub const fn job(&self) -> JobHandle {
+        self.job
+    }
+
+    /// Loads the current status of the changefeed.
+    pub fn status(&self, conn: &PgConnection) -> QueryResult<ChangefeedStatus> {
+        changefeed_status(vec![self.job_id()]).get_result(conn)
+    }
+
+    /// Pauses the changefeed. Once resumed, it continues from its high-water
+    /// mark, as long as that is still within the garbage collection window.
+    pub fn pause(&self, conn: &PgConnection) -> QueryResult<()> {
+        self.job.pause(conn)
+    }
+
+    /// Resumes the changefeed after it was paused.
+    pub fn resume(&self, conn: &PgConnection) -> QueryResult<()> {
+        self.job.resume(conn)
+    }
+
+    /// Cancels the changefeed, permanently stopping it.
+    pub fn cancel(&self, conn: &PgConnection) -> QueryResult<()> {
+        self.job.cancel(conn)
+    }
+
+    /// Creates an `ALTER CHANGEFEED` statement for this changefeed.
+    ///
+    /// See [`alter_changefeed`].
+    ///
+    /// [`alter_changefeed`]: fn.alter_changefeed.html
+    pub fn alter(&self) -> AlterChangefeedStatement<()> {
+        alter_changefeed(*self)
+    }
+}
+
+/// Creates an `ALTER CHANGEFEED` statement, changing the tables watched by a
+/// changefeed or its options without recreating it.
+///
+/// The changefeed must be paused before it is altered, and resumed after.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # table! { reviews (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::changefeed::{alter_changefeed, ChangefeedJob, ChangefeedOptions};
+///
+/// let job = ChangefeedJob::new(27_536_791_415_282);
+/// job.pause(&connection)?;
+/// alter_changefeed(job)
+///     .add_targets(reviews::table, true)
+///     .drop_targets(books::table)
+///     .set_options(ChangefeedOptions::new().updated())
+///     .execute(&connection)?;
+/// job.resume(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn alter_changefeed(job: ChangefeedJob) -> AlterChangefeedStatement<()> {
+    AlterChangefeedStatement {
+        job_id: job.job_id(),
+        commands: (),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `ALTER CHANGEFEED` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `C`: The commands altering the changefeed, in order
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-changefeed.html
+pub struct AlterChangefeedStatement<C> {
+    job_id: i64,
+    commands: C,
+}
+
+impl<C> AlterChangefeedStatement<C> {
+    fn then<N>(self, command: N) -> AlterChangefeedStatement<ChangefeedCommands<C, N>> {
+        AlterChangefeedStatement {
+            job_id: self.job_id,
+            commands: ChangefeedCommands(self.commands, command),
+        }
+    }
+
+    /// Start watching the target tables, optionally emitting their existing
+    /// rows first.
+    ///
+    /// ```sql
+    /// ALTER CHANGEFEED 27536791415282 ADD reviews WITH initial_scan = 'yes';
+    /// ```
+    pub fn add_targets<T, K>(
+        self,
+        targets: T,
+        initial_scan: bool,
+    ) -> AlterChangefeedStatement<ChangefeedCommands<C, AddTargets<T, K>>>
+    where
+        T: ChangefeedTargets<K>,
+    {
+        self.then(AddTargets {
+            targets,
+            initial_scan,
+            kind: PhantomData,
+        })
+    }
+
+    /// Stop watching the target tables.
+    ///
+    /// ```sql
+    /// ALTER CHANGEFEED 27536791415282 DROP books;
+    /// ```
+    pub fn drop_targets<T, K>(
+        self,
+        targets: T,
+    ) -> AlterChangefeedStatement<ChangefeedCommands<C, DropTargets<T, K>>>
+    where
+        T: ChangefeedTargets<K>,
+    {
+        self.then(DropTargets {
+            targets,
+            kind: PhantomData,
+        })
+    }
+
+    /// Set the given options.
+    ///
+    /// ```sql
+    /// ALTER CHANGEFEED 27536791415282 SET resolved = '10s';
+    /// ```
+    pub fn set_options(
+        self,
+        options: ChangefeedOptions,
+    ) -> AlterChangefeedStatement<ChangefeedCommands<C, SetOptions>> {
+        self.then(SetOptions(options))
+    }
+
+    /// Remove the options with the given names, restoring their defaults.
+    ///
+    /// ```sql
+    /// ALTER CHANGEFEED 27536791415282 UNSET diff, updated;
+    /// ```
+    pub fn unset_options(
+        self,
+        names: Vec<String>,
+    ) -> AlterChangefeedStatement<ChangefeedCommands<C, UnsetOptions>> {
+        self.then(UnsetOptions(names))
+    }
+}
+
+impl<C> QueryFragment<Pg> for AlterChangefeedStatement<C>
+where
+    C: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("ALTER CHANGEFEED ");
+        out.push_sql(&self.job_id.to_string());
+        self.commands.walk_ast(out.reborrow())
+    }
+}
+
+impl<C> RunQueryDsl<PgConnection> for AlterChangefeedStatement<C> {}
+
+impl<C> QueryId for AlterChangefeedStatement<C> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A list of `ALTER CHANGEFEED` commands, run in order.
+#[derive(Debug, Clone, Copy)]
+pub struct ChangefeedCommands<C, N>(C, N);
+
+impl<C, N> QueryFragment<Pg> for ChangefeedCommands<C, N>
+where
+    C: QueryFragment<Pg>,
+    N: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        self.0.walk_ast(out.reborrow())?;
+        self.1.walk_ast(out.reborrow())
+    }
+}
+
+/// The `ADD` command of an `ALTER CHANGEFEED` statement.
+#[derive(Debug, Clone, Copy)]
+pub struct AddTargets<T, K> {
+    targets: T,
+    initial_scan: bool,
+    kind: PhantomData<K>,
+}
+
+impl<T, K> QueryFragment<Pg> for AddTargets<T, K>
+where
+    T: ChangefeedTargets<K>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql(" ADD ");
+        self.targets.walk_targets(out.reborrow())?;
+        if self.initial_scan {
+            out.push_sql(" WITH initial_scan = 'yes'");
+        }
+        Ok(())
+    }
+}
+
+/// The `DROP` command of an `ALTER CHANGEFEED` statement.
+#[derive(Debug, Clone, Copy)]
+pub struct DropTargets<T, K> {
+    targets: T,
+    kind: PhantomData<K>,
+}
+
+impl<T, K> QueryFragment<Pg> for DropTargets<T, K>
+where
+    T: ChangefeedTargets<K>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql(" DROP ");
+        self.targets.walk_targets(out.reborrow())
+    }
+}
+
+/// The `SET` command of an `ALTER CHANGEFEED` statement.
+#[derive(Debug, Clone)]
+pub struct SetOptions(ChangefeedOptions);
+
+impl QueryFragment<Pg> for SetOptions {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        let options = self.0.to_vec();
+        if options.is_empty() {
+            return Err(no_options("SET"));
+        }
+        quote::push_options_clause(&mut out, " SET ", &options)
+    }
+}
+
+/// The `UNSET` command of an `ALTER CHANGEFEED` statement.
+#[derive(Debug, Clone)]
+pub struct UnsetOptions(Vec<String>);
+
+impl QueryFragment<Pg> for UnsetOptions {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        if self.0.is_empty() {
+            return Err(no_options("UNSET"));
+        }
+        let names: Vec<_> = self.0.iter().map(|name| (name.as_str(), None)).collect();
+        quote::push_options_clause(&mut out, " UNSET ", &names)
+    }
+}
+
+fn no_options(command: &str) -> diesel::result::Error {
+    diesel::result::Error::QueryBuilderError(
+        format!("At least one option is required for {}", command).into(),
+    )
+}
+
+/// Creates a query loading the [`ChangefeedStatus`] of the given changefeed
+/// jobs.
+///
+/// [`ChangefeedStatus`]: struct.ChangefeedStatus.html
+pub fn changefeed_status(job_ids: Vec<i64>) -> ChangefeedStatusQuery {
+    ChangefeedStatusQuery { job_ids }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A query loading [`ChangefeedStatus`] rows from `crdb_internal.jobs`.
+///
+/// [`ChangefeedStatus`]: struct.ChangefeedStatus.html
+pub struct ChangefeedStatusQuery {
+    job_ids: Vec<i64>,
+}
+
+impl QueryFragment<Pg> for ChangefeedStatusQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT job_id, status, high_water_timestamp::STRING, running_status, error \
+             FROM crdb_internal.jobs WHERE job_type = 'CHANGEFEED' AND job_id = ANY (",
+        );
+        out.push_bind_param::<Array<BigInt>, _>(&self.job_ids)?;
+        out.push_sql(") ORDER BY job_id");
+        Ok(())
+    }
+}
+
+impl Query for ChangefeedStatusQuery {
+    type SqlType = ChangefeedStatusSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for ChangefeedStatusQuery {}
+
+impl QueryId for ChangefeedStatusQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The status of a changefeed job, as returned by [`changefeed_status`].
+///
+/// [`changefeed_status`]: fn.changefeed_status.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangefeedStatus {
+    /// The ID of the changefeed job.
+    pub job_id: i64,
+    /// The status of the job.
+    pub status: JobStatus,
+    /// The timestamp up to which every change has been emitted, or `None`
+    /// before the initial scan has finished.
+    pub high_water_timestamp: Option<HlcTimestamp>,
+    /// A description of what the changefeed is currently doing.
+    pub running_status: Option<String>,
+    /// The error the job failed with, if any.
+    pub error: Option<String>,
+}
+
+impl Queryable<ChangefeedStatusSqlType, Pg> for ChangefeedStatus {
+    type Row = (
+        i64,
+        String,
+        Option<HlcTimestamp>,
+        Option<String>,
+        Option<String>,
+    );
+
+    fn build((job_id, status, high_water_timestamp, running_status, error): Self::Row) -> Self {
+        ChangefeedStatus {
+            job_id,
+            status: status.parse().unwrap_or_else(|never| match never {}),
+            high_water_timestamp,
+            running_status,
+            error: error.filter(|error| !error.is_empty()),
+        }
+    }
+}
+
+/// The SQL type of a row loaded by [`ChangefeedStatusQuery`].
+///
+/// [`ChangefeedStatusQuery`]: struct.ChangefeedStatusQuery.html
+pub type ChangefeedStatusSqlType = (BigInt, Text, Nullable<Text>, Nullable<Text>, Nullable<Text>);
+
+/// A resolved timestamp message, emitted by changefeeds with the `resolved`
+/// option. Every change up to the resolved timestamp has been emitted.
+///
+/// Requires the `json` feature.
+///
+/// ```
+/// use diesel_cockroach::{as_of_system_time::HlcTimestamp, changefeed::ResolvedMessage};
+///
+/// let message: ResolvedMessage =
+///     serde_json::from_str(r#"{"resolved":"1591300372587669851.0000000000"}"#).unwrap();
+/// assert_eq!(message.resolved, HlcTimestamp::new(1_591_300_372_587_669_851, 0));
+/// ```
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ResolvedMessage {
+    /// The resolved timestamp.
+    pub resolved: HlcTimestamp,
+}
+
+/// A change to a single row, as emitted by a changefeed with the default
+/// `wrapped` envelope.
+///
+/// `T` may be any struct implementing `Deserialize`, such as the one already
+/// used to load the table with `Queryable`, with fields named after the
+/// columns. `K` holds the primary key, a JSON array of the key column values.
+///
+/// Requires the `json` feature.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RowChange<T, K = serde_json::Value> {
+    /// The row after the change, or `None` if it was deleted.
+    pub after: Option<T>,
+    /// The row before the change, if the changefeed has the `diff` option.
+    pub before: Option<T>,
+    /// When the change was committed, if the changefeed has the `updated`
+    /// option.
+    pub updated: Option<HlcTimestamp>,
+    /// The primary key of the row.
+    ///
+    /// Sinks with message keys, such as Kafka, send the key separately.
+    /// It can be filled in using [`decode_message`].
+    ///
+    /// [`decode_message`]: fn.decode_message.html
+    pub key: Option<K>,
+}
+
+#[cfg(feature = "json")]
+impl<T, K> RowChange<T, K> {
+    /// Whether the row was deleted.
+    pub fn is_delete(&self) -> bool {
+        self.after.is_none()
+    }
+}
+
+/// A message emitted by a changefeed, either a change to a row or a resolved
+/// timestamp.
+///
+/// Requires the `json` feature.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum ChangefeedMessage<T, K = serde_json::Value> {
+    /// Every change up to the timestamp has been emitted.
+    Resolved(ResolvedMessage),
+    /// A change to a row.
+    Row(RowChange<T, K>),
+}
+
+/// Decodes a JSON changefeed message, given its message `key` where the sink
+/// sends one.
+///
+/// Requires the `json` feature.
+///
+/// ```
+/// use diesel_cockroach::changefeed::{decode_message, ChangefeedMessage};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Book {
+///     id: i64,
+///     title: String,
+/// }
+///
+/// let message = decode_message::<Book, (i64,)>(
+///     Some(br#"[1]"#),
+///     br#"{"after":{"id":1,"title":"Dune"}}"#,
+/// )
+/// .unwrap();
+/// let change = match message {
+///     ChangefeedMessage::Row(change) => change,
+///     ChangefeedMessage::Resolved(_) => unreachable!(),
+/// };
+/// assert_eq!(change.key, Some((1,)));
+/// assert_eq!(change.after.unwrap().title, "Dune");
+/// ```
+#[cfg(feature = "json")]
+pub fn decode_message<T, K>(
+    key: Option<&[u8]>,
+    value: &[u8],
+) -> Result<ChangefeedMessage<T, K>, serde_json::Error>
+where
+    T: serde::de::DeserializeOwned,
+    K: serde::de::DeserializeOwned,
+{
+    let mut message = serde_json::from_slice(value)?;
+    if let (ChangefeedMessage::Row(change), Some(key)) = (&mut message, key) {
+        if change.key.is_none() {
+            change.key = Some(serde_json::from_slice(key)?);
+        }
+    }
+    Ok(message)
+}
+
+/// Tracks the resolved timestamps of each partition of a changefeed's sink,
+/// to find the timestamp up to which changes have been emitted to every
+/// partition.
+///
+/// Changefeeds emit resolved timestamps to each partition independently, so
+/// a consumer may only rely on the earliest of them. Once the frontier is
+/// known, the table can be queried with [`AsOfSystemTime::Hlc`] for a snapshot
+/// consistent with the changes consumed so far.
+///
+/// ```
+/// use diesel_cockroach::{as_of_system_time::HlcTimestamp, changefeed::ResolvedFrontier};
+///
+/// let mut frontier = ResolvedFrontier::new(vec![0, 1]);
+/// frontier.observe(0, HlcTimestamp::new(20, 0));
+/// assert_eq!(frontier.frontier(), None);
+/// frontier.observe(1, HlcTimestamp::new(10, 0));
+/// assert_eq!(frontier.frontier(), Some(HlcTimestamp::new(10, 0)));
+/// ```
+///
+/// [`AsOfSystemTime::Hlc`]: ../as_of_system_time/enum.AsOfSystemTime.html#variant.Hlc
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedFrontier<P: Ord> {
+    partitions: BTreeMap<P, Option<HlcTimestamp>>,
+}
+
+impl<P: Ord> ResolvedFrontier<P> {
+    /// Track resolved timestamps across the given partitions.
+    pub fn new(partitions: impl IntoIterator<Item = P>) -> Self {
+        ResolvedFrontier {
+            partitions: partitions
+                .into_iter()
+                .map(|partition| (partition, None))
+                .collect(),
+        }
+    }
+
+    /// Record a resolved timestamp received from a partition, returning the
+    /// new frontier. Timestamps older than one already seen are ignored.
+    pub fn observe(&mut self, partition: P, resolved: HlcTimestamp) -> Option<HlcTimestamp> {
+        let entry = self.partitions.entry(partition).or_insert(None);
+        if entry.map_or(true, |previous| previous < resolved) {
+            *entry = Some(resolved);
+        }
+        self.frontier()
+    }
+
+    /// The earliest resolved timestamp across all partitions, or `None` until
+    /// every partition has reported one.
+    pub fn frontier(&self) -> Option<HlcTimestamp> {
+        self.partitions
+            .values()
+            .try_fold(None, |frontier: Option<HlcTimestamp>, resolved| {
+                let resolved = (*resolved)?;
+                Some(Some(
+                    frontier.map_or(resolved, |frontier| frontier.min(resolved)),
+                ))
+            })
+            .flatten()
+    }
+}
+
+/// Stores the timestamp up to which a consumer has processed changes, so a
+/// [`SinklessChangefeed`] can resume from it after restarting.
+///
+/// `Option<HlcTimestamp>` implements this trait, for checkpoints kept in
+/// memory.
+///
+/// [`SinklessChangefeed`]: struct.SinklessChangefeed.html
+pub trait CheckpointStore {
+    /// The error returned when the checkpoint cannot be loaded or saved.
+    type Error;
+
+    /// Loads the stored checkpoint, or `None` if the consumer has not
+    /// processed any changes yet.
+    fn load(&mut self) -> Result<Option<HlcTimestamp>, Self::Error>;
+
+    /// Stores a new checkpoint, after its changes have been processed.
+    fn save(&mut self, checkpoint: HlcTimestamp) -> Result<(), Self::Error>;
+}
+
+impl CheckpointStore for Option<HlcTimestamp> {
+    type Error = Infallible;
+
+    fn load(&mut self) -> Result<Option<HlcTimestamp>, Self::Error> {
+        Ok(*self)
+    }
+
+    fn save(&mut self, checkpoint: HlcTimestamp) -> Result<(), Self::Error> {
+        *self = Some(checkpoint);
+        Ok(())
+    }
+}
+
+/// The error returned when polling a [`SinklessChangefeed`] does not succeed.
+///
+/// [`SinklessChangefeed`]: struct.SinklessChangefeed.html
+#[derive(Debug)]
+pub enum PollError<E> {
+    /// Running the changefeed failed.
+    Query(diesel::result::Error),
+    /// Loading or saving the checkpoint failed.
+    Checkpoint(E),
+}
+
+impl<E: fmt::Display> fmt::Display for PollError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PollError::Query(err) => write!(f, "failed to run changefeed: {}", err),
+            PollError::Checkpoint(err) => write!(f, "failed to store checkpoint: {}", err),
+        }
+    }
+}
+
+impl<E: error::Error + 'static> error::Error for PollError<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PollError::Query(err) => Some(err),
+            PollError::Checkpoint(err) => Some(err),
+        }
+    }
+}
+
+impl<E> From<diesel::result::Error> for PollError<E> {
+    fn from(err: diesel::result::Error) -> Self {
+        PollError::Query(err)
+    }
+}
+
+/// Creates a sinkless changefeed, returning changes to the client instead
+/// of emitting them to a sink.
+///
+/// Diesel loads every row of a result before returning it, so changes are
+/// streamed by [`poll`]ing repeatedly. Each poll runs the changefeed from
+/// the stored checkpoint up to the current time, then advances the
+/// checkpoint. The first poll also emits every existing row, unless disabled
+/// with `initial_scan`.
+///
+/// With [`SchemaChangePolicy::Stop`], each poll stops at the next schema
+/// change, and reports it as a [`ChangefeedEvent::SchemaChange`]. The
+/// following poll carries on with the new schema, so consumers can react to
+/// column changes between the two.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let connection = PgConnection::establish("")?;
+/// use diesel_cockroach::changefeed::{
+///     sinkless_changefeed, ChangefeedEvent, ChangefeedOptions, SchemaChangePolicy,
+/// };
+///
+/// let changefeed = sinkless_changefeed(books::table)
+///     .options(ChangefeedOptions::new().schema_change_policy(SchemaChangePolicy::Stop));
+/// let mut checkpoint = None;
+/// loop {
+///     for event in changefeed.poll(&connection, &mut checkpoint)? {
+///         match event {
+///             ChangefeedEvent::Row(row) => println!("{:?}", row.value),
+///             ChangefeedEvent::SchemaChange(timestamp) => println!("schema changed at {}", timestamp),
+///         }
+///     }
+///     std::thread::sleep(std::time::Duration::from_secs(10));
+/// }
+/// # }
+/// ```
+///
+/// [`poll`]: struct.SinklessChangefeed.html#method.poll
+/// [`SchemaChangePolicy::Stop`]: enum.SchemaChangePolicy.html#variant.Stop
+/// [`ChangefeedEvent::SchemaChange`]: enum.ChangefeedEvent.html#variant.SchemaChange
+pub fn sinkless_changefeed<T, K>(targets: T) -> SinklessChangefeed<T, K>
+where
+    T: ChangefeedTargets<K>,
+{
+    SinklessChangefeed {
+        targets,
+        options: ChangefeedOptions::new(),
+        kind: PhantomData,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed sinkless changefeed.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The tables being watched
+/// - `K`: The kind of [`ChangefeedTargets`] implementation
+///
+/// See the [CockroachDB docs].
+///
+/// [`ChangefeedTargets`]: trait.ChangefeedTargets.html
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/changefeed-for.html
+pub struct SinklessChangefeed<T, K> {
+    targets: T,
+    options: ChangefeedOptions,
+    kind: PhantomData<K>,
+}
+
+impl<T, K> SinklessChangefeed<T, K> {
+    /// Set the options of the changefeed.
+    ///
+    /// The `cursor` and `end_time` options are overridden by [`poll`].
+    ///
+    /// [`poll`]: #method.poll
+    pub fn options(mut self, options: ChangefeedOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<T, K> SinklessChangefeed<T, K>
+where
+    T: ChangefeedTargets<K> + Clone,
+{
+    /// Return every change since the checkpoint in `store`, then advance the
+    /// checkpoint past them.
+    ///
+    /// When `store` has no checkpoint yet, every existing row is returned,
+    /// unless the options set a different `initial_scan`.
+    ///
+    /// The checkpoint is only saved once all changes have been loaded, so
+    /// after a failure the same changes are returned again.
+    pub fn poll<S>(
+        &self,
+        conn: &PgConnection,
+        store: &mut S,
+    ) -> Result<Vec<ChangefeedEvent>, PollError<S::Error>>
+    where
+        S: CheckpointStore,
+    {
+        let cursor = store.load().map_err(PollError::Checkpoint)?;
+        let end_time =
+            diesel::select(sql::<Text>("cluster_logical_timestamp()::STRING")).get_result(conn)?;
+        let events = match self.load_until(conn, cursor, end_time) {
+            Err(diesel::result::Error::DatabaseError(kind, info)) => {
+                let changed_at = match schema_change_timestamp(info.message()) {
+                    Some(changed_at) => changed_at,
+                    None => return Err(diesel::result::Error::DatabaseError(kind, info).into()),
+                };
+                // Return the changes up to the schema change, and carry on
+                // from it next time
+                let mut events = self.load_until(conn, cursor, changed_at)?;
+                events.push(ChangefeedEvent::SchemaChange(changed_at));
+                store.save(changed_at).map_err(PollError::Checkpoint)?;
+                return Ok(events);
+            }
+            events => events?,
+        };
+        store.save(end_time).map_err(PollError::Checkpoint)?;
+        Ok(events)
+    }
+
+    fn load_until(
+        &self,
+        conn: &PgConnection,
+        cursor: Option<HlcTimestamp>,
+        end_time: HlcTimestamp,
+    ) -> QueryResult<Vec<ChangefeedEvent>> {
+        let rows: Vec<ChangefeedRow> = self.until(cursor, end_time).load(conn)?;
+        Ok(rows.into_iter().map(ChangefeedEvent::Row).collect())
+    }
+
+    /// The changefeed emitting changes from `cursor` up to `end_time`.
+    ///
+    /// Without a cursor, the changefeed would start at its own statement
+    /// time, which is after `end_time`. Instead it starts at `end_time`, and
+    /// emits the existing rows unless another initial scan was requested.
+    fn until(&self, cursor: Option<HlcTimestamp>, end_time: HlcTimestamp) -> Self {
+        let mut options = self.options.clone();
+        if cursor.is_none() && options.initial_scan.is_none() {
+            options.initial_scan = Some(InitialScan::Yes);
+        }
+        options.cursor = Some(cursor.unwrap_or(end_time));
+        options.end_time = Some(end_time);
+        SinklessChangefeed {
+            targets: self.targets.clone(),
+            options,
+            kind: PhantomData,
+        }
+    }
+}
+
+impl<T, K> VersionCheck for SinklessChangefeed<T, K> {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        self.options.check_version(version)
+    }
+}
+
+impl<T, K> QueryFragment<Pg> for SinklessChangefeed<T, K>
+where
+    T: ChangefeedTargets<K>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("EXPERIMENTAL CHANGEFEED FOR TABLE ");
+        self.targets.walk_targets(out.reborrow())?;
+        quote::push_options(&mut out, &self.options.to_vec())?;
+        Ok(())
+    }
+}
+
+impl<T, K> Query for SinklessChangefeed<T, K> {
+    type SqlType = ChangefeedRowSqlType;
+}
+
+impl<T, K> RunQueryDsl<PgConnection> for SinklessChangefeed<T, K> {}
+
+impl<T, K> QueryId for SinklessChangefeed<T, K> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Finds the timestamp in the error a changefeed stops with after a schema
+/// change, under `schema_change_policy = 'stop'`.
+fn schema_change_timestamp(message: &str) -> Option<HlcTimestamp> {
+    const PREFIX: &str = "schema change occurred at ";
+    let start = message.find(PREFIX)? + PREFIX.len();
+    let timestamp = &message[start..];
+    let end = timestamp
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(timestamp.len());
+    timestamp[..end].parse().ok()
+}
+
+/// An event returned by polling a [`SinklessChangefeed`].
+///
+/// [`SinklessChangefeed`]: struct.SinklessChangefeed.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangefeedEvent {
+    /// A message emitted by the changefeed.
+    Row(ChangefeedRow),
+    /// The changefeed stopped at a schema change at the given timestamp.
+    /// Every change before it has been returned.
+    SchemaChange(HlcTimestamp),
+}
+
+/// The SQL type of a row returned by a sinkless changefeed.
+pub type ChangefeedRowSqlType = (Nullable<Text>, Nullable<Binary>, Nullable<Binary>);
+
+/// A message returned by a sinkless changefeed.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct ChangefeedRow {
+    /// The table the change was made to, or `None` for resolved timestamps.
+    pub table: Option<String>,
+    /// The primary key of the changed row, or `None` for resolved
+    /// timestamps.
+    pub key: Option<Vec<u8>>,
+    /// The message, which can be decoded with [`decode_message`] for JSON
+    /// changefeeds.
+    ///
+    /// [`decode_message`]: fn.decode_message.html
+    pub value: Option<Vec<u8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    table! {
+        authors (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn redacted_sink() {
+        let statement = create_changefeed(
+            books::table,
+            "kafka://broker:9092?sasl_enabled=true&sasl_user=feed&sasl_password=top-secret",
+        );
+        let debug = format!("{:?}", statement);
+        assert!(!debug.contains("top-secret"), "{}", debug);
+        assert!(debug.contains("sasl_password=redacted"));
+    }
+
+    #[test]
+    fn redacted_header() {
+        let statement = create_changefeed(books::table, "webhook-https://example.com/feed")
+            .options(ChangefeedOptions::new().webhook_auth_header("Basic dG9wLXNlY3JldA=="));
+        let debug = format!("{:?}", statement);
+        assert!(!debug.contains("dG9wLXNlY3JldA=="), "{}", debug);
+        assert!(debug.contains("redacted"));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE CHANGEFEED FOR TABLE "books" INTO 'webhook-https://example.com/feed' WITH webhook_auth_header = 'Basic dG9wLXNlY3JldA==' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn single() {
+        assert_eq!(
+            diesel::debug_query(&create_changefeed(books::table, "kafka://broker:9092"))
+                .to_string(),
+            r#"CREATE CHANGEFEED FOR TABLE "books" INTO 'kafka://broker:9092' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn multiple() {
+        let statement = create_changefeed(
+            (books::table, authors::table),
+            "webhook-https://example.com/changes",
+        )
+        .options(
+            ChangefeedOptions::new()
+                .updated()
+                .resolved(Some(Duration::from_secs(10))),
+        )
+        .with_option("min_checkpoint_frequency", Some("30s".to_owned()));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE CHANGEFEED FOR TABLE "books", "authors" INTO 'webhook-https://example.com/changes' WITH resolved = '10s', updated, min_checkpoint_frequency = '30s' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn options() {
+        let options = ChangefeedOptions::new()
+            .format(ChangefeedFormat::Avro {
+                confluent_schema_registry: "http://registry:8081".to_owned(),
+            })
+            .envelope(Envelope::Wrapped { diff: true })
+            .resolved(None)
+            .initial_scan(InitialScan::No)
+            .full_table_name();
+        let statement = create_changefeed(books::table, "kafka://broker:9092").options(options);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE CHANGEFEED FOR TABLE "books" INTO 'kafka://broker:9092' WITH format = 'avro', confluent_schema_registry = 'http://registry:8081', diff, resolved, initial_scan = 'no', full_table_name -- binds: []"#
+        );
+        let statement = create_changefeed(books::table, "kafka://broker:9092")
+            .options(ChangefeedOptions::new().envelope(Envelope::KeyOnly));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE CHANGEFEED FOR TABLE "books" INTO 'kafka://broker:9092' WITH envelope = 'key_only' -- binds: []"#
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Book {
+        id: i64,
+        title: String,
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn decode() {
+        let message = decode_message::<Book, serde_json::Value>(
+            None,
+            br#"{"after":null,"before":{"id":1,"title":"Dune"},"key":[1],"updated":"1591300372587669851.0000000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            ChangefeedMessage::Row(RowChange {
+                after: None,
+                before: Some(Book {
+                    id: 1,
+                    title: "Dune".to_owned()
+                }),
+                updated: Some(HlcTimestamp::new(1_591_300_372_587_669_851, 0)),
+                key: Some(serde_json::json!([1])),
+            })
+        );
+        match message {
+            ChangefeedMessage::Row(change) => assert!(change.is_delete()),
+            ChangefeedMessage::Resolved(_) => panic!("expected a row change"),
+        }
+
+        let message = decode_message::<Book, (i64,)>(
+            Some(b"[1]"),
+            br#"{"resolved":"1591300372587669851.0000000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            ChangefeedMessage::Resolved(ResolvedMessage {
+                resolved: HlcTimestamp::new(1_591_300_372_587_669_851, 0)
+            })
+        );
+    }
+
+    #[test]
+    fn version_check() {
+        let version = ClusterVersion::new(21, 2, 0);
+        let changefeed = create_changefeed(books::table, "webhook-https://example.com/feed");
+        assert!(version.check(&changefeed).is_ok());
+        assert!(ClusterVersion::new(21, 1, 0).check(&changefeed).is_err());
+        let changefeed =
+            changefeed.options(ChangefeedOptions::new().end_time(HlcTimestamp::new(1, 0)));
+        assert_eq!(
+            version.check(&changefeed).unwrap_err().to_string(),
+            "the changefeed end_time option is not supported on CockroachDB v21.2.0, and requires v22.1 or later"
+        );
+        let changefeed = create_changefeed(books::table, "kafka://broker:9092")
+            .options(ChangefeedOptions::new().initial_scan(InitialScan::Only));
+        assert_eq!(
+            version.check(&changefeed).unwrap_err().to_string(),
+            "the changefeed initial_scan option is not supported on CockroachDB v21.2.0, and requires v22.1 or later"
+        );
+        assert!(ClusterVersion::new(22, 1, 0).check(&changefeed).is_ok());
+    }
+
+    #[test]
+    fn invalid_option() {
+        let changefeed = create_changefeed(books::table, "kafka://broker:9092")
+            .with_option("updated; DROP TABLE books; --", None);
+        assert!(quote::render(&changefeed).is_err());
+        let changefeed = create_changefeed(books::table, "kafka://broker:9092")
+            .options(ChangefeedOptions::new().with_option("kafka_sink_config", None));
+        assert!(quote::render(&changefeed).is_ok());
+    }
+
+    #[test]
+    fn sinkless() {
+        let changefeed = sinkless_changefeed(books::table).options(
+            ChangefeedOptions::new()
+                .updated()
+                .cursor(HlcTimestamp::new(1_591_300_372_587_669_851, 0))
+                .end_time(HlcTimestamp::new(1_591_300_382_587_669_851, 1)),
+        );
+        assert_eq!(
+            diesel::debug_query(&changefeed).to_string(),
+            r#"EXPERIMENTAL CHANGEFEED FOR TABLE "books" WITH updated, cursor = '1591300372587669851.0000000000', end_time = '1591300382587669851.0000000001' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn first_poll() {
+        let changefeed = sinkless_changefeed(books::table);
+        let end_time = HlcTimestamp::new(1_591_300_382_587_669_851, 1);
+        assert_eq!(
+            diesel::debug_query(&changefeed.until(None, end_time)).to_string(),
+            r#"EXPERIMENTAL CHANGEFEED FOR TABLE "books" WITH initial_scan = 'yes', cursor = '1591300382587669851.0000000001', end_time = '1591300382587669851.0000000001' -- binds: []"#
+        );
+        let cursor = HlcTimestamp::new(1_591_300_372_587_669_851, 0);
+        assert_eq!(
+            diesel::debug_query(&changefeed.until(Some(cursor), end_time)).to_string(),
+            r#"EXPERIMENTAL CHANGEFEED FOR TABLE "books" WITH cursor = '1591300372587669851.0000000000', end_time = '1591300382587669851.0000000001' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn schema_change() {
+        let options = ChangefeedOptions::new()
+            .schema_change_events(SchemaChangeEvents::ColumnChanges)
+            .schema_change_policy(SchemaChangePolicy::Stop);
+        assert_eq!(
+            diesel::debug_query(&sinkless_changefeed(books::table).options(options)).to_string(),
+            r#"EXPERIMENTAL CHANGEFEED FOR TABLE "books" WITH schema_change_events = 'column_changes', schema_change_policy = 'stop' -- binds: []"#
+        );
+        assert_eq!(
+            schema_change_timestamp(
+                "schema change occurred at 1591300372587669851.0000000001: column added"
+            ),
+            Some(HlcTimestamp::new(1_591_300_372_587_669_851, 1))
+        );
+        assert_eq!(schema_change_timestamp("connection reset"), None);
+    }
+
+    #[test]
+    fn checkpoint() {
+        let mut store = None;
+        assert_eq!(store.load(), Ok(None));
+        store.save(HlcTimestamp::new(10, 0)).unwrap();
+        assert_eq!(store.load(), Ok(Some(HlcTimestamp::new(10, 0))));
+    }
+
+    #[test]
+    fn alter() {
+        let statement = ChangefeedJob::new(27_536_791_415_282)
+            .alter()
+            .add_targets((books::table, authors::table), true)
+            .drop_targets(books::table)
+            .set_options(
+                ChangefeedOptions::new()
+                    .resolved(Some(Duration::from_secs(10)))
+                    .updated(),
+            )
+            .unset_options(vec!["diff".to_owned()]);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER CHANGEFEED 27536791415282 ADD "books", "authors" WITH initial_scan = 'yes' DROP "books" SET resolved = '10s', updated UNSET diff -- binds: []"#
+        );
+        let statement = alter_changefeed(ChangefeedJob::new(1)).add_targets(books::table, false);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER CHANGEFEED 1 ADD "books" -- binds: []"#
+        );
+        let statement = ChangefeedJob::new(1)
+            .alter()
+            .set_options(ChangefeedOptions::new());
+        assert!(quote::render(&statement).is_err());
+        let statement = ChangefeedJob::new(1).alter().unset_options(vec![]);
+        assert!(quote::render(&statement).is_err());
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(
+            diesel::debug_query(&changefeed_status(vec![1])).to_string(),
+            r#"SELECT job_id, status, high_water_timestamp::STRING, running_status, error FROM crdb_internal.jobs WHERE job_type = 'CHANGEFEED' AND job_id = ANY ($1) ORDER BY job_id -- binds: [[1]]"#
+        );
+    }
+
+    #[test]
+    fn frontier() {
+        let mut frontier = ResolvedFrontier::new(vec!["a", "b"]);
+        assert_eq!(frontier.observe("a", HlcTimestamp::new(10, 1)), None);
+        assert_eq!(
+            frontier.observe("b", HlcTimestamp::new(10, 2)),
+            Some(HlcTimestamp::new(10, 1))
+        );
+        assert_eq!(
+            frontier.observe("a", HlcTimestamp::new(30, 0)),
+            Some(HlcTimestamp::new(10, 2))
+        );
+        assert_eq!(
+            frontier.observe("a", HlcTimestamp::new(20, 0)),
+            Some(HlcTimestamp::new(10, 2))
+        );
+        assert_eq!(
+            frontier.observe("b", HlcTimestamp::new(40, 0)),
+            Some(HlcTimestamp::new(30, 0))
+        );
+    }
+}
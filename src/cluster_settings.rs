@@ -0,0 +1,709 @@
+//! Support for reading and changing cluster settings.
+//!
+//! Well known settings are provided as constants with typed values. Any
+//! other setting can be read and written as a string with
+//! [`ClusterSetting::named`].
+//!
+//! See the [CockroachDB docs].
+//!
+//! Current settings can be compared against a desired configuration with
+//! [`audit_cluster_settings`].
+//!
+//! [`ClusterSetting::named`]: struct.ClusterSetting.html#method.named
+//! [`audit_cluster_settings`]: fn.audit_cluster_settings.html
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/cluster-settings.html
+
+use crate::quote;
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::Text,
+    Queryable,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    error, fmt,
+    marker::PhantomData,
+    time::Duration,
+};
+
+/// A value of a cluster setting.
+pub trait SettingValue: Sized {
+    /// Renders the value as a SQL literal, for `SET CLUSTER SETTING`.
+    ///
+    /// Values which cannot be written as a literal, such as a `NaN` float,
+    /// are an error.
+    fn to_sql_literal(&self) -> Result<String, InvalidSettingValue>;
+
+    /// Parses the value as listed by `SHOW ALL CLUSTER SETTINGS`.
+    fn from_setting(value: &str) -> Result<Self, InvalidSettingValue>;
+}
+
+impl SettingValue for String {
+    fn to_sql_literal(&self) -> Result<String, InvalidSettingValue> {
+        Ok(quote::string_literal(self))
+    }
+
+    fn from_setting(value: &str) -> Result<Self, InvalidSettingValue> {
+        Ok(value.to_owned())
+    }
+}
+
+impl SettingValue for bool {
+    fn to_sql_literal(&self) -> Result<String, InvalidSettingValue> {
+        Ok(self.to_string())
+    }
+
+    fn from_setting(value: &str) -> Result<Self, InvalidSettingValue> {
+        value
+            .parse()
+            .map_err(|_| InvalidSettingValue(value.to_owned()))
+    }
+}
+
+impl SettingValue for i64 {
+    fn to_sql_literal(&self) -> Result<String, InvalidSettingValue> {
+        Ok(self.to_string())
+    }
+
+    fn from_setting(value: &str) -> Result<Self, InvalidSettingValue> {
+        value
+            .parse()
+            .map_err(|_| InvalidSettingValue(value.to_owned()))
+    }
+}
+
+/// Only finite values can be written.
+impl SettingValue for f64 {
+    fn to_sql_literal(&self) -> Result<String, InvalidSettingValue> {
+        if self.is_finite() {
+            Ok(self.to_string())
+        } else {
+            Err(InvalidSettingValue(self.to_string()))
+        }
+    }
+
+    fn from_setting(value: &str) -> Result<Self, InvalidSettingValue> {
+        value
+            .parse()
+            .map_err(|_| InvalidSettingValue(value.to_owned()))
+    }
+}
+
+/// Durations are listed in the format of Go, such as `1h30m0s`.
+impl SettingValue for Duration {
+    fn to_sql_literal(&self) -> Result<String, InvalidSettingValue> {
+        Ok(quote::string_literal(&quote::interval(*self)))
+    }
+
+    fn from_setting(value: &str) -> Result<Self, InvalidSettingValue> {
+        quote::parse_duration(value).ok_or_else(|| InvalidSettingValue(value.to_owned()))
+    }
+}
+
+/// The error returned when a cluster setting has a value which cannot be
+/// parsed as the type of the setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSettingValue(pub String);
+
+impl fmt::Display for InvalidSettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid cluster setting value: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidSettingValue {}
+
+/// A cluster setting, with values of type `V`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterSetting<V> {
+    name: Cow<'static, str>,
+    value: PhantomData<fn() -> V>,
+}
+
+impl<V> ClusterSetting<V> {
+    /// A setting with the given name, such as `kv.rangefeed.enabled`.
+    pub const fn new(name: &'static str) -> Self {
+        ClusterSetting {
+            name: Cow::Borrowed(name),
+            value: PhantomData,
+        }
+    }
+
+    /// The name of the setting.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl ClusterSetting<String> {
+    /// Any setting, with its value read and written as a string.
+    ///
+    /// Names may only contain lower case letters, digits, `_` and `.`, and
+    /// statements changing a setting with any other name fail to build.
+    pub fn named(name: impl Into<String>) -> Self {
+        ClusterSetting {
+            name: Cow::Owned(name.into()),
+            value: PhantomData,
+        }
+    }
+}
+
+impl<V: SettingValue> ClusterSetting<V> {
+    /// Loads the current value of the setting.
+    ///
+    /// ```no_run
+    /// # use diesel::{pg::PgConnection, Connection};
+    /// # fn main() -> diesel::QueryResult<()> {
+    /// # let connection = PgConnection::establish("").unwrap();
+    /// use diesel_cockroach::cluster_settings::KV_RANGEFEED_ENABLED;
+    ///
+    /// if !KV_RANGEFEED_ENABLED.get(&connection)? {
+    ///     KV_RANGEFEED_ENABLED.set(&connection, &true)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get(&self, conn: &PgConnection) -> QueryResult<V> {
+        let value: String = show_cluster_setting(self.name()).get_result(conn)?;
+        V::from_setting(&value)
+            .map_err(|err| diesel::result::Error::DeserializationError(Box::new(err)))
+    }
+
+    /// Changes the value of the setting.
+    pub fn set(&self, conn: &PgConnection, value: &V) -> QueryResult<()> {
+        set_cluster_setting(self, value).execute(conn).map(|_| ())
+    }
+
+    /// Resets the setting to its default value.
+    pub fn reset(&self, conn: &PgConnection) -> QueryResult<()> {
+        reset_cluster_setting(self.name()).execute(conn).map(|_| ())
+    }
+}
+
+/// `cluster.organization`
+pub const CLUSTER_ORGANIZATION: ClusterSetting<String> =
+    ClusterSetting::new("cluster.organization");
+/// `cluster.preserve_downgrade_option`
+pub const CLUSTER_PRESERVE_DOWNGRADE_OPTION: ClusterSetting<String> =
+    ClusterSetting::new("cluster.preserve_downgrade_option");
+/// `diagnostics.reporting.enabled`
+pub const DIAGNOSTICS_REPORTING_ENABLED: ClusterSetting<bool> =
+    ClusterSetting::new("diagnostics.reporting.enabled");
+/// `jobs.retention_time`
+pub const JOBS_RETENTION_TIME: ClusterSetting<Duration> =
+    ClusterSetting::new("jobs.retention_time");
+/// `kv.closed_timestamp.target_duration`
+pub const KV_CLOSED_TIMESTAMP_TARGET_DURATION: ClusterSetting<Duration> =
+    ClusterSetting::new("kv.closed_timestamp.target_duration");
+/// `kv.rangefeed.enabled`, required by changefeeds.
+pub const KV_RANGEFEED_ENABLED: ClusterSetting<bool> = ClusterSetting::new("kv.rangefeed.enabled");
+/// `server.shutdown.drain_wait`
+pub const SERVER_SHUTDOWN_DRAIN_WAIT: ClusterSetting<Duration> =
+    ClusterSetting::new("server.shutdown.drain_wait");
+/// `server.time_until_store_dead`
+pub const SERVER_TIME_UNTIL_STORE_DEAD: ClusterSetting<Duration> =
+    ClusterSetting::new("server.time_until_store_dead");
+/// `sql.defaults.default_int_size`
+pub const SQL_DEFAULTS_DEFAULT_INT_SIZE: ClusterSetting<i64> =
+    ClusterSetting::new("sql.defaults.default_int_size");
+/// `sql.stats.automatic_collection.enabled`
+pub const SQL_STATS_AUTOMATIC_COLLECTION_ENABLED: ClusterSetting<bool> =
+    ClusterSetting::new("sql.stats.automatic_collection.enabled");
+
+const SHOW_ALL_COLUMNS: &str =
+    "SELECT variable, value, setting_type, description FROM [SHOW ALL CLUSTER SETTINGS]";
+
+/// Creates a query loading the value of the setting called `name`, as
+/// listed by `SHOW ALL CLUSTER SETTINGS`.
+///
+/// ```sql
+/// SELECT value FROM [SHOW ALL CLUSTER SETTINGS] WHERE variable = 'kv.rangefeed.enabled';
+/// ```
+pub fn show_cluster_setting(name: impl Into<String>) -> ShowClusterSettingStatement {
+    ShowClusterSettingStatement { name: name.into() }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query loading the value of a cluster setting.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-cluster-setting.html
+pub struct ShowClusterSettingStatement {
+    name: String,
+}
+
+impl QueryFragment<Pg> for ShowClusterSettingStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT value FROM [SHOW ALL CLUSTER SETTINGS] WHERE variable = ");
+        out.push_sql(&quote::string_literal(&self.name));
+        Ok(())
+    }
+}
+
+impl Query for ShowClusterSettingStatement {
+    type SqlType = Text;
+}
+
+impl RunQueryDsl<PgConnection> for ShowClusterSettingStatement {}
+
+impl QueryId for ShowClusterSettingStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW ALL CLUSTER SETTINGS` statement, listing every cluster
+/// setting.
+///
+/// ```sql
+/// SHOW ALL CLUSTER SETTINGS;
+/// ```
+pub fn show_all_cluster_settings() -> ShowAllClusterSettingsStatement {
+    ShowAllClusterSettingsStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW ALL CLUSTER SETTINGS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-cluster-setting.html
+pub struct ShowAllClusterSettingsStatement;
+
+impl QueryFragment<Pg> for ShowAllClusterSettingsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(SHOW_ALL_COLUMNS);
+        Ok(())
+    }
+}
+
+impl Query for ShowAllClusterSettingsStatement {
+    type SqlType = (Text, Text, Text, Text);
+}
+
+impl RunQueryDsl<PgConnection> for ShowAllClusterSettingsStatement {}
+
+impl QueryId for ShowAllClusterSettingsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A cluster setting, as returned by [`show_all_cluster_settings`].
+///
+/// [`show_all_cluster_settings`]: fn.show_all_cluster_settings.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct ClusterSettingRow {
+    /// The name of the setting.
+    pub variable: String,
+    /// The current value of the setting.
+    pub value: String,
+    /// The type of the setting, such as `b` for booleans or `d` for
+    /// durations.
+    pub setting_type: String,
+    /// A description of the setting.
+    pub description: String,
+}
+
+/// Creates a query listing the names of the cluster settings which have been
+/// changed from their defaults.
+///
+/// Only settings changed with `SET CLUSTER SETTING` are stored in the
+/// `system.settings` table, which requires the `admin` role to read.
+///
+/// ```sql
+/// SELECT name FROM system.settings ORDER BY name;
+/// ```
+pub fn show_changed_cluster_settings() -> ShowChangedClusterSettingsStatement {
+    ShowChangedClusterSettingsStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query listing changed cluster settings.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/cluster-settings.html
+pub struct ShowChangedClusterSettingsStatement;
+
+impl QueryFragment<Pg> for ShowChangedClusterSettingsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT name FROM system.settings ORDER BY name");
+        Ok(())
+    }
+}
+
+impl Query for ShowChangedClusterSettingsStatement {
+    type SqlType = Text;
+}
+
+impl RunQueryDsl<PgConnection> for ShowChangedClusterSettingsStatement {}
+
+impl QueryId for ShowChangedClusterSettingsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A cluster setting whose current value differs from the desired value, as
+/// found by [`audit_cluster_settings`].
+///
+/// [`audit_cluster_settings`]: fn.audit_cluster_settings.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingDrift {
+    /// The name of the setting.
+    pub name: String,
+    /// The current value of the setting, or `None` if the cluster has no
+    /// such setting.
+    pub current: Option<String>,
+    /// The desired value of the setting.
+    pub desired: String,
+    /// Whether the setting has been changed from its default.
+    pub changed: bool,
+}
+
+/// The result of comparing the cluster settings against a desired
+/// configuration with [`audit_cluster_settings`].
+///
+/// [`audit_cluster_settings`]: fn.audit_cluster_settings.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SettingsAudit {
+    /// The desired settings which have a different value, ordered by name.
+    pub drifted: Vec<SettingDrift>,
+    /// The settings changed from their defaults which are not part of the
+    /// desired configuration, ordered by name.
+    pub unmanaged: Vec<ClusterSettingRow>,
+}
+
+impl SettingsAudit {
+    /// Whether the cluster matches the desired configuration, ignoring
+    /// unmanaged settings.
+    pub fn is_clean(&self) -> bool {
+        self.drifted.is_empty()
+    }
+}
+
+/// Compares the cluster settings against `desired`, a map of setting names
+/// to values in the format accepted by `SET CLUSTER SETTING`.
+///
+/// Values are compared according to the type of each setting, so a desired
+/// duration of `1h` matches a current value of `1h0m0s`.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::cluster_settings::audit_cluster_settings;
+/// use std::collections::HashMap;
+///
+/// let mut desired = HashMap::new();
+/// desired.insert("kv.rangefeed.enabled".to_owned(), "true".to_owned());
+/// let audit = audit_cluster_settings(&connection, &desired)?;
+/// for drift in audit.drifted {
+///     println!("{}: {:?} != {}", drift.name, drift.current, drift.desired);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn audit_cluster_settings(
+    conn: &PgConnection,
+    desired: &HashMap<String, String>,
+) -> QueryResult<SettingsAudit> {
+    let settings = show_all_cluster_settings().load(conn)?;
+    let changed = show_changed_cluster_settings().load(conn)?;
+    Ok(audit(settings, &changed.into_iter().collect(), desired))
+}
+
+fn audit(
+    settings: Vec<ClusterSettingRow>,
+    changed: &HashSet<String>,
+    desired: &HashMap<String, String>,
+) -> SettingsAudit {
+    let mut audit = SettingsAudit::default();
+    let mut found = HashSet::new();
+    for setting in settings {
+        let is_changed = changed.contains(&setting.variable);
+        match desired.get(&setting.variable) {
+            Some(value) => {
+                found.insert(setting.variable.clone());
+                if !values_match(&setting.setting_type, &setting.value, value) {
+                    audit.drifted.push(SettingDrift {
+                        name: setting.variable,
+                        current: Some(setting.value),
+                        desired: value.clone(),
+                        changed: is_changed,
+                    });
+                }
+            }
+            None if is_changed => audit.unmanaged.push(setting),
+            None => {}
+        }
+    }
+    for (name, value) in desired {
+        if !found.contains(name) {
+            audit.drifted.push(SettingDrift {
+                name: name.clone(),
+                current: None,
+                desired: value.clone(),
+                changed: false,
+            });
+        }
+    }
+    audit.drifted.sort_by(|a, b| a.name.cmp(&b.name));
+    audit.unmanaged.sort_by(|a, b| a.variable.cmp(&b.variable));
+    audit
+}
+
+fn values_match(setting_type: &str, current: &str, desired: &str) -> bool {
+    let desired = desired.trim().trim_matches('\'');
+    match setting_type {
+        "b" => current.eq_ignore_ascii_case(desired),
+        "d" => match (
+            quote::parse_duration(current),
+            quote::parse_duration(desired),
+        ) {
+            (Some(current), Some(desired)) => current == desired,
+            _ => current == desired,
+        },
+        "f" | "i" => match (current.parse::<f64>(), desired.parse::<f64>()) {
+            (Ok(current), Ok(desired)) => current == desired,
+            _ => current == desired,
+        },
+        _ => current == desired,
+    }
+}
+
+/// Creates a `SET CLUSTER SETTING` statement.
+///
+/// ```sql
+/// SET CLUSTER SETTING kv.rangefeed.enabled = true;
+/// ```
+pub fn set_cluster_setting<V: SettingValue>(
+    setting: &ClusterSetting<V>,
+    value: &V,
+) -> SetClusterSettingStatement {
+    SetClusterSettingStatement {
+        name: setting.name().to_owned(),
+        value: Some(value.to_sql_literal()),
+    }
+}
+
+/// Creates a `RESET CLUSTER SETTING` statement, restoring the default value.
+///
+/// Like [`ClusterSetting::named`], `name` may only contain lower case
+/// letters, digits, `_` and `.`.
+///
+/// ```sql
+/// RESET CLUSTER SETTING kv.rangefeed.enabled;
+/// ```
+///
+/// [`ClusterSetting::named`]: struct.ClusterSetting.html#method.named
+pub fn reset_cluster_setting(name: impl Into<String>) -> SetClusterSettingStatement {
+    SetClusterSettingStatement {
+        name: name.into(),
+        value: None,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SET CLUSTER SETTING` or `RESET CLUSTER SETTING`
+/// statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/set-cluster-setting.html
+pub struct SetClusterSettingStatement {
+    name: String,
+    value: Option<Result<String, InvalidSettingValue>>,
+}
+
+impl QueryFragment<Pg> for SetClusterSettingStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        // Setting names are dotted paths, which are not quoted as a single
+        // identifier, so are checked instead.
+        let name = quote::setting_name(&self.name)?;
+        match &self.value {
+            Some(value) => {
+                let value = value.as_ref().map_err(|err| {
+                    diesel::result::Error::QueryBuilderError(Box::new(err.clone()))
+                })?;
+                out.push_sql("SET CLUSTER SETTING ");
+                out.push_sql(name);
+                out.push_sql(" = ");
+                out.push_sql(value);
+            }
+            None => {
+                out.push_sql("RESET CLUSTER SETTING ");
+                out.push_sql(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for SetClusterSettingStatement {}
+
+impl QueryId for SetClusterSettingStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn show() {
+        assert_eq!(
+            diesel::debug_query(&show_cluster_setting("kv.rangefeed.enabled")).to_string(),
+            r#"SELECT value FROM [SHOW ALL CLUSTER SETTINGS] WHERE variable = 'kv.rangefeed.enabled' -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_all_cluster_settings()).to_string(),
+            r#"SELECT variable, value, setting_type, description FROM [SHOW ALL CLUSTER SETTINGS] -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn set() {
+        assert_eq!(
+            diesel::debug_query(&set_cluster_setting(&KV_RANGEFEED_ENABLED, &true)).to_string(),
+            r#"SET CLUSTER SETTING kv.rangefeed.enabled = true -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&set_cluster_setting(
+                &SERVER_TIME_UNTIL_STORE_DEAD,
+                &Duration::from_secs(300)
+            ))
+            .to_string(),
+            r#"SET CLUSTER SETTING server.time_until_store_dead = '300s' -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&set_cluster_setting(
+                &ClusterSetting::named("cluster.organization"),
+                &"O'Reilly".to_owned()
+            ))
+            .to_string(),
+            r#"SET CLUSTER SETTING cluster.organization = 'O''Reilly' -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&reset_cluster_setting("kv.rangefeed.enabled")).to_string(),
+            r#"RESET CLUSTER SETTING kv.rangefeed.enabled -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let setting = ClusterSetting::named("cluster.organization = 'x'; DROP TABLE books; --");
+        let query = set_cluster_setting(&setting, &"y".to_owned());
+        assert!(quote::render(&query).is_err());
+        assert!(quote::render(&reset_cluster_setting("sql..stats")).is_err());
+        let setting = ClusterSetting::<f64>::new("sql.stats.histogram_samples.ratio");
+        assert!(quote::render(&set_cluster_setting(&setting, &f64::NAN)).is_err());
+        assert!(quote::render(&set_cluster_setting(&setting, &f64::INFINITY)).is_err());
+        assert_eq!(
+            quote::render(&set_cluster_setting(&setting, &0.25)).unwrap(),
+            "SET CLUSTER SETTING sql.stats.histogram_samples.ratio = 0.25"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(bool::from_setting("true"), Ok(true));
+        assert_eq!(i64::from_setting("8"), Ok(8));
+        assert_eq!(
+            Duration::from_setting("1h30m0s"),
+            Ok(Duration::from_secs(5400))
+        );
+        assert_eq!(
+            Duration::from_setting("1.5s"),
+            Ok(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            Duration::from_setting("250ms"),
+            Ok(Duration::from_millis(250))
+        );
+        assert_eq!(Duration::from_setting("0s"), Ok(Duration::from_secs(0)));
+        assert_eq!(
+            Duration::from_setting("5 minutes"),
+            Err(InvalidSettingValue("5 minutes".to_owned()))
+        );
+    }
+
+    #[test]
+    fn audit_settings() {
+        assert_eq!(
+            diesel::debug_query(&show_changed_cluster_settings()).to_string(),
+            r#"SELECT name FROM system.settings ORDER BY name -- binds: []"#
+        );
+
+        let row = |variable: &str, value: &str, setting_type: &str| ClusterSettingRow {
+            variable: variable.to_owned(),
+            value: value.to_owned(),
+            setting_type: setting_type.to_owned(),
+            description: String::new(),
+        };
+        let settings = vec![
+            row("cluster.organization", "Acme", "s"),
+            row("jobs.retention_time", "336h0m0s", "d"),
+            row("kv.rangefeed.enabled", "false", "b"),
+            row("sql.defaults.default_int_size", "8", "i"),
+        ];
+        let changed = ["cluster.organization", "jobs.retention_time"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let desired = [
+            ("jobs.retention_time", "336h"),
+            ("kv.rangefeed.enabled", "true"),
+            ("sql.defaults.default_int_size", "8"),
+            ("sql.unknown", "1"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        let audit = audit(settings, &changed, &desired);
+        assert_eq!(
+            audit.drifted,
+            vec![
+                SettingDrift {
+                    name: "kv.rangefeed.enabled".to_owned(),
+                    current: Some("false".to_owned()),
+                    desired: "true".to_owned(),
+                    changed: false,
+                },
+                SettingDrift {
+                    name: "sql.unknown".to_owned(),
+                    current: None,
+                    desired: "1".to_owned(),
+                    changed: false,
+                },
+            ]
+        );
+        assert_eq!(
+            audit.unmanaged,
+            vec![row("cluster.organization", "Acme", "s")]
+        );
+        assert!(!audit.is_clean());
+    }
+}
@@ -0,0 +1,161 @@
+//! Support for the `COLLATE` operator, comparing and ordering strings by the
+//! rules of a locale.
+//!
+//! ```no_run
+//! # #[macro_use] extern crate diesel;
+//! # use diesel::{pg::PgConnection, Connection, QueryDsl, RunQueryDsl};
+//! # table! { books (id) { id -> Int8, title -> Text, } }
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::collation::{CollateExpressionMethods, CASE_INSENSITIVE};
+//!
+//! let titles: Vec<String> = books::table
+//!     .select(books::title)
+//!     .order(books::title.collate(CASE_INSENSITIVE))
+//!     .load(&connection)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/collate.html
+
+use diesel::{
+    expression::{AppearsOnTable, Expression, NonAggregate, SelectableExpression},
+    pg::Pg,
+    query_builder::{AstPass, QueryFragment, QueryId},
+    result::QueryResult,
+    sql_types::Text,
+};
+use std::borrow::Cow;
+
+/// A collation, named by a Unicode locale identifier such as `de` or
+/// `und-u-ks-level2`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Collation {
+    name: Cow<'static, str>,
+}
+
+impl Collation {
+    /// A collation with the given name.
+    pub const fn new(name: &'static str) -> Self {
+        Collation {
+            name: Cow::Borrowed(name),
+        }
+    }
+
+    /// A collation with a name only known at runtime.
+    pub fn named(name: impl Into<String>) -> Self {
+        Collation {
+            name: Cow::Owned(name.into()),
+        }
+    }
+
+    /// The name of the collation.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// `und-u-ks-level2`: compares strings ignoring case, in any language.
+pub const CASE_INSENSITIVE: Collation = Collation::new("und-u-ks-level2");
+/// `und`: compares strings by the Unicode collation algorithm, without
+/// language specific rules.
+pub const UNICODE: Collation = Collation::new("und");
+/// `en`: English.
+pub const ENGLISH: Collation = Collation::new("en");
+/// `de`: German.
+pub const GERMAN: Collation = Collation::new("de");
+/// `fr`: French.
+pub const FRENCH: Collation = Collation::new("fr");
+
+/// Adds the [`collate`] method to text expressions.
+///
+/// [`collate`]: #method.collate
+pub trait CollateExpressionMethods: Expression<SqlType = Text> + Sized {
+    /// Creates a `COLLATE` expression, so comparisons and ordering follow the
+    /// rules of `collation`.
+    ///
+    /// Both sides of a comparison must have the same collation, such as a
+    /// collated `STRING` column and a string collated to match it.
+    ///
+    /// ```sql
+    /// title COLLATE "und-u-ks-level2"
+    /// ```
+    fn collate(self, collation: Collation) -> Collate<Self> {
+        Collate {
+            expr: self,
+            collation,
+        }
+    }
+}
+
+impl<T> CollateExpressionMethods for T where T: Expression<SqlType = Text> {}
+
+/// A `COLLATE` expression, created by [`collate`].
+///
+/// [`collate`]: trait.CollateExpressionMethods.html#method.collate
+#[derive(Debug, Clone)]
+pub struct Collate<E> {
+    expr: E,
+    collation: Collation,
+}
+
+// Collated strings have the same representation as strings.
+impl<E> Expression for Collate<E> {
+    type SqlType = Text;
+}
+
+impl<E: NonAggregate> NonAggregate for Collate<E> {}
+
+impl<E, QS> SelectableExpression<QS> for Collate<E> where E: SelectableExpression<QS> {}
+
+impl<E, QS> AppearsOnTable<QS> for Collate<E> where E: AppearsOnTable<QS> {}
+
+impl<E> QueryFragment<Pg> for Collate<E>
+where
+    E: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        self.expr.walk_ast(out.reborrow())?;
+        out.push_sql(" COLLATE ");
+        out.push_identifier(self.collation.name())
+    }
+}
+
+impl<E> QueryId for Collate<E> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, IntoSql, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn collate() {
+        let query = books::table
+            .select(books::id)
+            .filter(
+                books::title
+                    .collate(CASE_INSENSITIVE)
+                    .eq("dune".into_sql::<Text>().collate(CASE_INSENSITIVE)),
+            )
+            .order(books::title.collate(Collation::named("de-u-co-phonebk")));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "books"."id" FROM "books" WHERE "books"."title" COLLATE "und-u-ks-level2" = $1 COLLATE "und-u-ks-level2" ORDER BY "books"."title" COLLATE "de-u-co-phonebk" -- binds: ["dune"]"#
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Lists of columns used in DDL statements.
+
+use diesel::{
+    pg::Pg,
+    query_builder::AstPass,
+    query_source::{Column, Table},
+    result::QueryResult,
+};
+
+/// Represents an unqualified list of columns, such as the key columns of an index.
+///
+/// This trait is implemented by columns and tuples of columns from the same
+/// table. The `Kind` parameter only exists to keep these implementations apart,
+/// and never needs to be specified by hand.
+pub trait ColumnList<Kind> {
+    /// The table these columns belong to
+    type Table: Table;
+
+    /// The names of the columns in this list, in order.
+    fn names(&self) -> Vec<&'static str>;
+}
+
+/// Marks the [`ColumnList`] implementation for a single column.
+///
+/// [`ColumnList`]: trait.ColumnList.html
+#[derive(Debug, Clone, Copy)]
+pub struct SingleColumn;
+
+/// Marks the [`ColumnList`] implementation for a tuple of columns.
+///
+/// [`ColumnList`]: trait.ColumnList.html
+#[derive(Debug, Clone, Copy)]
+pub struct TupleOfColumns;
+
+impl<C> ColumnList<SingleColumn> for C
+where
+    C: Column,
+    C::Table: Table,
+{
+    type Table = <C as Column>::Table;
+
+    fn names(&self) -> Vec<&'static str> {
+        vec![C::NAME]
+    }
+}
+
+macro_rules! tuple_column_list {
+    ($($T:ident),+) => {
+        impl<Tab: Table, $($T: Column<Table = Tab>,)+> ColumnList<TupleOfColumns> for ($($T,)+) {
+            type Table = Tab;
+
+            fn names(&self) -> Vec<&'static str> {
+                vec![$($T::NAME,)+]
+            }
+        }
+    };
+}
+
+tuple_column_list!(A);
+tuple_column_list!(A, B);
+tuple_column_list!(A, B, C);
+tuple_column_list!(A, B, C, D);
+tuple_column_list!(A, B, C, D, E);
+tuple_column_list!(A, B, C, D, E, F);
+tuple_column_list!(A, B, C, D, E, F, G);
+tuple_column_list!(A, B, C, D, E, F, G, H);
+tuple_column_list!(A, B, C, D, E, F, G, H, I);
+tuple_column_list!(A, B, C, D, E, F, G, H, I, J);
+tuple_column_list!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_column_list!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Pushes a comma separated list of quoted identifiers.
+pub(crate) fn push_identifiers(out: &mut AstPass<Pg>, names: &[&str]) -> QueryResult<()> {
+    for (index, name) in names.iter().enumerate() {
+        if index > 0 {
+            out.push_sql(", ");
+        }
+        out.push_identifier(name)?;
+    }
+    Ok(())
+}
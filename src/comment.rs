@@ -0,0 +1,417 @@
+//! Support for `COMMENT ON` SQL queries, and for reading existing comments.
+
+use crate::quote;
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::{Column, QuerySource},
+    result::QueryResult,
+    sql_types::{Nullable, Text},
+    Queryable,
+};
+
+/// Creates a `COMMENT ON TABLE` statement for the target table.
+pub fn comment_on_table<T>(target: T) -> IncompleteCommentStatement<TableObject<T>> {
+    IncompleteCommentStatement::new(TableObject(target))
+}
+
+/// Creates a `COMMENT ON COLUMN` statement for a column of the target table.
+pub fn comment_on_column<T, C>(
+    target: T,
+    column: C,
+) -> IncompleteCommentStatement<ColumnObject<T, C>>
+where
+    C: Column<Table = T>,
+{
+    IncompleteCommentStatement::new(ColumnObject(target, column))
+}
+
+/// Creates a `COMMENT ON INDEX` statement for the named index on the target table.
+pub fn comment_on_index<T>(
+    target: T,
+    name: impl Into<String>,
+) -> IncompleteCommentStatement<IndexObject<T>> {
+    IncompleteCommentStatement::new(IndexObject(target, name.into()))
+}
+
+/// Creates a `COMMENT ON DATABASE` statement for the named database.
+pub fn comment_on_database(name: impl Into<String>) -> IncompleteCommentStatement<DatabaseObject> {
+    IncompleteCommentStatement::new(DatabaseObject(name.into()))
+}
+
+/// The structure returned by the `comment_on_*` functions.
+///
+/// The provided methods [`is`] and [`is_null`] set or remove the comment.
+///
+/// [`is`]: #method.is
+/// [`is_null`]: #method.is_null
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+pub struct IncompleteCommentStatement<O> {
+    object: O,
+}
+
+impl<O> IncompleteCommentStatement<O> {
+    fn new(object: O) -> Self {
+        IncompleteCommentStatement { object }
+    }
+
+    /// Set the comment on the object.
+    ///
+    /// ```sql
+    /// COMMENT ON TABLE books IS 'Books we own';
+    /// ```
+    pub fn is(self, comment: impl Into<String>) -> CommentStatement<O> {
+        CommentStatement::new(self.object, Some(comment.into()))
+    }
+
+    /// Remove any comment on the object.
+    ///
+    /// ```sql
+    /// COMMENT ON TABLE books IS NULL;
+    /// ```
+    pub fn is_null(self) -> CommentStatement<O> {
+        CommentStatement::new(self.object, None)
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `COMMENT ON` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `O`: The object we are commenting on
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/comment-on.html
+pub struct CommentStatement<O> {
+    object: O,
+    comment: Option<String>,
+}
+
+impl<O> CommentStatement<O> {
+    fn new(object: O, comment: Option<String>) -> Self {
+        CommentStatement { object, comment }
+    }
+}
+
+impl<O> QueryFragment<Pg> for CommentStatement<O>
+where
+    O: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("COMMENT ON ");
+        self.object.walk_ast(out.reborrow())?;
+        out.push_sql(" IS ");
+        match &self.comment {
+            Some(comment) => out.push_sql(&quote::string_literal(comment)),
+            None => out.push_sql("NULL"),
+        }
+        Ok(())
+    }
+}
+
+impl<O> RunQueryDsl<PgConnection> for CommentStatement<O> {}
+
+impl<O> QueryId for CommentStatement<O> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A table to comment on, created by [`comment_on_table`].
+///
+/// [`comment_on_table`]: fn.comment_on_table.html
+#[derive(Debug, Clone, Copy)]
+pub struct TableObject<T>(T);
+
+impl<T> QueryFragment<Pg> for TableObject<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("TABLE ");
+        self.0.from_clause().walk_ast(out.reborrow())
+    }
+}
+
+/// A column to comment on, created by [`comment_on_column`].
+///
+/// [`comment_on_column`]: fn.comment_on_column.html
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnObject<T, C>(T, C);
+
+impl<T, C> QueryFragment<Pg> for ColumnObject<T, C>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+    C: Column,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("COLUMN ");
+        self.0.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(".");
+        out.push_identifier(C::NAME)
+    }
+}
+
+/// An index to comment on, created by [`comment_on_index`].
+///
+/// [`comment_on_index`]: fn.comment_on_index.html
+#[derive(Debug, Clone)]
+pub struct IndexObject<T>(T, String);
+
+impl<T> QueryFragment<Pg> for IndexObject<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("INDEX ");
+        self.0.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql("@");
+        out.push_identifier(&self.1)
+    }
+}
+
+/// A database to comment on, created by [`comment_on_database`].
+///
+/// [`comment_on_database`]: fn.comment_on_database.html
+#[derive(Debug, Clone)]
+pub struct DatabaseObject(String);
+
+impl QueryFragment<Pg> for DatabaseObject {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("DATABASE ");
+        out.push_identifier(&self.0)
+    }
+}
+
+/// Creates a query reading the comment on the target table.
+///
+/// The query loads a single `Option<String>`, which is `None` if the table
+/// has no comment.
+pub fn table_comment<T>(target: T) -> CommentsQuery<T, Nullable<Text>> {
+    CommentsQuery::new(target, CommentsKind::Table)
+}
+
+/// Creates a query reading the comments on each column of the target table.
+///
+/// The query loads [`ColumnComment`] rows.
+///
+/// [`ColumnComment`]: struct.ColumnComment.html
+pub fn column_comments<T>(target: T) -> CommentsQuery<T, (Text, Nullable<Text>)> {
+    CommentsQuery::new(target, CommentsKind::Columns)
+}
+
+/// Creates a query reading the comments on each index of the target table.
+///
+/// The query loads [`IndexComment`] rows.
+///
+/// [`IndexComment`]: struct.IndexComment.html
+pub fn index_comments<T>(target: T) -> CommentsQuery<T, (Text, Nullable<Text>)> {
+    CommentsQuery::new(target, CommentsKind::Indexes)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CommentsKind {
+    Table,
+    Columns,
+    Indexes,
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A query reading existing comments on a table, created by
+/// [`table_comment`], [`column_comments`] or [`index_comments`].
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table we are reading comments for
+/// - `ST`: The SQL type of the returned rows
+///
+/// [`table_comment`]: fn.table_comment.html
+/// [`column_comments`]: fn.column_comments.html
+/// [`index_comments`]: fn.index_comments.html
+pub struct CommentsQuery<T, ST> {
+    target: T,
+    kind: CommentsKind,
+    sql_type: std::marker::PhantomData<ST>,
+}
+
+impl<T, ST> CommentsQuery<T, ST> {
+    fn new(target: T, kind: CommentsKind) -> Self {
+        CommentsQuery {
+            target,
+            kind,
+            sql_type: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, ST> QueryFragment<Pg> for CommentsQuery<T, ST>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        match self.kind {
+            CommentsKind::Table => {
+                let table = quote::render(&self.target.from_clause())?;
+                out.push_sql("SELECT obj_description(");
+                out.push_sql(&quote::string_literal(&table));
+                out.push_sql("::REGCLASS::OID, 'pg_class')");
+            }
+            CommentsKind::Columns => {
+                out.push_sql("SELECT column_name, comment FROM [SHOW COLUMNS FROM ");
+                self.target.from_clause().walk_ast(out.reborrow())?;
+                out.push_sql(" WITH COMMENT]");
+            }
+            CommentsKind::Indexes => {
+                out.push_sql("SELECT DISTINCT index_name, comment FROM [SHOW INDEXES FROM ");
+                self.target.from_clause().walk_ast(out.reborrow())?;
+                out.push_sql(" WITH COMMENT]");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, ST> Query for CommentsQuery<T, ST> {
+    type SqlType = ST;
+}
+
+impl<T, ST> RunQueryDsl<PgConnection> for CommentsQuery<T, ST> {}
+
+impl<T, ST> QueryId for CommentsQuery<T, ST> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a query reading the comment on the named database.
+///
+/// The query loads a single `Option<String>`, which is `None` if the
+/// database has no comment.
+pub fn database_comment(name: impl Into<String>) -> DatabaseCommentQuery {
+    DatabaseCommentQuery { name: name.into() }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A query reading the existing comment on a database, created by
+/// [`database_comment`].
+///
+/// [`database_comment`]: fn.database_comment.html
+pub struct DatabaseCommentQuery {
+    name: String,
+}
+
+impl QueryFragment<Pg> for DatabaseCommentQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT comment FROM [SHOW DATABASES WITH COMMENT] WHERE database_name = ");
+        out.push_sql(&quote::string_literal(&self.name));
+        Ok(())
+    }
+}
+
+impl Query for DatabaseCommentQuery {
+    type SqlType = Nullable<Text>;
+}
+
+impl RunQueryDsl<PgConnection> for DatabaseCommentQuery {}
+
+impl QueryId for DatabaseCommentQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A row returned by [`column_comments`].
+///
+/// [`column_comments`]: fn.column_comments.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct ColumnComment {
+    /// The name of the column.
+    pub column_name: String,
+    /// The comment on the column, if any.
+    pub comment: Option<String>,
+}
+
+/// A row returned by [`index_comments`].
+///
+/// [`index_comments`]: fn.index_comments.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct IndexComment {
+    /// The name of the index.
+    pub index_name: String,
+    /// The comment on the index, if any.
+    pub comment: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn comment() {
+        assert_eq!(
+            diesel::debug_query(&comment_on_table(books::table).is("Books we've read")).to_string(),
+            r#"COMMENT ON TABLE "books" IS 'Books we''ve read' -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(
+                &comment_on_column(books::table, books::title).is("The full title")
+            )
+            .to_string(),
+            r#"COMMENT ON COLUMN "books"."title" IS 'The full title' -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&comment_on_index(books::table, "books_title_idx").is_null())
+                .to_string(),
+            r#"COMMENT ON INDEX "books"@"books_title_idx" IS NULL -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&comment_on_database("library").is("Everything")).to_string(),
+            r#"COMMENT ON DATABASE "library" IS 'Everything' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn read() {
+        assert_eq!(
+            diesel::debug_query(&table_comment(books::table)).to_string(),
+            r#"SELECT obj_description('"books"'::REGCLASS::OID, 'pg_class') -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&column_comments(books::table)).to_string(),
+            r#"SELECT column_name, comment FROM [SHOW COLUMNS FROM "books" WITH COMMENT] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&index_comments(books::table)).to_string(),
+            r#"SELECT DISTINCT index_name, comment FROM [SHOW INDEXES FROM "books" WITH COMMENT] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&database_comment("library")).to_string(),
+            r#"SELECT comment FROM [SHOW DATABASES WITH COMMENT] WHERE database_name = 'library' -- binds: []"#
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Support for `COPY ... FROM STDIN` SQL queries, for bulk loading rows.
+//!
+//! Diesel's `PgConnection` does not expose the `COPY` sub-protocol, so these
+//! statements and their data must be sent with a driver which does, such as
+//! the `postgres` crate's `copy_in`. This module renders the statement for a
+//! Diesel table, and encodes rows in the text format it expects.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/copy-from.html
+
+use crate::column_list::{push_identifiers, ColumnList};
+use diesel::{
+    pg::Pg,
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_source::QuerySource,
+    result::QueryResult,
+};
+use std::io::{self, Write};
+
+/// Creates a `COPY ... FROM STDIN` statement, loading rows into the target
+/// table.
+///
+/// ```
+/// # #[macro_use] extern crate diesel;
+/// # table! { books (id) { id -> Int8, title -> Text, } }
+/// # fn main() -> std::io::Result<()> {
+/// use diesel_cockroach::copy::{copy_from, CopyWriter};
+///
+/// let statement = copy_from(books::table).columns((books::id, books::title));
+/// assert_eq!(
+///     diesel::debug_query(&statement).to_string(),
+///     r#"COPY "books" ("id", "title") FROM STDIN -- binds: []"#
+/// );
+///
+/// let mut writer = CopyWriter::new(Vec::new());
+/// writer.write_row(vec![Some("1"), Some("Dune")])?;
+/// writer.write_row(vec![Some("2"), None])?;
+/// assert_eq!(writer.finish()?, b"1\tDune\n2\t\\N\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn copy_from<T>(target: T) -> CopyFromStatement<T> {
+    CopyFromStatement {
+        target,
+        columns: Vec::new(),
+        csv: false,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Copy statements are only executed when sent with a driver exposing the `COPY` protocol."]
+/// A fully constructed `COPY ... FROM STDIN` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table rows are copied into
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/copy-from.html
+pub struct CopyFromStatement<T> {
+    target: T,
+    columns: Vec<&'static str>,
+    csv: bool,
+}
+
+impl<T> CopyFromStatement<T> {
+    /// Copy values for the given columns, in the order they appear in each
+    /// row. By default, each row has a value for every column of the table.
+    pub fn columns<C, K>(mut self, columns: C) -> Self
+    where
+        C: ColumnList<K, Table = T>,
+    {
+        self.columns = columns.names();
+        self
+    }
+
+    /// Copy comma separated values, such as the contents of a CSV file, rather
+    /// than the text format written by [`CopyWriter`].
+    ///
+    /// [`CopyWriter`]: struct.CopyWriter.html
+    pub fn csv(mut self) -> Self {
+        self.csv = true;
+        self
+    }
+}
+
+impl<T> QueryFragment<Pg> for CopyFromStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("COPY ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        if !self.columns.is_empty() {
+            out.push_sql(" (");
+            push_identifiers(&mut out, &self.columns)?;
+            out.push_sql(")");
+        }
+        out.push_sql(" FROM STDIN");
+        if self.csv {
+            out.push_sql(" WITH CSV");
+        }
+        Ok(())
+    }
+}
+
+impl<T> QueryId for CopyFromStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Encodes rows in the text format of `COPY ... FROM STDIN`, streaming them
+/// into a writer.
+///
+/// Values are separated by tabs, and `NULL` is written as `\N`.
+#[derive(Debug)]
+pub struct CopyWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> CopyWriter<W> {
+    /// Creates a writer encoding rows into `writer`.
+    pub fn new(writer: W) -> Self {
+        CopyWriter { writer }
+    }
+
+    /// Writes a single row of values, where `None` is `NULL`.
+    pub fn write_row<I, V>(&mut self, values: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = Option<V>>,
+        V: AsRef<str>,
+    {
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                self.writer.write_all(b"\t")?;
+            }
+            match value {
+                Some(value) => self.writer.write_all(escape(value.as_ref()).as_bytes())?,
+                None => self.writer.write_all(b"\\N")?,
+            }
+        }
+        self.writer.write_all(b"\n")
+    }
+
+    /// Writes every row from an iterator.
+    pub fn write_rows<R, I, V>(&mut self, rows: R) -> io::Result<()>
+    where
+        R: IntoIterator<Item = I>,
+        I: IntoIterator<Item = Option<V>>,
+        V: AsRef<str>,
+    {
+        for row in rows {
+            self.write_row(row)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Escapes a value for the text format.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn statement() {
+        assert_eq!(
+            diesel::debug_query(&copy_from(books::table)).to_string(),
+            r#"COPY "books" FROM STDIN -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&copy_from(books::table).columns(books::title).csv()).to_string(),
+            r#"COPY "books" ("title") FROM STDIN WITH CSV -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn escaped() {
+        let mut writer = CopyWriter::new(Vec::new());
+        writer
+            .write_rows(vec![
+                [Some("1"), Some("tab\there")],
+                [Some("2"), Some("back\\slash\nnewline")],
+                [None, Some("\\N")],
+            ])
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(writer.finish().unwrap()).unwrap(),
+            "1\ttab\\there\n2\tback\\\\slash\\nnewline\n\\N\t\\\\N\n"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Table definitions for commonly used tables of the `crdb_internal` schema,
+//! CockroachDB's introspection tables.
+//!
+//! These tables are not covered by any stability guarantees, and their
+//! columns change between CockroachDB versions. Only columns present since
+//! v21.2 are defined here.
+//!
+//! ```no_run
+//! # use diesel::{pg::PgConnection, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::crdb_internal::{node_build_info, NodeBuildInfoRow};
+//!
+//! let versions: Vec<NodeBuildInfoRow> = node_build_info::table
+//!     .filter(node_build_info::field.eq("Version"))
+//!     .load(&connection)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/crdb-internal.html
+
+use diesel::Queryable;
+use std::time::SystemTime;
+
+table! {
+    /// The jobs of the cluster, including those not shown by `SHOW JOBS`.
+    crdb_internal.jobs (job_id) {
+        job_id -> Int8,
+        job_type -> Nullable<Text>,
+        description -> Nullable<Text>,
+        statement -> Nullable<Text>,
+        user_name -> Nullable<Text>,
+        descriptor_ids -> Nullable<Array<Int8>>,
+        status -> Nullable<Text>,
+        running_status -> Nullable<Text>,
+        created -> Nullable<Timestamp>,
+        started -> Nullable<Timestamp>,
+        finished -> Nullable<Timestamp>,
+        modified -> Nullable<Timestamp>,
+        fraction_completed -> Nullable<Float8>,
+        error -> Nullable<Text>,
+        coordinator_id -> Nullable<Int8>,
+    }
+}
+
+/// A row of [`crdb_internal.jobs`].
+///
+/// [`crdb_internal.jobs`]: jobs/index.html
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct JobsRow {
+    /// The ID of the job.
+    pub job_id: i64,
+    /// The type of the job, such as `BACKUP`.
+    pub job_type: Option<String>,
+    /// A description of the job.
+    pub description: Option<String>,
+    /// The statement that started the job.
+    pub statement: Option<String>,
+    /// The user who started the job.
+    pub user_name: Option<String>,
+    /// The IDs of the descriptors the job affects.
+    pub descriptor_ids: Option<Vec<i64>>,
+    /// The current status of the job.
+    pub status: Option<String>,
+    /// A description of what a running job is currently doing.
+    pub running_status: Option<String>,
+    /// When the job was created.
+    pub created: Option<SystemTime>,
+    /// When the job started running.
+    pub started: Option<SystemTime>,
+    /// When the job finished.
+    pub finished: Option<SystemTime>,
+    /// When the job was last updated.
+    pub modified: Option<SystemTime>,
+    /// The fraction of the job completed, between `0.0` and `1.0`.
+    pub fraction_completed: Option<f64>,
+    /// The error the job failed with, if any.
+    pub error: Option<String>,
+    /// The node coordinating the job.
+    pub coordinator_id: Option<i64>,
+}
+
+table! {
+    /// The ranges of the cluster, without leaseholder information, which is
+    /// expensive to collect.
+    crdb_internal.ranges_no_leases (range_id) {
+        range_id -> Int8,
+        start_key -> Bytea,
+        start_pretty -> Text,
+        end_key -> Bytea,
+        end_pretty -> Text,
+        replicas -> Array<Int8>,
+        replica_localities -> Array<Text>,
+        voting_replicas -> Array<Int8>,
+        non_voting_replicas -> Array<Int8>,
+        learner_replicas -> Array<Int8>,
+        split_enforced_until -> Nullable<Timestamp>,
+    }
+}
+
+/// A row of [`crdb_internal.ranges_no_leases`].
+///
+/// [`crdb_internal.ranges_no_leases`]: ranges_no_leases/index.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct RangesNoLeasesRow {
+    /// The ID of the range.
+    pub range_id: i64,
+    /// The first key of the range.
+    pub start_key: Vec<u8>,
+    /// The first key of the range, in a readable form.
+    pub start_pretty: String,
+    /// The key after the end of the range.
+    pub end_key: Vec<u8>,
+    /// The key after the end of the range, in a readable form.
+    pub end_pretty: String,
+    /// The nodes holding replicas of the range.
+    pub replicas: Vec<i64>,
+    /// The localities of the replicas.
+    pub replica_localities: Vec<String>,
+    /// The replicas taking part in consensus.
+    pub voting_replicas: Vec<i64>,
+    /// The replicas serving follower reads only.
+    pub non_voting_replicas: Vec<i64>,
+    /// The replicas still being added to the range.
+    pub learner_replicas: Vec<i64>,
+    /// The time until which a manual split of the range is kept, if any.
+    pub split_enforced_until: Option<SystemTime>,
+}
+
+table! {
+    /// The build information of each node, with one row per field.
+    crdb_internal.node_build_info (node_id, field) {
+        node_id -> Int8,
+        field -> Text,
+        value -> Text,
+    }
+}
+
+/// A row of [`crdb_internal.node_build_info`].
+///
+/// [`crdb_internal.node_build_info`]: node_build_info/index.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct NodeBuildInfoRow {
+    /// The node the information is about.
+    pub node_id: i64,
+    /// The name of the field, such as `Version` or `Channel`.
+    pub field: String,
+    /// The value of the field.
+    pub value: String,
+}
+
+table! {
+    /// The cluster settings, including hidden settings.
+    crdb_internal.cluster_settings (variable) {
+        variable -> Text,
+        value -> Text,
+        #[sql_name = "type"]
+        setting_type -> Text,
+        public -> Bool,
+        description -> Text,
+    }
+}
+
+/// A row of [`crdb_internal.cluster_settings`].
+///
+/// [`crdb_internal.cluster_settings`]: cluster_settings/index.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct ClusterSettingsRow {
+    /// The name of the setting.
+    pub variable: String,
+    /// The current value of the setting.
+    pub value: String,
+    /// The type of the setting, such as `b` for booleans or `d` for
+    /// durations.
+    pub setting_type: String,
+    /// Whether the setting is documented.
+    pub public: bool,
+    /// A description of the setting.
+    pub description: String,
+}
+
+table! {
+    /// The indexes of every table in the current database.
+    crdb_internal.table_indexes (descriptor_id, index_id) {
+        descriptor_id -> Int8,
+        descriptor_name -> Text,
+        index_id -> Int8,
+        index_name -> Text,
+        index_type -> Text,
+        is_unique -> Bool,
+        is_inverted -> Bool,
+    }
+}
+
+/// A row of [`crdb_internal.table_indexes`].
+///
+/// [`crdb_internal.table_indexes`]: table_indexes/index.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct TableIndexesRow {
+    /// The ID of the table.
+    pub descriptor_id: i64,
+    /// The name of the table.
+    pub descriptor_name: String,
+    /// The ID of the index within the table.
+    pub index_id: i64,
+    /// The name of the index.
+    pub index_name: String,
+    /// The type of the index, either `primary` or `secondary`.
+    pub index_type: String,
+    /// Whether the index enforces uniqueness.
+    pub is_unique: bool,
+    /// Whether the index is an inverted index.
+    pub is_inverted: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn jobs() {
+        let query = jobs::table
+            .select((jobs::job_id, jobs::status))
+            .filter(jobs::job_type.eq("CHANGEFEED"));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT "crdb_internal"."jobs"."job_id", "crdb_internal"."jobs"."status" FROM "crdb_internal"."jobs" WHERE "crdb_internal"."jobs"."job_type" = $1 -- binds: ["CHANGEFEED"]"#
+        );
+    }
+
+    #[test]
+    fn cluster_settings() {
+        let query = cluster_settings::table
+            .select(cluster_settings::setting_type)
+            .filter(cluster_settings::public);
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT "crdb_internal"."cluster_settings"."type" FROM "crdb_internal"."cluster_settings" WHERE "crdb_internal"."cluster_settings"."public" -- binds: []"#
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Support for `CREATE TABLE ... AS` SQL queries.
+
+use crate::{migrations::Reversible, quote, storage_params::StorageParams};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AsQuery, AstPass, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+};
+
+/// Creates a `CREATE TABLE ... AS` statement, materializing the results of
+/// `query` into a new table called `name`.
+///
+/// Any select statement may be passed as the query, including one pinned to
+/// a historical timestamp, to take a consistent snapshot of existing data.
+///
+/// ```sql
+/// CREATE TABLE big_books AS SELECT id, title FROM books WHERE page_count > 500;
+/// ```
+pub fn create_table_as<Q>(name: impl Into<String>, query: Q) -> CreateTableAsStatement<Q::Query>
+where
+    Q: AsQuery,
+{
+    CreateTableAsStatement::new(name.into(), query.as_query())
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CREATE TABLE ... AS` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `Q`: The query used to populate the new table
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-table-as.html
+pub struct CreateTableAsStatement<Q> {
+    name: String,
+    query: Q,
+    if_not_exists: bool,
+    storage_params: StorageParams,
+}
+
+impl<Q> CreateTableAsStatement<Q> {
+    fn new(name: String, query: Q) -> Self {
+        CreateTableAsStatement {
+            name,
+            query,
+            if_not_exists: false,
+            storage_params: StorageParams::new(),
+        }
+    }
+
+    /// Do nothing if a table with the same name already exists.
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Set storage parameters on the new table.
+    ///
+    /// ```sql
+    /// CREATE TABLE books_copy WITH (exclude_data_from_backup = true) AS SELECT * FROM books;
+    /// ```
+    pub fn with_storage_params(mut self, storage_params: StorageParams) -> Self {
+        self.storage_params = storage_params;
+        self
+    }
+}
+
+impl<Q> QueryFragment<Pg> for CreateTableAsStatement<Q>
+where
+    Q: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CREATE TABLE ");
+        if self.if_not_exists {
+            out.push_sql("IF NOT EXISTS ");
+        }
+        out.push_identifier(&self.name)?;
+        if !self.storage_params.is_empty() {
+            out.push_sql(" WITH ");
+            self.storage_params.walk_ast(out.reborrow())?;
+        }
+        out.push_sql(" AS ");
+        self.query.walk_ast(out.reborrow())?;
+        Ok(())
+    }
+}
+
+impl<Q> Reversible for CreateTableAsStatement<Q>
+where
+    Q: QueryFragment<Pg>,
+{
+    /// Drops the table.
+    fn reverse_sql(&self) -> QueryResult<Option<String>> {
+        Ok(Some(format!(
+            "DROP TABLE {}",
+            quote::identifier(&self.name)
+        )))
+    }
+}
+
+impl<Q> RunQueryDsl<PgConnection> for CreateTableAsStatement<Q> {}
+
+impl<Q> QueryId for CreateTableAsStatement<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Bytea,
+            title -> Text,
+            page_count -> Int8,
+        }
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            diesel::debug_query(&create_table_as("books_copy", books::table)).to_string(),
+            r#"CREATE TABLE "books_copy" AS SELECT "books"."id", "books"."title", "books"."page_count" FROM "books" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn filtered() {
+        let query = books::table
+            .select((books::id, books::title))
+            .filter(books::page_count.gt(500));
+        assert_eq!(
+            diesel::debug_query(&create_table_as("big_books", query).if_not_exists()).to_string(),
+            r#"CREATE TABLE IF NOT EXISTS "big_books" AS SELECT "books"."id", "books"."title" FROM "books" WHERE "books"."page_count" > $1 -- binds: [500]"#
+        );
+    }
+
+    #[test]
+    fn storage_params() {
+        let statement = create_table_as("books_copy", books::table.select(books::id))
+            .with_storage_params(StorageParams::new().exclude_data_from_backup(true));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE TABLE "books_copy" WITH (exclude_data_from_backup = true) AS SELECT "books"."id" FROM "books" -- binds: []"#
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! Rust types which map to CockroachDB specific SQL data types.
+
+use crate::{
+    quote,
+    sql_types::{CrdbInternalRegion, Geography, Geometry, TimeTz},
+};
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{
+        data_types::{PgInterval, PgTime},
+        Pg,
+    },
+    serialize::{self, Output, ToSql},
+    sql_types::{Integer, Text},
+    AsExpression, FromSqlRow,
+};
+use std::{
+    convert::{TryFrom, TryInto},
+    error, fmt,
+    io::Write,
+    str::FromStr,
+    time::Duration,
+};
+
+/// A region of a multi-region database, such as `us-east1`.
+///
+/// Region names are received as the labels of the [`CrdbInternalRegion`]
+/// enum type.
+///
+/// [`CrdbInternalRegion`]: ../sql_types/struct.CrdbInternalRegion.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, AsExpression, FromSqlRow)]
+#[sql_type = "CrdbInternalRegion"]
+pub struct Region(pub String);
+
+impl Region {
+    /// Create a region from its name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Region(name.into())
+    }
+
+    /// The name of this region.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromSql<CrdbInternalRegion, Pg> for Region {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <String as FromSql<CrdbInternalRegion, Pg>>::from_sql(bytes).map(Region)
+    }
+}
+
+impl ToSql<CrdbInternalRegion, Pg> for Region {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<CrdbInternalRegion, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+/// Decodes region names listed as text, such as by `SHOW REGIONS`.
+impl FromSql<Text, Pg> for Region {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <String as FromSql<Text, Pg>>::from_sql(bytes).map(Region)
+    }
+}
+
+// Enum values are sent and received as their labels, in the same format as text.
+
+impl FromSql<CrdbInternalRegion, Pg> for String {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <String as FromSql<Text, Pg>>::from_sql(bytes)
+    }
+}
+
+impl ToSql<CrdbInternalRegion, Pg> for str {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self, out)
+    }
+}
+
+impl ToSql<CrdbInternalRegion, Pg> for String {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self, out)
+    }
+}
+
+/// A spatial value in the extended well-known binary format used by PostGIS
+/// and CockroachDB, which is the binary wire format of the [`Geometry`] and
+/// [`Geography`] types.
+///
+/// The bytes can be decoded by any EWKB or WKB reader, such as those of the
+/// `geo` ecosystem of crates.
+///
+/// This crate does not depend on `geo-types`, so there is no `geo` feature
+/// converting to and from its `Geometry`. Instead, [`from_wkb`] and
+/// [`to_wkb`] convert to and from the plain WKB read and written by crates
+/// such as `wkb`, adding or removing the SRID.
+///
+/// [`from_wkb`]: #method.from_wkb
+/// [`to_wkb`]: #method.to_wkb
+/// [`Geometry`]: ../sql_types/struct.Geometry.html
+/// [`Geography`]: ../sql_types/struct.Geography.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash, AsExpression, FromSqlRow)]
+#[sql_type = "Geometry"]
+#[sql_type = "Geography"]
+pub struct Ewkb(pub Vec<u8>);
+
+/// The flag set in the type of an EWKB value which includes an SRID.
+const EWKB_SRID_FLAG: u32 = 0x2000_0000;
+
+impl Ewkb {
+    /// The bytes of this value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The spatial reference system identifier of this value, such as `4326`
+    /// for WGS 84, if it has one.
+    pub fn srid(&self) -> Option<u32> {
+        let (big_endian, geometry_type) = wkb_header(&self.0)?;
+        if geometry_type & EWKB_SRID_FLAG == 0 {
+            return None;
+        }
+        read_u32(big_endian, self.0.get(5..9)?).filter(|srid| *srid != 0)
+    }
+
+    /// Converts a WKB value into EWKB, with the given SRID if any.
+    ///
+    /// Returns `None` if `wkb` does not start with a WKB header, or already
+    /// includes an SRID.
+    pub fn from_wkb(wkb: &[u8], srid: Option<u32>) -> Option<Self> {
+        let (big_endian, geometry_type) = wkb_header(wkb)?;
+        if geometry_type & EWKB_SRID_FLAG != 0 {
+            return None;
+        }
+        let srid = match srid {
+            Some(srid) => srid,
+            None => return Some(Ewkb(wkb.to_vec())),
+        };
+        let mut bytes = Vec::with_capacity(wkb.len() + 4);
+        bytes.push(wkb[0]);
+        bytes.extend_from_slice(&u32_bytes(big_endian, geometry_type | EWKB_SRID_FLAG));
+        bytes.extend_from_slice(&u32_bytes(big_endian, srid));
+        bytes.extend_from_slice(&wkb[5..]);
+        Some(Ewkb(bytes))
+    }
+
+    /// Converts this value into WKB, dropping any SRID.
+    ///
+    /// Returns `None` if this value does not start with an EWKB header.
+    pub fn to_wkb(&self) -> Option<Vec<u8>> {
+        let (big_endian, geometry_type) = wkb_header(&self.0)?;
+        if geometry_type & EWKB_SRID_FLAG == 0 {
+            return Some(self.0.clone());
+        }
+        let mut bytes = Vec::with_capacity(self.0.len() - 4);
+        bytes.push(self.0[0]);
+        bytes.extend_from_slice(&u32_bytes(big_endian, geometry_type & !EWKB_SRID_FLAG));
+        bytes.extend_from_slice(self.0.get(9..)?);
+        Some(bytes)
+    }
+}
+
+/// Reads the byte order and geometry type of a WKB or EWKB value.
+fn wkb_header(bytes: &[u8]) -> Option<(bool, u32)> {
+    let big_endian = match bytes.first()? {
+        0 => true,
+        1 => false,
+        _ => return None,
+    };
+    Some((big_endian, read_u32(big_endian, bytes.get(1..5)?)?))
+}
+
+fn read_u32(big_endian: bool, bytes: &[u8]) -> Option<u32> {
+    let bytes = bytes.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn u32_bytes(big_endian: bool, value: u32) -> [u8; 4] {
+    if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    }
+}
+
+impl FromSql<Geometry, Pg> for Ewkb {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <Vec<u8> as FromSql<diesel::sql_types::Binary, Pg>>::from_sql(bytes).map(Ewkb)
+    }
+}
+
+impl FromSql<Geography, Pg> for Ewkb {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <Ewkb as FromSql<Geometry, Pg>>::from_sql(bytes)
+    }
+}
+
+impl ToSql<Geometry, Pg> for Ewkb {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <[u8] as ToSql<diesel::sql_types::Binary, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+impl ToSql<Geography, Pg> for Ewkb {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <Ewkb as ToSql<Geometry, Pg>>::to_sql(self, out)
+    }
+}
+
+/// A time of day with a UTC offset, which is the binary wire format of the
+/// [`TimeTz`] type.
+///
+/// [`TimeTz`]: ../sql_types/struct.TimeTz.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[sql_type = "TimeTz"]
+pub struct PgTimeTz {
+    /// The local time of day, as microseconds since midnight.
+    pub time: PgTime,
+    /// The offset of the local time from UTC in seconds, positive east of
+    /// UTC.
+    pub utc_offset: i32,
+}
+
+impl PgTimeTz {
+    /// A time of day, with its offset from UTC in seconds.
+    pub fn new(time: PgTime, utc_offset: i32) -> Self {
+        PgTimeTz { time, utc_offset }
+    }
+
+    /// The same instant as UTC microseconds since midnight, which may be
+    /// outside of a single day.
+    pub fn utc_microseconds(&self) -> i64 {
+        self.time.0 - i64::from(self.utc_offset) * 1_000_000
+    }
+}
+
+impl FromSql<TimeTz, Pg> for PgTimeTz {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let bytes = not_none!(bytes);
+        if bytes.len() != 12 {
+            return Err(format!("invalid TIMETZ of {} bytes", bytes.len()).into());
+        }
+        let (time, zone) = bytes.split_at(8);
+        let time = <PgTime as FromSql<diesel::sql_types::Time, Pg>>::from_sql(Some(time))?;
+        // The zone is stored in seconds west of UTC.
+        let zone = <i32 as FromSql<Integer, Pg>>::from_sql(Some(zone))?;
+        Ok(PgTimeTz::new(time, -zone))
+    }
+}
+
+impl ToSql<TimeTz, Pg> for PgTimeTz {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        <PgTime as ToSql<diesel::sql_types::Time, Pg>>::to_sql(&self.time, out)?;
+        <i32 as ToSql<Integer, Pg>>::to_sql(&-self.utc_offset, out)
+    }
+}
+
+/// A length of time, parsed from a human readable string such as `90s`,
+/// `5m` or `1h30m`.
+///
+/// Intervals are accepted by [`AsOfSystemTime::ago`] and the row level TTL
+/// options of [`StorageParams`], and convert into the `Duration` of
+/// [`ChangefeedOptions::resolved`], so a configured value is validated when it
+/// is parsed rather than when a statement is run. They can also be sent as an
+/// `INTERVAL`.
+///
+/// There is no `humantime` feature, as this crate does not depend on it, but
+/// a `humantime::Duration` converts with `Interval::from(*duration)`.
+///
+/// ```
+/// use diesel_cockroach::data_types::Interval;
+/// use std::time::Duration;
+///
+/// let interval: Interval = "1h30m".parse().unwrap();
+/// assert_eq!(Duration::from(interval), Duration::from_secs(5400));
+/// assert!("90 seconds".parse::<Interval>().is_err());
+/// ```
+///
+/// [`AsOfSystemTime::ago`]: ../as_of_system_time/enum.AsOfSystemTime.html#method.ago
+/// [`StorageParams`]: ../storage_params/struct.StorageParams.html
+/// [`ChangefeedOptions::resolved`]: ../changefeed/struct.ChangefeedOptions.html#method.resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, AsExpression)]
+#[sql_type = "diesel::sql_types::Interval"]
+pub struct Interval(pub Duration);
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&quote::interval(self.0))
+    }
+}
+
+/// Parses a sequence of numbers with units, in the format of Go durations.
+/// The units are `h`, `m`, `s`, `ms`, `us` and `ns`.
+impl FromStr for Interval {
+    type Err = InvalidInterval;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(InvalidInterval(s.to_owned()));
+        }
+        quote::parse_duration(s)
+            .map(Interval)
+            .ok_or_else(|| InvalidInterval(s.to_owned()))
+    }
+}
+
+impl From<Duration> for Interval {
+    fn from(duration: Duration) -> Self {
+        Interval(duration)
+    }
+}
+
+impl From<Interval> for Duration {
+    fn from(interval: Interval) -> Self {
+        interval.0
+    }
+}
+
+/// Intervals longer than `i64::MAX` microseconds cannot be represented.
+impl TryFrom<Interval> for PgInterval {
+    type Error = InvalidInterval;
+
+    fn try_from(interval: Interval) -> Result<Self, Self::Error> {
+        i64::try_from(interval.0.as_micros())
+            .map(PgInterval::from_microseconds)
+            .map_err(|_| InvalidInterval(interval.to_string()))
+    }
+}
+
+impl ToSql<diesel::sql_types::Interval, Pg> for Interval {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let interval = PgInterval::try_from(*self)?;
+        <PgInterval as ToSql<diesel::sql_types::Interval, Pg>>::to_sql(&interval, out)
+    }
+}
+
+/// The error returned when parsing an invalid [`Interval`].
+///
+/// [`Interval`]: struct.Interval.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidInterval(pub String);
+
+impl fmt::Display for InvalidInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid interval: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidInterval {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::sql_types::CrdbInternalRegion;
+
+        users (id) {
+            id -> Int8,
+            name -> Text,
+            crdb_region -> CrdbInternalRegion,
+        }
+    }
+
+    #[test]
+    fn region_from_sql() {
+        assert_eq!(
+            <Region as FromSql<CrdbInternalRegion, Pg>>::from_sql(Some(b"us-east1")).unwrap(),
+            Region::new("us-east1")
+        );
+    }
+
+    #[test]
+    fn region_filter() {
+        let query = users::table
+            .select((users::name, users::crdb_region))
+            .filter(users::crdb_region.eq(Region::new("europe-west1")));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "users"."name", "users"."crdb_region" FROM "users" WHERE "users"."crdb_region" = $1 -- binds: [Region("europe-west1")]"#
+        );
+    }
+
+    #[test]
+    fn ewkb_srid() {
+        // POINT(1 2) with SRID 4326, little endian.
+        let point = Ewkb(vec![
+            0x01, 0x01, 0x00, 0x00, 0x20, 0xe6, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0xf0, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40,
+        ]);
+        assert_eq!(point.srid(), Some(4326));
+        assert_eq!(
+            <Ewkb as FromSql<Geography, Pg>>::from_sql(Some(point.as_bytes())).unwrap(),
+            point
+        );
+        // POINT(1 2) without an SRID, big endian.
+        let point = Ewkb(vec![
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        assert_eq!(point.srid(), None);
+        assert_eq!(Ewkb(vec![]).srid(), None);
+    }
+
+    #[test]
+    fn wkb() {
+        // POINT(1 2), little endian.
+        let wkb = vec![
+            0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40,
+        ];
+        let point = Ewkb::from_wkb(&wkb, Some(4326)).unwrap();
+        assert_eq!(
+            point.as_bytes(),
+            &[
+                0x01, 0x01, 0x00, 0x00, 0x20, 0xe6, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0xf0, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40,
+            ][..]
+        );
+        assert_eq!(point.srid(), Some(4326));
+        assert_eq!(point.to_wkb(), Some(wkb.clone()));
+        assert_eq!(Ewkb::from_wkb(&wkb, None), Some(Ewkb(wkb.clone())));
+        assert_eq!(Ewkb::from_wkb(point.as_bytes(), None), None);
+        assert_eq!(Ewkb::from_wkb(&[0x02], None), None);
+    }
+
+    #[test]
+    fn interval() {
+        assert_eq!("90s".parse(), Ok(Interval(Duration::from_secs(90))));
+        assert_eq!("5m".parse(), Ok(Interval(Duration::from_secs(300))));
+        assert_eq!("1h30m".parse(), Ok(Interval(Duration::from_secs(5400))));
+        assert_eq!("250ms".parse(), Ok(Interval(Duration::from_millis(250))));
+        assert_eq!("".parse::<Interval>(), Err(InvalidInterval("".to_owned())));
+        assert_eq!(
+            "5".parse::<Interval>(),
+            Err(InvalidInterval("5".to_owned()))
+        );
+        assert_eq!(
+            "1 day".parse::<Interval>(),
+            Err(InvalidInterval("1 day".to_owned()))
+        );
+        assert_eq!(Interval(Duration::from_secs(90)).to_string(), "90s");
+        assert_eq!(
+            PgInterval::try_from(Interval(Duration::from_millis(1500))),
+            Ok(PgInterval::from_microseconds(1_500_000))
+        );
+    }
+
+    #[test]
+    fn time_tz() {
+        // 12:30:00+01:00
+        let bytes = [
+            0x00, 0x00, 0x00, 0x0a, 0x7a, 0x35, 0x82, 0x00, 0xff, 0xff, 0xf1, 0xf0,
+        ];
+        let time = PgTimeTz::new(PgTime(45_000_000_000), 3600);
+        assert_eq!(
+            <PgTimeTz as FromSql<TimeTz, Pg>>::from_sql(Some(&bytes)).unwrap(),
+            time
+        );
+        assert_eq!(time.utc_microseconds(), 41_400_000_000);
+        assert!(<PgTimeTz as FromSql<TimeTz, Pg>>::from_sql(Some(&bytes[..8])).is_err());
+    }
+}
@@ -0,0 +1,520 @@
+//! Support for `ALTER DATABASE`, `SHOW CREATE DATABASE` and
+//! `SHOW SURVIVAL GOAL` SQL queries, and the multi-region topology of a
+//! database.
+
+use crate::{
+    admin::{show_regions_from_database, DatabaseRegion},
+    data_types::Region,
+    locality::Locality,
+    quote,
+};
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::{Nullable, Text},
+    FromSqlRow, Queryable,
+};
+use std::{error, fmt, str::FromStr};
+
+/// Creates an `ALTER DATABASE` statement for the database called `name`.
+///
+/// The alteration to make is chosen by calling one of the methods of
+/// [`IncompleteAlterDatabaseStatement`].
+///
+/// [`IncompleteAlterDatabaseStatement`]: struct.IncompleteAlterDatabaseStatement.html
+pub fn alter_database(name: impl Into<String>) -> IncompleteAlterDatabaseStatement {
+    IncompleteAlterDatabaseStatement { name: name.into() }
+}
+
+/// The structure returned by [`alter_database`].
+///
+/// Each method chooses an alteration to make, and returns the complete
+/// statement.
+///
+/// [`alter_database`]: fn.alter_database.html
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+pub struct IncompleteAlterDatabaseStatement {
+    name: String,
+}
+
+impl IncompleteAlterDatabaseStatement {
+    /// Set the placement policy of a multi-region database.
+    ///
+    /// ```sql
+    /// ALTER DATABASE movr PLACEMENT RESTRICTED;
+    /// ```
+    pub fn placement(self, placement: Placement) -> AlterDatabaseStatement {
+        AlterDatabaseStatement::new(self, AlterDatabaseAction::Placement(placement))
+    }
+}
+
+/// The placement policy of a multi-region database.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-database.html#placement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Placement {
+    /// `DEFAULT`: non-voting replicas of `REGIONAL` tables may be placed in
+    /// other regions, to serve follower reads.
+    Default,
+    /// `RESTRICTED`: replicas of `REGIONAL` tables are only placed in their
+    /// home region, so data is domiciled there.
+    Restricted,
+}
+
+impl Placement {
+    /// The SQL keyword for this placement policy.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Placement::Default => "DEFAULT",
+            Placement::Restricted => "RESTRICTED",
+        }
+    }
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum AlterDatabaseAction {
+    Placement(Placement),
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `ALTER DATABASE` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-database.html
+pub struct AlterDatabaseStatement {
+    name: String,
+    action: AlterDatabaseAction,
+}
+
+impl AlterDatabaseStatement {
+    fn new(incomplete: IncompleteAlterDatabaseStatement, action: AlterDatabaseAction) -> Self {
+        AlterDatabaseStatement {
+            name: incomplete.name,
+            action,
+        }
+    }
+}
+
+impl QueryFragment<Pg> for AlterDatabaseStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("ALTER DATABASE ");
+        out.push_identifier(&self.name)?;
+        match &self.action {
+            AlterDatabaseAction::Placement(placement) => {
+                out.push_sql(" PLACEMENT ");
+                out.push_sql(placement.as_str());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for AlterDatabaseStatement {}
+
+impl QueryId for AlterDatabaseStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW CREATE DATABASE` statement for the database called `name`.
+///
+/// Load the result as a [`CreateDatabase`].
+///
+/// [`CreateDatabase`]: struct.CreateDatabase.html
+pub fn show_create_database(name: impl Into<String>) -> ShowCreateDatabaseStatement {
+    ShowCreateDatabaseStatement { name: name.into() }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW CREATE DATABASE` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-create.html
+pub struct ShowCreateDatabaseStatement {
+    name: String,
+}
+
+impl QueryFragment<Pg> for ShowCreateDatabaseStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW CREATE DATABASE ");
+        out.push_identifier(&self.name)
+    }
+}
+
+impl Query for ShowCreateDatabaseStatement {
+    type SqlType = (Text, Text);
+}
+
+impl RunQueryDsl<PgConnection> for ShowCreateDatabaseStatement {}
+
+impl QueryId for ShowCreateDatabaseStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A row returned by `SHOW CREATE DATABASE`.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct CreateDatabase {
+    /// The name of the database.
+    pub database_name: String,
+    /// The statement which would create the database, including its regions
+    /// and survival goal.
+    pub create_statement: String,
+}
+
+impl CreateDatabase {
+    /// The placement policy of the database.
+    pub fn placement(&self) -> Placement {
+        if self.create_statement.contains(" PLACEMENT RESTRICTED") {
+            Placement::Restricted
+        } else {
+            Placement::Default
+        }
+    }
+}
+
+/// Loads the placement policy of the database called `name`.
+pub fn database_placement(conn: &PgConnection, name: impl Into<String>) -> QueryResult<Placement> {
+    show_create_database(name)
+        .get_result::<CreateDatabase>(conn)
+        .map(|create_database| create_database.placement())
+}
+
+/// The failures a multi-region database is configured to survive.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/multiregion-survival-goals.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromSqlRow)]
+pub enum SurvivalGoal {
+    /// `ZONE`: survive the failure of an availability zone.
+    Zone,
+    /// `REGION`: survive the failure of a whole region.
+    Region,
+}
+
+impl SurvivalGoal {
+    /// The SQL keyword for this survival goal.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SurvivalGoal::Zone => "ZONE",
+            SurvivalGoal::Region => "REGION",
+        }
+    }
+}
+
+impl fmt::Display for SurvivalGoal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the `zone` or `region` values returned by `SHOW SURVIVAL GOAL`.
+impl FromStr for SurvivalGoal {
+    type Err = InvalidSurvivalGoal;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("zone") {
+            Ok(SurvivalGoal::Zone)
+        } else if s.eq_ignore_ascii_case("region") {
+            Ok(SurvivalGoal::Region)
+        } else {
+            Err(InvalidSurvivalGoal(s.to_owned()))
+        }
+    }
+}
+
+impl FromSql<Text, Pg> for SurvivalGoal {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+/// The error returned when parsing an invalid [`SurvivalGoal`].
+///
+/// [`SurvivalGoal`]: enum.SurvivalGoal.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSurvivalGoal(pub String);
+
+impl fmt::Display for InvalidSurvivalGoal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid survival goal: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidSurvivalGoal {}
+
+/// Creates a `SHOW SURVIVAL GOAL` statement for the database called `name`.
+///
+/// Load the result as a [`SurvivalGoal`], or `Option<SurvivalGoal>` since
+/// the survival goal is `NULL` for databases which are not multi-region.
+///
+/// ```sql
+/// SHOW SURVIVAL GOAL FROM DATABASE movr;
+/// ```
+///
+/// [`SurvivalGoal`]: enum.SurvivalGoal.html
+pub fn show_survival_goal_from_database(name: impl Into<String>) -> ShowSurvivalGoalStatement {
+    ShowSurvivalGoalStatement { name: name.into() }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW SURVIVAL GOAL` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-survival-goal.html
+pub struct ShowSurvivalGoalStatement {
+    name: String,
+}
+
+impl QueryFragment<Pg> for ShowSurvivalGoalStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT survival_goal FROM [SHOW SURVIVAL GOAL FROM DATABASE ");
+        out.push_identifier(&self.name)?;
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl Query for ShowSurvivalGoalStatement {
+    type SqlType = Nullable<Text>;
+}
+
+impl RunQueryDsl<PgConnection> for ShowSurvivalGoalStatement {}
+
+impl QueryId for ShowSurvivalGoalStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a query listing the tables of the database called `name`, with
+/// their localities, from `SHOW TABLES`.
+///
+/// Load the result as [`TableLocality`] rows.
+///
+/// [`TableLocality`]: struct.TableLocality.html
+pub fn show_table_localities(name: impl Into<String>) -> ShowTableLocalitiesStatement {
+    ShowTableLocalitiesStatement { name: name.into() }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query listing table localities.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-tables.html
+pub struct ShowTableLocalitiesStatement {
+    name: String,
+}
+
+impl QueryFragment<Pg> for ShowTableLocalitiesStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT schema_name, table_name, locality FROM [SHOW TABLES FROM ");
+        out.push_identifier(&self.name)?;
+        out.push_sql("] WHERE type = ");
+        out.push_sql(&quote::string_literal("table"));
+        out.push_sql(" ORDER BY schema_name, table_name");
+        Ok(())
+    }
+}
+
+impl Query for ShowTableLocalitiesStatement {
+    type SqlType = TableLocalitySqlType;
+}
+
+impl RunQueryDsl<PgConnection> for ShowTableLocalitiesStatement {}
+
+impl QueryId for ShowTableLocalitiesStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The locality of a table, as returned by [`show_table_localities`].
+///
+/// [`show_table_localities`]: fn.show_table_localities.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLocality {
+    /// The schema of the table.
+    pub schema_name: String,
+    /// The name of the table.
+    pub table_name: String,
+    /// The locality of the table, or `None` if the database is not
+    /// multi-region.
+    pub locality: Option<Locality>,
+}
+
+impl diesel::Queryable<TableLocalitySqlType, Pg> for TableLocality {
+    type Row = (String, String, Option<String>);
+
+    fn build((schema_name, table_name, locality): Self::Row) -> Self {
+        TableLocality {
+            schema_name,
+            table_name,
+            // Localities added by newer versions are treated as unknown.
+            locality: locality.and_then(|locality| locality.parse().ok()),
+        }
+    }
+}
+
+/// The SQL type of a [`TableLocality`].
+///
+/// [`TableLocality`]: struct.TableLocality.html
+pub type TableLocalitySqlType = (Text, Text, Nullable<Text>);
+
+/// The multi-region configuration of a database, as loaded by
+/// [`multi_region_topology`].
+///
+/// [`multi_region_topology`]: fn.multi_region_topology.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiRegionTopology {
+    /// The name of the database.
+    pub database: String,
+    /// The survival goal of the database, or `None` if it is not
+    /// multi-region.
+    pub survival_goal: Option<SurvivalGoal>,
+    /// The placement policy of the database.
+    pub placement: Placement,
+    /// The regions of the database.
+    pub regions: Vec<DatabaseRegion>,
+    /// The tables of the database, with their localities.
+    pub tables: Vec<TableLocality>,
+}
+
+impl MultiRegionTopology {
+    /// The primary region of the database, if it is multi-region.
+    pub fn primary_region(&self) -> Option<&Region> {
+        self.regions
+            .iter()
+            .find(|region| region.primary)
+            .map(|region| &region.region)
+    }
+
+    /// The locality of the table called `name`, in any schema.
+    pub fn table_locality(&self, name: &str) -> Option<&Locality> {
+        self.tables
+            .iter()
+            .find(|table| table.table_name == name)
+            .and_then(|table| table.locality.as_ref())
+    }
+}
+
+/// Loads the survival goal, placement, regions and table localities of the
+/// database called `name`, so a deployment can be checked against the
+/// intended topology.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{
+///     database::{multi_region_topology, SurvivalGoal},
+///     locality::Locality,
+/// };
+///
+/// let topology = multi_region_topology(&connection, "movr")?;
+/// assert_eq!(topology.survival_goal, Some(SurvivalGoal::Region));
+/// assert_eq!(topology.table_locality("promo_codes"), Some(&Locality::Global));
+/// # Ok(())
+/// # }
+/// ```
+pub fn multi_region_topology(
+    conn: &PgConnection,
+    name: impl Into<String>,
+) -> QueryResult<MultiRegionTopology> {
+    let database = name.into();
+    Ok(MultiRegionTopology {
+        survival_goal: show_survival_goal_from_database(database.as_str()).get_result(conn)?,
+        placement: database_placement(conn, database.as_str())?,
+        regions: show_regions_from_database(database.as_str()).load(conn)?,
+        tables: show_table_localities(database.as_str()).load(conn)?,
+        database,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn placement() {
+        assert_eq!(
+            diesel::debug_query(&alter_database("movr").placement(Placement::Restricted))
+                .to_string(),
+            r#"ALTER DATABASE "movr" PLACEMENT RESTRICTED -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&alter_database("movr").placement(Placement::Default)).to_string(),
+            r#"ALTER DATABASE "movr" PLACEMENT DEFAULT -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show_create() {
+        assert_eq!(
+            diesel::debug_query(&show_create_database("movr")).to_string(),
+            r#"SHOW CREATE DATABASE "movr" -- binds: []"#
+        );
+
+        let mut create_database = CreateDatabase {
+            database_name: "movr".to_owned(),
+            create_statement: r#"CREATE DATABASE movr PRIMARY REGION "us-east1" REGIONS = "us-east1", "us-west1" SURVIVE ZONE FAILURE"#.to_owned(),
+        };
+        assert_eq!(create_database.placement(), Placement::Default);
+        create_database
+            .create_statement
+            .push_str(" PLACEMENT RESTRICTED");
+        assert_eq!(create_database.placement(), Placement::Restricted);
+    }
+
+    #[test]
+    fn survival_goal() {
+        assert_eq!(
+            diesel::debug_query(&show_survival_goal_from_database("movr")).to_string(),
+            r#"SELECT survival_goal FROM [SHOW SURVIVAL GOAL FROM DATABASE "movr"] -- binds: []"#
+        );
+        assert_eq!("region".parse(), Ok(SurvivalGoal::Region));
+        assert_eq!("ZONE".parse(), Ok(SurvivalGoal::Zone));
+        assert!("cluster".parse::<SurvivalGoal>().is_err());
+    }
+
+    #[test]
+    fn table_localities() {
+        assert_eq!(
+            diesel::debug_query(&show_table_localities("movr")).to_string(),
+            r#"SELECT schema_name, table_name, locality FROM [SHOW TABLES FROM "movr"] WHERE type = 'table' ORDER BY schema_name, table_name -- binds: []"#
+        );
+    }
+}
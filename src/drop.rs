@@ -0,0 +1,336 @@
+//! Support for `DROP TABLE` and `DROP INDEX` SQL queries.
+//!
+//! Dropping a table or index returns immediately, while the data is removed
+//! by schema change and garbage collection jobs in the background. Running
+//! the statement with `execute_tracked` returns the IDs of these jobs, which
+//! can be waited on with [`wait_for_jobs`].
+//!
+//! [`wait_for_jobs`]: ../jobs/fn.wait_for_jobs.html
+
+use crate::{
+    jobs::{pending_descriptor_jobs, JobProgress},
+    quote,
+};
+use diesel::{
+    connection::{Connection, SimpleConnection},
+    dsl::sql,
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_dsl::{methods::ExecuteDsl, RunQueryDsl},
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{BigInt, Nullable},
+};
+use std::time::Duration;
+
+/// Creates a `DROP TABLE` statement for the target table.
+///
+/// ```sql
+/// DROP TABLE books;
+/// ```
+pub fn drop_table<T>(target: T) -> DropTableStatement<T> {
+    DropTableStatement {
+        target,
+        options: DropOptions::default(),
+    }
+}
+
+/// Creates a `DROP INDEX` statement for the index called `name` on the
+/// target table.
+///
+/// ```sql
+/// DROP INDEX books@books_title_idx;
+/// ```
+pub fn drop_index<T>(target: T, name: impl Into<String>) -> DropIndexStatement<T> {
+    DropIndexStatement {
+        target,
+        name: name.into(),
+        options: DropOptions::default(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DropOptions {
+    if_exists: bool,
+    cascade: bool,
+    gc_ttl: Option<Duration>,
+}
+
+macro_rules! drop_options {
+    () => {
+        /// Do nothing if the object does not exist.
+        pub fn if_exists(mut self) -> Self {
+            self.options.if_exists = true;
+            self
+        }
+
+        /// Also drop any objects depending on this one, such as views or
+        /// foreign key constraints.
+        ///
+        /// Jobs dropping the dependent objects are not tracked by
+        /// `execute_tracked`.
+        pub fn cascade(mut self) -> Self {
+            self.options.cascade = true;
+            self
+        }
+
+        /// Lower the garbage collection TTL of the object before dropping it,
+        /// so its data is removed sooner.
+        ///
+        /// Only applied by `execute_tracked`, which lowers the TTL in the same
+        /// transaction as the drop, so it is left unchanged if the drop fails.
+        /// Running the statement with `execute` ignores this option.
+        ///
+        /// ```sql
+        /// ALTER TABLE books CONFIGURE ZONE USING gc.ttlseconds = 600;
+        /// ```
+        pub fn gc_ttl(mut self, gc_ttl: Duration) -> Self {
+            self.options.gc_ttl = Some(gc_ttl);
+            self
+        }
+    };
+}
+
+impl DropOptions {
+    fn walk_ast(&self, mut out: AstPass<Pg>, keyword: &str) {
+        out.push_sql("DROP ");
+        out.push_sql(keyword);
+        out.push_sql(" ");
+        if self.if_exists {
+            out.push_sql("IF EXISTS ");
+        }
+    }
+
+    /// Runs `statement`, after first lowering the GC TTL of `zone_target` in
+    /// the same transaction if requested, and returns the jobs started on
+    /// `table`.
+    fn execute_tracked<S>(
+        &self,
+        conn: &PgConnection,
+        statement: &S,
+        table: &str,
+        zone_target: &str,
+    ) -> QueryResult<Vec<i64>>
+    where
+        S: QueryFragment<Pg> + QueryId,
+    {
+        let descriptor_id = diesel::select(sql::<Nullable<BigInt>>(&format!(
+            "to_regclass({})::INT8",
+            quote::string_literal(table)
+        )))
+        .get_result::<Option<i64>>(conn)?;
+        let descriptor_id = match descriptor_id {
+            Some(descriptor_id) => descriptor_id,
+            None => {
+                // Let the statement either succeed or report a missing table
+                ExecuteDsl::execute(statement, conn)?;
+                return Ok(Vec::new());
+            }
+        };
+        match self.gc_ttl {
+            Some(gc_ttl) => conn.transaction(|| {
+                conn.batch_execute(&configure_gc_ttl(zone_target, gc_ttl))?;
+                ExecuteDsl::execute(statement, conn)
+            })?,
+            None => ExecuteDsl::execute(statement, conn)?,
+        };
+        Ok(pending_descriptor_jobs(descriptor_id)
+            .load(conn)?
+            .into_iter()
+            .map(|job: JobProgress| job.job_id)
+            .collect())
+    }
+}
+
+fn configure_gc_ttl(zone_target: &str, gc_ttl: Duration) -> String {
+    format!(
+        "ALTER {} CONFIGURE ZONE USING gc.ttlseconds = {}",
+        zone_target,
+        gc_ttl.as_secs()
+    )
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `DROP TABLE` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table being dropped
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/drop-table.html
+pub struct DropTableStatement<T> {
+    target: T,
+    options: DropOptions,
+}
+
+impl<T> DropTableStatement<T> {
+    drop_options!();
+}
+
+impl<T> DropTableStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    /// Drop the table, returning the IDs of the unfinished jobs removing it.
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate diesel;
+    /// # use diesel::{pg::PgConnection, Connection};
+    /// # table! { books (id) { id -> Int8, } }
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let connection = PgConnection::establish("")?;
+    /// use diesel_cockroach::{drop::drop_table, jobs::{wait_for_jobs, WaitOptions}};
+    /// use std::time::Duration;
+    ///
+    /// let job_ids = drop_table(books::table)
+    ///     .cascade()
+    ///     .gc_ttl(Duration::from_secs(600))
+    ///     .execute_tracked(&connection)?;
+    /// wait_for_jobs(&connection, job_ids, WaitOptions::default(), |_| {})?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn execute_tracked(&self, conn: &PgConnection) -> QueryResult<Vec<i64>> {
+        let table = quote::render(&self.target.from_clause())?;
+        self.options
+            .execute_tracked(conn, self, &table, &format!("TABLE {}", table))
+    }
+}
+
+impl<T> QueryFragment<Pg> for DropTableStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        self.options.walk_ast(out.reborrow(), "TABLE");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        if self.options.cascade {
+            out.push_sql(" CASCADE");
+        }
+        Ok(())
+    }
+}
+
+impl<T> RunQueryDsl<PgConnection> for DropTableStatement<T> {}
+
+impl<T> QueryId for DropTableStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `DROP INDEX` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table the index belongs to
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/drop-index.html
+pub struct DropIndexStatement<T> {
+    target: T,
+    name: String,
+    options: DropOptions,
+}
+
+impl<T> DropIndexStatement<T> {
+    drop_options!();
+}
+
+impl<T> DropIndexStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    /// Drop the index, returning the IDs of the unfinished jobs removing it.
+    ///
+    /// Index data is removed by jobs on the table, so any other unfinished
+    /// schema changes on the table are also returned.
+    pub fn execute_tracked(&self, conn: &PgConnection) -> QueryResult<Vec<i64>> {
+        let table = quote::render(&self.target.from_clause())?;
+        let zone_target = format!("INDEX {}@{}", table, quote::identifier(&self.name));
+        self.options
+            .execute_tracked(conn, self, &table, &zone_target)
+    }
+}
+
+impl<T> QueryFragment<Pg> for DropIndexStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        self.options.walk_ast(out.reborrow(), "INDEX");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql("@");
+        out.push_identifier(&self.name)?;
+        if self.options.cascade {
+            out.push_sql(" CASCADE");
+        }
+        Ok(())
+    }
+}
+
+impl<T> RunQueryDsl<PgConnection> for DropIndexStatement<T> {}
+
+impl<T> QueryId for DropIndexStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            diesel::debug_query(&drop_table(books::table)).to_string(),
+            r#"DROP TABLE "books" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&drop_table(books::table).if_exists().cascade()).to_string(),
+            r#"DROP TABLE IF EXISTS "books" CASCADE -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn index() {
+        assert_eq!(
+            diesel::debug_query(&drop_index(books::table, "books_title_idx").cascade()).to_string(),
+            r#"DROP INDEX "books"@"books_title_idx" CASCADE -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn gc_ttl() {
+        assert_eq!(
+            configure_gc_ttl(
+                r#"INDEX "books"@"books_title_idx""#,
+                Duration::from_secs(600)
+            ),
+            r#"ALTER INDEX "books"@"books_title_idx" CONFIGURE ZONE USING gc.ttlseconds = 600"#
+        );
+    }
+}
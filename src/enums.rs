@@ -0,0 +1,233 @@
+//! Support for `SHOW ENUMS` and `SHOW TYPES` SQL queries, listing the
+//! user-defined types of the current database.
+//!
+//! New enum values are usually added by migrations, so checking the loaded
+//! values against the variants of a Rust enum catches deploys where the two
+//! have drifted apart.
+//!
+//! ```no_run
+//! # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::enums::{show_enums, EnumType};
+//!
+//! let enums: Vec<EnumType> = show_enums().schema("public").load(&connection)?;
+//! let status = enums.iter().find(|e| e.name == "status").unwrap();
+//! assert!(status.missing_values(&["open", "closed"]).is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::quote;
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::{Array, Text},
+    Queryable,
+};
+
+/// Creates a `SHOW ENUMS` statement, listing the enum types of the current
+/// database and their values.
+///
+/// Load the result as [`EnumType`] rows.
+///
+/// ```sql
+/// SHOW ENUMS;
+/// ```
+///
+/// [`EnumType`]: struct.EnumType.html
+pub fn show_enums() -> ShowEnumsStatement {
+    ShowEnumsStatement { schema: None }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW ENUMS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-enums.html
+pub struct ShowEnumsStatement {
+    schema: Option<String>,
+}
+
+impl ShowEnumsStatement {
+    /// Only list the enum types in the schema called `name`.
+    pub fn schema(mut self, name: impl Into<String>) -> Self {
+        self.schema = Some(name.into());
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowEnumsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT schema, name, \"values\", owner FROM [SHOW ENUMS]");
+        push_schema_filter(&mut out, self.schema.as_deref());
+        Ok(())
+    }
+}
+
+impl Query for ShowEnumsStatement {
+    type SqlType = (Text, Text, Array<Text>, Text);
+}
+
+impl RunQueryDsl<PgConnection> for ShowEnumsStatement {}
+
+impl QueryId for ShowEnumsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// An enum type, as returned by `SHOW ENUMS`.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct EnumType {
+    /// The schema of the type.
+    pub schema: String,
+    /// The name of the type.
+    pub name: String,
+    /// The values of the enum, in their sort order.
+    pub values: Vec<String>,
+    /// The owner of the type.
+    pub owner: String,
+}
+
+impl EnumType {
+    /// Whether `value` is one of the values of the enum.
+    pub fn has_value(&self, value: &str) -> bool {
+        self.values.iter().any(|v| v == value)
+    }
+
+    /// The values in `expected` which the enum does not have.
+    pub fn missing_values<'a>(&self, expected: &[&'a str]) -> Vec<&'a str> {
+        expected
+            .iter()
+            .copied()
+            .filter(|value| !self.has_value(value))
+            .collect()
+    }
+
+    /// The values of the enum which are not in `expected`.
+    pub fn unexpected_values(&self, expected: &[&str]) -> Vec<&str> {
+        self.values
+            .iter()
+            .map(String::as_str)
+            .filter(|value| !expected.contains(value))
+            .collect()
+    }
+}
+
+/// Creates a `SHOW TYPES` statement, listing the user-defined types of the
+/// current database.
+///
+/// Load the result as [`UserDefinedType`] rows.
+///
+/// ```sql
+/// SHOW TYPES;
+/// ```
+///
+/// [`UserDefinedType`]: struct.UserDefinedType.html
+pub fn show_types() -> ShowTypesStatement {
+    ShowTypesStatement { schema: None }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW TYPES` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-types.html
+pub struct ShowTypesStatement {
+    schema: Option<String>,
+}
+
+impl ShowTypesStatement {
+    /// Only list the types in the schema called `name`.
+    pub fn schema(mut self, name: impl Into<String>) -> Self {
+        self.schema = Some(name.into());
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowTypesStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT schema, name, owner FROM [SHOW TYPES]");
+        push_schema_filter(&mut out, self.schema.as_deref());
+        Ok(())
+    }
+}
+
+impl Query for ShowTypesStatement {
+    type SqlType = (Text, Text, Text);
+}
+
+impl RunQueryDsl<PgConnection> for ShowTypesStatement {}
+
+impl QueryId for ShowTypesStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A user-defined type, as returned by `SHOW TYPES`.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct UserDefinedType {
+    /// The schema of the type.
+    pub schema: String,
+    /// The name of the type.
+    pub name: String,
+    /// The owner of the type.
+    pub owner: String,
+}
+
+fn push_schema_filter(out: &mut AstPass<Pg>, schema: Option<&str>) {
+    if let Some(schema) = schema {
+        out.push_sql(" WHERE schema = ");
+        out.push_sql(&quote::string_literal(schema));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            diesel::debug_query(&show_enums()).to_string(),
+            r#"SELECT schema, name, "values", owner FROM [SHOW ENUMS] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_enums().schema("public")).to_string(),
+            r#"SELECT schema, name, "values", owner FROM [SHOW ENUMS] WHERE schema = 'public' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            diesel::debug_query(&show_types().schema("public")).to_string(),
+            r#"SELECT schema, name, owner FROM [SHOW TYPES] WHERE schema = 'public' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn enum_values() {
+        let status = EnumType {
+            schema: "public".to_owned(),
+            name: "status".to_owned(),
+            values: vec!["open".to_owned(), "closed".to_owned()],
+            owner: "root".to_owned(),
+        };
+        assert_eq!(status.missing_values(&["open", "archived"]), ["archived"]);
+        assert_eq!(status.unexpected_values(&["open"]), ["closed"]);
+    }
+}
@@ -0,0 +1,626 @@
+//! Support for `EXPLAIN` and `EXPLAIN ANALYZE`, parsing the plan of a query
+//! into a tree, so tests can make assertions about its shape.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/explain-analyze.html
+
+use crate::{index::IndexRecommendation, quote};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::Text,
+};
+use std::time::Duration;
+
+/// Adds the [`explain`] and [`explain_analyze`] methods to all queries and
+/// statements.
+///
+/// [`explain`]: #method.explain
+/// [`explain_analyze`]: #method.explain_analyze
+pub trait ExplainDsl: QueryFragment<Pg> + Sized {
+    /// Show the plan of the query, without running it.
+    ///
+    /// ```sql
+    /// EXPLAIN SELECT title FROM books;
+    /// ```
+    fn explain(self) -> ExplainStatement<Self> {
+        ExplainStatement {
+            query: self,
+            analyze: false,
+            verbose: false,
+            types: false,
+            distsql: false,
+            debug: false,
+        }
+    }
+
+    /// Run the query, and show its plan with the rows and time taken by each
+    /// step.
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate diesel;
+    /// # use diesel::{pg::PgConnection, Connection, QueryDsl};
+    /// # table! { books (id) { id -> Int8, title -> Text, } }
+    /// # fn main() -> diesel::QueryResult<()> {
+    /// # let connection = PgConnection::establish("").unwrap();
+    /// use diesel_cockroach::explain::ExplainDsl;
+    ///
+    /// let plan = books::table.select(books::title).explain_analyze().plan(&connection)?;
+    /// assert!(!plan.has_full_scan());
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn explain_analyze(self) -> ExplainStatement<Self> {
+        ExplainStatement {
+            analyze: true,
+            ..self.explain()
+        }
+    }
+}
+
+impl<Q: QueryFragment<Pg>> ExplainDsl for Q {}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `EXPLAIN` statement, created by [`explain`] or
+/// [`explain_analyze`].
+///
+/// The parameters of this struct represent:
+///
+/// - `Q`: The query being explained
+///
+/// See the [CockroachDB docs].
+///
+/// [`explain`]: trait.ExplainDsl.html#method.explain
+/// [`explain_analyze`]: trait.ExplainDsl.html#method.explain_analyze
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/explain.html
+pub struct ExplainStatement<Q> {
+    query: Q,
+    analyze: bool,
+    verbose: bool,
+    types: bool,
+    distsql: bool,
+    debug: bool,
+}
+
+impl<Q> ExplainStatement<Q> {
+    /// Show more detail about each step, such as the columns it produces.
+    pub fn verbose(mut self) -> Self {
+        self.verbose = true;
+        self
+    }
+
+    /// Show the types of columns and expressions. Not supported with
+    /// `ANALYZE`.
+    pub fn types(mut self) -> Self {
+        self.types = true;
+        self
+    }
+
+    /// Show the distributed plan, with a link to a diagram of how it is
+    /// spread across nodes.
+    pub fn distsql(mut self) -> Self {
+        self.distsql = true;
+        self
+    }
+
+    /// Run the query, and collect a statement diagnostics bundle about its
+    /// execution. Implies `ANALYZE`.
+    pub fn debug(mut self) -> Self {
+        self.analyze = true;
+        self.debug = true;
+        self
+    }
+}
+
+impl<Q> ExplainStatement<Q>
+where
+    Q: QueryFragment<Pg>,
+{
+    /// Explain the query, and parse the plan.
+    pub fn plan(self, conn: &PgConnection) -> QueryResult<ExplainPlan> {
+        let lines: Vec<String> = self.load(conn)?;
+        Ok(ExplainPlan::parse(&lines))
+    }
+
+    /// Explain the distributed plan of the query, and return the URL of its
+    /// diagram, for attaching to bug reports or logs.
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate diesel;
+    /// # use diesel::{pg::PgConnection, Connection, QueryDsl};
+    /// # table! { books (id) { id -> Int8, title -> Text, } }
+    /// # fn main() -> diesel::QueryResult<()> {
+    /// # let connection = PgConnection::establish("").unwrap();
+    /// use diesel_cockroach::explain::ExplainDsl;
+    ///
+    /// if let Some(url) = books::table.count().explain().diagram_url(&connection)? {
+    ///     println!("distributed plan: {}", url);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn diagram_url(self, conn: &PgConnection) -> QueryResult<Option<String>> {
+        let lines: Vec<String> = self.distsql().load(conn)?;
+        Ok(diagram_url(&lines))
+    }
+
+    /// Run the query, collecting a statement diagnostics bundle, and return
+    /// where it can be downloaded from.
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate diesel;
+    /// # use diesel::{pg::PgConnection, Connection, QueryDsl};
+    /// # table! { books (id) { id -> Int8, title -> Text, } }
+    /// # fn main() -> diesel::QueryResult<()> {
+    /// # let connection = PgConnection::establish("").unwrap();
+    /// use diesel_cockroach::explain::ExplainDsl;
+    ///
+    /// let bundle = books::table.count().explain().statement_bundle(&connection)?;
+    /// if let Some(bundle) = bundle {
+    ///     println!("statement bundle {}: {:?}", bundle.id, bundle.url);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn statement_bundle(self, conn: &PgConnection) -> QueryResult<Option<StatementBundle>> {
+        let lines: Vec<String> = self.debug().load(conn)?;
+        Ok(StatementBundle::parse(&lines))
+    }
+}
+
+impl<Q> QueryFragment<Pg> for ExplainStatement<Q>
+where
+    Q: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("EXPLAIN ");
+        if self.analyze {
+            out.push_sql("ANALYZE ");
+        }
+        let options: Vec<&str> = [
+            (self.verbose, "VERBOSE"),
+            (self.types, "TYPES"),
+            (self.distsql, "DISTSQL"),
+            (self.debug, "DEBUG"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, option)| *option)
+        .collect();
+        if !options.is_empty() {
+            out.push_sql("(");
+            out.push_sql(&options.join(", "));
+            out.push_sql(") ");
+        }
+        self.query.walk_ast(out.reborrow())
+    }
+}
+
+impl<Q> Query for ExplainStatement<Q> {
+    type SqlType = Text;
+}
+
+impl<Q> RunQueryDsl<PgConnection> for ExplainStatement<Q> {}
+
+impl<Q> QueryId for ExplainStatement<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A query plan, parsed from the output of `EXPLAIN`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExplainPlan {
+    /// Properties of the whole plan, such as `planning time` or
+    /// `distribution`.
+    pub properties: Vec<(String, String)>,
+    /// The final step of the plan.
+    pub root: Option<PlanNode>,
+    /// The indexes CockroachDB recommends creating or changing to improve
+    /// the plan.
+    pub index_recommendations: Vec<IndexRecommendation>,
+}
+
+/// A step of a query plan, such as a `scan` or `lookup join`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanNode {
+    /// The name of the step.
+    pub name: String,
+    /// The attributes of the step, such as `table: books@books_pkey`.
+    pub attributes: Vec<(String, String)>,
+    /// The steps producing the input of this one.
+    pub children: Vec<PlanNode>,
+}
+
+/// A statement diagnostics bundle, collected by `EXPLAIN ANALYZE (DEBUG)`.
+///
+/// The bundle can also be downloaded with
+/// `cockroach statement-diag download <id>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementBundle {
+    /// The identifier of the bundle.
+    pub id: i64,
+    /// The link to download the bundle from the DB Console, if reported.
+    pub url: Option<String>,
+}
+
+impl StatementBundle {
+    /// Parses the lines returned by `EXPLAIN ANALYZE (DEBUG)`.
+    fn parse<S: AsRef<str>>(lines: &[S]) -> Option<Self> {
+        let mut url = None;
+        let mut id = None;
+        for line in lines {
+            let line = line.as_ref().trim();
+            if let Some(link) = line.strip_prefix("Direct link: ") {
+                url = Some(link.to_owned());
+            } else if let Some(command) = line.strip_prefix("Command line: ") {
+                id = command.rsplit(' ').next().and_then(|id| id.parse().ok());
+            }
+        }
+        let id = id.or_else(|| {
+            url.as_deref()
+                .and_then(|url| url.rsplit('/').next())
+                .and_then(|id| id.parse().ok())
+        })?;
+        Some(StatementBundle { id, url })
+    }
+}
+
+/// Finds the diagram link in the output of `EXPLAIN (DISTSQL)`.
+fn diagram_url<S: AsRef<str>>(lines: &[S]) -> Option<String> {
+    lines.iter().find_map(|line| {
+        let line = line.as_ref().trim();
+        line.strip_prefix("Diagram: ")
+            .or_else(|| Some(line).filter(|line| line.starts_with("https://")))
+            .map(str::to_owned)
+    })
+}
+
+/// Splits a `key: value` line.
+fn key_value(line: &str) -> (String, String) {
+    match line.find(": ") {
+        Some(colon) => (line[..colon].to_owned(), line[colon + 2..].to_owned()),
+        None => (line.trim_end_matches(':').to_owned(), String::new()),
+    }
+}
+
+/// Parses a count such as `1,234`, ignoring anything after it.
+fn parse_count(value: &str) -> Option<u64> {
+    let count: String = value
+        .split_whitespace()
+        .next()?
+        .chars()
+        .filter(|c| *c != ',')
+        .collect();
+    count.parse().ok()
+}
+
+impl ExplainPlan {
+    /// Parses the lines returned by `EXPLAIN`.
+    ///
+    /// ```
+    /// use diesel_cockroach::explain::ExplainPlan;
+    ///
+    /// let plan = ExplainPlan::parse(&[
+    ///     "distribution: local",
+    ///     "",
+    ///     "• scan",
+    ///     "  estimated row count: 1,000",
+    ///     "  table: books@books_pkey",
+    ///     "  spans: FULL SCAN",
+    /// ]);
+    /// assert_eq!(plan.property("distribution"), Some("local"));
+    /// assert!(plan.has_full_scan());
+    /// ```
+    pub fn parse<S: AsRef<str>>(lines: &[S]) -> Self {
+        let mut plan = ExplainPlan::default();
+        // The open nodes, with the column of their bullet
+        let mut stack: Vec<(usize, PlanNode)> = Vec::new();
+        let mut in_recommendations = false;
+        for line in lines {
+            let line = line.as_ref();
+            if line.trim().starts_with("index recommendations:") {
+                in_recommendations = true;
+                continue;
+            }
+            if in_recommendations && !line.contains('•') {
+                parse_recommendation(line.trim(), &mut plan.index_recommendations);
+                continue;
+            }
+            in_recommendations = false;
+            if let Some(bullet) = line.find('•') {
+                let column = line[..bullet].chars().count();
+                while stack.last().map_or(false, |(open, _)| *open >= column) {
+                    close_node(&mut stack, &mut plan);
+                }
+                let node = PlanNode {
+                    name: line[bullet + '•'.len_utf8()..].trim().to_owned(),
+                    attributes: Vec::new(),
+                    children: Vec::new(),
+                };
+                stack.push((column, node));
+                continue;
+            }
+            let content = line.trim_start_matches(|c| " │├└─".contains(c)).trim_end();
+            if content.is_empty() {
+                continue;
+            }
+            let attribute = key_value(content);
+            match stack.last_mut() {
+                Some((_, node)) => node.attributes.push(attribute),
+                None => plan.properties.push(attribute),
+            }
+        }
+        while !stack.is_empty() {
+            close_node(&mut stack, &mut plan);
+        }
+        plan
+    }
+
+    /// The value of a property of the whole plan.
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every step of the plan, starting from the root.
+    pub fn nodes(&self) -> Vec<&PlanNode> {
+        self.root.as_ref().map_or_else(Vec::new, PlanNode::nodes)
+    }
+
+    /// Whether any step scans every row of a table or index.
+    pub fn has_full_scan(&self) -> bool {
+        self.nodes().iter().any(|node| node.is_full_scan())
+    }
+}
+
+/// Parses a line of the `index recommendations` section, such as
+/// `1. type: index creation` or `SQL command: CREATE INDEX ...`.
+fn parse_recommendation(line: &str, recommendations: &mut Vec<IndexRecommendation>) {
+    let line = line.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let (key, value) = key_value(line.trim());
+    match key.as_str() {
+        "type" => recommendations.push(IndexRecommendation {
+            kind: value.parse().unwrap_or_else(|never| match never {}),
+            sql: String::new(),
+        }),
+        "SQL command" | "SQL commands" => {
+            if let Some(recommendation) = recommendations.last_mut() {
+                recommendation.sql = value;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn close_node(stack: &mut Vec<(usize, PlanNode)>, plan: &mut ExplainPlan) {
+    if let Some((_, node)) = stack.pop() {
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(node),
+            None => plan.root = Some(node),
+        }
+    }
+}
+
+impl PlanNode {
+    /// The value of an attribute of this step.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// This step and all of its descendants, depth first.
+    pub fn nodes(&self) -> Vec<&PlanNode> {
+        let mut nodes = vec![self];
+        for child in &self.children {
+            nodes.extend(child.nodes());
+        }
+        nodes
+    }
+
+    /// Whether this step scans every row of a table or index.
+    pub fn is_full_scan(&self) -> bool {
+        self.attribute("spans")
+            .map_or(false, |spans| spans.starts_with("FULL SCAN"))
+    }
+
+    /// The number of rows the optimizer estimated this step would produce.
+    pub fn estimated_row_count(&self) -> Option<u64> {
+        self.attribute("estimated row count").and_then(parse_count)
+    }
+
+    /// The number of rows this step produced, with `EXPLAIN ANALYZE`.
+    pub fn actual_row_count(&self) -> Option<u64> {
+        self.attribute("actual row count").and_then(parse_count)
+    }
+
+    /// The time this step spent executing, with `EXPLAIN ANALYZE`.
+    pub fn execution_time(&self) -> Option<Duration> {
+        self.attribute("execution time")
+            .and_then(quote::parse_duration)
+    }
+
+    /// The time this step spent reading from the storage layer, with
+    /// `EXPLAIN ANALYZE`.
+    pub fn kv_time(&self) -> Option<Duration> {
+        self.attribute("KV time").and_then(quote::parse_duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::IndexRecommendationKind;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn explain() {
+        assert_eq!(
+            diesel::debug_query(&books::table.select(books::title).explain()).to_string(),
+            r#"EXPLAIN SELECT "books"."title" FROM "books" -- binds: []"#
+        );
+        let query = books::table
+            .filter(books::id.eq(1))
+            .explain_analyze()
+            .verbose();
+        assert_eq!(
+            diesel::debug_query(&query).to_string(),
+            r#"EXPLAIN ANALYZE (VERBOSE) SELECT "books"."id", "books"."title" FROM "books" WHERE "books"."id" = $1 -- binds: [1]"#
+        );
+        let query = diesel::delete(books::table).explain().verbose().types();
+        assert_eq!(
+            diesel::debug_query(&query).to_string(),
+            r#"EXPLAIN (VERBOSE, TYPES) DELETE FROM "books" -- binds: []"#
+        );
+        let query = books::table.select(books::id).explain_analyze().distsql();
+        assert_eq!(
+            diesel::debug_query(&query).to_string(),
+            r#"EXPLAIN ANALYZE (DISTSQL) SELECT "books"."id" FROM "books" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn diagram() {
+        assert_eq!(
+            diagram_url(&[
+                "distribution: full",
+                "",
+                "• scan",
+                "  table: books@books_pkey",
+                "  spans: FULL SCAN",
+                "",
+                "Diagram: https://cockroachdb.github.io/distsqlplan/decode.html#eJyMj8",
+            ]),
+            Some("https://cockroachdb.github.io/distsqlplan/decode.html#eJyMj8".to_owned())
+        );
+        assert_eq!(diagram_url(&["• scan"]), None);
+    }
+
+    #[test]
+    fn statement_bundle() {
+        assert_eq!(
+            diesel::debug_query(&books::table.select(books::id).explain().debug()).to_string(),
+            r#"EXPLAIN ANALYZE (DEBUG) SELECT "books"."id" FROM "books" -- binds: []"#
+        );
+        assert_eq!(
+            StatementBundle::parse(&[
+                "Statement diagnostics bundle generated. Download from the Admin UI (Advanced Debug -> Statement Diagnostics History), via the direct link below, or using the SQL shell or command line.",
+                "Admin UI: http://127.0.0.1:8080",
+                "Direct link: http://127.0.0.1:8080/_admin/v1/stmtbundle/765493679630483457",
+                "SQL shell: \\statement-diag download 765493679630483457",
+                "Command line: cockroach statement-diag download 765493679630483457",
+            ]),
+            Some(StatementBundle {
+                id: 765_493_679_630_483_457,
+                url: Some(
+                    "http://127.0.0.1:8080/_admin/v1/stmtbundle/765493679630483457".to_owned()
+                ),
+            })
+        );
+        assert_eq!(StatementBundle::parse(&["• scan"]), None);
+    }
+
+    #[test]
+    fn parse() {
+        let plan = ExplainPlan::parse(&[
+            "planning time: 295µs",
+            "execution time: 2ms",
+            "distribution: local",
+            "",
+            "• sort",
+            "│ nodes: n1",
+            "│ actual row count: 1,234",
+            "│ order: +title",
+            "│",
+            "└── • hash join",
+            "    │ equality: (id) = (book_id)",
+            "    │",
+            "    ├── • scan",
+            "    │     actual row count: 10",
+            "    │     KV time: 1.5ms",
+            "    │     table: books@books_pkey",
+            "    │     spans: FULL SCAN",
+            "    │",
+            "    └── • scan",
+            "          estimated row count: 5 (0.50% of the table)",
+            "          table: reviews@reviews_book_id_idx",
+            "          spans: [/1 - /1]",
+        ]);
+        assert_eq!(plan.property("execution time"), Some("2ms"));
+        let root = plan.root.as_ref().unwrap();
+        assert_eq!(root.name, "sort");
+        assert_eq!(root.actual_row_count(), Some(1234));
+        assert_eq!(root.children.len(), 1);
+        let join = &root.children[0];
+        assert_eq!(join.name, "hash join");
+        assert_eq!(join.attribute("equality"), Some("(id) = (book_id)"));
+        assert_eq!(join.children.len(), 2);
+        let (books, reviews) = (&join.children[0], &join.children[1]);
+        assert!(books.is_full_scan());
+        assert_eq!(books.kv_time(), Some(Duration::from_micros(1500)));
+        assert!(!reviews.is_full_scan());
+        assert_eq!(reviews.estimated_row_count(), Some(5));
+        assert_eq!(
+            plan.nodes()
+                .iter()
+                .map(|node| node.name.as_str())
+                .collect::<Vec<_>>(),
+            ["sort", "hash join", "scan", "scan"]
+        );
+        assert!(plan.has_full_scan());
+        assert_eq!(plan.index_recommendations, []);
+    }
+
+    #[test]
+    fn parse_index_recommendations() {
+        let plan = ExplainPlan::parse(&[
+            "distribution: local",
+            "vectorized: true",
+            "",
+            "• filter",
+            "│ filter: author = 'Jane Austen'",
+            "│",
+            "└── • scan",
+            "      table: books@books_pkey",
+            "      spans: FULL SCAN",
+            "",
+            "index recommendations: 2",
+            "1. type: index creation",
+            "   SQL command: CREATE INDEX ON books (author);",
+            "2. type: index replacement",
+            "   SQL commands: CREATE INDEX ON books (title) STORING (author); DROP INDEX books@books_title_idx;",
+        ]);
+        assert_eq!(plan.nodes().len(), 2);
+        assert_eq!(
+            plan.index_recommendations,
+            [
+                IndexRecommendation {
+                    kind: IndexRecommendationKind::Creation,
+                    sql: "CREATE INDEX ON books (author);".to_owned(),
+                },
+                IndexRecommendation {
+                    kind: IndexRecommendationKind::Replacement,
+                    sql: "CREATE INDEX ON books (title) STORING (author); DROP INDEX books@books_title_idx;".to_owned(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Support for `EXPORT` SQL queries, writing the results of a query to files.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/export.html
+
+use crate::{
+    external_storage::ExternalStorageUri,
+    quote,
+    version::{ClusterVersion, Unsupported},
+};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AsQuery, AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::{BigInt, Text},
+    Queryable,
+};
+
+/// Creates an `EXPORT INTO CSV` statement, writing the results of `query`
+/// to CSV files under `uri`.
+///
+/// Any select statement may be exported, including one pinned to a
+/// historical timestamp with [`as_of_system_time`]. The statement returns an
+/// [`ExportedFile`] for each file written:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, QueryDsl, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, title -> Text, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{
+///     as_of_system_time::{AsOfSystemTime, AsOfSystemTimeDsl},
+///     export::{export_into_csv, CsvExportOptions, ExportedFile},
+/// };
+///
+/// let files: Vec<ExportedFile> = export_into_csv(
+///     "nodelocal://1/books",
+///     books::table
+///         .select((books::id, books::title))
+///         .as_of_system_time(AsOfSystemTime::FollowerReadTimestamp),
+///     CsvExportOptions::new().gzip(),
+/// )
+/// .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`as_of_system_time`]: ../as_of_system_time/trait.AsOfSystemTimeDsl.html#method.as_of_system_time
+/// [`ExportedFile`]: struct.ExportedFile.html
+pub fn export_into_csv<Q>(
+    uri: impl Into<ExternalStorageUri>,
+    query: Q,
+    options: CsvExportOptions,
+) -> ExportStatement<Q::Query>
+where
+    Q: AsQuery,
+{
+    ExportStatement::new(uri.into(), query.as_query(), ExportFormat::Csv(options))
+}
+
+/// Creates an `EXPORT INTO PARQUET` statement, writing the results of
+/// `query` to Parquet files under `uri`.
+///
+/// Parquet export requires CockroachDB v22.2 or later, so the
+/// [`ClusterVersion`] of the cluster must be given, and an [`Unsupported`]
+/// error is returned for older versions.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, title -> Text, } }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let connection = PgConnection::establish("")?;
+/// use diesel_cockroach::{
+///     export::{export_into_parquet, ExportedFile, ParquetCompression, ParquetExportOptions},
+///     version::ClusterVersion,
+/// };
+///
+/// let version = ClusterVersion::detect(&connection)?;
+/// let files: Vec<ExportedFile> = export_into_parquet(
+///     &version,
+///     "nodelocal://1/books",
+///     books::table,
+///     ParquetExportOptions::new().compression(ParquetCompression::Snappy),
+/// )?
+/// .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ClusterVersion`]: ../version/struct.ClusterVersion.html
+/// [`Unsupported`]: ../version/struct.Unsupported.html
+pub fn export_into_parquet<Q>(
+    version: &ClusterVersion,
+    uri: impl Into<ExternalStorageUri>,
+    query: Q,
+    options: ParquetExportOptions,
+) -> Result<ExportStatement<Q::Query>, Unsupported>
+where
+    Q: AsQuery,
+{
+    version.require("EXPORT INTO PARQUET", 22, 2)?;
+    Ok(ExportStatement::new(
+        uri.into(),
+        query.as_query(),
+        ExportFormat::Parquet(options),
+    ))
+}
+
+/// Options for exporting CSV files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvExportOptions {
+    delimiter: Option<char>,
+    nullas: Option<String>,
+    gzip: bool,
+}
+
+impl CsvExportOptions {
+    /// Options for uncompressed comma separated files, with `NULL` written
+    /// as an empty field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Separate fields with `delimiter`, rather than a comma.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Write `NULL` values as `nullas`.
+    pub fn nullas(mut self, nullas: impl Into<String>) -> Self {
+        self.nullas = Some(nullas.into());
+        self
+    }
+
+    /// Compress the files with gzip.
+    pub fn gzip(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    fn options(&self) -> Vec<(&'static str, Option<String>)> {
+        let mut options = Vec::new();
+        if let Some(delimiter) = self.delimiter {
+            options.push(("delimiter", Some(delimiter.to_string())));
+        }
+        if let Some(nullas) = &self.nullas {
+            options.push(("nullas", Some(nullas.clone())));
+        }
+        if self.gzip {
+            options.push(("compression", Some("gzip".to_owned())));
+        }
+        options
+    }
+}
+
+/// The compression codec of exported Parquet files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParquetCompression {
+    /// `gzip`
+    Gzip,
+    /// `snappy`
+    Snappy,
+}
+
+impl ParquetCompression {
+    /// The name of this codec.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParquetCompression::Gzip => "gzip",
+            ParquetCompression::Snappy => "snappy",
+        }
+    }
+}
+
+/// Options for exporting Parquet files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParquetExportOptions {
+    compression: Option<ParquetCompression>,
+}
+
+impl ParquetExportOptions {
+    /// Options for uncompressed Parquet files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress the files with the given codec.
+    pub fn compression(mut self, compression: ParquetCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    fn options(&self) -> Vec<(&'static str, Option<String>)> {
+        self.compression
+            .iter()
+            .map(|compression| ("compression", Some(compression.as_str().to_owned())))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ExportFormat {
+    Csv(CsvExportOptions),
+    Parquet(ParquetExportOptions),
+}
+
+impl ExportFormat {
+    fn keyword(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv(_) => "CSV",
+            ExportFormat::Parquet(_) => "PARQUET",
+        }
+    }
+
+    fn options(&self) -> Vec<(&'static str, Option<String>)> {
+        match self {
+            ExportFormat::Csv(options) => options.options(),
+            ExportFormat::Parquet(options) => options.options(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `EXPORT` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `Q`: The query whose results are exported
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/export.html
+pub struct ExportStatement<Q> {
+    uri: ExternalStorageUri,
+    query: Q,
+    format: ExportFormat,
+    chunk_rows: Option<u64>,
+    chunk_size: Option<String>,
+}
+
+impl<Q> ExportStatement<Q> {
+    fn new(uri: ExternalStorageUri, query: Q, format: ExportFormat) -> Self {
+        ExportStatement {
+            uri,
+            query,
+            format,
+            chunk_rows: None,
+            chunk_size: None,
+        }
+    }
+
+    /// Start a new file after every `rows` rows.
+    pub fn chunk_rows(mut self, rows: u64) -> Self {
+        self.chunk_rows = Some(rows);
+        self
+    }
+
+    /// Start a new file once a file reaches `size`, such as `32MiB`.
+    pub fn chunk_size(mut self, size: impl Into<String>) -> Self {
+        self.chunk_size = Some(size.into());
+        self
+    }
+}
+
+impl<Q> QueryFragment<Pg> for ExportStatement<Q>
+where
+    Q: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("EXPORT INTO ");
+        out.push_sql(self.format.keyword());
+        out.push_sql(" ");
+        out.push_sql(&quote::string_literal(&self.uri.expose()));
+        let mut options = self.format.options();
+        if let Some(rows) = self.chunk_rows {
+            options.push(("chunk_rows", Some(rows.to_string())));
+        }
+        if let Some(size) = &self.chunk_size {
+            options.push(("chunk_size", Some(size.clone())));
+        }
+        quote::push_options(&mut out, &options)?;
+        out.push_sql(" FROM ");
+        self.query.walk_ast(out.reborrow())
+    }
+}
+
+impl<Q> Query for ExportStatement<Q> {
+    type SqlType = (Text, BigInt, BigInt);
+}
+
+impl<Q> RunQueryDsl<PgConnection> for ExportStatement<Q> {}
+
+impl<Q> QueryId for ExportStatement<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A file written by an `EXPORT` statement.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct ExportedFile {
+    /// The name of the file, relative to the export URI.
+    pub filename: String,
+    /// The number of rows written to the file.
+    pub rows: i64,
+    /// The size of the file in bytes.
+    pub bytes: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as_of_system_time::{AsOfSystemTime, AsOfSystemTimeDsl};
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn csv() {
+        let statement = export_into_csv(
+            "nodelocal://1/books",
+            books::table.filter(books::id.gt(10)),
+            CsvExportOptions::new(),
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"EXPORT INTO CSV 'nodelocal://1/books' FROM SELECT "books"."id", "books"."title" FROM "books" WHERE "books"."id" > $1 -- binds: [10]"#
+        );
+    }
+
+    #[test]
+    fn csv_options_as_of_system_time() {
+        let statement = export_into_csv(
+            "nodelocal://1/books",
+            books::table
+                .select(books::title)
+                .as_of_system_time(AsOfSystemTime::Ago(Duration::from_secs(10))),
+            CsvExportOptions::new().delimiter('|').nullas("\\N").gzip(),
+        )
+        .chunk_rows(1000)
+        .chunk_size("32MiB");
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"EXPORT INTO CSV 'nodelocal://1/books' WITH delimiter = '|', nullas = '\N', compression = 'gzip', chunk_rows = '1000', chunk_size = '32MiB' FROM SELECT * FROM (SELECT "books"."title" FROM "books") AS OF SYSTEM TIME '-10s' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn parquet() {
+        let statement = export_into_parquet(
+            &ClusterVersion::new(22, 2, 0),
+            "nodelocal://1/books",
+            books::table.select(books::title),
+            ParquetExportOptions::new().compression(ParquetCompression::Snappy),
+        )
+        .unwrap();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"EXPORT INTO PARQUET 'nodelocal://1/books' WITH compression = 'snappy' FROM SELECT "books"."title" FROM "books" -- binds: []"#
+        );
+
+        let unsupported = export_into_parquet(
+            &ClusterVersion::new(22, 1, 12),
+            "nodelocal://1/books",
+            books::table,
+            ParquetExportOptions::new(),
+        )
+        .unwrap_err();
+        assert_eq!(unsupported.required, ClusterVersion::new(22, 2, 0));
+    }
+}
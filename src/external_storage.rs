@@ -0,0 +1,624 @@
+//! URIs for the external storage read and written by bulk operations, such
+//! as `BACKUP`, `RESTORE`, `IMPORT` and `EXPORT`, and uploading files to
+//! `userfile` storage.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/use-cloud-storage.html
+
+use crate::quote;
+use diesel::{
+    connection::{Connection, SimpleConnection},
+    dsl::sql,
+    pg::PgConnection,
+    sql_types::{BigInt, Bytea, Text},
+    RunQueryDsl,
+};
+use std::{error, fmt, fs, io, path::Path};
+
+/// The size of the chunks uploaded files are stored in.
+const USERFILE_CHUNK_SIZE: usize = 4 << 20;
+
+/// Percent-encodes `value`, leaving unreserved characters, and any of
+/// `keep`, as they are.
+fn percent_encode(value: &str, keep: &[u8]) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) || keep.contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// The query parameters holding credentials, which are redacted when a URI
+/// given as a string is displayed.
+const SECRET_PARAMS: &[&str] = &[
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+    "CREDENTIALS",
+    "BEARER_TOKEN",
+    "AZURE_ACCOUNT_KEY",
+    "AZURE_CLIENT_SECRET",
+    "client_key",
+    "sasl_password",
+];
+
+/// Encodes a path, without a leading slash.
+fn encode_path(path: &str) -> String {
+    percent_encode(path.trim_start_matches('/'), b"/")
+}
+
+/// A URI for external storage, with any credentials as query parameters.
+///
+/// Paths and parameters are percent-encoded when the URI is built. The
+/// `Debug` and `Display` implementations redact the values of secret
+/// parameters, so URIs, and the `Debug` output of the bulk operation
+/// statements holding them, can be logged safely. The full URI is available
+/// by converting it into a `String`, and is rendered into the SQL of a
+/// statement as a string literal.
+///
+/// **Warning:** the SQL of a statement holding credentials includes them in
+/// full, so the output of `diesel::debug_query` for such a statement must not
+/// be logged.
+///
+/// Every bulk operation builder accepts an `ExternalStorageUri`, or a string
+/// which is already encoded. The parameters of a string known to hold
+/// credentials, such as `AWS_SECRET_ACCESS_KEY`, are redacted in the same way.
+///
+/// ```
+/// use diesel_cockroach::external_storage::{ExternalStorageUri, S3Auth};
+///
+/// let uri = ExternalStorageUri::s3(
+///     "bucket",
+///     "backups",
+///     S3Auth::Specified {
+///         access_key_id: "AKIAEXAMPLE".to_owned(),
+///         secret_access_key: "a/secret+key".to_owned(),
+///         session_token: None,
+///     },
+/// )
+/// .param("AWS_REGION", "eu-west-1");
+/// assert_eq!(
+///     uri.to_string(),
+///     "s3://bucket/backups?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&AWS_SECRET_ACCESS_KEY=redacted&AWS_REGION=eu-west-1"
+/// );
+/// assert_eq!(
+///     String::from(uri),
+///     "s3://bucket/backups?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&AWS_SECRET_ACCESS_KEY=a%2Fsecret%2Bkey&AWS_REGION=eu-west-1"
+/// );
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct ExternalStorageUri {
+    base: String,
+    params: Vec<UriParam>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct UriParam {
+    key: String,
+    value: Option<String>,
+    secret: bool,
+}
+
+impl ExternalStorageUri {
+    pub(crate) fn new(scheme: &str, host: &str, path: &str) -> Self {
+        Self::with_base(format!("{}://{}/{}", scheme, host, encode_path(path)))
+    }
+
+    pub(crate) fn with_base(base: String) -> Self {
+        ExternalStorageUri {
+            base,
+            params: Vec::new(),
+        }
+    }
+
+    /// A file in the current user's `userfile` storage.
+    pub fn userfile(path: &str) -> Self {
+        Self::new("userfile", "", path)
+    }
+
+    /// A file in the external IO directory of a node.
+    pub fn nodelocal(node: NodeLocal, path: &str) -> Self {
+        let node = match node {
+            NodeLocal::Node(id) => id.to_string(),
+            NodeLocal::SelfNode => "self".to_owned(),
+        };
+        Self::new("nodelocal", &node, path)
+    }
+
+    /// A path in an Amazon S3 bucket.
+    pub fn s3(bucket: &str, path: &str, auth: S3Auth) -> Self {
+        let uri = Self::new("s3", bucket, path);
+        match auth {
+            S3Auth::Implicit => uri.param("AUTH", "implicit"),
+            S3Auth::Specified {
+                access_key_id,
+                secret_access_key,
+                session_token,
+            } => {
+                let uri = uri
+                    .param("AWS_ACCESS_KEY_ID", access_key_id)
+                    .secret_param("AWS_SECRET_ACCESS_KEY", secret_access_key);
+                match session_token {
+                    Some(session_token) => uri.secret_param("AWS_SESSION_TOKEN", session_token),
+                    None => uri,
+                }
+            }
+        }
+    }
+
+    /// A path in a Google Cloud Storage bucket.
+    pub fn gcs(bucket: &str, path: &str, auth: GcsAuth) -> Self {
+        let uri = Self::new("gs", bucket, path);
+        match auth {
+            GcsAuth::Implicit => uri.param("AUTH", "implicit"),
+            GcsAuth::Specified { credentials } => uri
+                .param("AUTH", "specified")
+                .secret_param("CREDENTIALS", credentials),
+        }
+    }
+
+    /// A path in an Azure Blob Storage container, authenticated with an
+    /// account key.
+    pub fn azure(
+        container: &str,
+        path: &str,
+        account_name: impl Into<String>,
+        account_key: impl Into<String>,
+    ) -> Self {
+        Self::new("azure", container, path)
+            .param("AZURE_ACCOUNT_NAME", account_name)
+            .secret_param("AZURE_ACCOUNT_KEY", account_key)
+    }
+
+    /// A path on an HTTP server.
+    pub fn http(host: &str, path: &str) -> Self {
+        Self::new("http", host, path)
+    }
+
+    /// A path on an HTTPS server.
+    pub fn https(host: &str, path: &str) -> Self {
+        Self::new("https", host, path)
+    }
+
+    /// Assume the given role before accessing the storage, such as an IAM
+    /// role ARN for S3, or a service account for Google Cloud Storage.
+    ///
+    /// A chain of roles may be given, separated by commas.
+    pub fn assume_role(self, role: impl Into<String>) -> Self {
+        self.param("ASSUME_ROLE", role)
+    }
+
+    /// Add any other query parameter, such as `AWS_REGION` or `AWS_ENDPOINT`.
+    pub fn param(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.push_param(key.into(), value.into(), false)
+    }
+
+    /// Add any other query parameter, redacting its value when displayed.
+    pub fn secret_param(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.push_param(key.into(), value.into(), true)
+    }
+
+    fn push_param(mut self, key: String, value: String, secret: bool) -> Self {
+        self.params.push(UriParam {
+            key: percent_encode(&key, b""),
+            value: Some(percent_encode(&value, b"")),
+            secret,
+        });
+        self
+    }
+
+    /// The URI before any query parameters.
+    pub(crate) fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// The full URI, including secrets, for rendering into SQL.
+    pub(crate) fn expose(&self) -> String {
+        self.render(false)
+    }
+
+    fn render(&self, redact: bool) -> String {
+        let mut uri = self.base.clone();
+        for (i, param) in self.params.iter().enumerate() {
+            uri.push(if i == 0 { '?' } else { '&' });
+            uri.push_str(&param.key);
+            if let Some(value) = &param.value {
+                uri.push('=');
+                if redact && param.secret {
+                    uri.push_str("redacted");
+                } else {
+                    uri.push_str(value);
+                }
+            }
+        }
+        uri
+    }
+}
+
+/// Parses a URI which is already encoded, treating the parameters known to
+/// hold credentials as secret.
+impl From<String> for ExternalStorageUri {
+    fn from(uri: String) -> Self {
+        let (base, query) = match uri.find('?') {
+            Some(i) => (&uri[..i], &uri[i + 1..]),
+            None => (uri.as_str(), ""),
+        };
+        let params = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (key, value) = match param.find('=') {
+                    Some(i) => (&param[..i], Some(param[i + 1..].to_owned())),
+                    None => (param, None),
+                };
+                UriParam {
+                    key: key.to_owned(),
+                    value,
+                    secret: SECRET_PARAMS
+                        .iter()
+                        .any(|secret| secret.eq_ignore_ascii_case(key)),
+                }
+            })
+            .collect();
+        ExternalStorageUri {
+            base: base.to_owned(),
+            params,
+        }
+    }
+}
+
+impl From<&str> for ExternalStorageUri {
+    fn from(uri: &str) -> Self {
+        ExternalStorageUri::from(uri.to_owned())
+    }
+}
+
+impl From<&String> for ExternalStorageUri {
+    fn from(uri: &String) -> Self {
+        ExternalStorageUri::from(uri.clone())
+    }
+}
+
+/// Displays the URI with secret parameters redacted.
+impl fmt::Display for ExternalStorageUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(true))
+    }
+}
+
+impl fmt::Debug for ExternalStorageUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ExternalStorageUri")
+            .field(&self.render(true))
+            .finish()
+    }
+}
+
+impl From<ExternalStorageUri> for String {
+    fn from(uri: ExternalStorageUri) -> Self {
+        uri.render(false)
+    }
+}
+
+/// Creates a URI for a file in the current user's `userfile` storage.
+///
+/// ```
+/// use diesel_cockroach::external_storage::userfile_uri;
+///
+/// assert_eq!(userfile_uri("imports/books 1.csv"), "userfile:///imports/books%201.csv");
+/// ```
+pub fn userfile_uri(path: &str) -> String {
+    ExternalStorageUri::userfile(path).into()
+}
+
+/// The node whose local file system a `nodelocal` URI refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeLocal {
+    /// The node with the given ID.
+    Node(u32),
+    /// The node the statement is run on.
+    SelfNode,
+}
+
+/// Creates a URI for a file in the external IO directory of a node.
+///
+/// ```
+/// use diesel_cockroach::external_storage::{nodelocal_uri, NodeLocal};
+///
+/// assert_eq!(nodelocal_uri(NodeLocal::Node(1), "/backups"), "nodelocal://1/backups");
+/// ```
+pub fn nodelocal_uri(node: NodeLocal, path: &str) -> String {
+    ExternalStorageUri::nodelocal(node, path).into()
+}
+
+/// How CockroachDB authenticates with Amazon S3.
+///
+/// The `Debug` implementation redacts secrets.
+#[derive(Clone, PartialEq, Eq)]
+pub enum S3Auth {
+    /// Use the credentials of the environment each node runs in.
+    Implicit,
+    /// Use the given access key.
+    Specified {
+        /// `AWS_ACCESS_KEY_ID`.
+        access_key_id: String,
+        /// `AWS_SECRET_ACCESS_KEY`.
+        secret_access_key: String,
+        /// `AWS_SESSION_TOKEN`, for temporary credentials.
+        session_token: Option<String>,
+    },
+}
+
+impl fmt::Debug for S3Auth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            S3Auth::Implicit => f.write_str("Implicit"),
+            S3Auth::Specified { access_key_id, .. } => f
+                .debug_struct("Specified")
+                .field("access_key_id", access_key_id)
+                .finish(),
+        }
+    }
+}
+
+/// Creates a URI for a path in an Amazon S3 bucket.
+///
+/// ```
+/// use diesel_cockroach::external_storage::{s3_uri, S3Auth};
+///
+/// assert_eq!(s3_uri("bucket", "backups", S3Auth::Implicit), "s3://bucket/backups?AUTH=implicit");
+/// ```
+pub fn s3_uri(bucket: &str, path: &str, auth: S3Auth) -> String {
+    ExternalStorageUri::s3(bucket, path, auth).into()
+}
+
+/// How CockroachDB authenticates with Google Cloud Storage.
+///
+/// The `Debug` implementation redacts secrets.
+#[derive(Clone, PartialEq, Eq)]
+pub enum GcsAuth {
+    /// Use the credentials of the environment each node runs in.
+    Implicit,
+    /// Use the given service account key, as base64 encoded JSON.
+    Specified {
+        /// `CREDENTIALS`.
+        credentials: String,
+    },
+}
+
+impl fmt::Debug for GcsAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GcsAuth::Implicit => f.write_str("Implicit"),
+            GcsAuth::Specified { .. } => f.write_str("Specified"),
+        }
+    }
+}
+
+/// Creates a URI for a path in a Google Cloud Storage bucket.
+///
+/// ```
+/// use diesel_cockroach::external_storage::{gcs_uri, GcsAuth};
+///
+/// assert_eq!(gcs_uri("bucket", "backups", GcsAuth::Implicit), "gs://bucket/backups?AUTH=implicit");
+/// ```
+pub fn gcs_uri(bucket: &str, path: &str, auth: GcsAuth) -> String {
+    ExternalStorageUri::gcs(bucket, path, auth).into()
+}
+
+/// The error returned when uploading a file to `userfile` storage fails.
+#[derive(Debug)]
+pub enum UploadError {
+    /// Reading the local file failed.
+    Io(io::Error),
+    /// Writing the file to the database failed.
+    Query(diesel::result::Error),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UploadError::Io(err) => write!(f, "failed to read file to upload: {}", err),
+            UploadError::Query(err) => write!(f, "failed to upload file: {}", err),
+        }
+    }
+}
+
+impl error::Error for UploadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            UploadError::Io(err) => Some(err),
+            UploadError::Query(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for UploadError {
+    fn from(err: io::Error) -> Self {
+        UploadError::Io(err)
+    }
+}
+
+impl From<diesel::result::Error> for UploadError {
+    fn from(err: diesel::result::Error) -> Self {
+        UploadError::Query(err)
+    }
+}
+
+/// Uploads a local file to `path` in the current user's `userfile` storage,
+/// like `cockroach userfile upload`, returning its URI.
+///
+/// The file is written in a single transaction to the tables backing the
+/// user's `userfile` storage, creating them if needed. Uploading fails if a
+/// file already exists at `path`.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let connection = PgConnection::establish("")?;
+/// use diesel_cockroach::external_storage::upload_userfile;
+///
+/// let uri = upload_userfile(&connection, "books.csv", "imports/books.csv")?;
+/// assert_eq!(uri, "userfile:///imports/books.csv");
+/// # Ok(())
+/// # }
+/// ```
+pub fn upload_userfile(
+    conn: &PgConnection,
+    local_path: impl AsRef<Path>,
+    path: &str,
+) -> Result<String, UploadError> {
+    let contents = fs::read(local_path)?;
+    let filename = format!("/{}", path.trim_start_matches('/'));
+
+    let user: String = diesel::select(sql::<Text>("current_user()")).get_result(conn)?;
+    let prefix = format!("userfiles_{}", user);
+    let files_table = userfile_table(&prefix, "_upload_files");
+    let payload_table = userfile_table(&prefix, "_upload_payload");
+
+    conn.batch_execute(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (\
+         filename STRING PRIMARY KEY, \
+         file_id UUID UNIQUE NOT NULL DEFAULT gen_random_uuid(), \
+         file_size INT8 NOT NULL, \
+         username STRING NOT NULL, \
+         upload_time TIMESTAMP DEFAULT now()); \
+         CREATE TABLE IF NOT EXISTS {} (\
+         file_id UUID, \
+         byte_offset INT8, \
+         payload BYTES, \
+         PRIMARY KEY (file_id, byte_offset))",
+        files_table, payload_table,
+    ))?;
+
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::sql_query(format!(
+            "INSERT INTO {} (filename, file_size, username) VALUES ($1, $2, current_user())",
+            files_table,
+        ))
+        .bind::<Text, _>(&filename)
+        .bind::<BigInt, _>(contents.len() as i64)
+        .execute(conn)?;
+        for (i, chunk) in contents.chunks(USERFILE_CHUNK_SIZE).enumerate() {
+            diesel::sql_query(format!(
+                "INSERT INTO {} (file_id, byte_offset, payload) \
+                 SELECT file_id, $2, $3 FROM {} WHERE filename = $1",
+                payload_table, files_table,
+            ))
+            .bind::<Text, _>(&filename)
+            .bind::<BigInt, _>((i * USERFILE_CHUNK_SIZE) as i64)
+            .bind::<Bytea, _>(chunk)
+            .execute(conn)?;
+        }
+        Ok(())
+    })?;
+
+    Ok(userfile_uri(path))
+}
+
+/// The name of one of the tables backing `userfile` storage.
+fn userfile_table(prefix: &str, suffix: &str) -> String {
+    format!(
+        "{}.{}.{}",
+        quote::identifier("defaultdb"),
+        quote::identifier("public"),
+        quote::identifier(&format!("{}{}", prefix, suffix)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn local() {
+        assert_eq!(userfile_uri("/books.csv"), "userfile:///books.csv");
+        assert_eq!(
+            nodelocal_uri(NodeLocal::SelfNode, "exports/books?.csv"),
+            "nodelocal://self/exports/books%3F.csv"
+        );
+    }
+
+    #[test]
+    fn s3() {
+        assert_eq!(
+            s3_uri("bucket", "/backups/nightly", S3Auth::Implicit),
+            "s3://bucket/backups/nightly?AUTH=implicit"
+        );
+        let auth = S3Auth::Specified {
+            access_key_id: "AKIAEXAMPLE".to_owned(),
+            secret_access_key: "secret".to_owned(),
+            session_token: Some("token&=".to_owned()),
+        };
+        assert_eq!(
+            s3_uri("bucket", "backups", auth),
+            "s3://bucket/backups?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&AWS_SECRET_ACCESS_KEY=secret&AWS_SESSION_TOKEN=token%26%3D"
+        );
+    }
+
+    #[test]
+    fn gcs() {
+        let auth = GcsAuth::Specified {
+            credentials: "eyJ0eXBlIjoi==".to_owned(),
+        };
+        assert_eq!(
+            gcs_uri("bucket", "backups", auth),
+            "gs://bucket/backups?AUTH=specified&CREDENTIALS=eyJ0eXBlIjoi%3D%3D"
+        );
+    }
+
+    #[test]
+    fn redacted() {
+        let uri = ExternalStorageUri::azure("container", "backups", "account", "key==")
+            .assume_role("arn:aws:iam::123456789012:role/backup");
+        assert_eq!(
+            format!("{:?}", uri),
+            r#"ExternalStorageUri("azure://container/backups?AZURE_ACCOUNT_NAME=account&AZURE_ACCOUNT_KEY=redacted&ASSUME_ROLE=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fbackup")"#
+        );
+        assert_eq!(
+            String::from(uri),
+            "azure://container/backups?AZURE_ACCOUNT_NAME=account&AZURE_ACCOUNT_KEY=key%3D%3D&ASSUME_ROLE=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fbackup"
+        );
+        let auth = GcsAuth::Specified {
+            credentials: "secret".to_owned(),
+        };
+        assert_eq!(format!("{:?}", auth), "Specified");
+    }
+
+    #[test]
+    fn parsed() {
+        let raw = "s3://bucket/backups?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&aws_secret_access_key=a%2Fsecret&AWS_REGION=eu-west-1&flag";
+        let uri = ExternalStorageUri::from(raw);
+        assert_eq!(
+            uri.to_string(),
+            "s3://bucket/backups?AWS_ACCESS_KEY_ID=AKIAEXAMPLE&aws_secret_access_key=redacted&AWS_REGION=eu-west-1&flag"
+        );
+        assert_eq!(uri.base(), "s3://bucket/backups");
+        assert_eq!(String::from(uri), raw);
+        assert_eq!(
+            ExternalStorageUri::from("nodelocal://1/backups").to_string(),
+            "nodelocal://1/backups"
+        );
+    }
+
+    #[test]
+    fn http() {
+        assert_eq!(
+            ExternalStorageUri::https("files.example.com", "exports/books.csv").to_string(),
+            "https://files.example.com/exports/books.csv"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(
+            userfile_table("userfiles_root", "_upload_files"),
+            r#""defaultdb"."public"."userfiles_root_upload_files""#
+        );
+    }
+}
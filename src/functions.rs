@@ -0,0 +1,356 @@
+//! Bindings for CockroachDB specific SQL functions.
+//!
+//! Functions with arguments are declared with `sql_function!`. Functions
+//! without arguments are declared with `no_arg_sql_function!`, as
+//! unit structs like Diesel's own `now`. They can be used anywhere an
+//! expression is expected, such as in `select`, `filter` or the values of an
+//! insert:
+//!
+//! ```no_run
+//! # #[macro_use] extern crate diesel;
+//! # use diesel::{pg::PgConnection, Connection, ExpressionMethods, RunQueryDsl};
+//! # table! { books (id) { id -> Uuid, title -> Text, } }
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::functions::gen_random_uuid;
+//!
+//! diesel::insert_into(books::table)
+//!     .values((books::id.eq(gen_random_uuid), books::title.eq("Dune")))
+//!     .execute(&connection)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/functions-and-operators.html
+
+use crate::{
+    quote,
+    sql_types::{CrdbInternalRegion, Geometry, HlcDecimal, SpatialType},
+};
+use diesel::sql_types::{BigInt, Bool, Double, Integer, Text, Uuid};
+use std::fmt;
+
+no_arg_sql_function!(
+    gen_random_uuid,
+    Uuid,
+    "The `gen_random_uuid()` function, generating a random version 4 UUID.
+
+Loading the result requires the `uuid` feature of Diesel."
+);
+
+no_arg_sql_function!(
+    unique_rowid,
+    BigInt,
+    "The `unique_rowid()` function, generating a unique `INT8` from the
+current timestamp and the ID of the node.
+
+IDs generated by one node increase over time, so may be compared with the
+IDs of existing rows, such as those of a `rowid` serial column."
+);
+
+no_arg_sql_function!(
+    unordered_unique_rowid,
+    BigInt,
+    "The `unordered_unique_rowid()` function, generating a unique `INT8` which
+is not ordered by time, so spreads inserts across ranges."
+);
+
+no_arg_sql_function!(
+    cluster_logical_timestamp,
+    HlcDecimal,
+    "The `cluster_logical_timestamp()` function, returning the timestamp of
+the current transaction as an [`HlcTimestamp`].
+
+The timestamp can be read from later with [`AsOfSystemTime::Hlc`], or used as
+the cursor of a changefeed.
+
+```no_run
+# use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+# fn main() -> diesel::QueryResult<()> {
+# let connection = PgConnection::establish(\"\").unwrap();
+use diesel_cockroach::{as_of_system_time::HlcTimestamp, functions::cluster_logical_timestamp};
+
+let timestamp: HlcTimestamp =
+    diesel::select(cluster_logical_timestamp).get_result(&connection)?;
+# Ok(())
+# }
+```
+
+[`HlcTimestamp`]: ../as_of_system_time/struct.HlcTimestamp.html
+[`AsOfSystemTime::Hlc`]: ../as_of_system_time/enum.AsOfSystemTime.html#variant.Hlc"
+);
+
+no_arg_sql_function!(
+    gateway_region,
+    Text,
+    "The `gateway_region()` function, returning the region of the node the
+session is connected to.
+
+The result may be loaded as a [`Region`].
+
+[`Region`]: ../data_types/struct.Region.html"
+);
+
+sql_function! {
+    /// The `default_to_database_primary_region()` function, returning
+    /// `region` if it is a region of the current database, or the primary
+    /// region of the database otherwise.
+    ///
+    /// Usually called with [`gateway_region`], so rows are homed in the
+    /// region they were inserted from where possible.
+    ///
+    /// [`gateway_region`]: struct.gateway_region.html
+    fn default_to_database_primary_region(region: Text) -> Text;
+}
+
+no_arg_sql_function!(
+    rehome_row,
+    CrdbInternalRegion,
+    "The `rehome_row()` function, returning the region of the gateway node, or
+the primary region if the database does not have that region.
+
+It is used as the `ON UPDATE` expression of the `crdb_region` column of a
+`REGIONAL BY ROW` table, to move rows to the region they are updated from.
+
+The result may be loaded as a [`Region`].
+
+[`Region`]: ../data_types/struct.Region.html"
+);
+
+sql_function! {
+    /// The `ST_MakePoint()` function, creating a point geometry from its
+    /// coordinates.
+    #[sql_name = "ST_MakePoint"]
+    fn st_makepoint(x: Double, y: Double) -> Geometry;
+}
+
+sql_function! {
+    /// The `ST_SetSRID()` function, setting the spatial reference system
+    /// identifier of a geometry, such as `4326` for WGS 84.
+    #[sql_name = "ST_SetSRID"]
+    fn st_setsrid(geometry: Geometry, srid: Integer) -> Geometry;
+}
+
+sql_function! {
+    /// The `ST_Contains()` function, returning whether geometry `a` contains
+    /// geometry `b`.
+    ///
+    /// Filters on this function can use an inverted index on `a`.
+    #[sql_name = "ST_Contains"]
+    fn st_contains(a: Geometry, b: Geometry) -> Bool;
+}
+
+sql_function! {
+    /// The `ST_Distance()` function, returning the distance between two
+    /// shapes.
+    ///
+    /// Distances between geographies are in meters, and distances between
+    /// geometries are in the units of their spatial reference system.
+    #[sql_name = "ST_Distance"]
+    fn st_distance<ST: SpatialType>(a: ST, b: ST) -> Double;
+}
+
+sql_function! {
+    /// The `ST_DWithin()` function, returning whether two shapes are within
+    /// `distance` of each other, in the same units as [`st_distance`].
+    ///
+    /// Unlike comparing the result of [`st_distance`], filters on this
+    /// function can use an inverted index on either shape.
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate diesel;
+    /// # use diesel::{pg::PgConnection, Connection, QueryDsl, RunQueryDsl};
+    /// # table! {
+    /// #     use diesel::sql_types::*;
+    /// #     use diesel_cockroach::sql_types::Geometry;
+    /// #     shops (id) { id -> Int8, name -> Text, location -> Geometry, }
+    /// # }
+    /// # fn main() -> diesel::QueryResult<()> {
+    /// # let connection = PgConnection::establish("").unwrap();
+    /// use diesel_cockroach::functions::{st_dwithin, st_makepoint, st_setsrid};
+    ///
+    /// let here = st_setsrid(st_makepoint(-0.1276, 51.5072), 4326);
+    /// let nearby: Vec<String> = shops::table
+    ///     .select(shops::name)
+    ///     .filter(st_dwithin(shops::location, here, 0.01))
+    ///     .load(&connection)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`st_distance`]: fn.st_distance.html
+    #[sql_name = "ST_DWithin"]
+    fn st_dwithin<ST: SpatialType>(a: ST, b: ST, distance: Double) -> Bool;
+}
+
+/// A `UUID` ID column definition, defaulting to [`gen_random_uuid`].
+///
+/// Random UUIDs spread inserts evenly across ranges, so are the recommended
+/// primary key type for CockroachDB. The column definition is rendered by
+/// its `Display` implementation, for use in a `CREATE TABLE` or
+/// `ALTER TABLE ... ADD COLUMN` statement:
+///
+/// ```
+/// use diesel_cockroach::functions::UuidColumn;
+///
+/// assert_eq!(
+///     UuidColumn::new("id").primary_key().to_string(),
+///     r#""id" UUID PRIMARY KEY DEFAULT gen_random_uuid()"#
+/// );
+/// ```
+///
+/// [`gen_random_uuid`]: struct.gen_random_uuid.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UuidColumn {
+    column: String,
+    primary_key: bool,
+}
+
+impl UuidColumn {
+    /// Define the UUID column `column`.
+    pub fn new(column: impl Into<String>) -> Self {
+        UuidColumn {
+            column: column.into(),
+            primary_key: false,
+        }
+    }
+
+    /// Make the column the primary key of the table.
+    pub fn primary_key(mut self) -> Self {
+        self.primary_key = true;
+        self
+    }
+}
+
+impl fmt::Display for UuidColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} UUID ", quote::identifier(&self.column))?;
+        if self.primary_key {
+            f.write_str("PRIMARY KEY")?;
+        } else {
+            f.write_str("NOT NULL")?;
+        }
+        f.write_str(" DEFAULT gen_random_uuid()")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Uuid,
+            title -> Text,
+        }
+    }
+
+    table! {
+        events (id) {
+            id -> Int8,
+        }
+    }
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::sql_types::{Geography, Geometry};
+
+        shops (id) {
+            id -> Int8,
+            location -> Geometry,
+            area -> Geometry,
+            address -> Geography,
+        }
+    }
+
+    #[test]
+    fn uuid() {
+        let query = diesel::insert_into(books::table)
+            .values((books::id.eq(gen_random_uuid), books::title.eq("Dune")));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"INSERT INTO "books" ("id", "title") VALUES (gen_random_uuid(), $1) -- binds: ["Dune"]"#
+        );
+        let query = books::table
+            .select(books::title)
+            .filter(books::id.ne(gen_random_uuid));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT "books"."title" FROM "books" WHERE "books"."id" != gen_random_uuid() -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn uuid_column() {
+        assert_eq!(
+            UuidColumn::new("id").to_string(),
+            r#""id" UUID NOT NULL DEFAULT gen_random_uuid()"#
+        );
+    }
+
+    #[test]
+    fn rowid() {
+        let query = events::table
+            .select(events::id)
+            .filter(events::id.lt(unique_rowid));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT "events"."id" FROM "events" WHERE "events"."id" < unique_rowid() -- binds: []"#
+        );
+        let query =
+            diesel::insert_into(events::table).values(events::id.eq(unordered_unique_rowid));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"INSERT INTO "events" ("id") VALUES (unordered_unique_rowid()) -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn logical_timestamp() {
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&diesel::select(cluster_logical_timestamp))
+                .to_string(),
+            r#"SELECT cluster_logical_timestamp() -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn regions() {
+        let query = diesel::select((
+            gateway_region,
+            default_to_database_primary_region(gateway_region),
+            rehome_row,
+        ));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT gateway_region(), default_to_database_primary_region(gateway_region()), rehome_row() -- binds: []"#
+        );
+        let query = diesel::select(default_to_database_primary_region("us-east1"));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT default_to_database_primary_region($1) -- binds: ["us-east1"]"#
+        );
+    }
+
+    #[test]
+    fn spatial() {
+        let point = st_setsrid(st_makepoint(1.0, 2.0), 4326);
+        let query = shops::table
+            .select(st_distance(shops::location, point))
+            .filter(st_dwithin(shops::location, point, 0.5))
+            .filter(st_contains(shops::area, shops::location));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT ST_Distance("shops"."location", ST_SetSRID(ST_MakePoint($1, $2), $3)) FROM "shops" WHERE ST_DWithin("shops"."location", ST_SetSRID(ST_MakePoint($4, $5), $6), $7) AND ST_Contains("shops"."area", "shops"."location") -- binds: [1.0, 2.0, 4326, 1.0, 2.0, 4326, 0.5]"#
+        );
+        let query = shops::table.select(st_distance(shops::address, shops::address));
+        assert_eq!(
+            diesel::debug_query::<diesel::pg::Pg, _>(&query).to_string(),
+            r#"SELECT ST_Distance("shops"."address", "shops"."address") FROM "shops" -- binds: []"#
+        );
+    }
+}
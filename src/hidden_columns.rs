@@ -0,0 +1,329 @@
+//! Support for the hidden columns CockroachDB adds to tables.
+//!
+//! Hidden columns are not returned by `SELECT *`, and are not set when
+//! inserting rows, but may be selected and filtered on explicitly. The
+//! [`crdb_table!`] macro declares them alongside a table's other columns.
+//!
+//! [`crdb_table!`]: ../macro.crdb_table.html
+
+use crate::sql_types::{CrdbInternalRegion, HlcDecimal};
+use diesel::{
+    expression::{AppearsOnTable, Expression, NonAggregate, SelectableExpression},
+    pg::Pg,
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_source::{AppearsInFromClause, Once, QuerySource, Table},
+    result::QueryResult,
+    sql_types::BigInt,
+};
+use std::marker::PhantomData;
+
+/// A hidden column of the table `T`, with SQL type `ST`.
+///
+/// Unlike the columns declared by `table!`, hidden columns do not implement
+/// `Column`, so cannot be used when inserting or updating rows.
+///
+/// Hidden columns may currently only be used in queries on their own table,
+/// and not in joins.
+#[derive(Debug, Clone, Copy)]
+pub struct HiddenColumn<T, ST> {
+    table: T,
+    name: &'static str,
+    sql_type: PhantomData<ST>,
+}
+
+impl<T, ST> HiddenColumn<T, ST> {
+    /// Refer to the hidden column `name` of `table`.
+    pub const fn new(table: T, name: &'static str) -> Self {
+        HiddenColumn {
+            table,
+            name,
+            sql_type: PhantomData,
+        }
+    }
+
+    /// The name of the column.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// The hidden `rowid` column, added to tables created without a primary key.
+pub type Rowid<T> = HiddenColumn<T, BigInt>;
+
+/// The hidden `crdb_region` column, added to `REGIONAL BY ROW` tables.
+pub type CrdbRegion<T> = HiddenColumn<T, CrdbInternalRegion>;
+
+/// The hidden `crdb_region` column of `table`, for tables not declared with
+/// [`crdb_table!`].
+///
+/// The column may be selected, filtered and grouped on, with values sent and
+/// received as a [`Region`]:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, QueryDsl, RunQueryDsl};
+/// # table! { users (id) { id -> Int8, name -> Text, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel::{dsl::sql, query_dsl::GroupByDsl, sql_types::BigInt};
+/// use diesel_cockroach::{data_types::Region, hidden_columns::crdb_region_of};
+///
+/// let users_per_region: Vec<(Region, i64)> = users::table
+///     .group_by(crdb_region_of(users::table))
+///     .select((crdb_region_of(users::table), sql::<BigInt>("count(*)")))
+///     .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`crdb_table!`]: ../macro.crdb_table.html
+/// [`Region`]: ../data_types/struct.Region.html
+pub const fn crdb_region_of<T>(table: T) -> CrdbRegion<T> {
+    HiddenColumn::new(table, "crdb_region")
+}
+
+/// The hidden `crdb_internal_mvcc_timestamp` column, present on every table,
+/// holding the timestamp each row was last written at.
+///
+/// Values are sent and received as an [`HlcTimestamp`].
+///
+/// [`HlcTimestamp`]: ../as_of_system_time/struct.HlcTimestamp.html
+pub type CrdbInternalMvccTimestamp<T> = HiddenColumn<T, HlcDecimal>;
+
+/// The hidden `crdb_internal_mvcc_timestamp` column of `table`, for tables
+/// not declared with [`crdb_table!`].
+///
+/// Comparing the timestamp a row was read at with its current timestamp
+/// detects concurrent changes to the row:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, title -> Text, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{as_of_system_time::HlcTimestamp, hidden_columns::mvcc_timestamp_of};
+///
+/// let (title, version): (String, HlcTimestamp) = books::table
+///     .select((books::title, mvcc_timestamp_of(books::table)))
+///     .find(1)
+///     .get_result(&connection)?;
+/// let updated = diesel::update(
+///     books::table
+///         .find(1)
+///         .filter(mvcc_timestamp_of(books::table).eq(version)),
+/// )
+/// .set(books::title.eq(title.to_uppercase()))
+/// .execute(&connection)?;
+/// if updated == 0 {
+///     println!("the book was changed concurrently");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`crdb_table!`]: ../macro.crdb_table.html
+pub const fn mvcc_timestamp_of<T>(table: T) -> CrdbInternalMvccTimestamp<T> {
+    HiddenColumn::new(table, "crdb_internal_mvcc_timestamp")
+}
+
+impl<T, ST> Expression for HiddenColumn<T, ST> {
+    type SqlType = ST;
+}
+
+impl<T, ST> NonAggregate for HiddenColumn<T, ST> {}
+
+impl<T, ST> SelectableExpression<T> for HiddenColumn<T, ST> where
+    T: Table + AppearsInFromClause<T, Count = Once>
+{
+}
+
+impl<T, ST, QS> AppearsOnTable<QS> for HiddenColumn<T, ST>
+where
+    T: Table,
+    QS: AppearsInFromClause<T, Count = Once>,
+{
+}
+
+impl<T, ST> QueryFragment<Pg> for HiddenColumn<T, ST>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        self.table.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(".");
+        out.push_identifier(self.name)
+    }
+}
+
+impl<T, ST> QueryId for HiddenColumn<T, ST> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Declares a table with `table!`, along with some of its hidden columns.
+///
+/// The hidden columns to declare are listed in a `#[hidden_columns(...)]`
+/// attribute, and may be any of `rowid`, `crdb_region` and
+/// `crdb_internal_mvcc_timestamp`. They are declared as constants in the
+/// table's module, next to its other columns.
+///
+/// ```
+/// #[macro_use] extern crate diesel;
+/// #[macro_use] extern crate diesel_cockroach;
+///
+/// crdb_table! {
+///     #[hidden_columns(rowid, crdb_internal_mvcc_timestamp)]
+///     events (id) {
+///         id -> Int8,
+///         kind -> Text,
+///     }
+/// }
+///
+/// # fn main() {
+/// use diesel::{pg::Pg, QueryDsl};
+///
+/// let query = events::table.select((events::rowid, events::kind));
+/// assert_eq!(
+///     diesel::debug_query::<Pg, _>(&query).to_string(),
+///     r#"SELECT "events"."rowid", "events"."kind" FROM "events" -- binds: []"#,
+/// );
+/// # }
+/// ```
+///
+/// `use` statements and attributes are passed through to `table!`. Since the
+/// table is declared in a nested module, `use` statements should refer to
+/// types by absolute paths, such as `crate::schema::MyType`.
+#[macro_export]
+macro_rules! crdb_table {
+    (
+        $(use $import_root:ident $(:: $import:tt)*;)*
+        #[hidden_columns($($hidden:ident),* $(,)?)]
+        $(#[$meta:meta])*
+        $name:ident $(($($pk:ident),+))? { $($body:tt)* }
+    ) => {
+        pub mod $name {
+            mod table_definition {
+                table! {
+                    $(use $import_root $(:: $import)*;)*
+                    $(#[$meta])*
+                    $name $(($($pk),+))? { $($body)* }
+                }
+            }
+
+            pub use self::table_definition::$name::*;
+
+            $($crate::__crdb_hidden_column!($hidden);)*
+        }
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __crdb_hidden_column {
+    (rowid) => {
+        /// The hidden `rowid` column
+        #[allow(non_upper_case_globals, dead_code)]
+        pub const rowid: $crate::hidden_columns::Rowid<table> =
+            $crate::hidden_columns::HiddenColumn::new(table, "rowid");
+    };
+    (crdb_region) => {
+        /// The hidden `crdb_region` column
+        #[allow(non_upper_case_globals, dead_code)]
+        pub const crdb_region: $crate::hidden_columns::CrdbRegion<table> =
+            $crate::hidden_columns::HiddenColumn::new(table, "crdb_region");
+    };
+    (crdb_internal_mvcc_timestamp) => {
+        /// The hidden `crdb_internal_mvcc_timestamp` column
+        #[allow(non_upper_case_globals, dead_code)]
+        pub const crdb_internal_mvcc_timestamp: $crate::hidden_columns::CrdbInternalMvccTimestamp<
+            table,
+        > = $crate::hidden_columns::HiddenColumn::new(table, "crdb_internal_mvcc_timestamp");
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_types::Region;
+    use diesel::{pg::Pg, ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    crdb_table! {
+        use diesel::sql_types::*;
+
+        #[hidden_columns(rowid, crdb_region, crdb_internal_mvcc_timestamp)]
+        users (id) {
+            id -> Int8,
+            name -> Text,
+        }
+    }
+
+    #[test]
+    fn select() {
+        let query = users::table.select((
+            users::rowid,
+            users::crdb_region,
+            users::crdb_internal_mvcc_timestamp,
+        ));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "users"."rowid", "users"."crdb_region", "users"."crdb_internal_mvcc_timestamp" FROM "users" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn filter() {
+        let query = users::table
+            .select(users::name)
+            .filter(users::crdb_region.eq(Region::new("us-east1")))
+            .order(users::rowid);
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "users"."name" FROM "users" WHERE "users"."crdb_region" = $1 ORDER BY "users"."rowid" -- binds: [Region("us-east1")]"#
+        );
+    }
+
+    #[test]
+    fn mvcc_timestamp() {
+        use crate::{as_of_system_time::HlcTimestamp, hidden_columns::mvcc_timestamp_of};
+
+        table! {
+            books (id) {
+                id -> Int8,
+                title -> Text,
+            }
+        }
+
+        let timestamp = HlcTimestamp::new(1_591_300_372_587_669_851, 2);
+        let query = books::table
+            .select(books::title)
+            .filter(mvcc_timestamp_of(books::table).eq(timestamp));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "books"."title" FROM "books" WHERE "books"."crdb_internal_mvcc_timestamp" = $1 -- binds: [HlcTimestamp { wall_time: 1591300372587669851, logical: 2 }]"#
+        );
+    }
+
+    #[test]
+    fn group_by_region() {
+        use crate::hidden_columns::crdb_region_of;
+        use diesel::{dsl::sql, query_dsl::GroupByDsl, sql_types::BigInt};
+
+        let query = users::table
+            .group_by(users::crdb_region)
+            .select((users::crdb_region, sql::<BigInt>("count(*)")));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "users"."crdb_region", count(*) FROM "users" GROUP BY "users"."crdb_region" -- binds: []"#
+        );
+        let query = users::table
+            .select(users::name)
+            .filter(crdb_region_of(users::table).ne(Region::new("us-east1")));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "users"."name" FROM "users" WHERE "users"."crdb_region" != $1 -- binds: [Region("us-east1")]"#
+        );
+    }
+}
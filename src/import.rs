@@ -0,0 +1,632 @@
+//! Support for `IMPORT INTO` SQL queries, bulk loading data from files.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/import-into.html
+
+use crate::{
+    column_list::{push_identifiers, ColumnList},
+    external_storage::ExternalStorageUri,
+    jobs::{BulkJobResultSqlType, Detached},
+    quote,
+};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+};
+
+/// Creates an `IMPORT INTO` statement, loading data into the existing
+/// target table.
+///
+/// The format of the data is chosen by calling one of the methods of
+/// [`IncompleteImportStatement`]. Once the import job finishes, the statement
+/// returns a [`BulkJobResult`]:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, title -> Text, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{
+///     import::{import_into, CsvOptions},
+///     jobs::BulkJobResult,
+/// };
+///
+/// let result: BulkJobResult = import_into(books::table)
+///     .columns((books::id, books::title))
+///     .csv_data(
+///         vec!["userfile:///books.csv".to_owned()],
+///         CsvOptions::new().skip(1).nullif(""),
+///     )
+///     .get_result(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`IncompleteImportStatement`]: struct.IncompleteImportStatement.html
+/// [`BulkJobResult`]: ../jobs/struct.BulkJobResult.html
+pub fn import_into<T>(target: T) -> IncompleteImportStatement<T> {
+    IncompleteImportStatement {
+        target,
+        columns: Vec::new(),
+    }
+}
+
+/// The structure returned by [`import_into`].
+///
+/// Each method chooses the format of the data to import, and returns the
+/// complete statement.
+///
+/// [`import_into`]: fn.import_into.html
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+pub struct IncompleteImportStatement<T> {
+    target: T,
+    columns: Vec<&'static str>,
+}
+
+impl<T> IncompleteImportStatement<T> {
+    /// Import values for the given columns, in the order they appear in the
+    /// data. By default, the data has a value for every column of the table.
+    pub fn columns<C, K>(mut self, columns: C) -> Self
+    where
+        C: ColumnList<K, Table = T>,
+    {
+        self.columns = columns.names();
+        self
+    }
+
+    /// Import CSV files from the given URIs.
+    ///
+    /// At least one URI must be given, or the statement fails to build.
+    ///
+    /// ```sql
+    /// IMPORT INTO books (id, title) CSV DATA ('userfile:///books.csv') WITH skip = '1';
+    /// ```
+    pub fn csv_data<U>(self, uris: Vec<U>, options: CsvOptions) -> ImportStatement<T>
+    where
+        U: Into<ExternalStorageUri>,
+    {
+        ImportStatement::new(self, uris, ImportFormat::Csv(options))
+    }
+
+    /// Import delimited files, such as those produced by MySQL's
+    /// `SELECT ... INTO OUTFILE`, from the given URIs.
+    ///
+    /// At least one URI must be given, or the statement fails to build.
+    ///
+    /// ```sql
+    /// IMPORT INTO books DELIMITED DATA ('userfile:///books.txt') WITH fields_terminated_by = '|';
+    /// ```
+    pub fn delimited_data<U>(self, uris: Vec<U>, options: DelimitedOptions) -> ImportStatement<T>
+    where
+        U: Into<ExternalStorageUri>,
+    {
+        ImportStatement::new(self, uris, ImportFormat::Delimited(options))
+    }
+
+    /// Import Avro files from the given URIs.
+    ///
+    /// At least one URI must be given, or the statement fails to build.
+    ///
+    /// ```sql
+    /// IMPORT INTO books AVRO DATA ('userfile:///books.avro') WITH strict_validation;
+    /// ```
+    pub fn avro_data<U>(self, uris: Vec<U>, options: AvroOptions) -> ImportStatement<T>
+    where
+        U: Into<ExternalStorageUri>,
+    {
+        ImportStatement::new(self, uris, ImportFormat::Avro(options))
+    }
+}
+
+/// Options for importing CSV data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvOptions {
+    delimiter: Option<char>,
+    comment: Option<char>,
+    nullif: Option<String>,
+    skip: Option<u64>,
+    strict_quotes: bool,
+    row_limit: Option<u64>,
+}
+
+impl CsvOptions {
+    /// Options for standard comma separated data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Separate fields with `delimiter`, rather than a comma.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Skip lines starting with `comment`.
+    pub fn comment(mut self, comment: char) -> Self {
+        self.comment = Some(comment);
+        self
+    }
+
+    /// Import fields equal to `nullif` as `NULL`.
+    pub fn nullif(mut self, nullif: impl Into<String>) -> Self {
+        self.nullif = Some(nullif.into());
+        self
+    }
+
+    /// Skip the first `lines` lines of each file, such as a header row.
+    pub fn skip(mut self, lines: u64) -> Self {
+        self.skip = Some(lines);
+        self
+    }
+
+    /// Reject fields containing unescaped double quotes.
+    pub fn strict_quotes(mut self) -> Self {
+        self.strict_quotes = true;
+        self
+    }
+
+    /// Only import the first `rows` rows of each file, such as to test an
+    /// import on a sample of the data.
+    pub fn row_limit(mut self, rows: u64) -> Self {
+        self.row_limit = Some(rows);
+        self
+    }
+
+    fn options(&self) -> Vec<(&'static str, Option<String>)> {
+        let mut options = Vec::new();
+        if let Some(delimiter) = self.delimiter {
+            options.push(("delimiter", Some(delimiter.to_string())));
+        }
+        if let Some(comment) = self.comment {
+            options.push(("comment", Some(comment.to_string())));
+        }
+        if let Some(nullif) = &self.nullif {
+            options.push(("nullif", Some(nullif.clone())));
+        }
+        if let Some(skip) = self.skip {
+            options.push(("skip", Some(skip.to_string())));
+        }
+        if self.strict_quotes {
+            options.push(("strict_quotes", None));
+        }
+        if let Some(row_limit) = self.row_limit {
+            options.push(("row_limit", Some(row_limit.to_string())));
+        }
+        options
+    }
+}
+
+/// Options for importing delimited data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DelimitedOptions {
+    fields_terminated_by: Option<String>,
+    fields_enclosed_by: Option<String>,
+    fields_escaped_by: Option<String>,
+    rows_terminated_by: Option<String>,
+    nullif: Option<String>,
+    skip: Option<u64>,
+    row_limit: Option<u64>,
+}
+
+impl DelimitedOptions {
+    /// Options for tab separated data, with one row per line.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Separate fields with `terminator`, rather than a tab.
+    pub fn fields_terminated_by(mut self, terminator: impl Into<String>) -> Self {
+        self.fields_terminated_by = Some(terminator.into());
+        self
+    }
+
+    /// Fields may be enclosed by `enclosure`, such as a double quote.
+    pub fn fields_enclosed_by(mut self, enclosure: impl Into<String>) -> Self {
+        self.fields_enclosed_by = Some(enclosure.into());
+        self
+    }
+
+    /// Escape special characters in fields with `escape`, such as a backslash.
+    pub fn fields_escaped_by(mut self, escape: impl Into<String>) -> Self {
+        self.fields_escaped_by = Some(escape.into());
+        self
+    }
+
+    /// Separate rows with `terminator`, rather than a newline.
+    pub fn rows_terminated_by(mut self, terminator: impl Into<String>) -> Self {
+        self.rows_terminated_by = Some(terminator.into());
+        self
+    }
+
+    /// Import fields equal to `nullif` as `NULL`.
+    pub fn nullif(mut self, nullif: impl Into<String>) -> Self {
+        self.nullif = Some(nullif.into());
+        self
+    }
+
+    /// Skip the first `lines` lines of each file, such as a header row.
+    pub fn skip(mut self, lines: u64) -> Self {
+        self.skip = Some(lines);
+        self
+    }
+
+    /// Only import the first `rows` rows of each file.
+    pub fn row_limit(mut self, rows: u64) -> Self {
+        self.row_limit = Some(rows);
+        self
+    }
+
+    fn options(&self) -> Vec<(&'static str, Option<String>)> {
+        let mut options = Vec::new();
+        let strings = [
+            ("fields_terminated_by", &self.fields_terminated_by),
+            ("fields_enclosed_by", &self.fields_enclosed_by),
+            ("fields_escaped_by", &self.fields_escaped_by),
+            ("rows_terminated_by", &self.rows_terminated_by),
+            ("nullif", &self.nullif),
+        ];
+        for (name, value) in strings.iter() {
+            if let Some(value) = value {
+                options.push((*name, Some(value.clone())));
+            }
+        }
+        if let Some(skip) = self.skip {
+            options.push(("skip", Some(skip.to_string())));
+        }
+        if let Some(row_limit) = self.row_limit {
+            options.push(("row_limit", Some(row_limit.to_string())));
+        }
+        options
+    }
+}
+
+/// The schema of Avro records, which must be given unless importing object
+/// container files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvroSchema {
+    /// The schema itself, as JSON.
+    Inline(String),
+    /// The URI of a file containing the schema.
+    Uri(String),
+}
+
+/// Avro data stored as a sequence of records, rather than in object
+/// container files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvroRecords {
+    schema: AvroSchema,
+    terminated_by: Option<String>,
+}
+
+impl AvroRecords {
+    /// Records with the given schema, one per line.
+    pub fn new(schema: AvroSchema) -> Self {
+        AvroRecords {
+            schema,
+            terminated_by: None,
+        }
+    }
+
+    /// Separate records with `terminator`, rather than a newline.
+    pub fn terminated_by(mut self, terminator: impl Into<String>) -> Self {
+        self.terminated_by = Some(terminator.into());
+        self
+    }
+}
+
+/// How Avro data is encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvroEncoding {
+    /// Object container files, which embed their own schema.
+    ObjectContainerFile,
+    /// Binary encoded records.
+    BinaryRecords(AvroRecords),
+    /// JSON encoded records.
+    JsonRecords(AvroRecords),
+}
+
+/// Options for importing Avro data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvroOptions {
+    encoding: AvroEncoding,
+    strict_validation: bool,
+    row_limit: Option<u64>,
+}
+
+impl AvroOptions {
+    /// Options for Avro data with the given encoding.
+    pub fn new(encoding: AvroEncoding) -> Self {
+        AvroOptions {
+            encoding,
+            strict_validation: false,
+            row_limit: None,
+        }
+    }
+
+    /// Reject records with fields which are not columns of the table,
+    /// rather than ignoring them.
+    pub fn strict_validation(mut self) -> Self {
+        self.strict_validation = true;
+        self
+    }
+
+    /// Only import the first `rows` rows of each file.
+    pub fn row_limit(mut self, rows: u64) -> Self {
+        self.row_limit = Some(rows);
+        self
+    }
+
+    fn options(&self) -> Vec<(&'static str, Option<String>)> {
+        let mut options = Vec::new();
+        if self.strict_validation {
+            options.push(("strict_validation", None));
+        }
+        let records = match &self.encoding {
+            AvroEncoding::ObjectContainerFile => None,
+            AvroEncoding::BinaryRecords(records) => {
+                options.push(("data_as_binary_records", None));
+                Some(records)
+            }
+            AvroEncoding::JsonRecords(records) => {
+                options.push(("data_as_json_records", None));
+                Some(records)
+            }
+        };
+        if let Some(records) = records {
+            match &records.schema {
+                AvroSchema::Inline(schema) => options.push(("schema", Some(schema.clone()))),
+                AvroSchema::Uri(uri) => options.push(("schema_uri", Some(uri.clone()))),
+            }
+            if let Some(terminator) = &records.terminated_by {
+                options.push(("records_terminated_by", Some(terminator.clone())));
+            }
+        }
+        if let Some(row_limit) = self.row_limit {
+            options.push(("row_limit", Some(row_limit.to_string())));
+        }
+        options
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ImportFormat {
+    Csv(CsvOptions),
+    Delimited(DelimitedOptions),
+    Avro(AvroOptions),
+}
+
+impl ImportFormat {
+    fn keyword(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv(_) => "CSV DATA",
+            ImportFormat::Delimited(_) => "DELIMITED DATA",
+            ImportFormat::Avro(_) => "AVRO DATA",
+        }
+    }
+
+    fn options(&self) -> Vec<(&'static str, Option<String>)> {
+        match self {
+            ImportFormat::Csv(options) => options.options(),
+            ImportFormat::Delimited(options) => options.options(),
+            ImportFormat::Avro(options) => options.options(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `IMPORT INTO` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table we are importing into
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/import-into.html
+pub struct ImportStatement<T> {
+    target: T,
+    columns: Vec<&'static str>,
+    uris: Vec<ExternalStorageUri>,
+    format: ImportFormat,
+    detached: bool,
+}
+
+impl<T> ImportStatement<T> {
+    fn new<U>(incomplete: IncompleteImportStatement<T>, uris: Vec<U>, format: ImportFormat) -> Self
+    where
+        U: Into<ExternalStorageUri>,
+    {
+        ImportStatement {
+            target: incomplete.target,
+            columns: incomplete.columns,
+            uris: uris.into_iter().map(Into::into).collect(),
+            format,
+            detached: false,
+        }
+    }
+
+    /// Return the ID of the import job as soon as it is created, rather than
+    /// waiting for it to finish.
+    pub fn detached(mut self) -> Detached<Self> {
+        self.detached = true;
+        Detached(self)
+    }
+}
+
+impl<T> QueryFragment<Pg> for ImportStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        if self.uris.is_empty() {
+            return Err(diesel::result::Error::QueryBuilderError(
+                "IMPORT requires at least one file to import".into(),
+            ));
+        }
+        out.push_sql("IMPORT INTO ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        if !self.columns.is_empty() {
+            out.push_sql(" (");
+            push_identifiers(&mut out, &self.columns)?;
+            out.push_sql(")");
+        }
+        out.push_sql(" ");
+        out.push_sql(self.format.keyword());
+        out.push_sql(" (");
+        for (i, uri) in self.uris.iter().enumerate() {
+            if i > 0 {
+                out.push_sql(", ");
+            }
+            out.push_sql(&quote::string_literal(&uri.expose()));
+        }
+        out.push_sql(")");
+        let mut options = self.format.options();
+        if self.detached {
+            options.push(("detached", None));
+        }
+        quote::push_options(&mut out, &options)?;
+        Ok(())
+    }
+}
+
+impl<T> Query for ImportStatement<T> {
+    type SqlType = BulkJobResultSqlType;
+}
+
+impl<T> RunQueryDsl<PgConnection> for ImportStatement<T> {}
+
+impl<T> QueryId for ImportStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn csv() {
+        let statement = import_into(books::table)
+            .csv_data(vec!["userfile:///books.csv".to_owned()], CsvOptions::new());
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"IMPORT INTO "books" CSV DATA ('userfile:///books.csv') -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn no_files() {
+        let statement = import_into(books::table).csv_data(Vec::<String>::new(), CsvOptions::new());
+        assert!(quote::render(&statement).is_err());
+    }
+
+    #[test]
+    fn detached() {
+        let statement = import_into(books::table)
+            .csv_data(
+                vec!["userfile:///books.csv".to_owned()],
+                CsvOptions::new().skip(1),
+            )
+            .detached();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"IMPORT INTO "books" CSV DATA ('userfile:///books.csv') WITH skip = '1', detached -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn csv_options() {
+        let statement = import_into(books::table)
+            .columns((books::id, books::title))
+            .csv_data(
+                vec![
+                    "s3://bucket/books-1.csv?AUTH=implicit".to_owned(),
+                    "s3://bucket/books-2.csv?AUTH=implicit".to_owned(),
+                ],
+                CsvOptions::new()
+                    .delimiter('|')
+                    .comment('#')
+                    .nullif("")
+                    .skip(1)
+                    .strict_quotes()
+                    .row_limit(100),
+            );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"IMPORT INTO "books" ("id", "title") CSV DATA ('s3://bucket/books-1.csv?AUTH=implicit', 's3://bucket/books-2.csv?AUTH=implicit') WITH delimiter = '|', comment = '#', nullif = '', skip = '1', strict_quotes, row_limit = '100' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn delimited() {
+        let statement = import_into(books::table).delimited_data(
+            vec!["userfile:///books.txt".to_owned()],
+            DelimitedOptions::new()
+                .fields_terminated_by("|")
+                .fields_enclosed_by("\"")
+                .fields_escaped_by("\\")
+                .rows_terminated_by("\n")
+                .nullif("\\N")
+                .skip(1),
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            "IMPORT INTO \"books\" DELIMITED DATA ('userfile:///books.txt') WITH fields_terminated_by = '|', fields_enclosed_by = '\"', fields_escaped_by = '\\', rows_terminated_by = '\n', nullif = '\\N', skip = '1' -- binds: []"
+        );
+    }
+
+    #[test]
+    fn avro() {
+        let statement = import_into(books::table).avro_data(
+            vec!["userfile:///books.avro".to_owned()],
+            AvroOptions::new(AvroEncoding::ObjectContainerFile).strict_validation(),
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"IMPORT INTO "books" AVRO DATA ('userfile:///books.avro') WITH strict_validation -- binds: []"#
+        );
+
+        let statement = import_into(books::table).avro_data(
+            vec!["userfile:///books.json".to_owned()],
+            AvroOptions::new(AvroEncoding::JsonRecords(
+                AvroRecords::new(AvroSchema::Uri("userfile:///books.avsc".to_owned()))
+                    .terminated_by(";"),
+            ))
+            .row_limit(10),
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"IMPORT INTO "books" AVRO DATA ('userfile:///books.json') WITH data_as_json_records, schema_uri = 'userfile:///books.avsc', records_terminated_by = ';', row_limit = '10' -- binds: []"#
+        );
+
+        let statement = import_into(books::table).avro_data(
+            vec!["userfile:///books.bin".to_owned()],
+            AvroOptions::new(AvroEncoding::BinaryRecords(AvroRecords::new(
+                AvroSchema::Inline(r#"{"type":"record","name":"book","fields":[]}"#.to_owned()),
+            ))),
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"IMPORT INTO "books" AVRO DATA ('userfile:///books.bin') WITH data_as_binary_records, schema = '{"type":"record","name":"book","fields":[]}' -- binds: []"#
+        );
+    }
+}
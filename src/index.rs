@@ -0,0 +1,720 @@
+//! Support for `CREATE INDEX` and `ALTER INDEX` SQL queries, and the index
+//! recommendations made by CockroachDB.
+
+use crate::{
+    column_list::{push_identifiers, ColumnList},
+    migrations::Reversible,
+    quote,
+    storage_params::{self, StorageParam, StorageParams},
+    version::{ClusterVersion, Unsupported, VersionCheck},
+};
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{Text, Timestamp},
+    FromSqlRow,
+};
+use std::{error, fmt, str::FromStr, time::SystemTime};
+
+/// Creates a `CREATE INDEX` statement on the given columns of the target table.
+///
+/// ```sql
+/// CREATE INDEX ON books (title);
+/// ```
+pub fn create_index<T, C, K>(target: T, columns: C) -> CreateIndexStatement<T>
+where
+    C: ColumnList<K, Table = T>,
+{
+    CreateIndexStatement::new(target, columns.names())
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CREATE INDEX` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table we are indexing
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-index.html
+pub struct CreateIndexStatement<T> {
+    target: T,
+    name: Option<String>,
+    columns: Vec<&'static str>,
+    storing: Vec<&'static str>,
+    unique: bool,
+    if_not_exists: bool,
+    storage_params: StorageParams,
+    not_visible: bool,
+}
+
+impl<T> CreateIndexStatement<T> {
+    fn new(target: T, columns: Vec<&'static str>) -> Self {
+        CreateIndexStatement {
+            target,
+            name: None,
+            columns,
+            storing: Vec::new(),
+            unique: false,
+            if_not_exists: false,
+            storage_params: StorageParams::new(),
+            not_visible: false,
+        }
+    }
+
+    /// Name the index, instead of letting CockroachDB generate a name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Create a `UNIQUE` index, which also enforces uniqueness of the indexed columns.
+    pub fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    /// Do nothing if an index with the same name already exists.
+    ///
+    /// This requires the index to be named with [`name`], and running an
+    /// unnamed statement is an error.
+    ///
+    /// [`name`]: #method.name
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Store additional columns in the index, without indexing them.
+    ///
+    /// Queries reading only indexed and stored columns can then be served from
+    /// the index alone, avoiding a join against the primary index.
+    ///
+    /// ```sql
+    /// CREATE INDEX ON books (title) STORING (page_count);
+    /// ```
+    pub fn storing<S, K>(mut self, columns: S) -> Self
+    where
+        S: ColumnList<K, Table = T>,
+    {
+        self.storing = columns.names();
+        self
+    }
+
+    /// Set storage parameters on the new index.
+    ///
+    /// ```sql
+    /// CREATE INDEX ON books (title) WITH (bucket_count = 8);
+    /// ```
+    pub fn with_storage_params(mut self, storage_params: StorageParams) -> Self {
+        self.storage_params = storage_params;
+        self
+    }
+
+    /// Create the index as `NOT VISIBLE`, so it is ignored by the optimizer
+    /// unless explicitly selected with an index hint.
+    ///
+    /// Requires CockroachDB v22.2 or later.
+    pub fn not_visible(mut self) -> Self {
+        self.not_visible = true;
+        self
+    }
+}
+
+impl<T> VersionCheck for CreateIndexStatement<T> {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        if self.not_visible {
+            version.require("NOT VISIBLE indexes", 22, 2)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> QueryFragment<Pg> for CreateIndexStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CREATE ");
+        if self.unique {
+            out.push_sql("UNIQUE ");
+        }
+        out.push_sql("INDEX ");
+        if self.if_not_exists {
+            if self.name.is_none() {
+                return Err(diesel::result::Error::QueryBuilderError(
+                    "IF NOT EXISTS requires the index to be named".into(),
+                ));
+            }
+            out.push_sql("IF NOT EXISTS ");
+        }
+        if let Some(name) = &self.name {
+            out.push_identifier(name)?;
+            out.push_sql(" ");
+        }
+        out.push_sql("ON ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(" (");
+        push_identifiers(&mut out, &self.columns)?;
+        out.push_sql(")");
+        if !self.storing.is_empty() {
+            out.push_sql(" STORING (");
+            push_identifiers(&mut out, &self.storing)?;
+            out.push_sql(")");
+        }
+        if !self.storage_params.is_empty() {
+            out.push_sql(" WITH ");
+            self.storage_params.walk_ast(out.reborrow())?;
+        }
+        if self.not_visible {
+            out.push_sql(" NOT VISIBLE");
+        }
+        Ok(())
+    }
+}
+
+impl<T> Reversible for CreateIndexStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    /// Drops the index, if it was named.
+    fn reverse_sql(&self) -> QueryResult<Option<String>> {
+        match &self.name {
+            Some(name) => Ok(Some(format!(
+                "DROP INDEX {}@{}",
+                quote::render(&self.target.from_clause())?,
+                quote::identifier(name)
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T> RunQueryDsl<PgConnection> for CreateIndexStatement<T> {}
+
+impl<T> QueryId for CreateIndexStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates an `ALTER INDEX` statement for the named index on the target table.
+///
+/// You may choose an alteration by calling `visible()`, `not_visible()`,
+/// `set_storage_params()` or `reset_storage_params()`.
+pub fn alter_index<T>(target: T, name: impl Into<String>) -> IncompleteAlterIndexStatement<T> {
+    IncompleteAlterIndexStatement::new(target, name.into())
+}
+
+/// The structure returned by [`alter_index`].
+///
+/// The provided methods [`visible`], [`not_visible`], [`set_storage_params`]
+/// and [`reset_storage_params`] will alter the targeted index.
+///
+/// [`alter_index`]: fn.alter_index.html
+/// [`visible`]: #method.visible
+/// [`not_visible`]: #method.not_visible
+/// [`set_storage_params`]: #method.set_storage_params
+/// [`reset_storage_params`]: #method.reset_storage_params
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+pub struct IncompleteAlterIndexStatement<T> {
+    target: T,
+    name: String,
+}
+
+impl<T> IncompleteAlterIndexStatement<T> {
+    fn new(target: T, name: String) -> Self {
+        IncompleteAlterIndexStatement { target, name }
+    }
+
+    /// Make the index visible to the optimizer.
+    ///
+    /// Requires CockroachDB v22.2 or later.
+    pub fn visible(self) -> AlterIndexStatement<T> {
+        AlterIndexStatement::new(self, AlterIndexAction::Visible(true))
+    }
+
+    /// Hide the index from the optimizer, unless explicitly selected with an
+    /// index hint. The index is still maintained on writes.
+    ///
+    /// Requires CockroachDB v22.2 or later.
+    pub fn not_visible(self) -> AlterIndexStatement<T> {
+        AlterIndexStatement::new(self, AlterIndexAction::Visible(false))
+    }
+
+    /// Set storage parameters on the index.
+    ///
+    /// ```sql
+    /// ALTER INDEX books@books_title_idx SET (bucket_count = 8);
+    /// ```
+    pub fn set_storage_params(self, storage_params: StorageParams) -> AlterIndexStatement<T> {
+        AlterIndexStatement::new(self, AlterIndexAction::SetStorageParams(storage_params))
+    }
+
+    /// Reset storage parameters on the index to their defaults.
+    ///
+    /// ```sql
+    /// ALTER INDEX books@books_title_idx RESET (bucket_count);
+    /// ```
+    pub fn reset_storage_params(self, params: Vec<StorageParam>) -> AlterIndexStatement<T> {
+        AlterIndexStatement::new(self, AlterIndexAction::ResetStorageParams(params))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum AlterIndexAction {
+    Visible(bool),
+    SetStorageParams(StorageParams),
+    ResetStorageParams(Vec<StorageParam>),
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `ALTER INDEX` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table the index belongs to
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-index.html
+pub struct AlterIndexStatement<T> {
+    target: T,
+    name: String,
+    action: AlterIndexAction,
+}
+
+impl<T> AlterIndexStatement<T> {
+    fn new(incomplete: IncompleteAlterIndexStatement<T>, action: AlterIndexAction) -> Self {
+        AlterIndexStatement {
+            target: incomplete.target,
+            name: incomplete.name,
+            action,
+        }
+    }
+}
+
+impl<T> VersionCheck for AlterIndexStatement<T> {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        match self.action {
+            AlterIndexAction::Visible(_) => version.require("index visibility", 22, 2),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T> QueryFragment<Pg> for AlterIndexStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("ALTER INDEX ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql("@");
+        out.push_identifier(&self.name)?;
+        match &self.action {
+            AlterIndexAction::Visible(true) => out.push_sql(" VISIBLE"),
+            AlterIndexAction::Visible(false) => out.push_sql(" NOT VISIBLE"),
+            AlterIndexAction::SetStorageParams(storage_params) => {
+                out.push_sql(" SET ");
+                storage_params.walk_ast(out.reborrow())?;
+            }
+            AlterIndexAction::ResetStorageParams(params) => {
+                out.push_sql(" RESET ");
+                storage_params::push_names(&mut out, params)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> RunQueryDsl<PgConnection> for AlterIndexStatement<T> {}
+
+impl<T> QueryId for AlterIndexStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The kind of change an [`IndexRecommendation`] makes.
+///
+/// [`IndexRecommendation`]: struct.IndexRecommendation.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IndexRecommendationKind {
+    /// Create a new index.
+    Creation,
+    /// Replace an existing index with one storing more columns.
+    Replacement,
+    /// Alter an existing index, such as making it visible.
+    Alteration,
+    /// Drop an unused index.
+    Drop,
+    /// Any other kind of recommendation, by name.
+    Other(String),
+}
+
+impl IndexRecommendationKind {
+    /// The name of this kind of recommendation, as used by CockroachDB.
+    pub fn as_str(&self) -> &str {
+        match self {
+            IndexRecommendationKind::Creation => "creation",
+            IndexRecommendationKind::Replacement => "replacement",
+            IndexRecommendationKind::Alteration => "alteration",
+            IndexRecommendationKind::Drop => "drop",
+            IndexRecommendationKind::Other(name) => name,
+        }
+    }
+}
+
+/// Accepts both `creation`, as stored in statement statistics, and
+/// `index creation`, as shown by `EXPLAIN`.
+impl FromStr for IndexRecommendationKind {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Ok(match s.strip_prefix("index ").unwrap_or(s) {
+            "creation" => IndexRecommendationKind::Creation,
+            "replacement" => IndexRecommendationKind::Replacement,
+            "alteration" => IndexRecommendationKind::Alteration,
+            "drop" => IndexRecommendationKind::Drop,
+            other => IndexRecommendationKind::Other(other.to_owned()),
+        })
+    }
+}
+
+/// A change to the indexes of a table recommended by CockroachDB, from
+/// `EXPLAIN` or statement statistics.
+///
+/// The SQL of the recommendation can be run directly with
+/// `diesel::sql_query`, or added to a migration with
+/// [`MigrationStep::sql`].
+///
+/// ```
+/// use diesel_cockroach::index::{IndexRecommendation, IndexRecommendationKind};
+///
+/// let recommendation: IndexRecommendation =
+///     "replacement : CREATE INDEX ON books (title) STORING (author); DROP INDEX books@books_title_idx;"
+///         .parse()
+///         .unwrap();
+/// assert_eq!(recommendation.kind, IndexRecommendationKind::Replacement);
+/// assert_eq!(
+///     recommendation.statements(),
+///     [
+///         "CREATE INDEX ON books (title) STORING (author)",
+///         "DROP INDEX books@books_title_idx",
+///     ]
+/// );
+/// ```
+///
+/// [`MigrationStep::sql`]: ../migrations/struct.MigrationStep.html#method.sql
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromSqlRow)]
+pub struct IndexRecommendation {
+    /// The kind of change recommended.
+    pub kind: IndexRecommendationKind,
+    /// The SQL making the change, which may be several statements.
+    pub sql: String,
+}
+
+impl IndexRecommendation {
+    /// The individual SQL statements making the change, without trailing
+    /// semicolons.
+    pub fn statements(&self) -> Vec<&str> {
+        self.sql
+            .split(';')
+            .map(str::trim)
+            .filter(|statement| !statement.is_empty())
+            .collect()
+    }
+}
+
+/// Parses the `kind : sql` format stored in statement statistics.
+impl FromStr for IndexRecommendation {
+    type Err = InvalidIndexRecommendation;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colon = s
+            .find(':')
+            .ok_or_else(|| InvalidIndexRecommendation(s.to_owned()))?;
+        Ok(IndexRecommendation {
+            kind: s[..colon].parse().unwrap_or_else(|never| match never {}),
+            sql: s[colon + 1..].trim().to_owned(),
+        })
+    }
+}
+
+impl FromSql<Text, Pg> for IndexRecommendation {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+/// The error returned when parsing an invalid [`IndexRecommendation`].
+///
+/// [`IndexRecommendation`]: struct.IndexRecommendation.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidIndexRecommendation(pub String);
+
+impl fmt::Display for InvalidIndexRecommendation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid index recommendation: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidIndexRecommendation {}
+
+/// Creates a query reading the distinct index recommendations made for the
+/// statements run on the cluster, from `crdb_internal.statement_statistics`.
+///
+/// Requires CockroachDB v22.2 or later.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # use std::time::{Duration, SystemTime};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::index::{index_recommendations, IndexRecommendation};
+///
+/// let day_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+/// let recommendations: Vec<IndexRecommendation> = index_recommendations()
+///     .app_name("bookshop")
+///     .since(day_ago)
+///     .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn index_recommendations() -> IndexRecommendationsQuery {
+    IndexRecommendationsQuery {
+        app_name: None,
+        since: None,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed query reading index recommendations.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/ui-insights-page.html
+pub struct IndexRecommendationsQuery {
+    app_name: Option<String>,
+    since: Option<SystemTime>,
+}
+
+impl IndexRecommendationsQuery {
+    /// Only read recommendations for statements run by the given
+    /// application.
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Only read recommendations for statements run since the given time.
+    pub fn since(mut self, time: SystemTime) -> Self {
+        self.since = Some(time);
+        self
+    }
+}
+
+impl VersionCheck for IndexRecommendationsQuery {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        version.require("index recommendations", 22, 2)
+    }
+}
+
+impl QueryFragment<Pg> for IndexRecommendationsQuery {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT DISTINCT recommendation FROM crdb_internal.statement_statistics, \
+             unnest(index_recommendations) AS recommendation",
+        );
+        let mut conjunction = " WHERE ";
+        if let Some(app_name) = &self.app_name {
+            out.push_sql(conjunction);
+            out.push_sql("app_name = ");
+            out.push_bind_param::<Text, _>(app_name)?;
+            conjunction = " AND ";
+        }
+        if let Some(since) = &self.since {
+            out.push_sql(conjunction);
+            out.push_sql("aggregated_ts >= ");
+            out.push_bind_param::<Timestamp, _>(since)?;
+        }
+        Ok(())
+    }
+}
+
+impl Query for IndexRecommendationsQuery {
+    type SqlType = Text;
+}
+
+impl RunQueryDsl<PgConnection> for IndexRecommendationsQuery {}
+
+impl QueryId for IndexRecommendationsQuery {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Bytea,
+            title -> Text,
+            author -> Text,
+            page_count -> Int8,
+        }
+    }
+
+    #[test]
+    fn single() {
+        assert_eq!(
+            diesel::debug_query(&create_index(books::table, books::title)).to_string(),
+            r#"CREATE INDEX ON "books" ("title") -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn named_unique() {
+        let statement = create_index(books::table, (books::author, books::title))
+            .name("books_author_title_idx")
+            .unique()
+            .if_not_exists();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS "books_author_title_idx" ON "books" ("author", "title") -- binds: []"#
+        );
+        let statement = create_index(books::table, books::title).if_not_exists();
+        assert!(quote::render(&statement).is_err());
+    }
+
+    #[test]
+    fn storing() {
+        let statement =
+            create_index(books::table, books::title).storing((books::author, books::page_count));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE INDEX ON "books" ("title") STORING ("author", "page_count") -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn create_not_visible() {
+        let statement = create_index(books::table, books::author)
+            .name("books_author_idx")
+            .not_visible();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE INDEX "books_author_idx" ON "books" ("author") NOT VISIBLE -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn alter_visibility() {
+        assert_eq!(
+            diesel::debug_query(&alter_index(books::table, "books_author_idx").visible())
+                .to_string(),
+            r#"ALTER INDEX "books"@"books_author_idx" VISIBLE -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&alter_index(books::table, "books_author_idx").not_visible())
+                .to_string(),
+            r#"ALTER INDEX "books"@"books_author_idx" NOT VISIBLE -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn version_check() {
+        let version = ClusterVersion::new(22, 1, 0);
+        assert!(version
+            .check(&create_index(books::table, books::title))
+            .is_ok());
+        assert!(version
+            .check(&create_index(books::table, books::title).not_visible())
+            .is_err());
+        assert!(version
+            .check(&alter_index(books::table, "books_title_idx").visible())
+            .is_err());
+        assert!(ClusterVersion::new(22, 2, 0)
+            .check(&alter_index(books::table, "books_title_idx").visible())
+            .is_ok());
+    }
+
+    #[test]
+    fn storage_params() {
+        let statement = create_index(books::table, books::title)
+            .with_storage_params(StorageParams::new().bucket_count(8))
+            .not_visible();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE INDEX ON "books" ("title") WITH (bucket_count = 8) NOT VISIBLE -- binds: []"#
+        );
+        let statement = alter_index(books::table, "books_title_idx")
+            .set_storage_params(StorageParams::new().bucket_count(16));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER INDEX "books"@"books_title_idx" SET (bucket_count = 16) -- binds: []"#
+        );
+        let statement = alter_index(books::table, "books_title_idx")
+            .reset_storage_params(vec![StorageParam::BucketCount]);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER INDEX "books"@"books_title_idx" RESET (bucket_count) -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn recommendations() {
+        assert_eq!(
+            diesel::debug_query(&index_recommendations().app_name("bookshop")).to_string(),
+            r#"SELECT DISTINCT recommendation FROM crdb_internal.statement_statistics, unnest(index_recommendations) AS recommendation WHERE app_name = $1 -- binds: ["bookshop"]"#
+        );
+        let recommendation: IndexRecommendation =
+            "creation : CREATE INDEX ON bookshop.public.books (author);"
+                .parse()
+                .unwrap();
+        assert_eq!(recommendation.kind, IndexRecommendationKind::Creation);
+        assert_eq!(
+            recommendation.statements(),
+            ["CREATE INDEX ON bookshop.public.books (author)"]
+        );
+        assert_eq!(
+            "index alteration".parse(),
+            Ok(IndexRecommendationKind::Alteration)
+        );
+        assert!("CREATE INDEX".parse::<IndexRecommendation>().is_err());
+        assert!(ClusterVersion::new(22, 2, 0)
+            .check(&index_recommendations())
+            .is_ok());
+        assert_eq!(
+            ClusterVersion::new(22, 1, 0)
+                .check(&index_recommendations())
+                .unwrap_err()
+                .to_string(),
+            "index recommendations is not supported on CockroachDB v22.1.0, and requires v22.2 or later"
+        );
+    }
+}
@@ -0,0 +1,1047 @@
+//! Support for tracking CockroachDB [jobs], such as asynchronous schema changes.
+//!
+//! [jobs]: https://www.cockroachlabs.com/docs/stable/show-jobs.html
+
+use crate::{quote, version::ClusterVersion};
+use diesel::{
+    connection::{Connection, SimpleConnection, TransactionManager},
+    dsl::sql,
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{Array, BigInt, Double, Nullable, Text},
+    Queryable,
+};
+use std::{
+    error, fmt,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The status of a job.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum JobStatus {
+    /// `pending`
+    Pending,
+    /// `running`
+    Running,
+    /// `pause-requested`
+    PauseRequested,
+    /// `paused`
+    Paused,
+    /// `cancel-requested`
+    CancelRequested,
+    /// `reverting`
+    Reverting,
+    /// `revert-failed`
+    RevertFailed,
+    /// `succeeded`
+    Succeeded,
+    /// `failed`
+    Failed,
+    /// `canceled`
+    Canceled,
+    /// Any other status, as reported by CockroachDB.
+    Other(String),
+}
+
+impl JobStatus {
+    /// The name of this status, as reported by CockroachDB.
+    pub fn as_str(&self) -> &str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::PauseRequested => "pause-requested",
+            JobStatus::Paused => "paused",
+            JobStatus::CancelRequested => "cancel-requested",
+            JobStatus::Reverting => "reverting",
+            JobStatus::RevertFailed => "revert-failed",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Canceled => "canceled",
+            JobStatus::Other(status) => status,
+        }
+    }
+
+    /// Whether the job has stopped, and will never run again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded
+                | JobStatus::Failed
+                | JobStatus::Canceled
+                | JobStatus::RevertFailed
+        )
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "pending" => JobStatus::Pending,
+            "running" => JobStatus::Running,
+            "pause-requested" => JobStatus::PauseRequested,
+            "paused" => JobStatus::Paused,
+            "cancel-requested" => JobStatus::CancelRequested,
+            "reverting" => JobStatus::Reverting,
+            "revert-failed" => JobStatus::RevertFailed,
+            "succeeded" => JobStatus::Succeeded,
+            "failed" => JobStatus::Failed,
+            "canceled" => JobStatus::Canceled,
+            other => JobStatus::Other(other.to_owned()),
+        })
+    }
+}
+
+/// The type of a job.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum JobType {
+    /// `SCHEMA CHANGE`
+    SchemaChange,
+    /// `NEW SCHEMA CHANGE`
+    NewSchemaChange,
+    /// `SCHEMA CHANGE GC`
+    SchemaChangeGc,
+    /// `IMPORT`
+    Import,
+    /// `BACKUP`
+    Backup,
+    /// `RESTORE`
+    Restore,
+    /// `CHANGEFEED`
+    Changefeed,
+    /// `CREATE STATS`
+    CreateStats,
+    /// `AUTO CREATE STATS`
+    AutoCreateStats,
+    /// Any other type, as reported by CockroachDB.
+    Other(String),
+}
+
+impl JobType {
+    /// The name of this type, as reported by CockroachDB.
+    pub fn as_str(&self) -> &str {
+        match self {
+            JobType::SchemaChange => "SCHEMA CHANGE",
+            JobType::NewSchemaChange => "NEW SCHEMA CHANGE",
+            JobType::SchemaChangeGc => "SCHEMA CHANGE GC",
+            JobType::Import => "IMPORT",
+            JobType::Backup => "BACKUP",
+            JobType::Restore => "RESTORE",
+            JobType::Changefeed => "CHANGEFEED",
+            JobType::CreateStats => "CREATE STATS",
+            JobType::AutoCreateStats => "AUTO CREATE STATS",
+            JobType::Other(job_type) => job_type,
+        }
+    }
+}
+
+impl fmt::Display for JobType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for JobType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "SCHEMA CHANGE" => JobType::SchemaChange,
+            "NEW SCHEMA CHANGE" => JobType::NewSchemaChange,
+            "SCHEMA CHANGE GC" => JobType::SchemaChangeGc,
+            "IMPORT" => JobType::Import,
+            "BACKUP" => JobType::Backup,
+            "RESTORE" => JobType::Restore,
+            "CHANGEFEED" => JobType::Changefeed,
+            "CREATE STATS" => JobType::CreateStats,
+            "AUTO CREATE STATS" => JobType::AutoCreateStats,
+            other => JobType::Other(other.to_owned()),
+        })
+    }
+}
+
+/// The progress of a job, as loaded by [`JobProgressQuery`].
+///
+/// [`JobProgressQuery`]: struct.JobProgressQuery.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobProgress {
+    /// The ID of the job.
+    pub job_id: i64,
+    /// The current status of the job.
+    pub status: JobStatus,
+    /// The fraction of the job completed, between `0.0` and `1.0`, if known.
+    pub fraction_completed: Option<f64>,
+    /// The error the job failed with, if any.
+    pub error: Option<String>,
+}
+
+impl Queryable<JobProgressSqlType, Pg> for JobProgress {
+    type Row = (i64, String, Option<f64>, Option<String>);
+
+    fn build((job_id, status, fraction_completed, error): Self::Row) -> Self {
+        JobProgress {
+            job_id,
+            status: status.parse().unwrap_or_else(|never| match never {}),
+            fraction_completed,
+            error: error.filter(|error| !error.is_empty()),
+        }
+    }
+}
+
+impl JobProgress {
+    /// A handle to the job.
+    pub const fn job(&self) -> JobHandle {
+        JobHandle::new(self.job_id)
+    }
+}
+
+/// The SQL type of a row loaded by [`JobProgressQuery`].
+///
+/// [`JobProgressQuery`]: struct.JobProgressQuery.html
+pub type JobProgressSqlType = (BigInt, Text, Nullable<Double>, Nullable<Text>);
+
+/// The row returned by bulk operations such as `IMPORT`, `BACKUP` and
+/// `RESTORE`, once their job has finished.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkJobResult {
+    /// The ID of the job.
+    pub job_id: i64,
+    /// The final status of the job.
+    pub status: JobStatus,
+    /// The fraction of the job completed, between `0.0` and `1.0`.
+    pub fraction_completed: f64,
+    /// The number of rows processed.
+    pub rows: i64,
+    /// The number of index entries processed.
+    pub index_entries: i64,
+    /// The number of bytes processed.
+    pub bytes: i64,
+}
+
+impl Queryable<BulkJobResultSqlType, Pg> for BulkJobResult {
+    type Row = (i64, String, f64, i64, i64, i64);
+
+    fn build((job_id, status, fraction_completed, rows, index_entries, bytes): Self::Row) -> Self {
+        BulkJobResult {
+            job_id,
+            status: status.parse().unwrap_or_else(|never| match never {}),
+            fraction_completed,
+            rows,
+            index_entries,
+            bytes,
+        }
+    }
+}
+
+impl BulkJobResult {
+    /// A handle to the job.
+    pub const fn job(&self) -> JobHandle {
+        JobHandle::new(self.job_id)
+    }
+}
+
+/// The SQL type of a [`BulkJobResult`].
+///
+/// [`BulkJobResult`]: struct.BulkJobResult.html
+pub type BulkJobResultSqlType = (BigInt, Text, Double, BigInt, BigInt, BigInt);
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A bulk operation run with the `detached` option, which returns the ID of
+/// its job as soon as the job is created, instead of waiting for it to finish.
+///
+/// The parameters of this struct represent:
+///
+/// - `S`: The statement being run
+pub struct Detached<S>(pub(crate) S);
+
+impl<S> Detached<S>
+where
+    S: QueryFragment<Pg>,
+{
+    /// Start the job, returning a handle to supervise it with.
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate diesel;
+    /// # use diesel::{pg::PgConnection, Connection};
+    /// # table! { books (id) { id -> Int8, } }
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let connection = PgConnection::establish("")?;
+    /// use diesel_cockroach::{backup::backup_table, jobs::WaitOptions};
+    ///
+    /// let job = backup_table(books::table, "nodelocal://1/backups")
+    ///     .detached()
+    ///     .start(&connection)?;
+    /// println!("backup is {:?} complete", job.progress(&connection)?.fraction_completed);
+    /// job.wait(&connection, WaitOptions::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start(self, conn: &PgConnection) -> QueryResult<JobHandle> {
+        let job_id = self.get_result(conn)?;
+        Ok(JobHandle::new(job_id))
+    }
+}
+
+impl<S> QueryFragment<Pg> for Detached<S>
+where
+    S: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, out: AstPass<Pg>) -> QueryResult<()> {
+        self.0.walk_ast(out)
+    }
+}
+
+impl<S> Query for Detached<S> {
+    type SqlType = BigInt;
+}
+
+impl<S> RunQueryDsl<PgConnection> for Detached<S> {}
+
+impl<S> QueryId for Detached<S> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A handle to a job, such as a detached bulk operation, a changefeed or a
+/// schema change.
+///
+/// Any job can be supervised with a handle created from its ID.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::jobs::{JobHandle, JobStatus};
+///
+/// let job = JobHandle::from(27536791415282);
+/// if job.status(&connection)? == JobStatus::Running {
+///     job.pause(&connection)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobHandle {
+    job_id: i64,
+}
+
+impl JobHandle {
+    /// A handle to the job with the given ID.
+    pub const fn new(job_id: i64) -> Self {
+        JobHandle { job_id }
+    }
+
+    /// The ID of the job.
+    pub const fn job_id(&self) -> i64 {
+        self.job_id
+    }
+
+    /// Loads the current progress of the job.
+    pub fn progress(&self, conn: &PgConnection) -> QueryResult<JobProgress> {
+        job_progress(vec![self.job_id]).get_result(conn)
+    }
+
+    /// Loads the current status of the job.
+    pub fn status(&self, conn: &PgConnection) -> QueryResult<JobStatus> {
+        self.progress(conn).map(|progress| progress.status)
+    }
+
+    /// Blocks until the job has succeeded, returning its final progress.
+    pub fn wait(
+        &self,
+        conn: &PgConnection,
+        options: WaitOptions,
+    ) -> Result<JobProgress, WaitError> {
+        let mut jobs = wait_for_jobs(conn, vec![self.job_id], options, |_| {})?;
+        jobs.pop()
+            .ok_or(WaitError::Query(diesel::result::Error::NotFound))
+    }
+
+    /// Pauses the job, until it is resumed.
+    pub fn pause(&self, conn: &PgConnection) -> QueryResult<()> {
+        pause_job(self.job_id).execute(conn).map(|_| ())
+    }
+
+    /// Resumes the job after it was paused.
+    pub fn resume(&self, conn: &PgConnection) -> QueryResult<()> {
+        resume_job(self.job_id).execute(conn).map(|_| ())
+    }
+
+    /// Cancels the job, reverting any changes it made.
+    pub fn cancel(&self, conn: &PgConnection) -> QueryResult<()> {
+        cancel_job(self.job_id).execute(conn).map(|_| ())
+    }
+}
+
+impl From<i64> for JobHandle {
+    fn from(job_id: i64) -> Self {
+        JobHandle::new(job_id)
+    }
+}
+
+/// Creates a `PAUSE JOB` statement.
+///
+/// ```sql
+/// PAUSE JOB 27536791415282;
+/// ```
+pub fn pause_job(job_id: i64) -> JobControlStatement {
+    JobControlStatement {
+        action: JobAction::Pause,
+        job_id,
+    }
+}
+
+/// Creates a `RESUME JOB` statement.
+///
+/// ```sql
+/// RESUME JOB 27536791415282;
+/// ```
+pub fn resume_job(job_id: i64) -> JobControlStatement {
+    JobControlStatement {
+        action: JobAction::Resume,
+        job_id,
+    }
+}
+
+/// Creates a `CANCEL JOB` statement.
+///
+/// ```sql
+/// CANCEL JOB 27536791415282;
+/// ```
+pub fn cancel_job(job_id: i64) -> JobControlStatement {
+    JobControlStatement {
+        action: JobAction::Cancel,
+        job_id,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum JobAction {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `PAUSE`, `RESUME` or `CANCEL JOB` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/pause-job.html
+pub struct JobControlStatement {
+    action: JobAction,
+    job_id: i64,
+}
+
+impl QueryFragment<Pg> for JobControlStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(match self.action {
+            JobAction::Pause => "PAUSE JOB ",
+            JobAction::Resume => "RESUME JOB ",
+            JobAction::Cancel => "CANCEL JOB ",
+        });
+        out.push_sql(&self.job_id.to_string());
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for JobControlStatement {}
+
+impl QueryId for JobControlStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+const JOB_PROGRESS_COLUMNS: &str =
+    "SELECT job_id, status, fraction_completed, error FROM crdb_internal.jobs WHERE ";
+
+/// Creates a query loading the [`JobProgress`] of schema changes on the
+/// target table which have not yet finished.
+///
+/// [`JobProgress`]: struct.JobProgress.html
+pub fn pending_schema_changes<T>(target: T) -> JobProgressQuery<PendingSchemaChanges<T>> {
+    JobProgressQuery(PendingSchemaChanges(target))
+}
+
+/// Creates a query loading the [`JobProgress`] of schema change and garbage
+/// collection jobs on the descriptor with the given ID which have not yet
+/// finished.
+///
+/// [`JobProgress`]: struct.JobProgress.html
+pub fn pending_descriptor_jobs(descriptor_id: i64) -> JobProgressQuery<PendingDescriptorJobs> {
+    JobProgressQuery(PendingDescriptorJobs(descriptor_id))
+}
+
+/// Creates a query loading the [`JobProgress`] of the given jobs.
+///
+/// [`JobProgress`]: struct.JobProgress.html
+pub fn job_progress(job_ids: Vec<i64>) -> JobProgressQuery<JobIds> {
+    JobProgressQuery(JobIds(job_ids))
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A query loading [`JobProgress`] rows from `crdb_internal.jobs`.
+///
+/// The parameters of this struct represent:
+///
+/// - `F`: The filter selecting the jobs to load
+///
+/// [`JobProgress`]: struct.JobProgress.html
+pub struct JobProgressQuery<F>(F);
+
+/// Selects unfinished schema change jobs on a table, for [`pending_schema_changes`].
+///
+/// [`pending_schema_changes`]: fn.pending_schema_changes.html
+#[derive(Debug, Clone, Copy)]
+pub struct PendingSchemaChanges<T>(T);
+
+impl<T> QueryFragment<Pg> for PendingSchemaChanges<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        let table = quote::render(&self.0.from_clause())?;
+        out.push_sql(
+            "job_type IN ('SCHEMA CHANGE', 'NEW SCHEMA CHANGE') \
+             AND status NOT IN ('succeeded', 'failed', 'canceled', 'revert-failed') \
+             AND ",
+        );
+        out.push_sql(&quote::string_literal(&table));
+        out.push_sql("::REGCLASS::INT8 = ANY (descriptor_ids)");
+        Ok(())
+    }
+}
+
+/// Selects unfinished jobs on a descriptor, for [`pending_descriptor_jobs`].
+///
+/// [`pending_descriptor_jobs`]: fn.pending_descriptor_jobs.html
+#[derive(Debug, Clone, Copy)]
+pub struct PendingDescriptorJobs(i64);
+
+impl QueryFragment<Pg> for PendingDescriptorJobs {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql(
+            "job_type IN ('SCHEMA CHANGE', 'NEW SCHEMA CHANGE', 'SCHEMA CHANGE GC') \
+             AND status NOT IN ('succeeded', 'failed', 'canceled', 'revert-failed') \
+             AND ",
+        );
+        out.push_bind_param::<BigInt, _>(&self.0)?;
+        out.push_sql(" = ANY (descriptor_ids)");
+        Ok(())
+    }
+}
+
+/// Selects jobs by ID, for [`job_progress`].
+///
+/// [`job_progress`]: fn.job_progress.html
+#[derive(Debug, Clone)]
+pub struct JobIds(Vec<i64>);
+
+impl QueryFragment<Pg> for JobIds {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("job_id = ANY (");
+        out.push_bind_param::<Array<BigInt>, _>(&self.0)?;
+        out.push_sql(")");
+        Ok(())
+    }
+}
+
+impl<F> QueryFragment<Pg> for JobProgressQuery<F>
+where
+    F: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(JOB_PROGRESS_COLUMNS);
+        self.0.walk_ast(out.reborrow())?;
+        out.push_sql(" ORDER BY job_id");
+        Ok(())
+    }
+}
+
+impl<F> Query for JobProgressQuery<F> {
+    type SqlType = JobProgressSqlType;
+}
+
+impl<F> RunQueryDsl<PgConnection> for JobProgressQuery<F> {}
+
+impl<F> QueryId for JobProgressQuery<F> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Options controlling how long to wait for jobs to finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitOptions {
+    /// Give up waiting after this long.
+    pub timeout: Duration,
+    /// How long to sleep between checks on the jobs.
+    pub poll_interval: Duration,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        WaitOptions {
+            timeout: Duration::from_secs(600),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The error returned when waiting for jobs does not succeed.
+#[derive(Debug)]
+pub enum WaitError {
+    /// The jobs did not finish within the timeout.
+    Timeout(Vec<JobProgress>),
+    /// A job finished without succeeding.
+    JobFailed(JobProgress),
+    /// Checking the progress of the jobs failed.
+    Query(diesel::result::Error),
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WaitError::Timeout(jobs) => write!(f, "timed out waiting for {} job(s)", jobs.len()),
+            WaitError::JobFailed(job) => {
+                write!(f, "job {} finished with status {}", job.job_id, job.status)?;
+                if let Some(error) = &job.error {
+                    write!(f, ": {}", error)?;
+                }
+                Ok(())
+            }
+            WaitError::Query(err) => write!(f, "failed to check job progress: {}", err),
+        }
+    }
+}
+
+impl error::Error for WaitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            WaitError::Query(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for WaitError {
+    fn from(err: diesel::result::Error) -> Self {
+        WaitError::Query(err)
+    }
+}
+
+/// Creates a `SHOW JOB WHEN COMPLETE` statement, which blocks until the job
+/// has finished, then loads its [`JobProgress`].
+///
+/// ```sql
+/// SELECT job_id, status, fraction_completed, error FROM [SHOW JOB WHEN COMPLETE 27536791415282];
+/// ```
+///
+/// [`JobProgress`]: struct.JobProgress.html
+pub fn show_job_when_complete(job_id: i64) -> ShowJobWhenCompleteStatement {
+    ShowJobWhenCompleteStatement { job_id }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW JOB WHEN COMPLETE` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-jobs.html
+pub struct ShowJobWhenCompleteStatement {
+    job_id: i64,
+}
+
+impl QueryFragment<Pg> for ShowJobWhenCompleteStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT job_id, status, fraction_completed, error FROM [SHOW JOB WHEN COMPLETE ",
+        );
+        out.push_sql(&self.job_id.to_string());
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl Query for ShowJobWhenCompleteStatement {
+    type SqlType = JobProgressSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for ShowJobWhenCompleteStatement {}
+
+impl QueryId for ShowJobWhenCompleteStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Blocks until the job has succeeded, returning its final progress.
+///
+/// On CockroachDB v20.1 or later, the job is waited on with
+/// `SHOW JOB WHEN COMPLETE`, under a `statement_timeout` of `timeout`. The
+/// previous `statement_timeout` is restored afterwards, and inside a
+/// transaction the timeout is only set with `SET LOCAL`. Older versions are
+/// polled every `poll_interval` instead.
+///
+/// If the wait times out inside a transaction, the transaction is aborted, so
+/// the progress of the job cannot be loaded and [`WaitError::Timeout`] is
+/// returned with no jobs.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let connection = PgConnection::establish("")?;
+/// use diesel_cockroach::{jobs::wait_for_job, version::ClusterVersion};
+/// use std::time::Duration;
+///
+/// let version = ClusterVersion::detect(&connection)?;
+/// let job = wait_for_job(
+///     &connection,
+///     &version,
+///     27_536_791_415_282,
+///     Duration::from_secs(600),
+///     Duration::from_secs(1),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`WaitError::Timeout`]: enum.WaitError.html#variant.Timeout
+pub fn wait_for_job(
+    conn: &PgConnection,
+    version: &ClusterVersion,
+    job_id: i64,
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<JobProgress, WaitError> {
+    if !version.supports_show_job_when_complete() {
+        let options = WaitOptions {
+            timeout,
+            poll_interval,
+        };
+        let mut jobs = wait_for_jobs(conn, vec![job_id], options, |_| {})?;
+        return jobs
+            .pop()
+            .ok_or(WaitError::Query(diesel::result::Error::NotFound));
+    }
+
+    let local =
+        TransactionManager::<PgConnection>::get_transaction_depth(conn.transaction_manager()) > 0;
+    let previous_timeout: String =
+        diesel::select(sql::<Text>("current_setting('statement_timeout')")).get_result(conn)?;
+    conn.batch_execute(&set_statement_timeout(local, &quote::interval(timeout)))?;
+    let result = show_job_when_complete(job_id).get_result::<JobProgress>(conn);
+    // Restore the timeout even if waiting failed, but report the failure to
+    // wait first, since restoring fails in an aborted transaction.
+    let restored = conn.batch_execute(&set_statement_timeout(local, &previous_timeout));
+
+    let job = match result {
+        Ok(job) => job,
+        Err(err) if is_statement_timeout(&err) => {
+            let jobs = match restored {
+                Ok(()) => job_progress(vec![job_id]).load(conn)?,
+                Err(_) => Vec::new(),
+            };
+            return Err(WaitError::Timeout(jobs));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    restored?;
+    if job.status != JobStatus::Succeeded {
+        return Err(WaitError::JobFailed(job));
+    }
+    Ok(job)
+}
+
+fn set_statement_timeout(local: bool, timeout: &str) -> String {
+    format!(
+        "SET {}statement_timeout = {}",
+        if local { "LOCAL " } else { "" },
+        quote::string_literal(timeout)
+    )
+}
+
+fn is_statement_timeout(err: &diesel::result::Error) -> bool {
+    match err {
+        diesel::result::Error::DatabaseError(_, info) => {
+            info.message().contains("statement timeout")
+        }
+        _ => false,
+    }
+}
+
+/// Blocks until all schema changes on the target table have finished.
+///
+/// Schema changes such as adding a column with a default, or creating an
+/// index, return before the existing rows have been backfilled. This looks up
+/// the jobs performing any such changes, and waits for them to succeed.
+///
+/// `on_progress` is called with the progress of each unfinished job after
+/// every check. The progress of every job waited on is returned.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, title -> Text, } }
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let connection = PgConnection::establish("")?;
+/// use diesel_cockroach::{index::create_index, jobs::{wait_for_schema_changes, WaitOptions}};
+///
+/// create_index(books::table, books::title).execute(&connection)?;
+/// wait_for_schema_changes(&connection, books::table, WaitOptions::default(), |job| {
+///     println!("job {} is {:?} complete", job.job_id, job.fraction_completed);
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn wait_for_schema_changes<T, F>(
+    conn: &PgConnection,
+    target: T,
+    options: WaitOptions,
+    on_progress: F,
+) -> Result<Vec<JobProgress>, WaitError>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+    F: FnMut(&JobProgress),
+{
+    let jobs: Vec<JobProgress> = pending_schema_changes(target).load(conn)?;
+    let job_ids: Vec<i64> = jobs.iter().map(|job| job.job_id).collect();
+    // Schema change jobs which have since been removed have finished.
+    poll_until_finished(
+        || job_progress(job_ids.clone()).load(conn),
+        thread::sleep,
+        options,
+        on_progress,
+    )
+}
+
+/// Blocks until all the given jobs have succeeded.
+///
+/// `on_progress` is called with the progress of each unfinished job after
+/// every check. The progress of every job is returned.
+///
+/// Any job which cannot be found, such as one removed after the job
+/// retention time, is a [`WaitError::Query`] of `NotFound`.
+///
+/// [`WaitError::Query`]: enum.WaitError.html#variant.Query
+pub fn wait_for_jobs<F>(
+    conn: &PgConnection,
+    job_ids: Vec<i64>,
+    options: WaitOptions,
+    on_progress: F,
+) -> Result<Vec<JobProgress>, WaitError>
+where
+    F: FnMut(&JobProgress),
+{
+    poll_until_finished(
+        || require_all(&job_ids, job_progress(job_ids.clone()).load(conn)?),
+        thread::sleep,
+        options,
+        on_progress,
+    )
+}
+
+/// Checks that every one of `job_ids` was loaded.
+fn require_all(job_ids: &[i64], jobs: Vec<JobProgress>) -> QueryResult<Vec<JobProgress>> {
+    if job_ids
+        .iter()
+        .all(|job_id| jobs.iter().any(|job| job.job_id == *job_id))
+    {
+        Ok(jobs)
+    } else {
+        Err(diesel::result::Error::NotFound)
+    }
+}
+
+fn poll_until_finished<P, S, F>(
+    mut poll: P,
+    mut sleep: S,
+    options: WaitOptions,
+    mut on_progress: F,
+) -> Result<Vec<JobProgress>, WaitError>
+where
+    P: FnMut() -> QueryResult<Vec<JobProgress>>,
+    S: FnMut(Duration),
+    F: FnMut(&JobProgress),
+{
+    let started = Instant::now();
+    loop {
+        let jobs = poll()?;
+        if let Some(job) = jobs
+            .iter()
+            .find(|job| job.status.is_terminal() && job.status != JobStatus::Succeeded)
+        {
+            return Err(WaitError::JobFailed(job.clone()));
+        }
+
+        let unfinished: Vec<JobProgress> = jobs
+            .iter()
+            .filter(|job| !job.status.is_terminal())
+            .cloned()
+            .collect();
+        if unfinished.is_empty() {
+            return Ok(jobs);
+        }
+        unfinished.iter().for_each(&mut on_progress);
+
+        if started.elapsed() + options.poll_interval > options.timeout {
+            return Err(WaitError::Timeout(unfinished));
+        }
+        sleep(options.poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    fn job(status: JobStatus, fraction_completed: f64) -> JobProgress {
+        JobProgress {
+            job_id: 1,
+            status,
+            fraction_completed: Some(fraction_completed),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn queries() {
+        assert_eq!(
+            diesel::debug_query(&pending_schema_changes(books::table)).to_string(),
+            r#"SELECT job_id, status, fraction_completed, error FROM crdb_internal.jobs WHERE job_type IN ('SCHEMA CHANGE', 'NEW SCHEMA CHANGE') AND status NOT IN ('succeeded', 'failed', 'canceled', 'revert-failed') AND '"books"'::REGCLASS::INT8 = ANY (descriptor_ids) ORDER BY job_id -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&pending_descriptor_jobs(53)).to_string(),
+            r#"SELECT job_id, status, fraction_completed, error FROM crdb_internal.jobs WHERE job_type IN ('SCHEMA CHANGE', 'NEW SCHEMA CHANGE', 'SCHEMA CHANGE GC') AND status NOT IN ('succeeded', 'failed', 'canceled', 'revert-failed') AND $1 = ANY (descriptor_ids) ORDER BY job_id -- binds: [53]"#
+        );
+        assert_eq!(
+            diesel::debug_query(&job_progress(vec![1, 2])).to_string(),
+            r#"SELECT job_id, status, fraction_completed, error FROM crdb_internal.jobs WHERE job_id = ANY ($1) ORDER BY job_id -- binds: [[1, 2]]"#
+        );
+    }
+
+    #[test]
+    fn when_complete() {
+        assert_eq!(
+            diesel::debug_query(&show_job_when_complete(27_536_791_415_282)).to_string(),
+            r#"SELECT job_id, status, fraction_completed, error FROM [SHOW JOB WHEN COMPLETE 27536791415282] -- binds: []"#
+        );
+        assert_eq!(
+            set_statement_timeout(false, "10m"),
+            "SET statement_timeout = '10m'"
+        );
+        assert_eq!(
+            set_statement_timeout(true, "10m"),
+            "SET LOCAL statement_timeout = '10m'"
+        );
+    }
+
+    #[test]
+    fn control() {
+        assert_eq!(
+            diesel::debug_query(&pause_job(27_536_791_415_282)).to_string(),
+            r#"PAUSE JOB 27536791415282 -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&resume_job(1)).to_string(),
+            r#"RESUME JOB 1 -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&cancel_job(1)).to_string(),
+            r#"CANCEL JOB 1 -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn wait_succeeded() {
+        let mut polls = vec![
+            vec![job(JobStatus::Succeeded, 1.0)],
+            vec![job(JobStatus::Running, 0.5)],
+            vec![job(JobStatus::Pending, 0.0)],
+        ];
+        let mut progress = Vec::new();
+        let jobs = poll_until_finished(
+            || Ok(polls.pop().unwrap()),
+            |_| {},
+            WaitOptions::default(),
+            |job| progress.push(job.fraction_completed.unwrap()),
+        )
+        .unwrap();
+        assert_eq!(jobs, vec![job(JobStatus::Succeeded, 1.0)]);
+        assert_eq!(progress, vec![0.0, 0.5]);
+    }
+
+    #[test]
+    fn wait_failed() {
+        let result = poll_until_finished(
+            || Ok(vec![job(JobStatus::Failed, 0.5)]),
+            |_| {},
+            WaitOptions::default(),
+            |_| {},
+        );
+        assert!(matches!(result, Err(WaitError::JobFailed(_))));
+    }
+
+    #[test]
+    fn wait_timeout() {
+        let options = WaitOptions {
+            timeout: Duration::from_secs(0),
+            poll_interval: Duration::from_secs(1),
+        };
+        let result = poll_until_finished(
+            || Ok(vec![job(JobStatus::Running, 0.5)]),
+            |_| panic!("should not sleep past the timeout"),
+            options,
+            |_| {},
+        );
+        assert!(matches!(result, Err(WaitError::Timeout(_))));
+    }
+
+    #[test]
+    fn wait_missing() {
+        let result = poll_until_finished(
+            || require_all(&[1, 2], vec![job(JobStatus::Succeeded, 1.0)]),
+            |_| {},
+            WaitOptions::default(),
+            |_| {},
+        );
+        assert!(matches!(
+            result,
+            Err(WaitError::Query(diesel::result::Error::NotFound))
+        ));
+        assert!(require_all(&[1], vec![job(JobStatus::Succeeded, 1.0)]).is_ok());
+    }
+}
@@ -0,0 +1,261 @@
+//! Support for querying `JSONB` columns.
+//!
+//! CockroachDB can answer some JSON filters from an inverted index, created
+//! with `CREATE INVERTED INDEX ON events (payload)`. The filters which may use
+//! an inverted index are:
+//!
+//! - containment, with [`contains`] and [`contained_by`]
+//! - key existence, with [`has_key`], [`has_any_key`] and [`has_all_keys`]
+//! - equality on a field, such as
+//!   `payload.retrieve_as_object("kind").eq(other_payload)`
+//!
+//! Other filters, such as comparing the text of a field, need a full scan of
+//! the table or an index on a computed column.
+//!
+//! ```no_run
+//! # #[macro_use] extern crate diesel;
+//! # use diesel::{pg::PgConnection, Connection, QueryDsl, RunQueryDsl};
+//! # table! { events (id) { id -> Int8, payload -> Jsonb, } }
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::jsonb::JsonbExpressionMethods;
+//!
+//! let user_ids: Vec<String> = events::table
+//!     .select(events::payload.retrieve_as_text("user_id"))
+//!     .filter(events::payload.has_key("user_id"))
+//!     .load(&connection)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! See the [CockroachDB docs].
+//!
+//! [`contains`]: trait.JsonbExpressionMethods.html#method.contains
+//! [`contained_by`]: trait.JsonbExpressionMethods.html#method.contained_by
+//! [`has_key`]: trait.JsonbExpressionMethods.html#method.has_key
+//! [`has_any_key`]: trait.JsonbExpressionMethods.html#method.has_any_key
+//! [`has_all_keys`]: trait.JsonbExpressionMethods.html#method.has_all_keys
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/inverted-indexes.html
+
+use diesel::{
+    expression::{AsExpression, Expression},
+    pg::Pg,
+    sql_types::{Array, Integer, Jsonb, Text},
+};
+
+diesel_infix_operator!(Contains, " @> ", backend: Pg);
+diesel_infix_operator!(ContainedBy, " <@ ", backend: Pg);
+diesel_infix_operator!(HasKey, " ? ", backend: Pg);
+diesel_infix_operator!(HasAnyKey, " ?| ", backend: Pg);
+diesel_infix_operator!(HasAllKeys, " ?& ", backend: Pg);
+diesel_infix_operator!(RetrieveAsObject, " -> ", Jsonb, backend: Pg);
+diesel_infix_operator!(RetrieveAsText, " ->> ", Text, backend: Pg);
+diesel_infix_operator!(RetrieveByPathAsObject, " #> ", Jsonb, backend: Pg);
+diesel_infix_operator!(RetrieveByPathAsText, " #>> ", Text, backend: Pg);
+
+sql_function! {
+    /// The `jsonb_typeof()` function, returning the type of the outermost
+    /// JSON value, such as `object`, `array` or `string`.
+    fn jsonb_typeof(value: Jsonb) -> Text;
+}
+
+sql_function! {
+    /// The `jsonb_array_length()` function, returning the number of elements
+    /// in a JSON array.
+    fn jsonb_array_length(value: Jsonb) -> Integer;
+}
+
+/// Adds JSON operators to `JSONB` expressions.
+///
+/// Retrieving a field which does not exist returns `NULL`. Use `.nullable()`
+/// when selecting fields which may be missing.
+pub trait JsonbExpressionMethods: Expression<SqlType = Jsonb> + Sized {
+    /// Creates a `@>` expression, which is true when this value contains
+    /// `other` at its top level. May use an inverted index.
+    ///
+    /// ```sql
+    /// payload @> '{"kind": "click"}'
+    /// ```
+    fn contains<T>(self, other: T) -> Contains<Self, T::Expression>
+    where
+        T: AsExpression<Jsonb>,
+    {
+        Contains::new(self, other.as_expression())
+    }
+
+    /// Creates a `<@` expression, which is true when `other` contains this
+    /// value at its top level. May use an inverted index.
+    ///
+    /// ```sql
+    /// payload <@ '{"kind": "click", "count": 1}'
+    /// ```
+    fn contained_by<T>(self, other: T) -> ContainedBy<Self, T::Expression>
+    where
+        T: AsExpression<Jsonb>,
+    {
+        ContainedBy::new(self, other.as_expression())
+    }
+
+    /// Creates a `?` expression, which is true when `key` is a top level key
+    /// of this object, or a string element of this array. May use an
+    /// inverted index.
+    ///
+    /// ```sql
+    /// payload ? 'user_id'
+    /// ```
+    fn has_key<T>(self, key: T) -> HasKey<Self, T::Expression>
+    where
+        T: AsExpression<Text>,
+    {
+        HasKey::new(self, key.as_expression())
+    }
+
+    /// Creates a `?|` expression, which is true when any of `keys` is a top
+    /// level key of this value. May use an inverted index.
+    ///
+    /// ```sql
+    /// payload ?| ARRAY['user_id', 'session_id']
+    /// ```
+    fn has_any_key<T>(self, keys: T) -> HasAnyKey<Self, T::Expression>
+    where
+        T: AsExpression<Array<Text>>,
+    {
+        HasAnyKey::new(self, keys.as_expression())
+    }
+
+    /// Creates a `?&` expression, which is true when all of `keys` are top
+    /// level keys of this value. May use an inverted index.
+    ///
+    /// ```sql
+    /// payload ?& ARRAY['user_id', 'session_id']
+    /// ```
+    fn has_all_keys<T>(self, keys: T) -> HasAllKeys<Self, T::Expression>
+    where
+        T: AsExpression<Array<Text>>,
+    {
+        HasAllKeys::new(self, keys.as_expression())
+    }
+
+    /// Creates a `->` expression, retrieving the field `key` as `JSONB`.
+    ///
+    /// ```sql
+    /// payload -> 'user'
+    /// ```
+    fn retrieve_as_object<T>(self, key: T) -> RetrieveAsObject<Self, T::Expression>
+    where
+        T: AsExpression<Text>,
+    {
+        RetrieveAsObject::new(self, key.as_expression())
+    }
+
+    /// Creates a `->` expression, retrieving the array element at `index` as
+    /// `JSONB`.
+    ///
+    /// ```sql
+    /// payload -> 0
+    /// ```
+    fn retrieve_element<T>(self, index: T) -> RetrieveAsObject<Self, T::Expression>
+    where
+        T: AsExpression<Integer>,
+    {
+        RetrieveAsObject::new(self, index.as_expression())
+    }
+
+    /// Creates a `->>` expression, retrieving the field `key` as text.
+    ///
+    /// ```sql
+    /// payload ->> 'user_id'
+    /// ```
+    fn retrieve_as_text<T>(self, key: T) -> RetrieveAsText<Self, T::Expression>
+    where
+        T: AsExpression<Text>,
+    {
+        RetrieveAsText::new(self, key.as_expression())
+    }
+
+    /// Creates a `#>` expression, retrieving the value at `path` as `JSONB`.
+    ///
+    /// ```sql
+    /// payload #> ARRAY['user', 'id']
+    /// ```
+    fn retrieve_by_path_as_object<T>(self, path: T) -> RetrieveByPathAsObject<Self, T::Expression>
+    where
+        T: AsExpression<Array<Text>>,
+    {
+        RetrieveByPathAsObject::new(self, path.as_expression())
+    }
+
+    /// Creates a `#>>` expression, retrieving the value at `path` as text.
+    ///
+    /// ```sql
+    /// payload #>> ARRAY['user', 'id']
+    /// ```
+    fn retrieve_by_path_as_text<T>(self, path: T) -> RetrieveByPathAsText<Self, T::Expression>
+    where
+        T: AsExpression<Array<Text>>,
+    {
+        RetrieveByPathAsText::new(self, path.as_expression())
+    }
+}
+
+impl<T> JsonbExpressionMethods for T where T: Expression<SqlType = Jsonb> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        events (id) {
+            id -> Int8,
+            payload -> Jsonb,
+            previous -> Jsonb,
+        }
+    }
+
+    #[test]
+    fn containment() {
+        let query = events::table
+            .select(events::id)
+            .filter(events::payload.contains(events::previous))
+            .filter(events::previous.contained_by(events::payload));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "events"."id" FROM "events" WHERE "events"."payload" @> "events"."previous" AND "events"."previous" <@ "events"."payload" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn existence() {
+        let query = events::table
+            .select(events::id)
+            .filter(events::payload.has_key("user_id"))
+            .filter(events::payload.has_any_key(vec!["a", "b"]))
+            .filter(events::payload.has_all_keys(vec!["c"]));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "events"."id" FROM "events" WHERE "events"."payload" ? $1 AND "events"."payload" ?| $2 AND "events"."payload" ?& $3 -- binds: ["user_id", ["a", "b"], ["c"]]"#
+        );
+    }
+
+    #[test]
+    fn paths() {
+        let query = events::table
+            .select((
+                events::payload.retrieve_as_text("user_id"),
+                events::payload.retrieve_by_path_as_text(vec!["user", "id"]),
+                jsonb_typeof(events::payload.retrieve_by_path_as_object(vec!["tags"])),
+                jsonb_array_length(events::payload.retrieve_as_object("tags")),
+            ))
+            .filter(
+                events::payload
+                    .retrieve_as_object("kind")
+                    .eq(events::previous.retrieve_element(0)),
+            );
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "events"."payload" ->> $1, "events"."payload" #>> $2, jsonb_typeof("events"."payload" #> $3), jsonb_array_length("events"."payload" -> $4) FROM "events" WHERE "events"."payload" -> $5 = "events"."previous" -> $6 -- binds: ["user_id", ["user", "id"], ["tags"], "tags", "kind", 0]"#
+        );
+    }
+}
@@ -1,7 +1,59 @@
 //! Adds support for the CockroachDB specific SQL queries to Diesel.
 
-#[cfg(test)]
+// Diesel 1.x derives expand to impls nested inside functions, which newer
+// compilers warn about.
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate diesel;
 
+pub mod admin;
+pub mod alter_table;
+pub mod array;
+pub mod as_of_system_time;
+pub mod backup;
+pub mod batch;
+pub mod changefeed;
+pub mod cluster_settings;
+pub mod collation;
+pub mod column_list;
+pub mod comment;
+pub mod copy;
+pub mod crdb_internal;
+pub mod create_table;
+pub mod data_types;
+pub mod database;
+pub mod drop;
+pub mod enums;
+pub mod explain;
+pub mod export;
+pub mod external_storage;
+pub mod functions;
+pub mod hidden_columns;
+pub mod import;
+pub mod index;
+pub mod jobs;
+pub mod jsonb;
+pub mod locality;
+pub mod migrations;
+pub mod partitions;
+pub mod privileges;
+mod quote;
+pub mod regclass;
+pub mod restore;
+pub mod roles;
+pub mod savepoint;
+pub mod schedules;
+pub mod sequence;
+pub mod serial;
+pub mod show_create;
+pub mod sink;
+pub mod sql_types;
+pub mod statistics;
+pub mod storage_params;
+pub mod trace;
+pub mod trigram;
+pub mod truncate;
 pub mod upsert;
+pub mod version;
+pub mod zone_config;
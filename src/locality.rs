@@ -0,0 +1,466 @@
+//! Support for the localities of tables in multi-region databases, and of
+//! the nodes serving connections.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/table-localities.html
+
+use crate::{
+    alter_table::{AlterTableStatement, IncompleteAlterTableStatement},
+    data_types::Region,
+    sql_types::CrdbInternalRegion,
+    version::{ClusterVersion, Unsupported, VersionCheck},
+};
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::Column,
+    result::QueryResult,
+    sql_types::Text,
+    FromSqlRow,
+};
+use std::{error, fmt, str::FromStr, sync::Mutex};
+
+/// The locality of a table, controlling where its data is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Locality {
+    /// `GLOBAL`: fast reads from every region, at the cost of slower writes.
+    Global,
+    /// `REGIONAL BY TABLE`: fast reads and writes from a single region, or
+    /// the primary region of the database if `None`.
+    RegionalByTable(Option<Region>),
+    /// `REGIONAL BY ROW`: fast reads and writes from the region of each row,
+    /// as stored in the hidden `crdb_region` column.
+    RegionalByRow,
+}
+
+impl QueryFragment<Pg> for Locality {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        match self {
+            Locality::Global => out.push_sql("GLOBAL"),
+            Locality::RegionalByTable(None) => out.push_sql("REGIONAL BY TABLE IN PRIMARY REGION"),
+            Locality::RegionalByTable(Some(region)) => {
+                out.push_sql("REGIONAL BY TABLE IN ");
+                out.push_identifier(region.as_str())?;
+            }
+            Locality::RegionalByRow => out.push_sql("REGIONAL BY ROW"),
+        }
+        Ok(())
+    }
+}
+
+/// Parses a locality as shown by `SHOW TABLES` or `SHOW CREATE TABLE`, such
+/// as `REGIONAL BY TABLE IN "us-east1"`.
+///
+/// The column of a `REGIONAL BY ROW AS` locality is ignored.
+impl FromStr for Locality {
+    type Err = InvalidLocality;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "GLOBAL" {
+            Ok(Locality::Global)
+        } else if s == "REGIONAL BY ROW" || s.starts_with("REGIONAL BY ROW AS ") {
+            Ok(Locality::RegionalByRow)
+        } else if s == "REGIONAL BY TABLE IN PRIMARY REGION" {
+            Ok(Locality::RegionalByTable(None))
+        } else if let Some(region) = s.strip_prefix("REGIONAL BY TABLE IN ") {
+            let region = region.trim_matches('"');
+            Ok(Locality::RegionalByTable(Some(Region::new(region))))
+        } else {
+            Err(InvalidLocality(s.to_owned()))
+        }
+    }
+}
+
+/// The `SET LOCALITY` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`set_locality`] or [`set_locality_regional_by_row_as`]
+/// on the result of [`alter_table`].
+///
+/// [`set_locality`]: ../alter_table/struct.IncompleteAlterTableStatement.html#method.set_locality
+/// [`set_locality_regional_by_row_as`]: ../alter_table/struct.IncompleteAlterTableStatement.html#method.set_locality_regional_by_row_as
+/// [`alter_table`]: ../alter_table/fn.alter_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetLocality {
+    locality: Locality,
+    region_column: Option<&'static str>,
+}
+
+impl QueryFragment<Pg> for SetLocality {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("SET LOCALITY ");
+        self.locality.walk_ast(out.reborrow())?;
+        if let Some(column) = self.region_column {
+            out.push_sql(" AS ");
+            out.push_identifier(column)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> VersionCheck for AlterTableStatement<T, SetLocality> {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        version.require("table localities", 21, 1)
+    }
+}
+
+impl<T> IncompleteAlterTableStatement<T> {
+    /// Change the locality of the table.
+    ///
+    /// Requires CockroachDB v21.1 or later.
+    ///
+    /// ```sql
+    /// ALTER TABLE users SET LOCALITY REGIONAL BY TABLE IN "us-east1";
+    /// ```
+    pub fn set_locality(self, locality: Locality) -> AlterTableStatement<T, SetLocality> {
+        AlterTableStatement::new(
+            self,
+            SetLocality {
+                locality,
+                region_column: None,
+            },
+        )
+    }
+
+    /// Make the table `REGIONAL BY ROW`, using an existing column to store
+    /// the region of each row, instead of the hidden `crdb_region` column.
+    ///
+    /// Requires CockroachDB v21.1 or later.
+    ///
+    /// CockroachDB requires the column to be a `NOT NULL` column of type
+    /// `crdb_internal_region`, so the column must have the SQL type
+    /// [`CrdbInternalRegion`]. A nullable column is rejected when compiling:
+    ///
+    /// ```compile_fail
+    /// # #[macro_use] extern crate diesel;
+    /// # use diesel_cockroach::alter_table::alter_table;
+    /// table! {
+    ///     use diesel::sql_types::*;
+    ///     use diesel_cockroach::sql_types::CrdbInternalRegion;
+    ///
+    ///     users (id) {
+    ///         id -> Int8,
+    ///         home_region -> Nullable<CrdbInternalRegion>,
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// alter_table(users::table).set_locality_regional_by_row_as(users::home_region);
+    /// # }
+    /// ```
+    ///
+    /// ```sql
+    /// ALTER TABLE users SET LOCALITY REGIONAL BY ROW AS home_region;
+    /// ```
+    ///
+    /// [`CrdbInternalRegion`]: ../sql_types/struct.CrdbInternalRegion.html
+    pub fn set_locality_regional_by_row_as<C>(
+        self,
+        column: C,
+    ) -> AlterTableStatement<T, SetLocality>
+    where
+        C: Column<Table = T, SqlType = CrdbInternalRegion>,
+    {
+        let _ = column;
+        AlterTableStatement::new(
+            self,
+            SetLocality {
+                locality: Locality::RegionalByRow,
+                region_column: Some(C::NAME),
+            },
+        )
+    }
+}
+
+/// Creates a `SHOW LOCALITY` statement, loading the locality of the node the
+/// connection is served by.
+///
+/// ```sql
+/// SHOW LOCALITY;
+/// ```
+pub fn show_locality() -> ShowLocalityStatement {
+    ShowLocalityStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW LOCALITY` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-locality.html
+pub struct ShowLocalityStatement;
+
+impl QueryFragment<Pg> for ShowLocalityStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW LOCALITY");
+        Ok(())
+    }
+}
+
+impl Query for ShowLocalityStatement {
+    type SqlType = Text;
+}
+
+impl RunQueryDsl<PgConnection> for ShowLocalityStatement {}
+
+impl QueryId for ShowLocalityStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// The locality of a node, as set by its `--locality` flag.
+///
+/// ```
+/// use diesel_cockroach::{data_types::Region, locality::NodeLocality};
+///
+/// let locality: NodeLocality = "region=us-east1,zone=us-east1b".parse().unwrap();
+/// assert_eq!(locality.region(), Some(Region::new("us-east1")));
+/// assert_eq!(locality.zone(), Some("us-east1b"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, FromSqlRow)]
+pub struct NodeLocality {
+    /// The tiers of the locality, from most to least inclusive, as keys and
+    /// values.
+    pub tiers: Vec<(String, String)>,
+}
+
+impl NodeLocality {
+    /// The value of a tier of the locality.
+    pub fn tier(&self, key: &str) -> Option<&str> {
+        self.tiers
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The region of the node, used by multi-region databases.
+    pub fn region(&self) -> Option<Region> {
+        self.tier("region").map(Region::new)
+    }
+
+    /// The availability zone of the node, from either the `zone` or `az`
+    /// tier.
+    pub fn zone(&self) -> Option<&str> {
+        self.tier("zone").or_else(|| self.tier("az"))
+    }
+}
+
+impl fmt::Display for NodeLocality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, (key, value)) in self.tiers.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for NodeLocality {
+    type Err = InvalidLocality;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tiers = s
+            .split(',')
+            .filter(|tier| !tier.is_empty())
+            .map(|tier| match tier.find('=') {
+                Some(equals) => Ok((tier[..equals].to_owned(), tier[equals + 1..].to_owned())),
+                None => Err(InvalidLocality(s.to_owned())),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(NodeLocality { tiers })
+    }
+}
+
+impl FromSql<Text, Pg> for NodeLocality {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let s = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+/// The error returned when parsing an invalid [`NodeLocality`].
+///
+/// [`NodeLocality`]: struct.NodeLocality.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLocality(pub String);
+
+impl fmt::Display for InvalidLocality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid locality: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidLocality {}
+
+/// Remembers the region of the gateway node of a connection, to avoid
+/// querying it for every routing decision.
+///
+/// A connection is served by the same gateway node for its whole lifetime,
+/// so a cache should be kept alongside each connection, and dropped with it.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::locality::GatewayRegionCache;
+///
+/// let cache = GatewayRegionCache::new();
+/// let region = cache.current_gateway_region(&connection)?;
+/// // Later calls do not query the database again
+/// assert_eq!(cache.current_gateway_region(&connection)?, region);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct GatewayRegionCache {
+    region: Mutex<Option<Option<Region>>>,
+}
+
+impl GatewayRegionCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The region of the gateway node, or `None` if the node has no `region`
+    /// locality tier.
+    ///
+    /// The region is loaded with `SHOW LOCALITY` on the first call only.
+    pub fn current_gateway_region(&self, conn: &PgConnection) -> QueryResult<Option<Region>> {
+        let mut cached = self
+            .region
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(region) = cached.as_ref() {
+            return Ok(region.clone());
+        }
+        let locality: NodeLocality = show_locality().get_result(conn)?;
+        let region = locality.region();
+        *cached = Some(region.clone());
+        Ok(region)
+    }
+
+    /// Forget the cached region, so it is loaded again on the next call.
+    pub fn clear(&self) {
+        *self
+            .region
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alter_table::alter_table;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::sql_types::CrdbInternalRegion;
+
+        users (id) {
+            id -> Int8,
+            home_region -> CrdbInternalRegion,
+        }
+    }
+
+    #[test]
+    fn set_locality() {
+        for (locality, expected) in [
+            (Locality::Global, "GLOBAL"),
+            (
+                Locality::RegionalByTable(None),
+                "REGIONAL BY TABLE IN PRIMARY REGION",
+            ),
+            (
+                Locality::RegionalByTable(Some(Region::new("us-east1"))),
+                r#"REGIONAL BY TABLE IN "us-east1""#,
+            ),
+            (Locality::RegionalByRow, "REGIONAL BY ROW"),
+        ] {
+            assert_eq!(
+                diesel::debug_query(&alter_table(users::table).set_locality(locality)).to_string(),
+                format!(
+                    r#"ALTER TABLE "users" SET LOCALITY {} -- binds: []"#,
+                    expected
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn set_locality_version() {
+        let statement = alter_table(users::table).set_locality(Locality::Global);
+        assert!(ClusterVersion::new(21, 1, 0).check(&statement).is_ok());
+        assert_eq!(
+            ClusterVersion::new(20, 2, 9).check(&statement).unwrap_err(),
+            Unsupported {
+                feature: "table localities",
+                required: ClusterVersion::new(21, 1, 0),
+                version: ClusterVersion::new(20, 2, 9),
+            }
+        );
+    }
+
+    #[test]
+    fn regional_by_row_as() {
+        assert_eq!(
+            diesel::debug_query(
+                &alter_table(users::table).set_locality_regional_by_row_as(users::home_region)
+            )
+            .to_string(),
+            r#"ALTER TABLE "users" SET LOCALITY REGIONAL BY ROW AS "home_region" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show_locality() {
+        assert_eq!(
+            diesel::debug_query(&super::show_locality()).to_string(),
+            r#"SHOW LOCALITY -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn node_locality() {
+        let locality: NodeLocality = "region=us-east1,az=us-east1b,dc=dc1".parse().unwrap();
+        assert_eq!(locality.region(), Some(Region::new("us-east1")));
+        assert_eq!(locality.zone(), Some("us-east1b"));
+        assert_eq!(locality.tier("dc"), Some("dc1"));
+        assert_eq!(locality.to_string(), "region=us-east1,az=us-east1b,dc=dc1");
+        assert_eq!("".parse(), Ok(NodeLocality::default()));
+        assert_eq!(NodeLocality::default().region(), None);
+        assert_eq!(
+            "region".parse::<NodeLocality>(),
+            Err(InvalidLocality("region".to_owned()))
+        );
+    }
+
+    #[test]
+    fn parse_locality() {
+        assert_eq!("GLOBAL".parse(), Ok(Locality::Global));
+        assert_eq!(
+            "REGIONAL BY TABLE IN PRIMARY REGION".parse(),
+            Ok(Locality::RegionalByTable(None))
+        );
+        assert_eq!(
+            r#"REGIONAL BY TABLE IN "us-east1""#.parse(),
+            Ok(Locality::RegionalByTable(Some(Region::new("us-east1"))))
+        );
+        assert_eq!(
+            "REGIONAL BY ROW AS home_region".parse(),
+            Ok(Locality::RegionalByRow)
+        );
+        assert!("EVERYWHERE".parse::<Locality>().is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! Support for using this crate's statement builders in Rust migrations.
+//!
+//! Diesel migrations run against a `SimpleConnection`, so statements are
+//! rendered to SQL before running. Statements must not contain any bind
+//! parameters, which most CockroachDB DDL statements do not accept anyway,
+//! and building a step from a statement with binds is an error.
+//!
+//! ```no_run
+//! # #[macro_use] extern crate diesel;
+//! use diesel::migration::Migration;
+//! use diesel_cockroach::{
+//!     index::create_index,
+//!     migrations::{CockroachMigration, MigrationStep},
+//! };
+//!
+//! table! {
+//!     events (id) {
+//!         id -> Int8,
+//!         ts -> Timestamptz,
+//!     }
+//! }
+//!
+//! struct AddEventsTsIndex;
+//!
+//! impl CockroachMigration for AddEventsTsIndex {
+//!     fn version(&self) -> &str {
+//!         "20210101000000"
+//!     }
+//!
+//!     fn steps(&self) -> diesel::QueryResult<Vec<MigrationStep>> {
+//!         Ok(vec![MigrationStep::reversible(
+//!             &create_index(events::table, events::ts).name("events_ts_idx"),
+//!         )?])
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let migration = AddEventsTsIndex.into_migration();
+//! # let _ = migration.version();
+//! # }
+//! ```
+
+use crate::quote;
+use diesel::{
+    connection::SimpleConnection,
+    migration::{Migration, RunMigrationsError},
+    pg::Pg,
+    query_builder::QueryFragment,
+    result::{Error, QueryResult},
+};
+
+/// A statement which can generate the statement undoing it.
+pub trait Reversible: QueryFragment<Pg> {
+    /// The SQL undoing this statement, or `None` if it cannot be determined
+    /// from the statement alone.
+    fn reverse_sql(&self) -> QueryResult<Option<String>>;
+}
+
+/// A single step of a migration, with the SQL to apply and revert it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    up: String,
+    down: Option<String>,
+}
+
+impl MigrationStep {
+    /// A step running `statement`, which cannot be reverted.
+    pub fn new<S: QueryFragment<Pg>>(statement: &S) -> QueryResult<Self> {
+        Ok(Self::sql(quote::render_unbound(statement)?, None))
+    }
+
+    /// A step running `statement`, reverted by the statement it generates.
+    ///
+    /// The step cannot be reverted if the statement cannot be reversed, such
+    /// as an unnamed index.
+    pub fn reversible<S: Reversible>(statement: &S) -> QueryResult<Self> {
+        Ok(Self::sql(
+            quote::render_unbound(statement)?,
+            statement.reverse_sql()?,
+        ))
+    }
+
+    /// A step running raw SQL, optionally reverted by other raw SQL.
+    pub fn sql(up: impl Into<String>, down: Option<String>) -> Self {
+        MigrationStep {
+            up: up.into(),
+            down,
+        }
+    }
+
+    /// Revert the step by running `statement`.
+    pub fn down<S: QueryFragment<Pg>>(mut self, statement: &S) -> QueryResult<Self> {
+        self.down = Some(quote::render_unbound(statement)?);
+        Ok(self)
+    }
+
+    /// The SQL applying this step.
+    pub fn up_sql(&self) -> &str {
+        &self.up
+    }
+
+    /// The SQL reverting this step, if it can be reverted.
+    pub fn down_sql(&self) -> Option<&str> {
+        self.down.as_deref()
+    }
+}
+
+/// A migration built from this crate's statement builders.
+///
+/// Convert it to a Diesel `Migration` with [`into_migration`], to run it with
+/// `diesel_migrations`.
+///
+/// Diesel runs each migration in a transaction. CockroachDB only commits
+/// schema changes when the transaction commits, and some schema changes
+/// cannot be combined in one transaction, so such changes should be split
+/// into separate migrations.
+///
+/// [`into_migration`]: #method.into_migration
+pub trait CockroachMigration {
+    /// The version of the migration, such as `20210101000000`.
+    fn version(&self) -> &str;
+
+    /// The steps of the migration, in the order they are applied.
+    fn steps(&self) -> QueryResult<Vec<MigrationStep>>;
+
+    /// Convert into a Diesel `Migration`.
+    fn into_migration(self) -> Box<dyn Migration>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(RustMigration(self))
+    }
+}
+
+/// A Diesel `Migration` running a [`CockroachMigration`].
+///
+/// Reverting runs the down SQL of each step in reverse order, and fails
+/// without running anything if any step cannot be reverted.
+///
+/// [`CockroachMigration`]: trait.CockroachMigration.html
+#[derive(Debug, Clone)]
+pub struct RustMigration<M>(pub M);
+
+impl<M> Migration for RustMigration<M>
+where
+    M: CockroachMigration,
+{
+    fn version(&self) -> &str {
+        self.0.version()
+    }
+
+    fn run(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        let steps = self.0.steps()?;
+        if steps.is_empty() {
+            return Err(RunMigrationsError::EmptyMigration);
+        }
+        for step in &steps {
+            conn.batch_execute(&step.up)?;
+        }
+        Ok(())
+    }
+
+    fn revert(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        let steps = self.0.steps()?;
+        let down = steps
+            .iter()
+            .rev()
+            .map(|step| {
+                step.down_sql().ok_or_else(|| {
+                    Error::QueryBuilderError(
+                        format!("Migration step cannot be reverted: {}", step.up).into(),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for sql in down {
+            conn.batch_execute(sql)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alter_table::alter_table, create_table::create_table_as, index::create_index,
+        sequence::create_sequence, storage_params::StorageParams,
+    };
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+    use std::{cell::RefCell, time::Duration};
+
+    table! {
+        events (id) {
+            id -> Int8,
+            ts -> Timestamptz,
+            kind -> Text,
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingConnection(RefCell<Vec<String>>);
+
+    impl SimpleConnection for RecordingConnection {
+        fn batch_execute(&self, query: &str) -> QueryResult<()> {
+            self.0.borrow_mut().push(query.to_owned());
+            Ok(())
+        }
+    }
+
+    struct TestMigration(Vec<MigrationStep>);
+
+    impl CockroachMigration for TestMigration {
+        fn version(&self) -> &str {
+            "20210101000000"
+        }
+
+        fn steps(&self) -> QueryResult<Vec<MigrationStep>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn reverse_sql<S: Reversible>(statement: S) -> Option<String> {
+        statement.reverse_sql().unwrap()
+    }
+
+    #[test]
+    fn reverse() {
+        assert_eq!(
+            reverse_sql(create_index(events::table, events::ts).name("events_ts_idx")),
+            Some(r#"DROP INDEX "events"@"events_ts_idx""#.to_owned())
+        );
+        assert_eq!(reverse_sql(create_index(events::table, events::ts)), None);
+        assert_eq!(
+            reverse_sql(create_sequence("events_seq")),
+            Some(r#"DROP SEQUENCE "events_seq""#.to_owned())
+        );
+        assert_eq!(
+            reverse_sql(create_table_as(
+                "event_kinds",
+                events::table.select(events::kind)
+            )),
+            Some(r#"DROP TABLE "event_kinds""#.to_owned())
+        );
+        assert_eq!(
+            reverse_sql(
+                alter_table(events::table).set_storage_params(
+                    StorageParams::new()
+                        .ttl_expire_after(Duration::from_secs(86400))
+                        .ttl_job_cron("@hourly")
+                )
+            ),
+            Some(r#"ALTER TABLE "events" RESET (ttl_expire_after, ttl_job_cron)"#.to_owned())
+        );
+        assert_eq!(
+            reverse_sql(
+                alter_table(events::table)
+                    .add_unique_without_index("events_kind_key", events::kind)
+            ),
+            Some(r#"ALTER TABLE "events" DROP CONSTRAINT "events_kind_key""#.to_owned())
+        );
+    }
+
+    #[test]
+    fn binds() {
+        let statement = create_table_as(
+            "login_events",
+            events::table
+                .select(events::id)
+                .filter(events::kind.eq("login")),
+        );
+        assert!(MigrationStep::new(&statement).is_err());
+        assert!(MigrationStep::reversible(&statement).is_err());
+        assert!(MigrationStep::sql("SELECT 1", None)
+            .down(&statement)
+            .is_err());
+        let statement = create_table_as("event_kinds", events::table.select(events::kind));
+        assert!(MigrationStep::reversible(&statement).is_ok());
+    }
+
+    #[test]
+    fn run_and_revert() {
+        let migration = TestMigration(vec![
+            MigrationStep::reversible(&create_sequence("events_seq")).unwrap(),
+            MigrationStep::reversible(
+                &create_index(events::table, events::ts).name("events_ts_idx"),
+            )
+            .unwrap(),
+        ])
+        .into_migration();
+
+        let conn = RecordingConnection::default();
+        migration.run(&conn).unwrap();
+        migration.revert(&conn).unwrap();
+        assert_eq!(
+            conn.0.into_inner(),
+            vec![
+                r#"CREATE SEQUENCE "events_seq""#,
+                r#"CREATE INDEX "events_ts_idx" ON "events" ("ts")"#,
+                r#"DROP INDEX "events"@"events_ts_idx""#,
+                r#"DROP SEQUENCE "events_seq""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn revert_irreversible() {
+        let migration = TestMigration(vec![
+            MigrationStep::reversible(&create_sequence("events_seq")).unwrap(),
+            MigrationStep::new(&create_index(events::table, events::ts)).unwrap(),
+        ])
+        .into_migration();
+
+        let conn = RecordingConnection::default();
+        match migration.revert(&conn) {
+            Err(RunMigrationsError::QueryError(Error::QueryBuilderError(error))) => assert_eq!(
+                error.to_string(),
+                r#"Migration step cannot be reverted: CREATE INDEX ON "events" ("ts")"#
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(conn.0.into_inner().is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+//! Support for `SHOW PARTITIONS` SQL queries.
+
+use crate::quote;
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{Nullable, Text},
+    QueryableByName,
+};
+
+/// Creates a `SHOW PARTITIONS FROM TABLE` statement for the target table.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection};
+/// # table! { users (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::partitions::show_partitions_from_table;
+///
+/// let partitions = show_partitions_from_table(users::table).load(&connection)?;
+/// assert!(partitions.iter().any(|partition| partition.partition_name == "us_east"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_partitions_from_table<T>(target: T) -> ShowPartitionsStatement<TablePartitions<T>> {
+    ShowPartitionsStatement(TablePartitions(target))
+}
+
+/// Creates a `SHOW PARTITIONS FROM INDEX` statement for the named index on
+/// the target table.
+pub fn show_partitions_from_index<T>(
+    target: T,
+    name: impl Into<String>,
+) -> ShowPartitionsStatement<IndexPartitions<T>> {
+    ShowPartitionsStatement(IndexPartitions(target, name.into()))
+}
+
+/// Creates a `SHOW PARTITIONS FROM DATABASE` statement for the named database.
+pub fn show_partitions_from_database(
+    name: impl Into<String>,
+) -> ShowPartitionsStatement<DatabasePartitions> {
+    ShowPartitionsStatement(DatabasePartitions(name.into()))
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW PARTITIONS` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `S`: The table, index or database whose partitions are shown
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-partitions.html
+pub struct ShowPartitionsStatement<S>(S);
+
+impl<S> ShowPartitionsStatement<S>
+where
+    S: QueryFragment<Pg>,
+{
+    /// Load the partitions.
+    pub fn load(&self, conn: &PgConnection) -> QueryResult<Vec<Partition>> {
+        diesel::sql_query(quote::render(self)?).load(conn)
+    }
+}
+
+impl<S> QueryFragment<Pg> for ShowPartitionsStatement<S>
+where
+    S: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW PARTITIONS FROM ");
+        self.0.walk_ast(out.reborrow())
+    }
+}
+
+impl<S> QueryId for ShowPartitionsStatement<S> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A table to show partitions of, created by [`show_partitions_from_table`].
+///
+/// [`show_partitions_from_table`]: fn.show_partitions_from_table.html
+#[derive(Debug, Clone, Copy)]
+pub struct TablePartitions<T>(T);
+
+impl<T> QueryFragment<Pg> for TablePartitions<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("TABLE ");
+        self.0.from_clause().walk_ast(out.reborrow())
+    }
+}
+
+/// An index to show partitions of, created by [`show_partitions_from_index`].
+///
+/// [`show_partitions_from_index`]: fn.show_partitions_from_index.html
+#[derive(Debug, Clone)]
+pub struct IndexPartitions<T>(T, String);
+
+impl<T> QueryFragment<Pg> for IndexPartitions<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("INDEX ");
+        self.0.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql("@");
+        out.push_identifier(&self.1)
+    }
+}
+
+/// A database to show partitions of, created by [`show_partitions_from_database`].
+///
+/// [`show_partitions_from_database`]: fn.show_partitions_from_database.html
+#[derive(Debug, Clone)]
+pub struct DatabasePartitions(String);
+
+impl QueryFragment<Pg> for DatabasePartitions {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("DATABASE ");
+        out.push_identifier(&self.0)
+    }
+}
+
+/// A row returned by `SHOW PARTITIONS`.
+#[derive(Debug, Clone, PartialEq, Eq, QueryableByName)]
+pub struct Partition {
+    /// The database of the partitioned table.
+    #[sql_type = "Text"]
+    pub database_name: String,
+    /// The partitioned table.
+    #[sql_type = "Text"]
+    pub table_name: String,
+    /// The name of the partition.
+    #[sql_type = "Text"]
+    pub partition_name: String,
+    /// The partition this one subpartitions, if any.
+    #[sql_type = "Nullable<Text>"]
+    pub parent_partition: Option<String>,
+    /// The columns the partition is defined on, separated by commas.
+    #[sql_type = "Text"]
+    pub column_names: String,
+    /// The partitioned index, such as `users@users_pkey`.
+    #[sql_type = "Text"]
+    pub index_name: String,
+    /// The values in the partition, such as `('us-east1')`.
+    #[sql_type = "Text"]
+    pub partition_value: String,
+    /// The zone configuration set directly on the partition, if any.
+    #[sql_type = "Nullable<Text>"]
+    pub zone_config: Option<String>,
+    /// The full zone configuration applying to the partition, including
+    /// inherited values.
+    #[sql_type = "Nullable<Text>"]
+    pub full_zone_config: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        users (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn show_partitions() {
+        assert_eq!(
+            diesel::debug_query(&show_partitions_from_table(users::table)).to_string(),
+            r#"SHOW PARTITIONS FROM TABLE "users" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_partitions_from_index(users::table, "users_pkey"))
+                .to_string(),
+            r#"SHOW PARTITIONS FROM INDEX "users"@"users_pkey" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_partitions_from_database("movr")).to_string(),
+            r#"SHOW PARTITIONS FROM DATABASE "movr" -- binds: []"#
+        );
+    }
+}
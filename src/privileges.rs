@@ -0,0 +1,864 @@
+//! Support for `ALTER DEFAULT PRIVILEGES`, `SHOW DEFAULT PRIVILEGES` and
+//! `SHOW GRANTS` SQL queries.
+
+use crate::{
+    quote,
+    version::{ClusterVersion, Unsupported, VersionCheck},
+};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{Bool, Nullable, Text},
+    Queryable,
+};
+use std::str::FromStr;
+
+/// A privilege on a database object.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/security-reference/authorization.html#supported-privileges
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Privilege {
+    /// `ALL`
+    All,
+    /// `CREATE`
+    Create,
+    /// `DROP`
+    Drop,
+    /// `SELECT`
+    Select,
+    /// `INSERT`
+    Insert,
+    /// `UPDATE`
+    Update,
+    /// `DELETE`
+    Delete,
+    /// `USAGE`
+    Usage,
+    /// `EXECUTE`
+    Execute,
+    /// `ZONECONFIG`
+    ZoneConfig,
+    /// `CHANGEFEED`
+    Changefeed,
+    /// Any other privilege, by keyword.
+    Other(String),
+}
+
+impl Privilege {
+    /// The SQL keyword for this privilege.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Privilege::All => "ALL",
+            Privilege::Create => "CREATE",
+            Privilege::Drop => "DROP",
+            Privilege::Select => "SELECT",
+            Privilege::Insert => "INSERT",
+            Privilege::Update => "UPDATE",
+            Privilege::Delete => "DELETE",
+            Privilege::Usage => "USAGE",
+            Privilege::Execute => "EXECUTE",
+            Privilege::ZoneConfig => "ZONECONFIG",
+            Privilege::Changefeed => "CHANGEFEED",
+            Privilege::Other(keyword) => keyword,
+        }
+    }
+}
+
+impl FromStr for Privilege {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "ALL" => Privilege::All,
+            "CREATE" => Privilege::Create,
+            "DROP" => Privilege::Drop,
+            "SELECT" => Privilege::Select,
+            "INSERT" => Privilege::Insert,
+            "UPDATE" => Privilege::Update,
+            "DELETE" => Privilege::Delete,
+            "USAGE" => Privilege::Usage,
+            "EXECUTE" => Privilege::Execute,
+            "ZONECONFIG" => Privilege::ZoneConfig,
+            "CHANGEFEED" => Privilege::Changefeed,
+            other => Privilege::Other(other.to_owned()),
+        })
+    }
+}
+
+/// The kind of object default privileges apply to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefaultPrivilegeObject {
+    /// `TABLES`, which also covers views and sequences.
+    Tables,
+    /// `SEQUENCES`
+    Sequences,
+    /// `SCHEMAS`
+    Schemas,
+    /// `TYPES`
+    Types,
+    /// `FUNCTIONS`
+    Functions,
+}
+
+impl DefaultPrivilegeObject {
+    /// The SQL keyword for this kind of object.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DefaultPrivilegeObject::Tables => "TABLES",
+            DefaultPrivilegeObject::Sequences => "SEQUENCES",
+            DefaultPrivilegeObject::Schemas => "SCHEMAS",
+            DefaultPrivilegeObject::Types => "TYPES",
+            DefaultPrivilegeObject::Functions => "FUNCTIONS",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Creators {
+    CurrentRole,
+    Role(String),
+    AllRoles,
+}
+
+/// The roles and schema whose new objects default privileges apply to.
+#[derive(Debug, Clone)]
+struct DefaultPrivilegesScope {
+    creators: Creators,
+    schema: Option<String>,
+}
+
+impl DefaultPrivilegesScope {
+    fn new() -> Self {
+        DefaultPrivilegesScope {
+            creators: Creators::CurrentRole,
+            schema: None,
+        }
+    }
+
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        match &self.creators {
+            Creators::CurrentRole => {}
+            Creators::Role(role) => {
+                out.push_sql(" FOR ROLE ");
+                out.push_identifier(role)?;
+            }
+            Creators::AllRoles => out.push_sql(" FOR ALL ROLES"),
+        }
+        if let Some(schema) = &self.schema {
+            out.push_sql(" IN SCHEMA ");
+            out.push_identifier(schema)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builder methods choosing the objects default privileges apply to.
+macro_rules! default_privileges_scope {
+    () => {
+        /// Apply to objects created by the role called `role`, rather than
+        /// by the current role.
+        pub fn for_role(mut self, role: impl Into<String>) -> Self {
+            self.scope.creators = Creators::Role(role.into());
+            self
+        }
+
+        /// Apply to objects created by any role.
+        pub fn for_all_roles(mut self) -> Self {
+            self.scope.creators = Creators::AllRoles;
+            self
+        }
+
+        /// Only apply to objects created in the schema called `schema`.
+        pub fn in_schema(mut self, schema: impl Into<String>) -> Self {
+            self.scope.schema = Some(schema.into());
+            self
+        }
+    };
+}
+
+/// Creates an `ALTER DEFAULT PRIVILEGES` statement, changing the privileges
+/// granted on objects created in future.
+///
+/// ```sql
+/// ALTER DEFAULT PRIVILEGES FOR ALL ROLES GRANT SELECT ON TABLES TO analyst;
+/// ```
+///
+/// Requires CockroachDB v21.2 or later.
+pub fn alter_default_privileges() -> IncompleteAlterDefaultPrivilegesStatement {
+    IncompleteAlterDefaultPrivilegesStatement {
+        scope: DefaultPrivilegesScope::new(),
+    }
+}
+
+/// The structure returned by [`alter_default_privileges`].
+///
+/// The provided methods [`grant`] and [`revoke`] choose the privileges to
+/// change.
+///
+/// [`alter_default_privileges`]: fn.alter_default_privileges.html
+/// [`grant`]: #method.grant
+/// [`revoke`]: #method.revoke
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+pub struct IncompleteAlterDefaultPrivilegesStatement {
+    scope: DefaultPrivilegesScope,
+}
+
+impl IncompleteAlterDefaultPrivilegesStatement {
+    default_privileges_scope!();
+
+    /// Grant privileges on new objects to the role called `grantee`.
+    pub fn grant(
+        self,
+        privileges: Vec<Privilege>,
+        object: DefaultPrivilegeObject,
+        grantee: impl Into<String>,
+    ) -> AlterDefaultPrivilegesStatement {
+        AlterDefaultPrivilegesStatement::new(self, true, privileges, object, grantee.into())
+    }
+
+    /// Stop granting privileges on new objects to the role called `grantee`.
+    pub fn revoke(
+        self,
+        privileges: Vec<Privilege>,
+        object: DefaultPrivilegeObject,
+        grantee: impl Into<String>,
+    ) -> AlterDefaultPrivilegesStatement {
+        AlterDefaultPrivilegesStatement::new(self, false, privileges, object, grantee.into())
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `ALTER DEFAULT PRIVILEGES` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-default-privileges.html
+pub struct AlterDefaultPrivilegesStatement {
+    scope: DefaultPrivilegesScope,
+    grant: bool,
+    privileges: Vec<Privilege>,
+    object: DefaultPrivilegeObject,
+    grantee: String,
+    grant_option: bool,
+}
+
+impl AlterDefaultPrivilegesStatement {
+    fn new(
+        incomplete: IncompleteAlterDefaultPrivilegesStatement,
+        grant: bool,
+        privileges: Vec<Privilege>,
+        object: DefaultPrivilegeObject,
+        grantee: String,
+    ) -> Self {
+        AlterDefaultPrivilegesStatement {
+            scope: incomplete.scope,
+            grant,
+            privileges,
+            object,
+            grantee,
+            grant_option: false,
+        }
+    }
+
+    /// When granting, also allow the grantee to grant the privileges to
+    /// others. When revoking, only revoke the ability to grant them.
+    pub fn grant_option(mut self) -> Self {
+        self.grant_option = true;
+        self
+    }
+}
+
+impl VersionCheck for AlterDefaultPrivilegesStatement {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        version.require("ALTER DEFAULT PRIVILEGES", 21, 2)
+    }
+}
+
+impl QueryFragment<Pg> for AlterDefaultPrivilegesStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("ALTER DEFAULT PRIVILEGES");
+        self.scope.walk_ast(out.reborrow())?;
+        if self.grant {
+            out.push_sql(" GRANT ");
+        } else if self.grant_option {
+            out.push_sql(" REVOKE GRANT OPTION FOR ");
+        } else {
+            out.push_sql(" REVOKE ");
+        }
+        for (i, privilege) in self.privileges.iter().enumerate() {
+            if i > 0 {
+                out.push_sql(", ");
+            }
+            out.push_sql(privilege.as_str());
+        }
+        out.push_sql(" ON ");
+        out.push_sql(self.object.as_str());
+        out.push_sql(if self.grant { " TO " } else { " FROM " });
+        out.push_identifier(&self.grantee)?;
+        if self.grant && self.grant_option {
+            out.push_sql(" WITH GRANT OPTION");
+        }
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for AlterDefaultPrivilegesStatement {}
+
+impl QueryId for AlterDefaultPrivilegesStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW DEFAULT PRIVILEGES` statement, for objects created by the
+/// current role.
+///
+/// Load the result as [`DefaultPrivilege`] rows.
+///
+/// [`DefaultPrivilege`]: struct.DefaultPrivilege.html
+pub fn show_default_privileges() -> ShowDefaultPrivilegesStatement {
+    ShowDefaultPrivilegesStatement {
+        scope: DefaultPrivilegesScope::new(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW DEFAULT PRIVILEGES` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-default-privileges.html
+pub struct ShowDefaultPrivilegesStatement {
+    scope: DefaultPrivilegesScope,
+}
+
+impl ShowDefaultPrivilegesStatement {
+    default_privileges_scope!();
+}
+
+impl QueryFragment<Pg> for ShowDefaultPrivilegesStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT role, for_all_roles, object_type, grantee, privilege_type \
+             FROM [SHOW DEFAULT PRIVILEGES",
+        );
+        self.scope.walk_ast(out.reborrow())?;
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl Query for ShowDefaultPrivilegesStatement {
+    type SqlType = (Nullable<Text>, Bool, Text, Text, Text);
+}
+
+impl RunQueryDsl<PgConnection> for ShowDefaultPrivilegesStatement {}
+
+impl QueryId for ShowDefaultPrivilegesStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A row returned by `SHOW DEFAULT PRIVILEGES`.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct DefaultPrivilege {
+    /// The role whose new objects the privilege applies to, or `None` for all roles.
+    pub role: Option<String>,
+    /// Whether the privilege applies to objects created by any role.
+    pub for_all_roles: bool,
+    /// The kind of object, such as `tables`.
+    pub object_type: String,
+    /// The role granted the privilege.
+    pub grantee: String,
+    /// The privilege granted, such as `SELECT`.
+    pub privilege_type: String,
+}
+
+#[derive(Debug, Clone)]
+enum GrantTarget {
+    Database(String),
+    Schema(String),
+}
+
+/// The grantees and privilege `SHOW GRANTS` is filtered to.
+#[derive(Debug, Clone, Default)]
+struct GrantsFilter {
+    grantees: Vec<String>,
+    privilege: Option<Privilege>,
+}
+
+impl GrantsFilter {
+    fn walk_for(&self, out: &mut AstPass<Pg>) -> QueryResult<()> {
+        for (index, grantee) in self.grantees.iter().enumerate() {
+            out.push_sql(if index == 0 { " FOR " } else { ", " });
+            out.push_identifier(grantee)?;
+        }
+        Ok(())
+    }
+
+    fn walk_where(&self, out: &mut AstPass<Pg>) {
+        if let Some(privilege) = &self.privilege {
+            out.push_sql(" WHERE privilege_type = ");
+            out.push_sql(&quote::string_literal(privilege.as_str()));
+        }
+    }
+}
+
+macro_rules! grants_filter {
+    () => {
+        /// Only show privileges granted to the given role. Can be called
+        /// multiple times to show several roles.
+        pub fn for_role(mut self, grantee: impl Into<String>) -> Self {
+            self.filter.grantees.push(grantee.into());
+            self
+        }
+
+        /// Only show grants of the given privilege.
+        pub fn privilege(mut self, privilege: Privilege) -> Self {
+            self.filter.privilege = Some(privilege);
+            self
+        }
+    };
+}
+
+/// Creates a `SHOW GRANTS ON DATABASE` statement, listing the privileges
+/// granted on a database.
+///
+/// Load the result as [`Grant`] rows.
+///
+/// ```sql
+/// SHOW GRANTS ON DATABASE bookshop;
+/// ```
+///
+/// [`Grant`]: struct.Grant.html
+pub fn show_grants_on_database(name: impl Into<String>) -> ShowGrantsStatement {
+    ShowGrantsStatement {
+        target: GrantTarget::Database(name.into()),
+        filter: GrantsFilter::default(),
+    }
+}
+
+/// Creates a `SHOW GRANTS ON SCHEMA` statement, listing the privileges
+/// granted on a schema.
+///
+/// Load the result as [`Grant`] rows.
+///
+/// ```sql
+/// SHOW GRANTS ON SCHEMA tenant_1;
+/// ```
+///
+/// [`Grant`]: struct.Grant.html
+pub fn show_grants_on_schema(name: impl Into<String>) -> ShowGrantsStatement {
+    ShowGrantsStatement {
+        target: GrantTarget::Schema(name.into()),
+        filter: GrantsFilter::default(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW GRANTS` statement for a database or schema.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-grants.html
+pub struct ShowGrantsStatement {
+    target: GrantTarget,
+    filter: GrantsFilter,
+}
+
+impl ShowGrantsStatement {
+    grants_filter!();
+}
+
+impl QueryFragment<Pg> for ShowGrantsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        match &self.target {
+            GrantTarget::Database(name) => {
+                out.push_sql(
+                    "SELECT database_name, NULL::STRING, NULL::STRING, grantee, privilege_type, \
+                     is_grantable FROM [SHOW GRANTS ON DATABASE ",
+                );
+                out.push_identifier(name)?;
+            }
+            GrantTarget::Schema(name) => {
+                out.push_sql(
+                    "SELECT database_name, schema_name, NULL::STRING, grantee, privilege_type, \
+                     is_grantable FROM [SHOW GRANTS ON SCHEMA ",
+                );
+                out.push_identifier(name)?;
+            }
+        }
+        self.filter.walk_for(&mut out)?;
+        out.push_sql("]");
+        self.filter.walk_where(&mut out);
+        Ok(())
+    }
+}
+
+impl Query for ShowGrantsStatement {
+    type SqlType = GrantSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for ShowGrantsStatement {}
+
+impl QueryId for ShowGrantsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW GRANTS ON TABLE` statement, listing the privileges
+/// granted on a table.
+///
+/// Load the result as [`Grant`] rows.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::privileges::{show_grants_on_table, Grant, Privilege};
+///
+/// let grants: Vec<Grant> = show_grants_on_table(books::table)
+///     .privilege(Privilege::Delete)
+///     .load(&connection)?;
+/// for grant in grants {
+///     println!("{} can delete books", grant.grantee);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Grant`]: struct.Grant.html
+pub fn show_grants_on_table<T>(target: T) -> ShowTableGrantsStatement<T> {
+    ShowTableGrantsStatement {
+        target,
+        filter: GrantsFilter::default(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW GRANTS ON TABLE` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table whose grants are shown
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-grants.html
+pub struct ShowTableGrantsStatement<T> {
+    target: T,
+    filter: GrantsFilter,
+}
+
+impl<T> ShowTableGrantsStatement<T> {
+    grants_filter!();
+}
+
+impl<T> QueryFragment<Pg> for ShowTableGrantsStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT database_name, schema_name, table_name, grantee, privilege_type, \
+             is_grantable FROM [SHOW GRANTS ON TABLE ",
+        );
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        self.filter.walk_for(&mut out)?;
+        out.push_sql("]");
+        self.filter.walk_where(&mut out);
+        Ok(())
+    }
+}
+
+impl<T> Query for ShowTableGrantsStatement<T> {
+    type SqlType = GrantSqlType;
+}
+
+impl<T> RunQueryDsl<PgConnection> for ShowTableGrantsStatement<T> {}
+
+impl<T> QueryId for ShowTableGrantsStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A privilege granted on a database object, as returned by `SHOW GRANTS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grant {
+    /// The database of the object.
+    pub database_name: String,
+    /// The schema of the object, or `None` for databases.
+    pub schema_name: Option<String>,
+    /// The table the privilege is granted on, or `None` for databases and
+    /// schemas.
+    pub table_name: Option<String>,
+    /// The role granted the privilege.
+    pub grantee: String,
+    /// The privilege granted.
+    pub privilege: Privilege,
+    /// Whether the grantee can grant the privilege to other roles.
+    pub is_grantable: bool,
+}
+
+impl Queryable<GrantSqlType, Pg> for Grant {
+    type Row = (String, Option<String>, Option<String>, String, String, bool);
+
+    fn build(
+        (database_name, schema_name, table_name, grantee, privilege, is_grantable): Self::Row,
+    ) -> Self {
+        Grant {
+            database_name,
+            schema_name,
+            table_name,
+            grantee,
+            privilege: privilege.parse().unwrap_or_else(|never| match never {}),
+            is_grantable,
+        }
+    }
+}
+
+/// The SQL type of a [`Grant`].
+///
+/// [`Grant`]: struct.Grant.html
+pub type GrantSqlType = (Text, Nullable<Text>, Nullable<Text>, Text, Text, Bool);
+
+/// Creates a `SHOW GRANTS ON ROLE` statement, listing the members of a role.
+///
+/// Load the result as [`RoleMembership`] rows.
+///
+/// ```sql
+/// SHOW GRANTS ON ROLE analyst;
+/// ```
+///
+/// [`RoleMembership`]: struct.RoleMembership.html
+pub fn show_grants_on_role(role: impl Into<String>) -> ShowRoleGrantsStatement {
+    ShowRoleGrantsStatement {
+        roles: vec![role.into()],
+        members: Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW GRANTS ON ROLE` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-grants.html
+pub struct ShowRoleGrantsStatement {
+    roles: Vec<String>,
+    members: Vec<String>,
+}
+
+impl ShowRoleGrantsStatement {
+    /// Also show the members of another role.
+    pub fn and_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+
+    /// Only show whether the given role is a member. Can be called multiple
+    /// times to show several members.
+    pub fn for_member(mut self, member: impl Into<String>) -> Self {
+        self.members.push(member.into());
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowRoleGrantsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT role_name, member, is_admin FROM [SHOW GRANTS ON ROLE ");
+        for (index, role) in self.roles.iter().enumerate() {
+            if index > 0 {
+                out.push_sql(", ");
+            }
+            out.push_identifier(role)?;
+        }
+        for (index, member) in self.members.iter().enumerate() {
+            out.push_sql(if index == 0 { " FOR " } else { ", " });
+            out.push_identifier(member)?;
+        }
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl Query for ShowRoleGrantsStatement {
+    type SqlType = (Text, Text, Bool);
+}
+
+impl RunQueryDsl<PgConnection> for ShowRoleGrantsStatement {}
+
+impl QueryId for ShowRoleGrantsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Membership of a role, as returned by `SHOW GRANTS ON ROLE`.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct RoleMembership {
+    /// The role.
+    pub role_name: String,
+    /// The role or user who is a member of the role.
+    pub member: String,
+    /// Whether the member can manage the membership of the role.
+    pub is_admin: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn alter_default_privileges_grant() {
+        let statement = alter_default_privileges().for_all_roles().grant(
+            vec![Privilege::Select, Privilege::Insert],
+            DefaultPrivilegeObject::Tables,
+            "analyst",
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER DEFAULT PRIVILEGES FOR ALL ROLES GRANT SELECT, INSERT ON TABLES TO "analyst" -- binds: []"#
+        );
+        let statement = alter_default_privileges()
+            .for_role("owner")
+            .in_schema("tenant_1")
+            .grant(vec![Privilege::Usage], DefaultPrivilegeObject::Types, "app")
+            .grant_option();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER DEFAULT PRIVILEGES FOR ROLE "owner" IN SCHEMA "tenant_1" GRANT USAGE ON TYPES TO "app" WITH GRANT OPTION -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn alter_default_privileges_version() {
+        let statement = alter_default_privileges().grant(
+            vec![Privilege::Select],
+            DefaultPrivilegeObject::Tables,
+            "analyst",
+        );
+        assert!(ClusterVersion::new(21, 2, 0).check(&statement).is_ok());
+        assert_eq!(
+            ClusterVersion::new(21, 1, 11)
+                .check(&statement)
+                .unwrap_err()
+                .to_string(),
+            "ALTER DEFAULT PRIVILEGES is not supported on CockroachDB v21.1.11, and requires v21.2 or later"
+        );
+    }
+
+    #[test]
+    fn alter_default_privileges_revoke() {
+        let statement = alter_default_privileges().revoke(
+            vec![Privilege::All],
+            DefaultPrivilegeObject::Sequences,
+            "app",
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER DEFAULT PRIVILEGES REVOKE ALL ON SEQUENCES FROM "app" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&statement.grant_option()).to_string(),
+            r#"ALTER DEFAULT PRIVILEGES REVOKE GRANT OPTION FOR ALL ON SEQUENCES FROM "app" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show() {
+        assert_eq!(
+            diesel::debug_query(&show_default_privileges()).to_string(),
+            r#"SELECT role, for_all_roles, object_type, grantee, privilege_type FROM [SHOW DEFAULT PRIVILEGES] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(
+                &show_default_privileges()
+                    .for_role("owner")
+                    .in_schema("tenant_1")
+            )
+            .to_string(),
+            r#"SELECT role, for_all_roles, object_type, grantee, privilege_type FROM [SHOW DEFAULT PRIVILEGES FOR ROLE "owner" IN SCHEMA "tenant_1"] -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show_grants() {
+        assert_eq!(
+            diesel::debug_query(&show_grants_on_database("bookshop")).to_string(),
+            r#"SELECT database_name, NULL::STRING, NULL::STRING, grantee, privilege_type, is_grantable FROM [SHOW GRANTS ON DATABASE "bookshop"] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_grants_on_schema("tenant_1").for_role("app")).to_string(),
+            r#"SELECT database_name, schema_name, NULL::STRING, grantee, privilege_type, is_grantable FROM [SHOW GRANTS ON SCHEMA "tenant_1" FOR "app"] -- binds: []"#
+        );
+        let statement = show_grants_on_table(books::table)
+            .for_role("app")
+            .for_role("analyst")
+            .privilege(Privilege::Select);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"SELECT database_name, schema_name, table_name, grantee, privilege_type, is_grantable FROM [SHOW GRANTS ON TABLE "books" FOR "app", "analyst"] WHERE privilege_type = 'SELECT' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show_role_grants() {
+        assert_eq!(
+            diesel::debug_query(&show_grants_on_role("analyst")).to_string(),
+            r#"SELECT role_name, member, is_admin FROM [SHOW GRANTS ON ROLE "analyst"] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(
+                &show_grants_on_role("analyst")
+                    .and_role("writer")
+                    .for_member("alice")
+            )
+            .to_string(),
+            r#"SELECT role_name, member, is_admin FROM [SHOW GRANTS ON ROLE "analyst", "writer" FOR "alice"] -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn privilege_from_str() {
+        assert_eq!("ZONECONFIG".parse(), Ok(Privilege::ZoneConfig));
+        assert_eq!(
+            "VIEWACTIVITY".parse(),
+            Ok(Privilege::Other("VIEWACTIVITY".to_owned()))
+        );
+    }
+}
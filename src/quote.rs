@@ -0,0 +1,158 @@
+//! Quoting of identifiers and string literals in generated SQL.
+//!
+//! Most CockroachDB DDL statements do not accept bind parameters, so values
+//! such as comments and URIs have to be rendered as escaped literals.
+
+use diesel::{
+    pg::{Pg, PgQueryBuilder},
+    query_builder::{AstPass, QueryBuilder, QueryFragment},
+    result::{Error, QueryResult},
+};
+use std::time::Duration;
+
+/// Quotes an identifier, such as a table or column name.
+pub(crate) fn identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Quotes a string literal.
+pub(crate) fn string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Checks that `name` is a dotted path of names such as
+/// `kv.rangefeed.enabled`, so it can be rendered unquoted.
+pub(crate) fn setting_name(name: &str) -> QueryResult<&str> {
+    let valid = name.split('.').all(|part| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    });
+    if valid {
+        Ok(name)
+    } else {
+        Err(Error::QueryBuilderError(
+            format!("Invalid cluster setting name: {}", name).into(),
+        ))
+    }
+}
+
+/// Renders a query fragment to SQL, so it can be embedded in a literal.
+///
+/// Any bind parameters of the fragment are not rendered.
+pub(crate) fn render<T: QueryFragment<Pg>>(fragment: &T) -> QueryResult<String> {
+    let mut query_builder = PgQueryBuilder::new();
+    fragment.to_sql(&mut query_builder)?;
+    Ok(query_builder.finish())
+}
+
+/// Renders a query fragment to SQL, to be run without any bind parameters.
+///
+/// Fragments with bind parameters are an error, as their placeholders would
+/// be left in the SQL.
+pub(crate) fn render_unbound<T: QueryFragment<Pg>>(fragment: &T) -> QueryResult<String> {
+    let sql = render(fragment)?;
+    // The binds are only exposed through the `Debug` output of the query.
+    let debug = format!("{:?}", diesel::debug_query::<Pg, _>(fragment));
+    if debug.ends_with(", binds: [] }") {
+        Ok(sql)
+    } else {
+        Err(Error::QueryBuilderError(
+            format!("Statement cannot be run without binds: {}", sql).into(),
+        ))
+    }
+}
+
+/// Renders a ` WITH name = 'value', flag` clause of options, or nothing if
+/// there are no options.
+pub(crate) fn push_options(
+    out: &mut AstPass<Pg>,
+    options: &[(&str, Option<String>)],
+) -> QueryResult<()> {
+    push_options_clause(out, " WITH ", options)
+}
+
+/// Renders a clause of options starting with `keyword`, or nothing if there
+/// are no options.
+///
+/// Option names are rendered unquoted, so names which are not a lower case
+/// identifier are an error.
+pub(crate) fn push_options_clause(
+    out: &mut AstPass<Pg>,
+    keyword: &str,
+    options: &[(&str, Option<String>)],
+) -> QueryResult<()> {
+    for (i, (name, value)) in options.iter().enumerate() {
+        let name = option_name(name)?;
+        out.push_sql(if i == 0 { keyword } else { ", " });
+        out.push_sql(name);
+        if let Some(value) = value {
+            out.push_sql(" = ");
+            out.push_sql(&string_literal(value));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `name` is a lower case identifier such as `ttl_pause`, so it
+/// can be rendered unquoted as the name of an option or storage parameter.
+pub(crate) fn option_name(name: &str) -> QueryResult<&str> {
+    if is_option_name(name) {
+        Ok(name)
+    } else {
+        Err(Error::QueryBuilderError(
+            format!("Invalid option name: {}", name).into(),
+        ))
+    }
+}
+
+fn is_option_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Renders a duration in the largest unit which represents it exactly.
+pub(crate) fn interval(duration: Duration) -> String {
+    let micros = duration.as_micros();
+    if micros % 1_000_000 == 0 {
+        format!("{}s", micros / 1_000_000)
+    } else if micros % 1_000 == 0 {
+        format!("{}ms", micros / 1_000)
+    } else {
+        format!("{}us", micros)
+    }
+}
+
+/// Parses a duration in the format of Go, as used by CockroachDB in
+/// settings and statement output, such as `1h2m3.5s` or `12µs`.
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    if value == "0" {
+        return Some(Duration::from_secs(0));
+    }
+    let mut rest = value;
+    let mut total = Duration::from_secs(0);
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let number: f64 = rest[..number_end].parse().ok()?;
+        rest = &rest[number_end..];
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let nanos_per_unit = match &rest[..unit_end] {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return None,
+        };
+        rest = &rest[unit_end..];
+        total += Duration::from_nanos((number * nanos_per_unit).round() as u64);
+    }
+    Some(total)
+}
@@ -0,0 +1,217 @@
+//! Expressions converting between table names and descriptor IDs.
+//!
+//! CockroachDB identifies each table, view and sequence by a descriptor ID,
+//! which `crdb_internal` tables, jobs and range keys refer to instead of its
+//! name. Casting a name to `REGCLASS` looks up its descriptor ID, and casting
+//! a descriptor ID to `REGCLASS` looks up its name:
+//!
+//! ```no_run
+//! # #[macro_use] extern crate diesel;
+//! # use diesel::{pg::PgConnection, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+//! # table! { books (id) { id -> Int8, title -> Text, } }
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::{crdb_internal::table_indexes, regclass::descriptor_id_of};
+//!
+//! let indexes: Vec<String> = table_indexes::table
+//!     .select(table_indexes::index_name)
+//!     .filter(table_indexes::descriptor_id.eq(descriptor_id_of(books::table)))
+//!     .load(&connection)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Indexes are not descriptors, and are instead identified by the descriptor
+//! ID of their table and an index ID, as listed in
+//! [`crdb_internal.table_indexes`].
+//!
+//! See the [CockroachDB docs].
+//!
+//! [`crdb_internal.table_indexes`]: ../crdb_internal/table_indexes/index.html
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/oid.html
+
+use crate::quote;
+use diesel::{
+    expression::{AppearsOnTable, AsExpression, Expression, NonAggregate, SelectableExpression},
+    pg::Pg,
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{BigInt, Text},
+};
+
+/// The descriptor ID of the table named by `name`, as `name::REGCLASS::INT8`.
+///
+/// The name is resolved like a table name in a query, so may be qualified by
+/// a schema, and must be quoted if it is not lower case. Resolving a table
+/// which does not exist is an error.
+pub fn descriptor_id<T>(name: T) -> DescriptorId<T::Expression>
+where
+    T: AsExpression<Text>,
+{
+    DescriptorId(name.as_expression())
+}
+
+/// The descriptor ID of `table`, such as `users::table`.
+pub fn descriptor_id_of<T>(table: T) -> DescriptorId<TableName<T>> {
+    DescriptorId(TableName(table))
+}
+
+/// The name of the table with descriptor ID `id`, as `id::REGCLASS::STRING`.
+///
+/// The name is quoted and qualified by its schema where needed, so can be
+/// used in SQL as is.
+pub fn descriptor_name<T>(id: T) -> DescriptorName<T::Expression>
+where
+    T: AsExpression<BigInt>,
+{
+    DescriptorName(id.as_expression())
+}
+
+/// The expression returned by [`descriptor_id`] and [`descriptor_id_of`].
+///
+/// [`descriptor_id`]: fn.descriptor_id.html
+/// [`descriptor_id_of`]: fn.descriptor_id_of.html
+#[derive(Debug, Clone, Copy)]
+pub struct DescriptorId<E>(E);
+
+impl<E> Expression for DescriptorId<E> {
+    type SqlType = BigInt;
+}
+
+impl<E> QueryFragment<Pg> for DescriptorId<E>
+where
+    E: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("(");
+        self.0.walk_ast(out.reborrow())?;
+        out.push_sql(")::REGCLASS::INT8");
+        Ok(())
+    }
+}
+
+/// The expression returned by [`descriptor_name`].
+///
+/// [`descriptor_name`]: fn.descriptor_name.html
+#[derive(Debug, Clone, Copy)]
+pub struct DescriptorName<E>(E);
+
+impl<E> Expression for DescriptorName<E> {
+    type SqlType = Text;
+}
+
+impl<E> QueryFragment<Pg> for DescriptorName<E>
+where
+    E: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("(");
+        self.0.walk_ast(out.reborrow())?;
+        out.push_sql(")::REGCLASS::STRING");
+        Ok(())
+    }
+}
+
+macro_rules! impl_wrapper_expression {
+    ($name:ident) => {
+        impl<E> NonAggregate for $name<E> where E: NonAggregate {}
+
+        impl<E, QS> AppearsOnTable<QS> for $name<E> where E: AppearsOnTable<QS> {}
+
+        impl<E, QS> SelectableExpression<QS> for $name<E> where E: SelectableExpression<QS> {}
+
+        impl<E> QueryId for $name<E> {
+            type QueryId = ();
+
+            const HAS_STATIC_QUERY_ID: bool = false;
+        }
+    };
+}
+
+impl_wrapper_expression!(DescriptorId);
+impl_wrapper_expression!(DescriptorName);
+
+/// The name of the table `T` as a string literal, for [`descriptor_id_of`].
+///
+/// [`descriptor_id_of`]: fn.descriptor_id_of.html
+#[derive(Debug, Clone, Copy)]
+pub struct TableName<T>(T);
+
+impl<T> Expression for TableName<T> {
+    type SqlType = Text;
+}
+
+impl<T> NonAggregate for TableName<T> {}
+
+impl<T, QS> AppearsOnTable<QS> for TableName<T> {}
+
+impl<T, QS> SelectableExpression<QS> for TableName<T> {}
+
+impl<T> QueryFragment<Pg> for TableName<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        let table = quote::render(&self.0.from_clause())?;
+        out.push_sql(&quote::string_literal(&table));
+        Ok(())
+    }
+}
+
+impl<T> QueryId for TableName<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crdb_internal::table_indexes;
+    use diesel::{pg::Pg, ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn id() {
+        let query = table_indexes::table
+            .select(table_indexes::index_name)
+            .filter(table_indexes::descriptor_id.eq(descriptor_id_of(books::table)));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "crdb_internal"."table_indexes"."index_name" FROM "crdb_internal"."table_indexes" WHERE "crdb_internal"."table_indexes"."descriptor_id" = ('"books"')::REGCLASS::INT8 -- binds: []"#
+        );
+        let query = diesel::select(descriptor_id("public.books"));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT ($1)::REGCLASS::INT8 -- binds: ["public.books"]"#
+        );
+    }
+
+    #[test]
+    fn name() {
+        let query = table_indexes::table
+            .select((
+                descriptor_name(table_indexes::descriptor_id),
+                table_indexes::index_name,
+            ))
+            .filter(table_indexes::is_unique.eq(true));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT ("crdb_internal"."table_indexes"."descriptor_id")::REGCLASS::STRING, "crdb_internal"."table_indexes"."index_name" FROM "crdb_internal"."table_indexes" WHERE "crdb_internal"."table_indexes"."is_unique" = $1 -- binds: [true]"#
+        );
+        let query = diesel::select(descriptor_name(53));
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT ($1)::REGCLASS::STRING -- binds: [53]"#
+        );
+    }
+}
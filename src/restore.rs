@@ -0,0 +1,247 @@
+//! Support for `RESTORE` SQL queries.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/restore.html
+
+use crate::{
+    as_of_system_time::AsOfSystemTime,
+    backup::{ClusterTarget, DatabasesTarget, TableTarget},
+    external_storage::ExternalStorageUri,
+    jobs::{BulkJobResultSqlType, Detached},
+    quote,
+};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+};
+
+/// Creates a `RESTORE` statement, restoring the whole cluster from the most
+/// recent backup in the collection at `uri`.
+///
+/// ```sql
+/// RESTORE FROM LATEST IN 's3://bucket/backups?AUTH=implicit';
+/// ```
+pub fn restore_cluster(uri: impl Into<ExternalStorageUri>) -> RestoreStatement<ClusterTarget> {
+    RestoreStatement::new(ClusterTarget, uri.into())
+}
+
+/// Creates a `RESTORE DATABASE` statement, restoring the named databases
+/// from the most recent backup in the collection at `uri`.
+///
+/// ```sql
+/// RESTORE DATABASE movr FROM LATEST IN 's3://bucket/backups?AUTH=implicit';
+/// ```
+pub fn restore_databases(
+    names: Vec<String>,
+    uri: impl Into<ExternalStorageUri>,
+) -> RestoreStatement<DatabasesTarget> {
+    RestoreStatement::new(DatabasesTarget(names), uri.into())
+}
+
+/// Creates a `RESTORE TABLE` statement, restoring the target table from the
+/// most recent backup in the collection at `uri`.
+///
+/// Once the restore job finishes, the statement returns a [`BulkJobResult`]:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{jobs::BulkJobResult, restore::restore_table};
+///
+/// let result: BulkJobResult = restore_table(books::table, "nodelocal://1/backups")
+///     .into_db("library_restored")
+///     .skip_missing_foreign_keys()
+///     .get_result(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`BulkJobResult`]: ../jobs/struct.BulkJobResult.html
+pub fn restore_table<T>(
+    target: T,
+    uri: impl Into<ExternalStorageUri>,
+) -> RestoreStatement<TableTarget<T>> {
+    RestoreStatement::new(TableTarget(target), uri.into())
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `RESTORE` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `O`: The objects being restored
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/restore.html
+pub struct RestoreStatement<O> {
+    target: O,
+    subdirectory: Option<String>,
+    uri: ExternalStorageUri,
+    as_of_system_time: Option<AsOfSystemTime>,
+    into_db: Option<String>,
+    skip_missing_foreign_keys: bool,
+    incremental_location: Option<ExternalStorageUri>,
+    detached: bool,
+}
+
+impl<O> RestoreStatement<O> {
+    fn new(target: O, uri: ExternalStorageUri) -> Self {
+        RestoreStatement {
+            target,
+            subdirectory: None,
+            uri,
+            as_of_system_time: None,
+            into_db: None,
+            skip_missing_foreign_keys: false,
+            incremental_location: None,
+            detached: false,
+        }
+    }
+
+    /// Restore from the backup in the given subdirectory of the collection,
+    /// as listed by `SHOW BACKUPS IN`, rather than the most recent backup.
+    ///
+    /// ```sql
+    /// RESTORE FROM '2022/10/14-120000.00' IN 'nodelocal://1/backups';
+    /// ```
+    pub fn from_subdirectory(mut self, subdirectory: impl Into<String>) -> Self {
+        self.subdirectory = Some(subdirectory.into());
+        self
+    }
+
+    /// Restore data as it was at a historical timestamp, which must be
+    /// covered by a backup taken with `revision_history`.
+    pub fn as_of_system_time(mut self, as_of_system_time: AsOfSystemTime) -> Self {
+        self.as_of_system_time = Some(as_of_system_time);
+        self
+    }
+
+    /// Restore tables into a different database than the one they were
+    /// backed up from.
+    pub fn into_db(mut self, database: impl Into<String>) -> Self {
+        self.into_db = Some(database.into());
+        self
+    }
+
+    /// Restore tables even when the tables their foreign keys reference are
+    /// not being restored, dropping those foreign key constraints.
+    pub fn skip_missing_foreign_keys(mut self) -> Self {
+        self.skip_missing_foreign_keys = true;
+        self
+    }
+
+    /// Read incremental backups from the given URI, where they were stored
+    /// with `incremental_location` when backing up.
+    pub fn incremental_location(mut self, uri: impl Into<ExternalStorageUri>) -> Self {
+        self.incremental_location = Some(uri.into());
+        self
+    }
+
+    /// Return the ID of the restore job as soon as it is created, rather than
+    /// waiting for it to finish.
+    pub fn detached(mut self) -> Detached<Self> {
+        self.detached = true;
+        Detached(self)
+    }
+}
+
+impl<O> QueryFragment<Pg> for RestoreStatement<O>
+where
+    O: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("RESTORE ");
+        self.target.walk_ast(out.reborrow())?;
+        out.push_sql("FROM ");
+        match &self.subdirectory {
+            Some(subdirectory) => out.push_sql(&quote::string_literal(subdirectory)),
+            None => out.push_sql("LATEST"),
+        }
+        out.push_sql(" IN ");
+        out.push_sql(&quote::string_literal(&self.uri.expose()));
+        if let Some(as_of_system_time) = &self.as_of_system_time {
+            out.push_sql(" ");
+            as_of_system_time.walk_ast(out.reborrow())?;
+        }
+        let mut options = Vec::new();
+        if let Some(into_db) = &self.into_db {
+            options.push(("into_db", Some(into_db.clone())));
+        }
+        if self.skip_missing_foreign_keys {
+            options.push(("skip_missing_foreign_keys", None));
+        }
+        if let Some(incremental_location) = &self.incremental_location {
+            options.push(("incremental_location", Some(incremental_location.expose())));
+        }
+        if self.detached {
+            options.push(("detached", None));
+        }
+        quote::push_options(&mut out, &options)?;
+        Ok(())
+    }
+}
+
+impl<O> Query for RestoreStatement<O> {
+    type SqlType = BulkJobResultSqlType;
+}
+
+impl<O> RunQueryDsl<PgConnection> for RestoreStatement<O> {}
+
+impl<O> QueryId for RestoreStatement<O> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn cluster() {
+        assert_eq!(
+            diesel::debug_query(&restore_cluster("nodelocal://1/backups")).to_string(),
+            r#"RESTORE FROM LATEST IN 'nodelocal://1/backups' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn databases() {
+        let statement = restore_databases(vec!["movr".to_owned()], "nodelocal://1/backups")
+            .from_subdirectory("2022/10/14-120000.00")
+            .incremental_location("nodelocal://1/incrementals")
+            .detached();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"RESTORE DATABASE "movr" FROM '2022/10/14-120000.00' IN 'nodelocal://1/backups' WITH incremental_location = 'nodelocal://1/incrementals', detached -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn table() {
+        let statement = restore_table(books::table, "nodelocal://1/backups")
+            .into_db("library's copy")
+            .skip_missing_foreign_keys();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"RESTORE TABLE "books" FROM LATEST IN 'nodelocal://1/backups' WITH into_db = 'library''s copy', skip_missing_foreign_keys -- binds: []"#
+        );
+    }
+}
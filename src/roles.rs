@@ -0,0 +1,738 @@
+//! Support for `CREATE ROLE`, `ALTER ROLE`, `GRANT SYSTEM` and `SHOW ROLES`
+//! SQL queries, including CockroachDB specific role options and system
+//! privileges.
+
+use crate::{
+    quote,
+    version::{ClusterVersion, Unsupported, VersionCheck},
+};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::{Array, Text},
+    Queryable,
+};
+use std::{error, fmt, str::FromStr};
+
+/// Creates a `CREATE ROLE` statement for a new role called `name`.
+///
+/// ```sql
+/// CREATE ROLE ops WITH LOGIN VIEWACTIVITY CANCELQUERY;
+/// ```
+pub fn create_role(name: impl Into<String>) -> CreateRoleStatement {
+    CreateRoleStatement::new(name.into())
+}
+
+/// Creates an `ALTER ROLE` statement for the existing role called `name`.
+///
+/// ```sql
+/// ALTER ROLE ops WITH NOCANCELQUERY;
+/// ```
+pub fn alter_role(name: impl Into<String>) -> AlterRoleStatement {
+    AlterRoleStatement::new(name.into())
+}
+
+/// A role option which can be granted, or revoked with its `NO` form.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-role.html#role-options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoleOption {
+    /// `LOGIN`: the role may log in.
+    Login,
+    /// `SQLLOGIN`: the role may log in to SQL sessions.
+    SqlLogin,
+    /// `CREATEROLE`: the role may create, alter and drop other non-admin roles.
+    CreateRole,
+    /// `CREATELOGIN`: the role may manage the login options of other roles.
+    CreateLogin,
+    /// `CREATEDB`: the role may create and rename databases.
+    CreateDb,
+    /// `CONTROLJOB`: the role may pause, resume and cancel jobs.
+    ControlJob,
+    /// `CONTROLCHANGEFEED`: the role may create changefeeds on any table it can `SELECT`.
+    ControlChangefeed,
+    /// `VIEWACTIVITY`: the role may see the queries and sessions of other users.
+    ViewActivity,
+    /// `VIEWACTIVITYREDACTED`: as `VIEWACTIVITY`, but with query constants redacted.
+    ViewActivityRedacted,
+    /// `CANCELQUERY`: the role may cancel the queries and sessions of other users.
+    CancelQuery,
+    /// `MODIFYCLUSTERSETTING`: the role may change cluster settings.
+    ModifyClusterSetting,
+    /// `VIEWCLUSTERSETTING`: the role may read cluster settings.
+    ViewClusterSetting,
+}
+
+impl RoleOption {
+    /// The SQL keyword granting this option.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoleOption::Login => "LOGIN",
+            RoleOption::SqlLogin => "SQLLOGIN",
+            RoleOption::CreateRole => "CREATEROLE",
+            RoleOption::CreateLogin => "CREATELOGIN",
+            RoleOption::CreateDb => "CREATEDB",
+            RoleOption::ControlJob => "CONTROLJOB",
+            RoleOption::ControlChangefeed => "CONTROLCHANGEFEED",
+            RoleOption::ViewActivity => "VIEWACTIVITY",
+            RoleOption::ViewActivityRedacted => "VIEWACTIVITYREDACTED",
+            RoleOption::CancelQuery => "CANCELQUERY",
+            RoleOption::ModifyClusterSetting => "MODIFYCLUSTERSETTING",
+            RoleOption::ViewClusterSetting => "VIEWCLUSTERSETTING",
+        }
+    }
+}
+
+impl FromStr for RoleOption {
+    type Err = InvalidRoleOption;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_uppercase().as_str() {
+            "LOGIN" => RoleOption::Login,
+            "SQLLOGIN" => RoleOption::SqlLogin,
+            "CREATEROLE" => RoleOption::CreateRole,
+            "CREATELOGIN" => RoleOption::CreateLogin,
+            "CREATEDB" => RoleOption::CreateDb,
+            "CONTROLJOB" => RoleOption::ControlJob,
+            "CONTROLCHANGEFEED" => RoleOption::ControlChangefeed,
+            "VIEWACTIVITY" => RoleOption::ViewActivity,
+            "VIEWACTIVITYREDACTED" => RoleOption::ViewActivityRedacted,
+            "CANCELQUERY" => RoleOption::CancelQuery,
+            "MODIFYCLUSTERSETTING" => RoleOption::ModifyClusterSetting,
+            "VIEWCLUSTERSETTING" => RoleOption::ViewClusterSetting,
+            _ => return Err(InvalidRoleOption(s.to_owned())),
+        })
+    }
+}
+
+/// The error returned when parsing an invalid [`RoleOption`].
+///
+/// [`RoleOption`]: enum.RoleOption.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidRoleOption(pub String);
+
+impl fmt::Display for InvalidRoleOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid role option: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidRoleOption {}
+
+#[derive(Debug, Clone)]
+enum RoleOptionValue {
+    Grant(RoleOption),
+    Revoke(RoleOption),
+    Password(Option<String>),
+    ValidUntil(Option<String>),
+}
+
+impl RoleOptionValue {
+    fn walk_ast(&self, mut out: AstPass<Pg>) {
+        match self {
+            RoleOptionValue::Grant(option) => out.push_sql(option.as_str()),
+            RoleOptionValue::Revoke(option) => {
+                out.push_sql("NO");
+                out.push_sql(option.as_str());
+            }
+            RoleOptionValue::Password(Some(password)) => {
+                out.push_sql("PASSWORD ");
+                out.push_sql(&quote::string_literal(password));
+            }
+            RoleOptionValue::Password(None) => out.push_sql("PASSWORD NULL"),
+            RoleOptionValue::ValidUntil(Some(timestamp)) => {
+                out.push_sql("VALID UNTIL ");
+                out.push_sql(&quote::string_literal(timestamp));
+            }
+            RoleOptionValue::ValidUntil(None) => out.push_sql("VALID UNTIL NULL"),
+        }
+    }
+}
+
+fn push_role_options(out: &mut AstPass<Pg>, options: &[RoleOptionValue]) {
+    if options.is_empty() {
+        return;
+    }
+    out.push_sql(" WITH");
+    for option in options {
+        out.push_sql(" ");
+        option.walk_ast(out.reborrow());
+    }
+}
+
+/// Builder methods for the options shared by `CREATE ROLE` and `ALTER ROLE`.
+macro_rules! role_options {
+    () => {
+        /// Grant a role option.
+        pub fn with(mut self, option: RoleOption) -> Self {
+            self.options.push(RoleOptionValue::Grant(option));
+            self
+        }
+
+        /// Revoke a role option, with its `NO` form such as `NOLOGIN`.
+        pub fn without(mut self, option: RoleOption) -> Self {
+            self.options.push(RoleOptionValue::Revoke(option));
+            self
+        }
+
+        /// Set the password of the role, or `None` to prevent password
+        /// authentication.
+        ///
+        /// The password is embedded in the statement as a literal, so may be
+        /// logged by CockroachDB.
+        pub fn password(mut self, password: Option<String>) -> Self {
+            self.options.push(RoleOptionValue::Password(password));
+            self
+        }
+
+        /// Set when the password of the role expires, such as `2021-01-01`,
+        /// or `None` for it to never expire.
+        pub fn valid_until(mut self, timestamp: Option<String>) -> Self {
+            self.options.push(RoleOptionValue::ValidUntil(timestamp));
+            self
+        }
+    };
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CREATE ROLE` statement.
+///
+/// Options are rendered in the order they were added.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-role.html
+pub struct CreateRoleStatement {
+    name: String,
+    if_not_exists: bool,
+    options: Vec<RoleOptionValue>,
+}
+
+impl CreateRoleStatement {
+    fn new(name: String) -> Self {
+        CreateRoleStatement {
+            name,
+            if_not_exists: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Do nothing if a role with the same name already exists.
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    role_options!();
+}
+
+impl QueryFragment<Pg> for CreateRoleStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CREATE ROLE ");
+        if self.if_not_exists {
+            out.push_sql("IF NOT EXISTS ");
+        }
+        out.push_identifier(&self.name)?;
+        push_role_options(&mut out, &self.options);
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for CreateRoleStatement {}
+
+impl QueryId for CreateRoleStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `ALTER ROLE` statement.
+///
+/// Options are rendered in the order they were added.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-role.html
+pub struct AlterRoleStatement {
+    name: String,
+    if_exists: bool,
+    options: Vec<RoleOptionValue>,
+}
+
+impl AlterRoleStatement {
+    fn new(name: String) -> Self {
+        AlterRoleStatement {
+            name,
+            if_exists: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Do nothing if the role does not exist.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+
+    role_options!();
+}
+
+impl QueryFragment<Pg> for AlterRoleStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("ALTER ROLE ");
+        if self.if_exists {
+            out.push_sql("IF EXISTS ");
+        }
+        out.push_identifier(&self.name)?;
+        push_role_options(&mut out, &self.options);
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for AlterRoleStatement {}
+
+impl QueryId for AlterRoleStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A system-level privilege, granted with `GRANT SYSTEM`.
+///
+/// Requires CockroachDB v22.2 or later.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/security-reference/authorization.html#supported-privileges
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SystemPrivilege {
+    /// `ALL`
+    All,
+    /// `BACKUP`
+    Backup,
+    /// `RESTORE`
+    Restore,
+    /// `CANCELQUERY`
+    CancelQuery,
+    /// `CONTROLJOB`
+    ControlJob,
+    /// `CREATEROLE`
+    CreateRole,
+    /// `CREATELOGIN`
+    CreateLogin,
+    /// `CREATEDB`
+    CreateDb,
+    /// `EXTERNALCONNECTION`
+    ExternalConnection,
+    /// `MODIFYCLUSTERSETTING`
+    ModifyClusterSetting,
+    /// `MODIFYSQLCLUSTERSETTING`
+    ModifySqlClusterSetting,
+    /// `NOSQLLOGIN`
+    NoSqlLogin,
+    /// `REPAIRCLUSTERMETADATA`
+    RepairClusterMetadata,
+    /// `VIEWACTIVITY`
+    ViewActivity,
+    /// `VIEWACTIVITYREDACTED`
+    ViewActivityRedacted,
+    /// `VIEWCLUSTERMETADATA`
+    ViewClusterMetadata,
+    /// `VIEWCLUSTERSETTING`
+    ViewClusterSetting,
+    /// `VIEWDEBUG`
+    ViewDebug,
+    /// `VIEWJOB`
+    ViewJob,
+    /// Any other system privilege, by keyword.
+    Other(String),
+}
+
+impl SystemPrivilege {
+    /// The SQL keyword for this privilege.
+    pub fn as_str(&self) -> &str {
+        match self {
+            SystemPrivilege::All => "ALL",
+            SystemPrivilege::Backup => "BACKUP",
+            SystemPrivilege::Restore => "RESTORE",
+            SystemPrivilege::CancelQuery => "CANCELQUERY",
+            SystemPrivilege::ControlJob => "CONTROLJOB",
+            SystemPrivilege::CreateRole => "CREATEROLE",
+            SystemPrivilege::CreateLogin => "CREATELOGIN",
+            SystemPrivilege::CreateDb => "CREATEDB",
+            SystemPrivilege::ExternalConnection => "EXTERNALCONNECTION",
+            SystemPrivilege::ModifyClusterSetting => "MODIFYCLUSTERSETTING",
+            SystemPrivilege::ModifySqlClusterSetting => "MODIFYSQLCLUSTERSETTING",
+            SystemPrivilege::NoSqlLogin => "NOSQLLOGIN",
+            SystemPrivilege::RepairClusterMetadata => "REPAIRCLUSTERMETADATA",
+            SystemPrivilege::ViewActivity => "VIEWACTIVITY",
+            SystemPrivilege::ViewActivityRedacted => "VIEWACTIVITYREDACTED",
+            SystemPrivilege::ViewClusterMetadata => "VIEWCLUSTERMETADATA",
+            SystemPrivilege::ViewClusterSetting => "VIEWCLUSTERSETTING",
+            SystemPrivilege::ViewDebug => "VIEWDEBUG",
+            SystemPrivilege::ViewJob => "VIEWJOB",
+            SystemPrivilege::Other(keyword) => keyword,
+        }
+    }
+}
+
+/// Creates a `GRANT SYSTEM` statement, granting system privileges to the
+/// role called `role`.
+///
+/// ```sql
+/// GRANT SYSTEM VIEWACTIVITY, CANCELQUERY TO ops;
+/// ```
+///
+/// Requires CockroachDB v22.2 or later.
+pub fn grant_system(
+    privileges: Vec<SystemPrivilege>,
+    role: impl Into<String>,
+) -> SystemPrivilegeStatement {
+    SystemPrivilegeStatement::new(true, privileges, role.into())
+}
+
+/// Creates a `REVOKE SYSTEM` statement, revoking system privileges from the
+/// role called `role`.
+///
+/// ```sql
+/// REVOKE SYSTEM CANCELQUERY FROM ops;
+/// ```
+///
+/// Requires CockroachDB v22.2 or later.
+pub fn revoke_system(
+    privileges: Vec<SystemPrivilege>,
+    role: impl Into<String>,
+) -> SystemPrivilegeStatement {
+    SystemPrivilegeStatement::new(false, privileges, role.into())
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `GRANT SYSTEM` or `REVOKE SYSTEM` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/grant.html
+pub struct SystemPrivilegeStatement {
+    grant: bool,
+    privileges: Vec<SystemPrivilege>,
+    role: String,
+    grant_option: bool,
+}
+
+impl SystemPrivilegeStatement {
+    fn new(grant: bool, privileges: Vec<SystemPrivilege>, role: String) -> Self {
+        SystemPrivilegeStatement {
+            grant,
+            privileges,
+            role,
+            grant_option: false,
+        }
+    }
+
+    /// When granting, also allow the role to grant the privileges to others.
+    /// When revoking, only revoke the ability to grant the privileges.
+    pub fn grant_option(mut self) -> Self {
+        self.grant_option = true;
+        self
+    }
+}
+
+impl VersionCheck for SystemPrivilegeStatement {
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported> {
+        version.require("system privileges", 22, 2)
+    }
+}
+
+impl QueryFragment<Pg> for SystemPrivilegeStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        if self.grant {
+            out.push_sql("GRANT SYSTEM ");
+        } else if self.grant_option {
+            out.push_sql("REVOKE GRANT OPTION FOR SYSTEM ");
+        } else {
+            out.push_sql("REVOKE SYSTEM ");
+        }
+        for (i, privilege) in self.privileges.iter().enumerate() {
+            if i > 0 {
+                out.push_sql(", ");
+            }
+            out.push_sql(privilege.as_str());
+        }
+        out.push_sql(if self.grant { " TO " } else { " FROM " });
+        out.push_identifier(&self.role)?;
+        if self.grant && self.grant_option {
+            out.push_sql(" WITH GRANT OPTION");
+        }
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for SystemPrivilegeStatement {}
+
+impl QueryId for SystemPrivilegeStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW ROLES` statement, listing every user and role with their
+/// options and the roles they are members of.
+///
+/// Load the result as [`RoleListing`] rows.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::roles::{show_roles, RoleListing, RoleOption};
+///
+/// let roles: Vec<RoleListing> = show_roles().load(&connection)?;
+/// for role in roles.iter().filter(|role| role.has_option(RoleOption::ControlJob)) {
+///     println!("{} may control jobs", role.username);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`RoleListing`]: struct.RoleListing.html
+pub fn show_roles() -> ShowRolesStatement {
+    ShowRolesStatement { keyword: "ROLES" }
+}
+
+/// Creates a `SHOW USERS` statement, which lists the same rows as
+/// [`show_roles`].
+///
+/// ```sql
+/// SHOW USERS;
+/// ```
+///
+/// [`show_roles`]: fn.show_roles.html
+pub fn show_users() -> ShowRolesStatement {
+    ShowRolesStatement { keyword: "USERS" }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW ROLES` or `SHOW USERS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-roles.html
+pub struct ShowRolesStatement {
+    keyword: &'static str,
+}
+
+impl QueryFragment<Pg> for ShowRolesStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SELECT username, options, member_of FROM [SHOW ");
+        out.push_sql(self.keyword);
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl Query for ShowRolesStatement {
+    type SqlType = RoleListingSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for ShowRolesStatement {}
+
+impl QueryId for ShowRolesStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A user or role, as returned by [`show_roles`].
+///
+/// [`show_roles`]: fn.show_roles.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleListing {
+    /// The name of the user or role.
+    pub username: String,
+    /// The options of the role as shown by CockroachDB, such as `NOLOGIN` or
+    /// `VALID UNTIL=2021-01-01 00:00:00+00:00`.
+    pub options: Vec<String>,
+    /// The roles this role is a direct member of.
+    pub member_of: Vec<String>,
+}
+
+impl RoleListing {
+    /// Whether the role has been granted `option`.
+    pub fn has_option(&self, option: RoleOption) -> bool {
+        self.options.iter().any(|o| o == option.as_str())
+    }
+
+    /// The role options granted to the role, ignoring `NO` options and
+    /// options this crate does not know about.
+    pub fn role_options(&self) -> Vec<RoleOption> {
+        self.options
+            .iter()
+            .filter_map(|option| option.parse().ok())
+            .collect()
+    }
+
+    /// Whether the role may log in, which is the case unless it has the
+    /// `NOLOGIN` option.
+    pub fn can_login(&self) -> bool {
+        !self.options.iter().any(|o| o == "NOLOGIN")
+    }
+
+    /// When the password of the role expires, if ever.
+    pub fn valid_until(&self) -> Option<&str> {
+        self.options
+            .iter()
+            .find_map(|option| option.strip_prefix("VALID UNTIL="))
+    }
+
+    /// Whether the role is a direct member of the role called `role`.
+    pub fn is_member_of(&self, role: &str) -> bool {
+        self.member_of.iter().any(|r| r == role)
+    }
+}
+
+impl Queryable<RoleListingSqlType, Pg> for RoleListing {
+    type Row = (String, String, Vec<String>);
+
+    fn build((username, options, member_of): Self::Row) -> Self {
+        RoleListing {
+            username,
+            options: parse_options(&options),
+            member_of,
+        }
+    }
+}
+
+/// The SQL type of a [`RoleListing`].
+///
+/// [`RoleListing`]: struct.RoleListing.html
+pub type RoleListingSqlType = (Text, Text, Array<Text>);
+
+fn parse_options(options: &str) -> Vec<String> {
+    options
+        .split(", ")
+        .map(str::trim)
+        .filter(|option| !option.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn create() {
+        let statement = create_role("ops")
+            .if_not_exists()
+            .with(RoleOption::Login)
+            .password(Some("it's secret".to_owned()))
+            .with(RoleOption::ViewActivity)
+            .with(RoleOption::CancelQuery)
+            .with(RoleOption::ControlJob);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE ROLE IF NOT EXISTS "ops" WITH LOGIN PASSWORD 'it''s secret' VIEWACTIVITY CANCELQUERY CONTROLJOB -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&create_role("ops")).to_string(),
+            r#"CREATE ROLE "ops" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn alter() {
+        let statement = alter_role("ops")
+            .if_exists()
+            .without(RoleOption::CancelQuery)
+            .password(None)
+            .valid_until(Some("2021-01-01".to_owned()));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER ROLE IF EXISTS "ops" WITH NOCANCELQUERY PASSWORD NULL VALID UNTIL '2021-01-01' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn system_privileges() {
+        let privileges = vec![SystemPrivilege::ViewActivity, SystemPrivilege::CancelQuery];
+        assert_eq!(
+            diesel::debug_query(&grant_system(privileges.clone(), "ops")).to_string(),
+            r#"GRANT SYSTEM VIEWACTIVITY, CANCELQUERY TO "ops" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&grant_system(privileges.clone(), "ops").grant_option())
+                .to_string(),
+            r#"GRANT SYSTEM VIEWACTIVITY, CANCELQUERY TO "ops" WITH GRANT OPTION -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&revoke_system(privileges.clone(), "ops")).to_string(),
+            r#"REVOKE SYSTEM VIEWACTIVITY, CANCELQUERY FROM "ops" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&revoke_system(privileges, "ops").grant_option()).to_string(),
+            r#"REVOKE GRANT OPTION FOR SYSTEM VIEWACTIVITY, CANCELQUERY FROM "ops" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn system_privileges_version() {
+        let statement = grant_system(vec![SystemPrivilege::ViewActivity], "ops");
+        assert!(ClusterVersion::new(22, 2, 0).check(&statement).is_ok());
+        assert_eq!(
+            ClusterVersion::new(22, 1, 4)
+                .check(&revoke_system(vec![SystemPrivilege::ViewActivity], "ops"))
+                .unwrap_err()
+                .to_string(),
+            "system privileges is not supported on CockroachDB v22.1.4, and requires v22.2 or later"
+        );
+        assert!(ClusterVersion::new(22, 1, 4).check(&statement).is_err());
+    }
+
+    #[test]
+    fn roles() {
+        assert_eq!(
+            diesel::debug_query(&show_roles()).to_string(),
+            r#"SELECT username, options, member_of FROM [SHOW ROLES] -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_users()).to_string(),
+            r#"SELECT username, options, member_of FROM [SHOW USERS] -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn role_listing() {
+        let role = RoleListing::build((
+            "ops".to_owned(),
+            "CONTROLJOB, NOLOGIN, VALID UNTIL=2021-01-01 00:00:00+00:00".to_owned(),
+            vec!["admin".to_owned()],
+        ));
+        assert_eq!(role.role_options(), [RoleOption::ControlJob]);
+        assert!(role.has_option(RoleOption::ControlJob));
+        assert!(!role.can_login());
+        assert_eq!(role.valid_until(), Some("2021-01-01 00:00:00+00:00"));
+        assert!(role.is_member_of("admin"));
+        assert_eq!(
+            RoleListing::build(("root".to_owned(), "".to_owned(), vec![])).options,
+            Vec::<String>::new()
+        );
+    }
+}
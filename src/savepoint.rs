@@ -0,0 +1,101 @@
+//! Support for the `SHOW SAVEPOINT STATUS` SQL query, describing the
+//! savepoints of the current transaction.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-savepoint-status.html
+
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::{Bool, Text},
+    Queryable,
+};
+
+/// Creates a `SHOW SAVEPOINT STATUS` statement, listing the active
+/// savepoints of the current transaction, from outermost to innermost.
+///
+/// Outside of a transaction, no rows are returned.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::savepoint::{show_savepoint_status, SavepointStatus};
+///
+/// connection.transaction::<_, diesel::result::Error, _>(|| {
+///     let savepoints: Vec<SavepointStatus> = show_savepoint_status().load(&connection)?;
+///     assert!(savepoints.is_empty());
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_savepoint_status() -> ShowSavepointStatusStatement {
+    ShowSavepointStatusStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW SAVEPOINT STATUS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-savepoint-status.html
+pub struct ShowSavepointStatusStatement;
+
+impl QueryFragment<Pg> for ShowSavepointStatusStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW SAVEPOINT STATUS");
+        Ok(())
+    }
+}
+
+impl Query for ShowSavepointStatusStatement {
+    type SqlType = (Text, Bool);
+}
+
+impl RunQueryDsl<PgConnection> for ShowSavepointStatusStatement {}
+
+impl QueryId for ShowSavepointStatusStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// An active savepoint, as returned by [`show_savepoint_status`].
+///
+/// [`show_savepoint_status`]: fn.show_savepoint_status.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct SavepointStatus {
+    /// The name of the savepoint.
+    pub savepoint_name: String,
+    /// Whether the savepoint was created first in the transaction, so that
+    /// rolling back to it restarts the whole transaction. This is the case
+    /// for the `cockroach_restart` savepoint used to retry transactions.
+    pub is_initial_savepoint: bool,
+}
+
+/// The innermost active savepoint of the current transaction, if any.
+pub fn current_savepoint(conn: &PgConnection) -> QueryResult<Option<SavepointStatus>> {
+    let mut savepoints: Vec<SavepointStatus> = show_savepoint_status().load(conn)?;
+    Ok(savepoints.pop())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn show_status() {
+        assert_eq!(
+            diesel::debug_query(&show_savepoint_status()).to_string(),
+            r#"SHOW SAVEPOINT STATUS -- binds: []"#
+        );
+    }
+}
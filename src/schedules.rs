@@ -0,0 +1,617 @@
+//! Support for scheduled jobs, with `CREATE SCHEDULE FOR BACKUP`,
+//! `SHOW SCHEDULES`, and `PAUSE`, `RESUME` or `DROP SCHEDULES`.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/manage-a-backup-schedule.html
+
+use crate::{backup::BackupStatement, quote};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::{BigInt, Nullable, Text, Timestamp},
+    Queryable,
+};
+use std::time::SystemTime;
+
+/// Creates a `CREATE SCHEDULE FOR BACKUP` statement, running `backup` on the
+/// schedule given by the `crontab` expression.
+///
+/// The backup is run `detached` by the scheduler, and must not have an
+/// `AS OF SYSTEM TIME` clause.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::{
+///     backup::backup_cluster,
+///     schedules::{create_schedule_for_backup, CreatedSchedule, FullBackup},
+/// };
+///
+/// let schedules: Vec<CreatedSchedule> = create_schedule_for_backup(
+///     "nightly",
+///     backup_cluster("s3://bucket/backups?AUTH=implicit").revision_history(),
+///     "@daily",
+/// )
+/// .full_backup(FullBackup::Crontab("@weekly".to_owned()))
+/// .first_run_now()
+/// .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_schedule_for_backup<O>(
+    label: impl Into<String>,
+    backup: BackupStatement<O>,
+    crontab: impl Into<String>,
+) -> CreateScheduleForBackupStatement<O> {
+    CreateScheduleForBackupStatement {
+        label: label.into(),
+        if_not_exists: false,
+        backup,
+        crontab: crontab.into(),
+        full_backup: None,
+        first_run: None,
+        on_execution_failure: None,
+        on_previous_running: None,
+        ignore_existing_backups: false,
+    }
+}
+
+/// How often a full backup is taken by a backup schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FullBackup {
+    /// Take full backups on the schedule given by a crontab expression, and
+    /// incremental backups in between.
+    Crontab(String),
+    /// Take a full backup every time the schedule runs.
+    Always,
+}
+
+/// What a schedule does when one of its jobs fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnExecutionFailure {
+    /// Retry the job as soon as possible.
+    Retry,
+    /// Run the job again at its next scheduled time.
+    Reschedule,
+    /// Pause the schedule until it is resumed.
+    Pause,
+}
+
+impl OnExecutionFailure {
+    fn as_str(self) -> &'static str {
+        match self {
+            OnExecutionFailure::Retry => "retry",
+            OnExecutionFailure::Reschedule => "reschedule",
+            OnExecutionFailure::Pause => "pause",
+        }
+    }
+}
+
+/// What a schedule does when its previous job is still running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnPreviousRunning {
+    /// Start the new job anyway.
+    Start,
+    /// Skip this run, and try again at the next scheduled time.
+    Skip,
+    /// Wait for the previous job to finish first.
+    Wait,
+}
+
+impl OnPreviousRunning {
+    fn as_str(self) -> &'static str {
+        match self {
+            OnPreviousRunning::Start => "start",
+            OnPreviousRunning::Skip => "skip",
+            OnPreviousRunning::Wait => "wait",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CREATE SCHEDULE FOR BACKUP` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `O`: The objects being backed up
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-schedule-for-backup.html
+pub struct CreateScheduleForBackupStatement<O> {
+    label: String,
+    if_not_exists: bool,
+    backup: BackupStatement<O>,
+    crontab: String,
+    full_backup: Option<FullBackup>,
+    first_run: Option<String>,
+    on_execution_failure: Option<OnExecutionFailure>,
+    on_previous_running: Option<OnPreviousRunning>,
+    ignore_existing_backups: bool,
+}
+
+impl<O> CreateScheduleForBackupStatement<O> {
+    /// Do nothing if a schedule with the same label already exists.
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Set how often a full backup is taken. By default, CockroachDB picks a
+    /// full backup frequency based on the frequency of the schedule.
+    pub fn full_backup(mut self, full_backup: FullBackup) -> Self {
+        self.full_backup = Some(full_backup);
+        self
+    }
+
+    /// Run the first backup at the given timestamp, such as
+    /// `2022-10-14 12:00:00+00`.
+    pub fn first_run(mut self, timestamp: impl Into<String>) -> Self {
+        self.first_run = Some(timestamp.into());
+        self
+    }
+
+    /// Run the first backup as soon as the schedule is created.
+    pub fn first_run_now(self) -> Self {
+        self.first_run("now")
+    }
+
+    /// Set what the schedule does when a backup fails.
+    pub fn on_execution_failure(mut self, on_execution_failure: OnExecutionFailure) -> Self {
+        self.on_execution_failure = Some(on_execution_failure);
+        self
+    }
+
+    /// Set what the schedule does when the previous backup is still running.
+    pub fn on_previous_running(mut self, on_previous_running: OnPreviousRunning) -> Self {
+        self.on_previous_running = Some(on_previous_running);
+        self
+    }
+
+    /// Create the schedule even if the collection already contains backups.
+    pub fn ignore_existing_backups(mut self) -> Self {
+        self.ignore_existing_backups = true;
+        self
+    }
+}
+
+impl<O> QueryFragment<Pg> for CreateScheduleForBackupStatement<O>
+where
+    O: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CREATE SCHEDULE ");
+        if self.if_not_exists {
+            out.push_sql("IF NOT EXISTS ");
+        }
+        out.push_sql(&quote::string_literal(&self.label));
+        out.push_sql(" FOR ");
+        self.backup.walk_ast(out.reborrow())?;
+        out.push_sql(" RECURRING ");
+        out.push_sql(&quote::string_literal(&self.crontab));
+        match &self.full_backup {
+            Some(FullBackup::Crontab(crontab)) => {
+                out.push_sql(" FULL BACKUP ");
+                out.push_sql(&quote::string_literal(crontab));
+            }
+            Some(FullBackup::Always) => out.push_sql(" FULL BACKUP ALWAYS"),
+            None => {}
+        }
+        let mut options = Vec::new();
+        if let Some(first_run) = &self.first_run {
+            options.push(("first_run", Some(first_run.clone())));
+        }
+        if let Some(on_execution_failure) = self.on_execution_failure {
+            options.push((
+                "on_execution_failure",
+                Some(on_execution_failure.as_str().to_owned()),
+            ));
+        }
+        if let Some(on_previous_running) = self.on_previous_running {
+            options.push((
+                "on_previous_running",
+                Some(on_previous_running.as_str().to_owned()),
+            ));
+        }
+        if self.ignore_existing_backups {
+            options.push(("ignore_existing_backups", None));
+        }
+        quote::push_options_clause(&mut out, " WITH SCHEDULE OPTIONS ", &options)?;
+        Ok(())
+    }
+}
+
+// `first_run` is a `TIMESTAMPTZ`, which has the same binary representation as
+// a `TIMESTAMP` in UTC.
+impl<O> Query for CreateScheduleForBackupStatement<O> {
+    type SqlType = (BigInt, Text, Text, Nullable<Timestamp>, Text, Text);
+}
+
+impl<O> RunQueryDsl<PgConnection> for CreateScheduleForBackupStatement<O> {}
+
+impl<O> QueryId for CreateScheduleForBackupStatement<O> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A schedule created by [`create_schedule_for_backup`].
+///
+/// Unless full backups are always taken, two schedules are created: one for
+/// full backups, and one for incremental backups.
+///
+/// [`create_schedule_for_backup`]: fn.create_schedule_for_backup.html
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct CreatedSchedule {
+    /// The ID of the schedule.
+    pub schedule_id: i64,
+    /// The label of the schedule.
+    pub label: String,
+    /// The status of the schedule, such as `ACTIVE` or `PAUSED`.
+    pub status: String,
+    /// When the schedule first runs, or `None` if it is paused.
+    pub first_run: Option<SystemTime>,
+    /// The crontab expression of the schedule.
+    pub schedule: String,
+    /// The `BACKUP` statement run by the schedule.
+    pub backup_stmt: String,
+}
+
+/// Which schedules a [`show_schedules`], [`pause_schedules`],
+/// [`resume_schedules`] or [`drop_schedules`] statement applies to.
+///
+/// [`show_schedules`]: fn.show_schedules.html
+/// [`pause_schedules`]: fn.pause_schedules.html
+/// [`resume_schedules`]: fn.resume_schedules.html
+/// [`drop_schedules`]: fn.drop_schedules.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleFilter {
+    /// Schedules with any of the given IDs. An empty list matches no schedules.
+    Ids(Vec<i64>),
+    /// Schedules with the given label.
+    Label(String),
+}
+
+impl ScheduleFilter {
+    fn walk_where_clause(&self, mut out: AstPass<Pg>) {
+        match self {
+            ScheduleFilter::Ids(ids) if ids.is_empty() => out.push_sql(" WHERE false"),
+            ScheduleFilter::Ids(ids) => {
+                out.push_sql(" WHERE id IN (");
+                for (i, id) in ids.iter().enumerate() {
+                    if i > 0 {
+                        out.push_sql(", ");
+                    }
+                    out.push_sql(&id.to_string());
+                }
+                out.push_sql(")");
+            }
+            ScheduleFilter::Label(label) => {
+                out.push_sql(" WHERE label = ");
+                out.push_sql(&quote::string_literal(label));
+            }
+        }
+    }
+}
+
+/// Creates a `SHOW SCHEDULES` statement, listing all schedules.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::schedules::{show_schedules, Schedule, ScheduleFilter};
+///
+/// let schedules: Vec<Schedule> = show_schedules()
+///     .for_backup()
+///     .filter(ScheduleFilter::Label("nightly".to_owned()))
+///     .load(&connection)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_schedules() -> ShowSchedulesStatement {
+    ShowSchedulesStatement {
+        status: None,
+        for_backup: false,
+        filter: None,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ShowSchedulesStatus {
+    Running,
+    Paused,
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW SCHEDULES` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-schedules.html
+pub struct ShowSchedulesStatement {
+    status: Option<ShowSchedulesStatus>,
+    for_backup: bool,
+    filter: Option<ScheduleFilter>,
+}
+
+impl ShowSchedulesStatement {
+    /// Only show active schedules.
+    pub fn running(mut self) -> Self {
+        self.status = Some(ShowSchedulesStatus::Running);
+        self
+    }
+
+    /// Only show paused schedules.
+    pub fn paused(mut self) -> Self {
+        self.status = Some(ShowSchedulesStatus::Paused);
+        self
+    }
+
+    /// Only show backup schedules.
+    pub fn for_backup(mut self) -> Self {
+        self.for_backup = true;
+        self
+    }
+
+    /// Only show the schedules matching `filter`.
+    pub fn filter(mut self, filter: ScheduleFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
+
+impl QueryFragment<Pg> for ShowSchedulesStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT id, label, schedule_status, next_run, state, recurrence, jobsrunning, \
+             owner, created FROM [SHOW ",
+        );
+        match self.status {
+            Some(ShowSchedulesStatus::Running) => out.push_sql("RUNNING "),
+            Some(ShowSchedulesStatus::Paused) => out.push_sql("PAUSED "),
+            None => {}
+        }
+        out.push_sql("SCHEDULES");
+        if self.for_backup {
+            out.push_sql(" FOR BACKUP");
+        }
+        out.push_sql("]");
+        if let Some(filter) = &self.filter {
+            filter.walk_where_clause(out.reborrow());
+        }
+        Ok(())
+    }
+}
+
+// `next_run` and `created` are `TIMESTAMPTZ`, which have the same binary
+// representation as a `TIMESTAMP` in UTC.
+impl Query for ShowSchedulesStatement {
+    type SqlType = (
+        BigInt,
+        Text,
+        Nullable<Text>,
+        Nullable<Timestamp>,
+        Nullable<Text>,
+        Nullable<Text>,
+        BigInt,
+        Text,
+        Timestamp,
+    );
+}
+
+impl RunQueryDsl<PgConnection> for ShowSchedulesStatement {}
+
+impl QueryId for ShowSchedulesStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A schedule, as returned by [`show_schedules`].
+///
+/// [`show_schedules`]: fn.show_schedules.html
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct Schedule {
+    /// The ID of the schedule.
+    pub id: i64,
+    /// The label of the schedule.
+    pub label: String,
+    /// The status of the schedule, such as `ACTIVE` or `PAUSED`.
+    pub schedule_status: Option<String>,
+    /// When the schedule next runs, or `None` if it is paused.
+    pub next_run: Option<SystemTime>,
+    /// A description of the current state of the schedule.
+    pub state: Option<String>,
+    /// The crontab expression of the schedule.
+    pub recurrence: Option<String>,
+    /// The number of jobs currently running for the schedule.
+    pub jobs_running: i64,
+    /// The user owning the schedule.
+    pub owner: String,
+    /// When the schedule was created.
+    pub created: SystemTime,
+}
+
+/// Creates a `PAUSE SCHEDULES` statement, pausing the schedules matching
+/// `filter` until they are resumed.
+///
+/// ```sql
+/// PAUSE SCHEDULES SELECT id FROM [SHOW SCHEDULES] WHERE label = 'nightly';
+/// ```
+pub fn pause_schedules(filter: ScheduleFilter) -> ScheduleControlStatement {
+    ScheduleControlStatement {
+        action: ScheduleAction::Pause,
+        filter,
+    }
+}
+
+/// Creates a `RESUME SCHEDULES` statement, resuming the paused schedules
+/// matching `filter`.
+///
+/// ```sql
+/// RESUME SCHEDULES SELECT id FROM [SHOW SCHEDULES] WHERE id IN (588796190000218113);
+/// ```
+pub fn resume_schedules(filter: ScheduleFilter) -> ScheduleControlStatement {
+    ScheduleControlStatement {
+        action: ScheduleAction::Resume,
+        filter,
+    }
+}
+
+/// Creates a `DROP SCHEDULES` statement, removing the schedules matching
+/// `filter`. Jobs already started by the schedules keep running.
+///
+/// ```sql
+/// DROP SCHEDULES SELECT id FROM [SHOW SCHEDULES] WHERE label = 'nightly';
+/// ```
+pub fn drop_schedules(filter: ScheduleFilter) -> ScheduleControlStatement {
+    ScheduleControlStatement {
+        action: ScheduleAction::Drop,
+        filter,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ScheduleAction {
+    Pause,
+    Resume,
+    Drop,
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `PAUSE`, `RESUME` or `DROP SCHEDULES` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/pause-schedules.html
+pub struct ScheduleControlStatement {
+    action: ScheduleAction,
+    filter: ScheduleFilter,
+}
+
+impl QueryFragment<Pg> for ScheduleControlStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(match self.action {
+            ScheduleAction::Pause => "PAUSE",
+            ScheduleAction::Resume => "RESUME",
+            ScheduleAction::Drop => "DROP",
+        });
+        out.push_sql(" SCHEDULES SELECT id FROM [SHOW SCHEDULES]");
+        self.filter.walk_where_clause(out.reborrow());
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for ScheduleControlStatement {}
+
+impl QueryId for ScheduleControlStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{backup_cluster, backup_databases};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn create() {
+        let statement = create_schedule_for_backup(
+            "nightly",
+            backup_cluster("nodelocal://1/backups"),
+            "@daily",
+        );
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE SCHEDULE 'nightly' FOR BACKUP INTO 'nodelocal://1/backups' RECURRING '@daily' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn options() {
+        let statement = create_schedule_for_backup(
+            "movr hourly",
+            backup_databases(vec!["movr".to_owned()], "nodelocal://1/backups").revision_history(),
+            "@hourly",
+        )
+        .if_not_exists()
+        .full_backup(FullBackup::Crontab("@daily".to_owned()))
+        .first_run_now()
+        .on_execution_failure(OnExecutionFailure::Pause)
+        .on_previous_running(OnPreviousRunning::Skip)
+        .ignore_existing_backups();
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE SCHEDULE IF NOT EXISTS 'movr hourly' FOR BACKUP DATABASE "movr" INTO 'nodelocal://1/backups' WITH revision_history RECURRING '@hourly' FULL BACKUP '@daily' WITH SCHEDULE OPTIONS first_run = 'now', on_execution_failure = 'pause', on_previous_running = 'skip', ignore_existing_backups -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn full_backup_always() {
+        let statement = create_schedule_for_backup(
+            "nightly",
+            backup_cluster("nodelocal://1/backups"),
+            "@daily",
+        )
+        .full_backup(FullBackup::Always);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE SCHEDULE 'nightly' FOR BACKUP INTO 'nodelocal://1/backups' RECURRING '@daily' FULL BACKUP ALWAYS -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show() {
+        assert_eq!(
+            diesel::debug_query(&show_schedules()).to_string(),
+            r#"SELECT id, label, schedule_status, next_run, state, recurrence, jobsrunning, owner, created FROM [SHOW SCHEDULES] -- binds: []"#
+        );
+        let statement = show_schedules()
+            .paused()
+            .for_backup()
+            .filter(ScheduleFilter::Label("nightly's".to_owned()));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"SELECT id, label, schedule_status, next_run, state, recurrence, jobsrunning, owner, created FROM [SHOW PAUSED SCHEDULES FOR BACKUP] WHERE label = 'nightly''s' -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn control() {
+        assert_eq!(
+            diesel::debug_query(&pause_schedules(ScheduleFilter::Label(
+                "nightly".to_owned()
+            )))
+            .to_string(),
+            r#"PAUSE SCHEDULES SELECT id FROM [SHOW SCHEDULES] WHERE label = 'nightly' -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&resume_schedules(ScheduleFilter::Ids(vec![1, 2]))).to_string(),
+            r#"RESUME SCHEDULES SELECT id FROM [SHOW SCHEDULES] WHERE id IN (1, 2) -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&drop_schedules(ScheduleFilter::Ids(vec![3]))).to_string(),
+            r#"DROP SCHEDULES SELECT id FROM [SHOW SCHEDULES] WHERE id IN (3) -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&drop_schedules(ScheduleFilter::Ids(vec![]))).to_string(),
+            r#"DROP SCHEDULES SELECT id FROM [SHOW SCHEDULES] WHERE false -- binds: []"#
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Support for `CREATE SEQUENCE` and `ALTER SEQUENCE` SQL queries.
+
+use crate::{migrations::Reversible, quote};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+};
+
+/// Creates a `CREATE SEQUENCE` statement for a new sequence called `name`.
+///
+/// ```sql
+/// CREATE SEQUENCE book_ids START 1000 CACHE 100;
+/// ```
+pub fn create_sequence(name: impl Into<String>) -> CreateSequenceStatement {
+    CreateSequenceStatement::new(name.into())
+}
+
+/// Creates an `ALTER SEQUENCE` statement for the existing sequence called `name`.
+///
+/// ```sql
+/// ALTER SEQUENCE book_ids INCREMENT 10;
+/// ```
+pub fn alter_sequence(name: impl Into<String>) -> AlterSequenceStatement {
+    AlterSequenceStatement::new(name.into())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SequenceOption {
+    Increment(i64),
+    MinValue(Option<i64>),
+    MaxValue(Option<i64>),
+    Start(i64),
+    Restart(Option<i64>),
+    Cache(i64),
+    PerNodeCache(i64),
+    Virtual,
+}
+
+impl SequenceOption {
+    fn walk_ast(&self, mut out: AstPass<Pg>) {
+        match self {
+            SequenceOption::Increment(value) => out.push_sql(&format!(" INCREMENT {}", value)),
+            SequenceOption::MinValue(Some(value)) => out.push_sql(&format!(" MINVALUE {}", value)),
+            SequenceOption::MinValue(None) => out.push_sql(" NO MINVALUE"),
+            SequenceOption::MaxValue(Some(value)) => out.push_sql(&format!(" MAXVALUE {}", value)),
+            SequenceOption::MaxValue(None) => out.push_sql(" NO MAXVALUE"),
+            SequenceOption::Start(value) => out.push_sql(&format!(" START {}", value)),
+            SequenceOption::Restart(Some(value)) => out.push_sql(&format!(" RESTART {}", value)),
+            SequenceOption::Restart(None) => out.push_sql(" RESTART"),
+            SequenceOption::Cache(value) => out.push_sql(&format!(" CACHE {}", value)),
+            SequenceOption::PerNodeCache(value) => {
+                out.push_sql(&format!(" PER NODE CACHE {}", value))
+            }
+            SequenceOption::Virtual => out.push_sql(" VIRTUAL"),
+        }
+    }
+}
+
+/// Builder methods for the options shared by `CREATE SEQUENCE` and `ALTER SEQUENCE`.
+macro_rules! sequence_options {
+    () => {
+        /// Set the value added to the sequence on each call to `nextval`.
+        /// Negative values create a descending sequence.
+        pub fn increment(mut self, increment: i64) -> Self {
+            self.options.push(SequenceOption::Increment(increment));
+            self
+        }
+
+        /// Set the minimum value of the sequence, or `None` to use the default
+        /// for the sequence type.
+        pub fn min_value(mut self, min_value: Option<i64>) -> Self {
+            self.options.push(SequenceOption::MinValue(min_value));
+            self
+        }
+
+        /// Set the maximum value of the sequence, or `None` to use the default
+        /// for the sequence type.
+        pub fn max_value(mut self, max_value: Option<i64>) -> Self {
+            self.options.push(SequenceOption::MaxValue(max_value));
+            self
+        }
+
+        /// Set the first value of the sequence.
+        pub fn start(mut self, start: i64) -> Self {
+            self.options.push(SequenceOption::Start(start));
+            self
+        }
+
+        /// Cache `count` values in memory for reuse within each session,
+        /// reducing contention on the sequence at the cost of gaps in the
+        /// generated values.
+        pub fn cache(mut self, count: i64) -> Self {
+            self.options.push(SequenceOption::Cache(count));
+            self
+        }
+
+        /// Cache `count` values in memory for reuse by all sessions on each node.
+        ///
+        /// Requires CockroachDB v24.1 or later.
+        pub fn per_node_cache(mut self, count: i64) -> Self {
+            self.options.push(SequenceOption::PerNodeCache(count));
+            self
+        }
+    };
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CREATE SEQUENCE` statement.
+///
+/// Options are rendered in the order they were added.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-sequence.html
+pub struct CreateSequenceStatement {
+    name: String,
+    if_not_exists: bool,
+    options: Vec<SequenceOption>,
+}
+
+impl CreateSequenceStatement {
+    fn new(name: String) -> Self {
+        CreateSequenceStatement {
+            name,
+            if_not_exists: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Do nothing if a sequence with the same name already exists.
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Create a virtual sequence, which generates values with `unique_rowid()`
+    /// rather than storing any state.
+    pub fn virtual_sequence(mut self) -> Self {
+        self.options.push(SequenceOption::Virtual);
+        self
+    }
+
+    sequence_options!();
+}
+
+impl QueryFragment<Pg> for CreateSequenceStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CREATE SEQUENCE ");
+        if self.if_not_exists {
+            out.push_sql("IF NOT EXISTS ");
+        }
+        out.push_identifier(&self.name)?;
+        for option in &self.options {
+            option.walk_ast(out.reborrow());
+        }
+        Ok(())
+    }
+}
+
+impl Reversible for CreateSequenceStatement {
+    /// Drops the sequence.
+    fn reverse_sql(&self) -> QueryResult<Option<String>> {
+        Ok(Some(format!(
+            "DROP SEQUENCE {}",
+            quote::identifier(&self.name)
+        )))
+    }
+}
+
+impl RunQueryDsl<PgConnection> for CreateSequenceStatement {}
+
+impl QueryId for CreateSequenceStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `ALTER SEQUENCE` statement.
+///
+/// Options are rendered in the order they were added.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/alter-sequence.html
+pub struct AlterSequenceStatement {
+    name: String,
+    if_exists: bool,
+    options: Vec<SequenceOption>,
+}
+
+impl AlterSequenceStatement {
+    fn new(name: String) -> Self {
+        AlterSequenceStatement {
+            name,
+            if_exists: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Do nothing if the sequence does not exist.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+
+    /// Restart the sequence at `value`, or at its start value if `None`.
+    pub fn restart(mut self, value: Option<i64>) -> Self {
+        self.options.push(SequenceOption::Restart(value));
+        self
+    }
+
+    sequence_options!();
+}
+
+impl QueryFragment<Pg> for AlterSequenceStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("ALTER SEQUENCE ");
+        if self.if_exists {
+            out.push_sql("IF EXISTS ");
+        }
+        out.push_identifier(&self.name)?;
+        for option in &self.options {
+            option.walk_ast(out.reborrow());
+        }
+        Ok(())
+    }
+}
+
+impl RunQueryDsl<PgConnection> for AlterSequenceStatement {}
+
+impl QueryId for AlterSequenceStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn create() {
+        assert_eq!(
+            diesel::debug_query(&create_sequence("book_ids")).to_string(),
+            r#"CREATE SEQUENCE "book_ids" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn create_with_options() {
+        let statement = create_sequence("book_ids")
+            .if_not_exists()
+            .increment(-1)
+            .min_value(Some(-1000))
+            .max_value(None)
+            .start(-1)
+            .per_node_cache(256);
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE SEQUENCE IF NOT EXISTS "book_ids" INCREMENT -1 MINVALUE -1000 NO MAXVALUE START -1 PER NODE CACHE 256 -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn alter() {
+        let statement = alter_sequence("book_ids")
+            .if_exists()
+            .cache(100)
+            .restart(Some(5000));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"ALTER SEQUENCE IF EXISTS "book_ids" CACHE 100 RESTART 5000 -- binds: []"#
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Support for ID columns which behave the same regardless of the cluster's
+//! [`serial_normalization`] setting.
+//!
+//! Columns declared as `SERIAL` are interpreted differently depending on the
+//! session's `serial_normalization`, so the same migration can produce
+//! different schemas on different clusters. The helpers here render the
+//! explicit column definition for a chosen normalization instead.
+//!
+//! [`serial_normalization`]: https://www.cockroachlabs.com/docs/stable/serial.html
+
+use crate::{
+    quote,
+    sequence::{create_sequence, CreateSequenceStatement},
+};
+use diesel::{
+    pg::PgConnection,
+    query_dsl::RunQueryDsl,
+    result::{Error, QueryResult},
+    sql_types::Text,
+    QueryableByName,
+};
+use std::{error, fmt, str::FromStr};
+
+/// A value of the `serial_normalization` session setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SerialNormalization {
+    /// `rowid`: the column defaults to `unique_rowid()`.
+    Rowid,
+    /// `virtual_sequence`: the column defaults to a virtual sequence, backed by `unique_rowid()`.
+    VirtualSequence,
+    /// `sql_sequence`: the column defaults to a real SQL sequence.
+    SqlSequence,
+    /// `sql_sequence_cached`: the column defaults to a real SQL sequence, cached per session.
+    SqlSequenceCached,
+    /// `sql_sequence_cached_node`: the column defaults to a real SQL sequence, cached per node.
+    SqlSequenceCachedNode,
+    /// `unordered_rowid`: the column defaults to `unordered_unique_rowid()`.
+    UnorderedRowid,
+}
+
+impl SerialNormalization {
+    /// The name of this value, as used by the setting.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SerialNormalization::Rowid => "rowid",
+            SerialNormalization::VirtualSequence => "virtual_sequence",
+            SerialNormalization::SqlSequence => "sql_sequence",
+            SerialNormalization::SqlSequenceCached => "sql_sequence_cached",
+            SerialNormalization::SqlSequenceCachedNode => "sql_sequence_cached_node",
+            SerialNormalization::UnorderedRowid => "unordered_rowid",
+        }
+    }
+}
+
+impl fmt::Display for SerialNormalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SerialNormalization {
+    type Err = UnknownSerialNormalization;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "rowid" => SerialNormalization::Rowid,
+            "virtual_sequence" => SerialNormalization::VirtualSequence,
+            "sql_sequence" => SerialNormalization::SqlSequence,
+            "sql_sequence_cached" => SerialNormalization::SqlSequenceCached,
+            "sql_sequence_cached_node" => SerialNormalization::SqlSequenceCachedNode,
+            "unordered_rowid" => SerialNormalization::UnorderedRowid,
+            _ => return Err(UnknownSerialNormalization(s.to_owned())),
+        })
+    }
+}
+
+/// The error returned when parsing an unrecognised `serial_normalization` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSerialNormalization(pub String);
+
+impl fmt::Display for UnknownSerialNormalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown serial_normalization value: {}", self.0)
+    }
+}
+
+impl error::Error for UnknownSerialNormalization {}
+
+#[derive(QueryableByName)]
+struct SerialNormalizationRow {
+    #[sql_type = "Text"]
+    serial_normalization: String,
+}
+
+/// Reads the current session's `serial_normalization` setting.
+pub fn serial_normalization(conn: &PgConnection) -> QueryResult<SerialNormalization> {
+    let row: SerialNormalizationRow =
+        diesel::sql_query("SHOW serial_normalization").get_result(conn)?;
+    row.serial_normalization
+        .parse()
+        .map_err(|err| Error::DeserializationError(Box::new(err)))
+}
+
+/// An `INT8` ID column definition for a given serial normalization.
+///
+/// The column definition is rendered by its `Display` implementation, for
+/// use in a `CREATE TABLE` or `ALTER TABLE ... ADD COLUMN` statement:
+///
+/// ```
+/// use diesel_cockroach::serial::{IdColumn, SerialNormalization};
+///
+/// let column = IdColumn::new("books", "id", SerialNormalization::UnorderedRowid);
+/// assert_eq!(
+///     column.to_string(),
+///     r#""id" INT8 NOT NULL DEFAULT unordered_unique_rowid()"#
+/// );
+/// ```
+///
+/// The sequence based normalizations also require a sequence to be created
+/// first, which is returned by [`sequence`].
+///
+/// [`sequence`]: #method.sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdColumn {
+    table: String,
+    column: String,
+    normalization: SerialNormalization,
+}
+
+/// The cache size CockroachDB uses for cached serial sequences by default.
+const SERIAL_SEQUENCE_CACHE_SIZE: i64 = 256;
+
+impl IdColumn {
+    /// Define the ID column `column` of `table`.
+    pub fn new(
+        table: impl Into<String>,
+        column: impl Into<String>,
+        normalization: SerialNormalization,
+    ) -> Self {
+        IdColumn {
+            table: table.into(),
+            column: column.into(),
+            normalization,
+        }
+    }
+
+    /// The name of the backing sequence, following CockroachDB's `<table>_<column>_seq` convention.
+    pub fn sequence_name(&self) -> String {
+        format!("{}_{}_seq", self.table, self.column)
+    }
+
+    /// The statement creating the sequence this column defaults to, if any.
+    pub fn sequence(&self) -> Option<CreateSequenceStatement> {
+        let sequence = create_sequence(self.sequence_name()).if_not_exists();
+        match self.normalization {
+            SerialNormalization::Rowid | SerialNormalization::UnorderedRowid => None,
+            SerialNormalization::VirtualSequence => Some(sequence.virtual_sequence()),
+            SerialNormalization::SqlSequence => Some(sequence),
+            SerialNormalization::SqlSequenceCached => {
+                Some(sequence.cache(SERIAL_SEQUENCE_CACHE_SIZE))
+            }
+            SerialNormalization::SqlSequenceCachedNode => {
+                Some(sequence.per_node_cache(SERIAL_SEQUENCE_CACHE_SIZE))
+            }
+        }
+    }
+}
+
+impl fmt::Display for IdColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} INT8 NOT NULL DEFAULT ",
+            quote::identifier(&self.column)
+        )?;
+        match self.normalization {
+            SerialNormalization::Rowid => f.write_str("unique_rowid()"),
+            SerialNormalization::UnorderedRowid => f.write_str("unordered_unique_rowid()"),
+            _ => write!(
+                f,
+                "nextval({})",
+                quote::string_literal(&quote::identifier(&self.sequence_name()))
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse() {
+        for normalization in &[
+            SerialNormalization::Rowid,
+            SerialNormalization::VirtualSequence,
+            SerialNormalization::SqlSequence,
+            SerialNormalization::SqlSequenceCached,
+            SerialNormalization::SqlSequenceCachedNode,
+            SerialNormalization::UnorderedRowid,
+        ] {
+            assert_eq!(normalization.as_str().parse(), Ok(*normalization));
+        }
+        assert_eq!(
+            "serial".parse::<SerialNormalization>(),
+            Err(UnknownSerialNormalization("serial".to_owned()))
+        );
+    }
+
+    #[test]
+    fn rowid() {
+        let column = IdColumn::new("books", "id", SerialNormalization::Rowid);
+        assert_eq!(
+            column.to_string(),
+            r#""id" INT8 NOT NULL DEFAULT unique_rowid()"#
+        );
+        assert!(column.sequence().is_none());
+    }
+
+    #[test]
+    fn sequence() {
+        let column = IdColumn::new("books", "id", SerialNormalization::SqlSequenceCachedNode);
+        assert_eq!(
+            column.to_string(),
+            r#""id" INT8 NOT NULL DEFAULT nextval('"books_id_seq"')"#
+        );
+        assert_eq!(
+            diesel::debug_query(&column.sequence().unwrap()).to_string(),
+            r#"CREATE SEQUENCE IF NOT EXISTS "books_id_seq" PER NODE CACHE 256 -- binds: []"#
+        );
+    }
+}
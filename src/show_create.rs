@@ -0,0 +1,254 @@
+//! Support for `SHOW CREATE TABLE` SQL queries.
+
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::Text,
+    Queryable,
+};
+
+/// Creates a `SHOW CREATE TABLE` statement for the target table.
+///
+/// Load the result as a [`CreateTable`]:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::show_create::{show_create_table, CreateTable};
+///
+/// let create_table: CreateTable = show_create_table(books::table).get_result(&connection)?;
+/// let summary = create_table.summary();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`CreateTable`]: struct.CreateTable.html
+pub fn show_create_table<T>(target: T) -> ShowCreateTableStatement<T> {
+    ShowCreateTableStatement::new(target)
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW CREATE TABLE` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table we are describing
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-create.html
+pub struct ShowCreateTableStatement<T> {
+    target: T,
+}
+
+impl<T> ShowCreateTableStatement<T> {
+    fn new(target: T) -> Self {
+        ShowCreateTableStatement { target }
+    }
+}
+
+impl<T> QueryFragment<Pg> for ShowCreateTableStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW CREATE TABLE ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        Ok(())
+    }
+}
+
+impl<T> Query for ShowCreateTableStatement<T> {
+    type SqlType = (Text, Text);
+}
+
+impl<T> RunQueryDsl<PgConnection> for ShowCreateTableStatement<T> {}
+
+impl<T> QueryId for ShowCreateTableStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A row returned by `SHOW CREATE TABLE`.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct CreateTable {
+    /// The name of the table.
+    pub table_name: String,
+    /// The statement which would create the table, including its indexes
+    /// and column families.
+    pub create_statement: String,
+}
+
+/// The indexes, column families and locality parsed from a [`CreateTable`].
+///
+/// [`CreateTable`]: struct.CreateTable.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableSummary {
+    /// The definition of the primary key constraint, if shown.
+    pub primary_key: Option<String>,
+    /// The secondary indexes of the table.
+    pub indexes: Vec<IndexSummary>,
+    /// The names of the column families of the table.
+    pub families: Vec<String>,
+    /// The locality of the table, such as `REGIONAL BY ROW`.
+    pub locality: Option<String>,
+}
+
+/// A secondary index parsed from a [`CreateTable`].
+///
+/// [`CreateTable`]: struct.CreateTable.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexSummary {
+    /// The name of the index.
+    pub name: String,
+    /// Whether this is a `UNIQUE` index.
+    pub unique: bool,
+    /// Whether this is an `INVERTED` index.
+    pub inverted: bool,
+    /// The full definition of the index.
+    pub definition: String,
+}
+
+impl CreateTable {
+    /// Parse a summary of the indexes, families and locality of the table.
+    ///
+    /// Lines of the statement which are not recognised are ignored.
+    pub fn summary(&self) -> TableSummary {
+        let mut summary = TableSummary::default();
+        for line in self.create_statement.lines() {
+            let line = line.trim().trim_end_matches(',');
+            if let Some(locality) = line.strip_prefix(") LOCALITY ") {
+                summary.locality = Some(locality.trim_end_matches(';').to_owned());
+            } else if let Some(family) = line.strip_prefix("FAMILY ") {
+                summary.families.push(leading_name(family));
+            } else if line.starts_with("CONSTRAINT ") && line.contains(" PRIMARY KEY ") {
+                summary.primary_key = Some(line.to_owned());
+            } else if let Some(index) = parse_index(line) {
+                summary.indexes.push(index);
+            }
+        }
+        summary
+    }
+}
+
+fn parse_index(line: &str) -> Option<IndexSummary> {
+    let (unique, inverted, rest) = if let Some(rest) = line.strip_prefix("UNIQUE INDEX ") {
+        (true, false, rest)
+    } else if let Some(rest) = line.strip_prefix("INVERTED INDEX ") {
+        (false, true, rest)
+    } else if let Some(rest) = line.strip_prefix("INDEX ") {
+        (false, false, rest)
+    } else {
+        return None;
+    };
+    Some(IndexSummary {
+        name: leading_name(rest),
+        unique,
+        inverted,
+        definition: line.to_owned(),
+    })
+}
+
+/// Parses the possibly quoted name at the start of a definition.
+fn leading_name(definition: &str) -> String {
+    if let Some(quoted) = definition.strip_prefix('"') {
+        let mut name = String::new();
+        let mut chars = quoted.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            name.push(c);
+        }
+        name
+    } else {
+        definition
+            .split(|c: char| c.is_whitespace() || c == '(')
+            .next()
+            .unwrap_or_default()
+            .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn statement() {
+        assert_eq!(
+            diesel::debug_query(&show_create_table(books::table)).to_string(),
+            r#"SHOW CREATE TABLE "books" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let create_table = CreateTable {
+            table_name: "books".to_owned(),
+            create_statement: r#"CREATE TABLE public.books (
+	id INT8 NOT NULL,
+	title STRING NULL,
+	tags STRING[] NULL,
+	CONSTRAINT books_pkey PRIMARY KEY (id ASC),
+	UNIQUE INDEX books_title_key (title ASC),
+	INDEX "books by title" (title DESC) STORING (tags),
+	INVERTED INDEX books_tags_idx (tags),
+	FAMILY "primary" (id, title),
+	FAMILY tags (tags)
+) LOCALITY REGIONAL BY ROW"#
+                .to_owned(),
+        };
+        assert_eq!(
+            create_table.summary(),
+            TableSummary {
+                primary_key: Some("CONSTRAINT books_pkey PRIMARY KEY (id ASC)".to_owned()),
+                indexes: vec![
+                    IndexSummary {
+                        name: "books_title_key".to_owned(),
+                        unique: true,
+                        inverted: false,
+                        definition: "UNIQUE INDEX books_title_key (title ASC)".to_owned(),
+                    },
+                    IndexSummary {
+                        name: "books by title".to_owned(),
+                        unique: false,
+                        inverted: false,
+                        definition: r#"INDEX "books by title" (title DESC) STORING (tags)"#
+                            .to_owned(),
+                    },
+                    IndexSummary {
+                        name: "books_tags_idx".to_owned(),
+                        unique: false,
+                        inverted: true,
+                        definition: "INVERTED INDEX books_tags_idx (tags)".to_owned(),
+                    },
+                ],
+                families: vec!["primary".to_owned(), "tags".to_owned()],
+                locality: Some("REGIONAL BY ROW".to_owned()),
+            }
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! URIs for the Kafka and webhook sinks changefeeds emit messages to.
+//!
+//! Sink configuration is validated when the URI is built, rather than when
+//! the changefeed is created. Cloud storage sinks use the same URIs as bulk
+//! operations, from the [`external_storage`] module.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [`external_storage`]: ../external_storage/index.html
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/changefeed-sinks.html
+
+use crate::external_storage::ExternalStorageUri;
+use std::{error, fmt};
+
+/// The longest topic name accepted by Kafka.
+const MAX_TOPIC_LENGTH: usize = 249;
+
+/// The error returned when building an invalid sink URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSinkUri(pub String);
+
+impl fmt::Display for InvalidSinkUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid sink URI: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidSinkUri {}
+
+fn invalid<T>(reason: String) -> Result<T, InvalidSinkUri> {
+    Err(InvalidSinkUri(reason))
+}
+
+/// Certificates are passed as base64 encoded PEM, and pasting the PEM itself
+/// is an easy mistake to make.
+fn validate_base64(name: &str, value: &str) -> Result<(), InvalidSinkUri> {
+    let valid = !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"+/=".contains(&byte));
+    if !valid {
+        return invalid(format!("{} must be base64 encoded", name));
+    }
+    Ok(())
+}
+
+fn validate_topic(name: &str, topic: &str) -> Result<(), InvalidSinkUri> {
+    if topic.is_empty() || topic.len() > MAX_TOPIC_LENGTH {
+        return invalid(format!(
+            "{} must be between 1 and {} characters",
+            name, MAX_TOPIC_LENGTH
+        ));
+    }
+    if !topic
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || b"._-".contains(&byte))
+    {
+        return invalid(format!(
+            "{} may only contain letters, digits, '.', '_' and '-'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// The TLS parameters shared by Kafka and webhook sinks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TlsParams {
+    ca_cert: Option<String>,
+    client_cert: Option<(String, String)>,
+    insecure_tls_skip_verify: bool,
+}
+
+impl TlsParams {
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    fn apply(&self, mut uri: ExternalStorageUri) -> Result<ExternalStorageUri, InvalidSinkUri> {
+        if let Some(ca_cert) = &self.ca_cert {
+            validate_base64("ca_cert", ca_cert)?;
+            uri = uri.param("ca_cert", ca_cert.as_str());
+        }
+        if let Some((client_cert, client_key)) = &self.client_cert {
+            validate_base64("client_cert", client_cert)?;
+            validate_base64("client_key", client_key)?;
+            uri = uri
+                .param("client_cert", client_cert.as_str())
+                .secret_param("client_key", client_key.as_str());
+        }
+        if self.insecure_tls_skip_verify {
+            uri = uri.param("insecure_tls_skip_verify", "true");
+        }
+        Ok(uri)
+    }
+}
+
+macro_rules! tls_options {
+    () => {
+        /// Verify the server certificate with the given CA certificate,
+        /// as base64 encoded PEM.
+        pub fn ca_cert(mut self, ca_cert: impl Into<String>) -> Self {
+            self.tls.ca_cert = Some(ca_cert.into());
+            self
+        }
+
+        /// Authenticate with the given client certificate and private key,
+        /// as base64 encoded PEM. The key is redacted when displayed.
+        pub fn client_cert(
+            mut self,
+            client_cert: impl Into<String>,
+            client_key: impl Into<String>,
+        ) -> Self {
+            self.tls.client_cert = Some((client_cert.into(), client_key.into()));
+            self
+        }
+
+        /// Skip verifying the server certificate. Only use this for testing.
+        pub fn insecure_tls_skip_verify(mut self) -> Self {
+            self.tls.insecure_tls_skip_verify = true;
+            self
+        }
+    };
+}
+
+/// The SASL mechanism used to authenticate with Kafka.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaslMechanism {
+    /// `PLAIN`, the default.
+    Plain,
+    /// `SCRAM-SHA-256`.
+    ScramSha256,
+    /// `SCRAM-SHA-512`.
+    ScramSha512,
+}
+
+impl SaslMechanism {
+    fn as_str(self) -> &'static str {
+        match self {
+            SaslMechanism::Plain => "PLAIN",
+            SaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            SaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct Sasl {
+    mechanism: SaslMechanism,
+    user: String,
+    password: String,
+}
+
+/// A builder for a Kafka sink URI.
+///
+/// ```
+/// use diesel_cockroach::sink::{KafkaSink, SaslMechanism};
+///
+/// let uri = KafkaSink::new("broker.example.com:9092")
+///     .topic_prefix("library.")
+///     .tls()
+///     .sasl(SaslMechanism::ScramSha256, "changefeed", "secret")
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     uri.to_string(),
+///     "kafka://broker.example.com:9092?topic_prefix=library.&tls_enabled=true&sasl_enabled=true&sasl_mechanism=SCRAM-SHA-256&sasl_user=changefeed&sasl_password=redacted"
+/// );
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct KafkaSink {
+    broker: String,
+    topic_prefix: Option<String>,
+    topic_name: Option<String>,
+    tls_enabled: bool,
+    tls: TlsParams,
+    sasl: Option<Sasl>,
+}
+
+impl KafkaSink {
+    /// A sink emitting to the Kafka cluster with the given bootstrap broker,
+    /// such as `broker.example.com:9092`.
+    pub fn new(broker: impl Into<String>) -> Self {
+        KafkaSink {
+            broker: broker.into(),
+            topic_prefix: None,
+            topic_name: None,
+            tls_enabled: false,
+            tls: TlsParams::default(),
+            sasl: None,
+        }
+    }
+
+    /// Prefix the topic of each table, named after the table by default.
+    pub fn topic_prefix(mut self, topic_prefix: impl Into<String>) -> Self {
+        self.topic_prefix = Some(topic_prefix.into());
+        self
+    }
+
+    /// Emit the messages of every table to a single topic.
+    pub fn topic_name(mut self, topic_name: impl Into<String>) -> Self {
+        self.topic_name = Some(topic_name.into());
+        self
+    }
+
+    /// Connect to the brokers with TLS.
+    ///
+    /// The other TLS options are only valid with TLS enabled.
+    pub fn tls(mut self) -> Self {
+        self.tls_enabled = true;
+        self
+    }
+
+    tls_options!();
+
+    /// Authenticate with SASL. The password is redacted when displayed.
+    pub fn sasl(
+        mut self,
+        mechanism: SaslMechanism,
+        user: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.sasl = Some(Sasl {
+            mechanism,
+            user: user.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    /// Validate the options, and build the URI.
+    pub fn build(&self) -> Result<ExternalStorageUri, InvalidSinkUri> {
+        let (host, port) = match self.broker.rfind(':') {
+            Some(colon) => (&self.broker[..colon], Some(&self.broker[colon + 1..])),
+            None => (self.broker.as_str(), None),
+        };
+        if host.is_empty() || host.contains('/') {
+            return invalid(format!("invalid Kafka broker: {}", self.broker));
+        }
+        if let Some(port) = port {
+            if port.parse::<u16>().is_err() {
+                return invalid(format!("invalid Kafka broker port: {}", self.broker));
+            }
+        }
+        let mut uri = ExternalStorageUri::with_base(format!("kafka://{}", self.broker));
+        if let Some(topic_prefix) = &self.topic_prefix {
+            validate_topic("topic_prefix", topic_prefix)?;
+            uri = uri.param("topic_prefix", topic_prefix.as_str());
+        }
+        if let Some(topic_name) = &self.topic_name {
+            validate_topic("topic_name", topic_name)?;
+            uri = uri.param("topic_name", topic_name.as_str());
+        }
+        if self.tls_enabled {
+            uri = self.tls.apply(uri.param("tls_enabled", "true"))?;
+        } else if !self.tls.is_empty() {
+            return invalid("TLS options require tls_enabled".to_owned());
+        }
+        if let Some(sasl) = &self.sasl {
+            if sasl.user.is_empty() || sasl.password.is_empty() {
+                return invalid("SASL requires a user and password".to_owned());
+            }
+            uri = uri
+                .param("sasl_enabled", "true")
+                .param("sasl_mechanism", sasl.mechanism.as_str())
+                .param("sasl_user", sasl.user.as_str())
+                .secret_param("sasl_password", sasl.password.as_str());
+        }
+        Ok(uri)
+    }
+}
+
+/// Redacts secrets.
+impl fmt::Debug for KafkaSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KafkaSink")
+            .field("broker", &self.broker)
+            .field("topic_prefix", &self.topic_prefix)
+            .field("topic_name", &self.topic_name)
+            .field("tls_enabled", &self.tls_enabled)
+            .field("sasl_user", &self.sasl.as_ref().map(|sasl| &sasl.user))
+            .finish()
+    }
+}
+
+/// A builder for a webhook sink URI, emitting batches of messages to an
+/// HTTPS endpoint.
+///
+/// An `Authorization` header can be sent with the `webhook_auth_header`
+/// changefeed option.
+///
+/// ```
+/// use diesel_cockroach::sink::WebhookSink;
+///
+/// let uri = WebhookSink::new("example.com:8443", "changes").build().unwrap();
+/// assert_eq!(uri.to_string(), "webhook-https://example.com:8443/changes");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct WebhookSink {
+    host: String,
+    path: String,
+    tls: TlsParams,
+}
+
+impl WebhookSink {
+    /// A sink sending messages to the given host and path.
+    pub fn new(host: impl Into<String>, path: impl Into<String>) -> Self {
+        WebhookSink {
+            host: host.into(),
+            path: path.into(),
+            tls: TlsParams::default(),
+        }
+    }
+
+    tls_options!();
+
+    /// Validate the options, and build the URI.
+    pub fn build(&self) -> Result<ExternalStorageUri, InvalidSinkUri> {
+        if self.host.is_empty() || self.host.contains('/') {
+            return invalid(format!("invalid webhook host: {}", self.host));
+        }
+        self.tls.apply(ExternalStorageUri::new(
+            "webhook-https",
+            &self.host,
+            &self.path,
+        ))
+    }
+}
+
+/// Redacts secrets.
+impl fmt::Debug for WebhookSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebhookSink")
+            .field("host", &self.host)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn kafka() {
+        let uri = KafkaSink::new("broker:9092")
+            .topic_name("books")
+            .tls()
+            .ca_cert("Q0E=")
+            .client_cert("Q0VSVA==", "S0VZ")
+            .build()
+            .unwrap();
+        assert_eq!(
+            uri.to_string(),
+            "kafka://broker:9092?topic_name=books&tls_enabled=true&ca_cert=Q0E%3D&client_cert=Q0VSVA%3D%3D&client_key=redacted"
+        );
+        assert_eq!(
+            String::from(uri),
+            "kafka://broker:9092?topic_name=books&tls_enabled=true&ca_cert=Q0E%3D&client_cert=Q0VSVA%3D%3D&client_key=S0VZ"
+        );
+    }
+
+    #[test]
+    fn kafka_invalid() {
+        assert_eq!(
+            KafkaSink::new("broker:kafka").build(),
+            Err(InvalidSinkUri(
+                "invalid Kafka broker port: broker:kafka".to_owned()
+            ))
+        );
+        assert_eq!(
+            KafkaSink::new("broker").ca_cert("Q0E=").build(),
+            Err(InvalidSinkUri("TLS options require tls_enabled".to_owned()))
+        );
+        assert_eq!(
+            KafkaSink::new("broker")
+                .tls()
+                .ca_cert("-----BEGIN CERTIFICATE-----")
+                .build(),
+            Err(InvalidSinkUri("ca_cert must be base64 encoded".to_owned()))
+        );
+        assert!(KafkaSink::new("broker")
+            .topic_prefix("library/")
+            .build()
+            .is_err());
+        assert!(KafkaSink::new("broker")
+            .sasl(SaslMechanism::Plain, "changefeed", "")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn webhook() {
+        let uri = WebhookSink::new("example.com", "/hooks/books changes")
+            .insecure_tls_skip_verify()
+            .build()
+            .unwrap();
+        assert_eq!(
+            String::from(uri),
+            "webhook-https://example.com/hooks/books%20changes?insecure_tls_skip_verify=true"
+        );
+        assert!(WebhookSink::new("", "changes").build().is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! Types which represent CockroachDB specific SQL data types.
+//!
+//! These can be used in `table!` definitions alongside the types in
+//! `diesel::sql_types`.
+//!
+//! Other user-defined `ENUM` types can be declared in the same way as
+//! [`CrdbInternalRegion`], by deriving `SqlType` with
+//! `#[postgres(type_name = "...")]`.
+//!
+//! [`CrdbInternalRegion`]: struct.CrdbInternalRegion.html
+
+use diesel::{QueryId, SqlType};
+
+/// The `crdb_internal_region` enum type, created automatically for each
+/// multi-region database.
+///
+/// It is the type of the hidden `crdb_region` column of `REGIONAL BY ROW`
+/// tables, and may be sent or received as a [`Region`], `String` or `&str`.
+///
+/// Since each database has its own enum type, its OID is looked up by name
+/// at runtime.
+///
+/// [`Region`]: ../data_types/struct.Region.html
+#[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+#[postgres(type_name = "crdb_internal_region")]
+pub struct CrdbInternalRegion;
+
+/// A `DECIMAL` holding a hybrid logical clock timestamp, such as the result
+/// of [`cluster_logical_timestamp`].
+///
+/// It has the same representation as `diesel::sql_types::Numeric`, but is
+/// received as an [`HlcTimestamp`] rather than an arbitrary precision number.
+///
+/// [`cluster_logical_timestamp`]: ../functions/struct.cluster_logical_timestamp.html
+/// [`HlcTimestamp`]: ../as_of_system_time/struct.HlcTimestamp.html
+#[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+#[postgres(oid = "1700", array_oid = "1231")]
+pub struct HlcDecimal;
+
+/// The `GEOMETRY` spatial type, for shapes on a flat plane.
+///
+/// Values are sent and received as [`Ewkb`]. Since spatial types are not
+/// built in to Postgres, their OIDs are looked up by name at runtime.
+///
+/// [`Ewkb`]: ../data_types/struct.Ewkb.html
+#[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+#[postgres(type_name = "geometry")]
+pub struct Geometry;
+
+/// The `GEOGRAPHY` spatial type, for shapes on the surface of the Earth.
+///
+/// Values are sent and received as [`Ewkb`].
+///
+/// [`Ewkb`]: ../data_types/struct.Ewkb.html
+#[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+#[postgres(type_name = "geography")]
+pub struct Geography;
+
+/// The `TIMETZ` type, a time of day with a UTC offset.
+///
+/// Values are sent and received as a [`PgTimeTz`].
+///
+/// [`PgTimeTz`]: ../data_types/struct.PgTimeTz.html
+#[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+#[postgres(oid = "1266", array_oid = "1270")]
+pub struct TimeTz;
+
+/// The spatial SQL types, [`Geometry`] and [`Geography`], which functions
+/// such as [`st_distance`] accept either of.
+///
+/// [`Geometry`]: struct.Geometry.html
+/// [`Geography`]: struct.Geography.html
+/// [`st_distance`]: ../functions/fn.st_distance.html
+pub trait SpatialType {}
+
+impl SpatialType for Geometry {}
+
+impl SpatialType for Geography {}
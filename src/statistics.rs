@@ -0,0 +1,410 @@
+//! Support for `CREATE STATISTICS`, `SHOW STATISTICS` and
+//! `ALTER TABLE ... INJECT STATISTICS` SQL queries.
+
+use crate::{
+    alter_table::{AlterTableStatement, IncompleteAlterTableStatement},
+    as_of_system_time::AsOfSystemTime,
+    column_list::{push_identifiers, ColumnList},
+    quote,
+};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::{Array, BigInt, Bool, Nullable, Text, Timestamp},
+    Queryable,
+};
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Creates a `CREATE STATISTICS` statement, collecting statistics called
+/// `name` for the target table.
+///
+/// Statistics are collected for every indexed column by default. To collect
+/// statistics for specific columns instead, call `on()`.
+///
+/// ```sql
+/// CREATE STATISTICS books_stats FROM books;
+/// ```
+pub fn create_statistics<T>(name: impl Into<String>, target: T) -> CreateStatisticsStatement<T> {
+    CreateStatisticsStatement::new(name.into(), target)
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `CREATE STATISTICS` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table we are collecting statistics for
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/create-statistics.html
+pub struct CreateStatisticsStatement<T> {
+    name: String,
+    target: T,
+    columns: Vec<&'static str>,
+    as_of_system_time: Option<AsOfSystemTime>,
+}
+
+impl<T> CreateStatisticsStatement<T> {
+    fn new(name: String, target: T) -> Self {
+        CreateStatisticsStatement {
+            name,
+            target,
+            columns: Vec::new(),
+            as_of_system_time: None,
+        }
+    }
+
+    /// Collect multi-column statistics for the given columns, rather than for
+    /// each indexed column.
+    ///
+    /// ```sql
+    /// CREATE STATISTICS books_stats ON author, title FROM books;
+    /// ```
+    pub fn on<C, K>(mut self, columns: C) -> Self
+    where
+        C: ColumnList<K, Table = T>,
+    {
+        self.columns = columns.names();
+        self
+    }
+
+    /// Collect statistics from historical data, reducing contention with
+    /// foreground traffic.
+    ///
+    /// ```sql
+    /// CREATE STATISTICS books_stats FROM books WITH OPTIONS AS OF SYSTEM TIME '-10s';
+    /// ```
+    pub fn as_of_system_time(mut self, as_of_system_time: AsOfSystemTime) -> Self {
+        self.as_of_system_time = Some(as_of_system_time);
+        self
+    }
+}
+
+impl<T> QueryFragment<Pg> for CreateStatisticsStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("CREATE STATISTICS ");
+        out.push_identifier(&self.name)?;
+        if !self.columns.is_empty() {
+            out.push_sql(" ON ");
+            push_identifiers(&mut out, &self.columns)?;
+        }
+        out.push_sql(" FROM ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        if let Some(as_of_system_time) = &self.as_of_system_time {
+            out.push_sql(" WITH OPTIONS ");
+            as_of_system_time.walk_ast(out.reborrow())?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> RunQueryDsl<PgConnection> for CreateStatisticsStatement<T> {}
+
+impl<T> QueryId for CreateStatisticsStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// Creates a `SHOW STATISTICS` statement, listing the statistics collected
+/// for a table.
+///
+/// Load the result as [`StatisticsSummary`] rows.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::statistics::{show_statistics_for, StatisticsSummary};
+///
+/// let statistics: Vec<StatisticsSummary> = show_statistics_for(books::table).load(&connection)?;
+/// for statistic in statistics {
+///     println!("{:?}: {} rows", statistic.columns, statistic.row_count);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`StatisticsSummary`]: struct.StatisticsSummary.html
+pub fn show_statistics_for<T>(target: T) -> ShowStatisticsStatement<T> {
+    ShowStatisticsStatement { target }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW STATISTICS` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table whose statistics are shown
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-statistics.html
+pub struct ShowStatisticsStatement<T> {
+    target: T,
+}
+
+impl<T> QueryFragment<Pg> for ShowStatisticsStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT statistics_name, column_names, created, row_count, distinct_count, \
+             null_count, histogram_id IS NOT NULL FROM [SHOW STATISTICS FOR TABLE ",
+        );
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql("]");
+        Ok(())
+    }
+}
+
+impl<T> Query for ShowStatisticsStatement<T> {
+    type SqlType = (
+        Nullable<Text>,
+        Array<Text>,
+        Timestamp,
+        BigInt,
+        BigInt,
+        BigInt,
+        Bool,
+    );
+}
+
+impl<T> RunQueryDsl<PgConnection> for ShowStatisticsStatement<T> {}
+
+impl<T> QueryId for ShowStatisticsStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A statistic collected for a table, as returned by [`show_statistics_for`].
+///
+/// [`show_statistics_for`]: fn.show_statistics_for.html
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct StatisticsSummary {
+    /// The name of the statistic, such as `__auto__` for automatic
+    /// statistics.
+    pub name: Option<String>,
+    /// The columns the statistic was collected for.
+    pub columns: Vec<String>,
+    /// When the statistic was collected.
+    pub created: SystemTime,
+    /// The number of rows in the table.
+    pub row_count: i64,
+    /// The number of distinct values of the columns.
+    pub distinct_count: i64,
+    /// The number of rows where the columns are `NULL`.
+    pub null_count: i64,
+    /// Whether a histogram was collected for the columns.
+    pub has_histogram: bool,
+}
+
+/// A single table statistic, in the JSON format used by CockroachDB.
+///
+/// Requires the `json` feature.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableStatistic {
+    /// The name of the statistic, or `__auto__` for automatic statistics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The columns the statistic was collected for.
+    pub columns: Vec<String>,
+    /// When the statistic was collected, such as `2021-01-01 00:00:00.000000`.
+    pub created_at: String,
+    /// The number of rows in the table.
+    pub row_count: u64,
+    /// The number of distinct values of the columns.
+    pub distinct_count: u64,
+    /// The number of rows where the columns are `NULL`.
+    pub null_count: u64,
+    /// The average size of the columns in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_size: Option<u64>,
+    /// The SQL type of the histogram's upper bounds, if the statistic has a histogram.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histo_col_type: Option<String>,
+    /// The buckets of the histogram, if the statistic has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histo_buckets: Option<Vec<HistogramBucket>>,
+    /// The version of the histogram format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histo_version: Option<u32>,
+}
+
+/// A bucket of a [`TableStatistic`] histogram.
+///
+/// Requires the `json` feature.
+///
+/// [`TableStatistic`]: struct.TableStatistic.html
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// The number of rows equal to the upper bound.
+    pub num_eq: u64,
+    /// The number of rows between the previous and this upper bound.
+    pub num_range: u64,
+    /// The number of distinct values between the previous and this upper bound.
+    pub distinct_range: f64,
+    /// The upper bound of the bucket, rendered as a string.
+    pub upper_bound: String,
+}
+
+/// The `INJECT STATISTICS` alteration of an `ALTER TABLE` statement.
+///
+/// Created by calling [`inject_statistics`] on the result of [`alter_table`].
+///
+/// [`inject_statistics`]: ../alter_table/struct.IncompleteAlterTableStatement.html#method.inject_statistics
+/// [`alter_table`]: ../alter_table/fn.alter_table.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectStatistics {
+    json: String,
+}
+
+impl QueryFragment<Pg> for InjectStatistics {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("INJECT STATISTICS ");
+        out.push_sql(&quote::string_literal(&self.json));
+        Ok(())
+    }
+}
+
+impl<T> IncompleteAlterTableStatement<T> {
+    /// Replace the statistics of the table with statistics in CockroachDB's
+    /// JSON format, as returned by `SHOW STATISTICS USING JSON`.
+    ///
+    /// Injecting statistics captured from a production cluster allows a test
+    /// cluster to plan queries as if it held production data.
+    ///
+    /// ```sql
+    /// ALTER TABLE books INJECT STATISTICS '[{"columns": ["id"], ...}]';
+    /// ```
+    pub fn inject_statistics(
+        self,
+        json: impl Into<String>,
+    ) -> AlterTableStatement<T, InjectStatistics> {
+        AlterTableStatement::new(self, InjectStatistics { json: json.into() })
+    }
+
+    /// Replace the statistics of the table with the given statistics.
+    ///
+    /// Requires the `json` feature.
+    #[cfg(feature = "json")]
+    pub fn inject_table_statistics(
+        self,
+        statistics: &[TableStatistic],
+    ) -> Result<AlterTableStatement<T, InjectStatistics>, serde_json::Error> {
+        Ok(self.inject_statistics(serde_json::to_string(statistics)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alter_table::alter_table;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    table! {
+        books (id) {
+            id -> Bytea,
+            title -> Text,
+            author -> Text,
+        }
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            diesel::debug_query(&create_statistics("books_stats", books::table)).to_string(),
+            r#"CREATE STATISTICS "books_stats" FROM "books" -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn show_statistics() {
+        assert_eq!(
+            diesel::debug_query(&show_statistics_for(books::table)).to_string(),
+            r#"SELECT statistics_name, column_names, created, row_count, distinct_count, null_count, histogram_id IS NOT NULL FROM [SHOW STATISTICS FOR TABLE "books"] -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn columns_as_of_system_time() {
+        let statement = create_statistics("books_stats", books::table)
+            .on((books::author, books::title))
+            .as_of_system_time(AsOfSystemTime::Ago(Duration::from_secs(30)));
+        assert_eq!(
+            diesel::debug_query(&statement).to_string(),
+            r#"CREATE STATISTICS "books_stats" ON "author", "title" FROM "books" WITH OPTIONS AS OF SYSTEM TIME '-30s' -- binds: []"#
+        );
+    }
+
+    #[cfg(feature = "json")]
+    const STATISTICS: &str = r#"[{"name":"__auto__","columns":["id"],"created_at":"2021-01-01 00:00:00.000000","row_count":2,"distinct_count":2,"null_count":0,"histo_col_type":"INT8","histo_buckets":[{"num_eq":1,"num_range":0,"distinct_range":0.0,"upper_bound":"1"},{"num_eq":1,"num_range":0,"distinct_range":0.0,"upper_bound":"2"}],"histo_version":1},{"columns":["title"],"created_at":"2021-01-01 00:00:00.000000","row_count":2,"distinct_count":1,"null_count":0,"avg_size":16}]"#;
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn round_trip() {
+        let statistics: Vec<TableStatistic> = serde_json::from_str(STATISTICS).unwrap();
+        assert_eq!(statistics.len(), 2);
+        assert_eq!(statistics[0].histo_buckets.as_ref().unwrap().len(), 2);
+        assert_eq!(statistics[1].avg_size, Some(16));
+        assert_eq!(serde_json::to_string(&statistics).unwrap(), STATISTICS);
+    }
+
+    #[test]
+    fn inject() {
+        assert_eq!(
+            diesel::debug_query(&alter_table(books::table).inject_statistics("[]")).to_string(),
+            r#"ALTER TABLE "books" INJECT STATISTICS '[]' -- binds: []"#
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn inject_table_statistics() {
+        let statistics = vec![TableStatistic {
+            name: Some("it's".to_owned()),
+            columns: vec!["title".to_owned()],
+            created_at: "2021-01-01 00:00:00.000000".to_owned(),
+            row_count: 1000,
+            distinct_count: 999,
+            null_count: 1,
+            avg_size: None,
+            histo_col_type: None,
+            histo_buckets: None,
+            histo_version: None,
+        }];
+        assert_eq!(
+            diesel::debug_query(
+                &alter_table(books::table)
+                    .inject_table_statistics(&statistics)
+                    .unwrap()
+            )
+            .to_string(),
+            r#"ALTER TABLE "books" INJECT STATISTICS '[{"name":"it''s","columns":["title"],"created_at":"2021-01-01 00:00:00.000000","row_count":1000,"distinct_count":999,"null_count":1}]' -- binds: []"#
+        );
+    }
+}
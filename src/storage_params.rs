@@ -0,0 +1,306 @@
+//! Storage parameters, set with `WITH (...)` when creating tables and indexes,
+//! or with `SET (...)` and `RESET (...)` when altering them.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/with-storage-parameter.html
+
+use crate::{data_types::Interval, quote};
+use diesel::{
+    pg::Pg,
+    query_builder::{AstPass, QueryFragment},
+    result::QueryResult,
+};
+/// The name of a storage parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StorageParam {
+    /// `ttl_expire_after`: how long rows live before row-level TTL deletes them.
+    TtlExpireAfter,
+    /// `ttl_expiration_expression`: an expression giving the time each row expires.
+    TtlExpirationExpression,
+    /// `ttl_job_cron`: how often the row-level TTL job runs.
+    TtlJobCron,
+    /// `ttl_select_batch_size`: the number of rows the TTL job selects at a time.
+    TtlSelectBatchSize,
+    /// `ttl_delete_batch_size`: the number of rows the TTL job deletes at a time.
+    TtlDeleteBatchSize,
+    /// `ttl_delete_rate_limit`: the maximum number of rows the TTL job deletes per second, per node.
+    TtlDeleteRateLimit,
+    /// `ttl_pause`: whether the row-level TTL job is paused.
+    TtlPause,
+    /// `ttl_row_stats_poll_interval`: how often the TTL job collects row statistics.
+    TtlRowStatsPollInterval,
+    /// `sql_stats_automatic_collection_enabled`: whether statistics are collected automatically.
+    SqlStatsAutomaticCollectionEnabled,
+    /// `exclude_data_from_backup`: whether the table's rows are excluded from backups.
+    ExcludeDataFromBackup,
+    /// `bucket_count`: the number of buckets of a hash-sharded index.
+    BucketCount,
+    /// `fillfactor`: accepted for PostgreSQL compatibility, but has no effect.
+    FillFactor,
+    /// Any other storage parameter, by name.
+    Other(String),
+}
+
+impl StorageParam {
+    /// The name of this parameter.
+    pub fn name(&self) -> &str {
+        match self {
+            StorageParam::TtlExpireAfter => "ttl_expire_after",
+            StorageParam::TtlExpirationExpression => "ttl_expiration_expression",
+            StorageParam::TtlJobCron => "ttl_job_cron",
+            StorageParam::TtlSelectBatchSize => "ttl_select_batch_size",
+            StorageParam::TtlDeleteBatchSize => "ttl_delete_batch_size",
+            StorageParam::TtlDeleteRateLimit => "ttl_delete_rate_limit",
+            StorageParam::TtlPause => "ttl_pause",
+            StorageParam::TtlRowStatsPollInterval => "ttl_row_stats_poll_interval",
+            StorageParam::SqlStatsAutomaticCollectionEnabled => {
+                "sql_stats_automatic_collection_enabled"
+            }
+            StorageParam::ExcludeDataFromBackup => "exclude_data_from_backup",
+            StorageParam::BucketCount => "bucket_count",
+            StorageParam::FillFactor => "fillfactor",
+            StorageParam::Other(name) => name,
+        }
+    }
+}
+
+/// The value of a storage parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageParamValue {
+    /// A boolean value.
+    Bool(bool),
+    /// An integer value.
+    Int(i64),
+    /// A string value, rendered as an escaped literal.
+    String(String),
+}
+
+impl StorageParamValue {
+    fn walk_ast(&self, mut out: AstPass<Pg>) {
+        match self {
+            StorageParamValue::Bool(value) => out.push_sql(&value.to_string()),
+            StorageParamValue::Int(value) => out.push_sql(&value.to_string()),
+            StorageParamValue::String(value) => out.push_sql(&quote::string_literal(value)),
+        }
+    }
+}
+
+/// A list of storage parameters and their values.
+///
+/// Parameters with well known types have their own builder methods, and
+/// any other parameter may be set with [`set`].
+///
+/// ```sql
+/// WITH (ttl_expire_after = '86400s', ttl_job_cron = '@hourly')
+/// ```
+///
+/// [`set`]: #method.set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageParams {
+    params: Vec<(StorageParam, StorageParamValue)>,
+}
+
+impl StorageParams {
+    /// Create an empty list of storage parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set any storage parameter to the given value.
+    pub fn set(mut self, param: StorageParam, value: StorageParamValue) -> Self {
+        self.params.push((param, value));
+        self
+    }
+
+    /// Whether no parameters have been set.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// The names of the parameters which have been set.
+    pub(crate) fn names(&self) -> Vec<StorageParam> {
+        self.params.iter().map(|(param, _)| param.clone()).collect()
+    }
+
+    /// Set `ttl_expire_after`, enabling row-level TTL.
+    ///
+    /// Accepts a `Duration`, or an [`Interval`] parsed from configuration.
+    ///
+    /// [`Interval`]: ../data_types/struct.Interval.html
+    pub fn ttl_expire_after(self, expire_after: impl Into<Interval>) -> Self {
+        self.set(
+            StorageParam::TtlExpireAfter,
+            StorageParamValue::String(expire_after.into().to_string()),
+        )
+    }
+
+    /// Set `ttl_expiration_expression`, enabling row-level TTL with an
+    /// expiry time for each row given by the SQL `expression`.
+    pub fn ttl_expiration_expression(self, expression: impl Into<String>) -> Self {
+        self.set(
+            StorageParam::TtlExpirationExpression,
+            StorageParamValue::String(expression.into()),
+        )
+    }
+
+    /// Set `ttl_job_cron`, the crontab expression scheduling the row-level TTL job.
+    pub fn ttl_job_cron(self, cron: impl Into<String>) -> Self {
+        self.set(
+            StorageParam::TtlJobCron,
+            StorageParamValue::String(cron.into()),
+        )
+    }
+
+    /// Set `ttl_select_batch_size`.
+    pub fn ttl_select_batch_size(self, batch_size: i64) -> Self {
+        self.set(
+            StorageParam::TtlSelectBatchSize,
+            StorageParamValue::Int(batch_size),
+        )
+    }
+
+    /// Set `ttl_delete_batch_size`.
+    pub fn ttl_delete_batch_size(self, batch_size: i64) -> Self {
+        self.set(
+            StorageParam::TtlDeleteBatchSize,
+            StorageParamValue::Int(batch_size),
+        )
+    }
+
+    /// Set `ttl_delete_rate_limit`.
+    pub fn ttl_delete_rate_limit(self, rows_per_second: i64) -> Self {
+        self.set(
+            StorageParam::TtlDeleteRateLimit,
+            StorageParamValue::Int(rows_per_second),
+        )
+    }
+
+    /// Set `ttl_pause`.
+    pub fn ttl_pause(self, pause: bool) -> Self {
+        self.set(StorageParam::TtlPause, StorageParamValue::Bool(pause))
+    }
+
+    /// Set `ttl_row_stats_poll_interval`.
+    pub fn ttl_row_stats_poll_interval(self, interval: impl Into<Interval>) -> Self {
+        self.set(
+            StorageParam::TtlRowStatsPollInterval,
+            StorageParamValue::String(interval.into().to_string()),
+        )
+    }
+
+    /// Set `sql_stats_automatic_collection_enabled`.
+    pub fn sql_stats_automatic_collection_enabled(self, enabled: bool) -> Self {
+        self.set(
+            StorageParam::SqlStatsAutomaticCollectionEnabled,
+            StorageParamValue::Bool(enabled),
+        )
+    }
+
+    /// Set `exclude_data_from_backup`.
+    pub fn exclude_data_from_backup(self, exclude: bool) -> Self {
+        self.set(
+            StorageParam::ExcludeDataFromBackup,
+            StorageParamValue::Bool(exclude),
+        )
+    }
+
+    /// Set `bucket_count`, for hash-sharded indexes.
+    pub fn bucket_count(self, bucket_count: i64) -> Self {
+        self.set(
+            StorageParam::BucketCount,
+            StorageParamValue::Int(bucket_count),
+        )
+    }
+}
+
+/// Renders the parenthesised list, such as `(ttl_pause = true)`.
+///
+/// An empty list, or a parameter name which is not a lower case identifier,
+/// is an error.
+impl QueryFragment<Pg> for StorageParams {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        if self.params.is_empty() {
+            return Err(no_params());
+        }
+        out.push_sql("(");
+        for (index, (param, value)) in self.params.iter().enumerate() {
+            if index > 0 {
+                out.push_sql(", ");
+            }
+            out.push_sql(quote::option_name(param.name())?);
+            out.push_sql(" = ");
+            value.walk_ast(out.reborrow());
+        }
+        out.push_sql(")");
+        Ok(())
+    }
+}
+
+/// Pushes a parenthesised list of storage parameter names, as used by `RESET`.
+pub(crate) fn push_names(out: &mut AstPass<Pg>, params: &[StorageParam]) -> QueryResult<()> {
+    if params.is_empty() {
+        return Err(no_params());
+    }
+    out.push_sql("(");
+    for (index, param) in params.iter().enumerate() {
+        if index > 0 {
+            out.push_sql(", ");
+        }
+        out.push_sql(quote::option_name(param.name())?);
+    }
+    out.push_sql(")");
+    Ok(())
+}
+
+fn no_params() -> diesel::result::Error {
+    diesel::result::Error::QueryBuilderError("At least one storage parameter is required".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    #[test]
+    fn params() {
+        let params = StorageParams::new()
+            .ttl_expire_after(Duration::from_secs(86400))
+            .ttl_job_cron("@hourly")
+            .ttl_delete_batch_size(500)
+            .exclude_data_from_backup(true)
+            .set(
+                StorageParam::Other("schema_locked".to_owned()),
+                StorageParamValue::Bool(false),
+            );
+        assert_eq!(
+            diesel::debug_query(&params).to_string(),
+            r#"(ttl_expire_after = '86400s', ttl_job_cron = '@hourly', ttl_delete_batch_size = 500, exclude_data_from_backup = true, schema_locked = false) -- binds: []"#
+        );
+        let params = StorageParams::new().ttl_expire_after("30m".parse::<Interval>().unwrap());
+        assert_eq!(
+            diesel::debug_query(&params).to_string(),
+            r#"(ttl_expire_after = '1800s') -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn escaped() {
+        let params = StorageParams::new().ttl_expiration_expression("created_at + '1 day'");
+        assert_eq!(
+            diesel::debug_query(&params).to_string(),
+            r#"(ttl_expiration_expression = 'created_at + ''1 day''') -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let params = StorageParams::new().set(
+            StorageParam::Other("ttl_pause = true); DROP TABLE books; --".to_owned()),
+            StorageParamValue::Bool(true),
+        );
+        assert!(quote::render(&params).is_err());
+        assert!(quote::render(&StorageParams::new()).is_err());
+    }
+}
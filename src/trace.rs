@@ -0,0 +1,169 @@
+//! Support for capturing session traces, to investigate where the time
+//! taken by queries is spent.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-trace.html
+
+use diesel::{
+    connection::SimpleConnection,
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    result::QueryResult,
+    sql_types::{BigInt, Nullable, Text, Timestamp},
+    Queryable,
+};
+use std::time::{Duration, SystemTime};
+
+/// Runs `f` with tracing enabled on the session, returning its result and
+/// the events recorded while it ran.
+///
+/// Tracing is turned off again before the trace is loaded. If `f` fails,
+/// tracing is still turned off, and its error is returned rather than any
+/// error turning tracing off.
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, QueryDsl, RunQueryDsl};
+/// # table! { books (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::trace::trace_session;
+///
+/// let (count, events) = trace_session(&connection, |conn| {
+///     books::table.count().get_result::<i64>(conn)
+/// })?;
+/// for event in events {
+///     println!("{:?} {}: {}", event.age, event.operation, event.message);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn trace_session<F, R, E>(conn: &PgConnection, f: F) -> Result<(R, Vec<TraceEvent>), E>
+where
+    F: FnOnce(&PgConnection) -> Result<R, E>,
+    E: From<diesel::result::Error>,
+{
+    conn.batch_execute("SET tracing = on")?;
+    let result = f(conn);
+    let stopped = conn.batch_execute("SET tracing = off");
+    let result = result?;
+    stopped?;
+    Ok((result, show_trace_for_session().load(conn)?))
+}
+
+/// Creates a `SHOW TRACE FOR SESSION` statement, loading the events recorded
+/// by the most recent trace of the session.
+///
+/// ```sql
+/// SHOW TRACE FOR SESSION;
+/// ```
+pub fn show_trace_for_session() -> ShowTraceStatement {
+    ShowTraceStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW TRACE FOR SESSION` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-trace.html
+pub struct ShowTraceStatement;
+
+impl QueryFragment<Pg> for ShowTraceStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql(
+            "SELECT \"timestamp\", (extract(epoch FROM age) * 1000000)::INT8, message, tag, \
+             location, operation, span FROM [SHOW TRACE FOR SESSION]",
+        );
+        Ok(())
+    }
+}
+
+// `timestamp` is a `TIMESTAMPTZ`, which has the same binary representation
+// as a `TIMESTAMP` in UTC.
+impl Query for ShowTraceStatement {
+    type SqlType = TraceEventSqlType;
+}
+
+impl RunQueryDsl<PgConnection> for ShowTraceStatement {}
+
+impl QueryId for ShowTraceStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// An event recorded by a session trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// When the event was recorded.
+    pub timestamp: SystemTime,
+    /// The time since the start of the trace.
+    pub age: Duration,
+    /// The message of the event.
+    pub message: String,
+    /// Context of the event, such as the node and range it happened on.
+    pub tag: Option<String>,
+    /// The source code location the event was recorded from.
+    pub location: Option<String>,
+    /// The operation the event was part of.
+    pub operation: String,
+    /// The index of the span of the operation.
+    pub span: i64,
+}
+
+impl Queryable<TraceEventSqlType, Pg> for TraceEvent {
+    type Row = (
+        SystemTime,
+        i64,
+        String,
+        Option<String>,
+        Option<String>,
+        String,
+        i64,
+    );
+
+    fn build((timestamp, age, message, tag, location, operation, span): Self::Row) -> Self {
+        TraceEvent {
+            timestamp,
+            age: Duration::from_micros(age.max(0) as u64),
+            message,
+            tag: tag.filter(|tag| !tag.is_empty()),
+            location: location.filter(|location| !location.is_empty()),
+            operation,
+            span,
+        }
+    }
+}
+
+/// The SQL type of a [`TraceEvent`].
+///
+/// [`TraceEvent`]: struct.TraceEvent.html
+pub type TraceEventSqlType = (
+    Timestamp,
+    BigInt,
+    Text,
+    Nullable<Text>,
+    Nullable<Text>,
+    Text,
+    BigInt,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn show_trace() {
+        assert_eq!(
+            diesel::debug_query(&show_trace_for_session()).to_string(),
+            r#"SELECT "timestamp", (extract(epoch FROM age) * 1000000)::INT8, message, tag, location, operation, span FROM [SHOW TRACE FOR SESSION] -- binds: []"#
+        );
+    }
+}
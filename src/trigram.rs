@@ -0,0 +1,98 @@
+//! Support for trigram similarity queries, for fuzzy matching of strings.
+//!
+//! Filters on [`trigram_similar`] can use a trigram index, created with the
+//! `gin_trgm_ops` operator class:
+//!
+//! ```sql
+//! CREATE INDEX ON books USING GIN (title gin_trgm_ops);
+//! ```
+//!
+//! ```no_run
+//! # #[macro_use] extern crate diesel;
+//! # use diesel::{pg::PgConnection, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
+//! # table! { books (id) { id -> Int8, title -> Text, } }
+//! # fn main() -> diesel::QueryResult<()> {
+//! # let connection = PgConnection::establish("").unwrap();
+//! use diesel_cockroach::trigram::{similarity, TrigramExpressionMethods};
+//!
+//! let titles: Vec<String> = books::table
+//!     .select(books::title)
+//!     .filter(books::title.trigram_similar("Dnue"))
+//!     .order(similarity(books::title, "Dnue").desc())
+//!     .load(&connection)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! See the [CockroachDB docs].
+//!
+//! [`trigram_similar`]: trait.TrigramExpressionMethods.html#method.trigram_similar
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/trigram-indexes.html
+
+use diesel::{
+    expression::{AsExpression, Expression},
+    pg::Pg,
+    sql_types::{Array, Double, Text},
+};
+
+diesel_infix_operator!(TrigramSimilar, " % ", backend: Pg);
+
+sql_function! {
+    /// The `similarity()` function, returning how similar two strings are by
+    /// the proportion of trigrams they share, between `0.0` and `1.0`.
+    fn similarity(left: Text, right: Text) -> Double;
+}
+
+sql_function! {
+    /// The `show_trgm()` function, returning the trigrams of a string.
+    fn show_trgm(input: Text) -> Array<Text>;
+}
+
+/// Adds the [`trigram_similar`] method to text expressions.
+///
+/// [`trigram_similar`]: #method.trigram_similar
+pub trait TrigramExpressionMethods: Expression<SqlType = Text> + Sized {
+    /// Creates a `%` expression, which is true when the [`similarity`] of the
+    /// strings is at least the `pg_trgm.similarity_threshold` session
+    /// setting, `0.3` by default.
+    ///
+    /// ```sql
+    /// title % 'Dnue'
+    /// ```
+    ///
+    /// [`similarity`]: fn.similarity.html
+    fn trigram_similar<T>(self, other: T) -> TrigramSimilar<Self, T::Expression>
+    where
+        T: AsExpression<Text>,
+    {
+        TrigramSimilar::new(self, other.as_expression())
+    }
+}
+
+impl<T> TrigramExpressionMethods for T where T: Expression<SqlType = Text> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+            title -> Text,
+        }
+    }
+
+    #[test]
+    fn similar() {
+        let query = books::table
+            .select((books::title, show_trgm(books::title)))
+            .filter(books::title.trigram_similar("Dnue"))
+            .order(similarity(books::title, "Dnue").desc());
+        assert_eq!(
+            diesel::debug_query::<Pg, _>(&query).to_string(),
+            r#"SELECT "books"."title", show_trgm("books"."title") FROM "books" WHERE "books"."title" % $1 ORDER BY similarity("books"."title", $2) DESC -- binds: ["Dnue", "Dnue"]"#
+        );
+    }
+}
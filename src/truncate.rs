@@ -0,0 +1,129 @@
+//! Support for `TRUNCATE` SQL queries.
+//!
+//! In CockroachDB, `TRUNCATE` is a schema change rather than a delete: it
+//! replaces each index of the table with a new, empty one, and the old data is
+//! removed by a garbage collection job once the GC TTL has passed. It is much
+//! faster than deleting every row, but like other schema changes, it is not
+//! transactional.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/truncate.html
+
+use crate::jobs::{wait_for_schema_changes, JobProgress, WaitError, WaitOptions};
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_dsl::{methods::ExecuteDsl, RunQueryDsl},
+    query_source::QuerySource,
+    result::QueryResult,
+};
+
+/// Creates a `TRUNCATE` statement, removing all rows from the target table.
+///
+/// ```sql
+/// TRUNCATE books;
+/// ```
+pub fn truncate<T>(target: T) -> TruncateStatement<T> {
+    TruncateStatement {
+        target,
+        cascade: false,
+    }
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `TRUNCATE` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `T`: The table being truncated
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/truncate.html
+pub struct TruncateStatement<T> {
+    target: T,
+    cascade: bool,
+}
+
+impl<T> TruncateStatement<T>
+where
+    T: QuerySource + Clone,
+    T::FromClause: QueryFragment<Pg>,
+{
+    /// Also truncate any tables with foreign keys referencing this one.
+    ///
+    /// ```sql
+    /// TRUNCATE books CASCADE;
+    /// ```
+    pub fn cascade(mut self) -> Self {
+        self.cascade = true;
+        self
+    }
+
+    /// Truncates the table, and blocks until the schema change replacing its
+    /// indexes has finished.
+    ///
+    /// This is intended for tests, which truncate tables between cases and
+    /// should not race the schema change. Garbage collection of the old data
+    /// is not waited for.
+    pub fn execute_and_wait(
+        self,
+        conn: &PgConnection,
+        options: WaitOptions,
+    ) -> Result<Vec<JobProgress>, WaitError> {
+        let target = self.target.clone();
+        ExecuteDsl::execute(self, conn)?;
+        wait_for_schema_changes(conn, target, options, |_| {})
+    }
+}
+
+impl<T> QueryFragment<Pg> for TruncateStatement<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("TRUNCATE ");
+        self.target.from_clause().walk_ast(out.reborrow())?;
+        if self.cascade {
+            out.push_sql(" CASCADE");
+        }
+        Ok(())
+    }
+}
+
+impl<T> RunQueryDsl<PgConnection> for TruncateStatement<T> {}
+
+impl<T> QueryId for TruncateStatement<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        books (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn truncate_table() {
+        assert_eq!(
+            diesel::debug_query(&truncate(books::table)).to_string(),
+            r#"TRUNCATE "books" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&truncate(books::table).cascade()).to_string(),
+            r#"TRUNCATE "books" CASCADE -- binds: []"#
+        );
+    }
+}
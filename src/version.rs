@@ -0,0 +1,262 @@
+//! Detection of the CockroachDB version of a cluster, and of the features it
+//! supports.
+
+use diesel::{
+    dsl::sql,
+    pg::PgConnection,
+    query_dsl::RunQueryDsl,
+    result::{Error, QueryResult},
+    sql_types::Text,
+};
+use std::{error, fmt};
+
+/// The version of CockroachDB a cluster is running, such as `v22.2.3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClusterVersion {
+    /// The major version, such as `22`.
+    pub major: u32,
+    /// The minor version, such as `2`.
+    pub minor: u32,
+    /// The patch version, such as `3`.
+    pub patch: u32,
+}
+
+impl ClusterVersion {
+    /// A version from its components.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        ClusterVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Detect the version of the cluster from `version()`, which returns the
+    /// same build information as `crdb_version()`.
+    ///
+    /// ```no_run
+    /// # use diesel::{pg::PgConnection, Connection};
+    /// # fn main() -> diesel::QueryResult<()> {
+    /// # let connection = PgConnection::establish("").unwrap();
+    /// use diesel_cockroach::version::ClusterVersion;
+    ///
+    /// let version = ClusterVersion::detect(&connection)?;
+    /// if !version.supports_invisible_indexes() {
+    ///     println!("{} cannot hide indexes", version);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect(conn: &PgConnection) -> QueryResult<Self> {
+        let version: String = diesel::select(sql::<Text>("version()")).get_result(conn)?;
+        Self::parse(&version).ok_or_else(|| {
+            Error::DeserializationError(
+                format!("Unrecognised CockroachDB version: {}", version).into(),
+            )
+        })
+    }
+
+    /// Parse a version from the output of `version()`, such as
+    /// `CockroachDB CCL v22.2.3 (x86_64-pc-linux-gnu, built 2023/01/04 17:23:00, go1.19.1)`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let number = version
+            .split_whitespace()
+            .find_map(|word| word.strip_prefix('v'))?;
+        let number = number.split('-').next()?;
+        let mut parts = number.split('.').map(str::parse);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch))) => {
+                Some(Self::new(major, minor, patch))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether this version is at least `major.minor`.
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+
+    /// Check that this version is at least `major.minor`, as required by
+    /// `feature`.
+    pub fn require(
+        &self,
+        feature: &'static str,
+        major: u32,
+        minor: u32,
+    ) -> Result<(), Unsupported> {
+        if self.at_least(major, minor) {
+            Ok(())
+        } else {
+            Err(Unsupported {
+                feature,
+                required: Self::new(major, minor, 0),
+                version: *self,
+            })
+        }
+    }
+
+    /// Check that a statement is supported by this version.
+    pub fn check<S: VersionCheck + ?Sized>(&self, statement: &S) -> Result<(), Unsupported> {
+        statement.check_version(self)
+    }
+
+    /// Whether `EXPORT INTO PARQUET` is supported, from v22.2.
+    pub fn supports_parquet_export(&self) -> bool {
+        self.at_least(22, 2)
+    }
+
+    /// Whether `SHOW JOB WHEN COMPLETE` is supported, from v20.1.
+    pub fn supports_show_job_when_complete(&self) -> bool {
+        self.at_least(20, 1)
+    }
+
+    /// Whether `SHOW RANGES` only lists the size and leaseholder of ranges
+    /// `WITH DETAILS`, from v23.1.
+    pub fn show_ranges_needs_details(&self) -> bool {
+        self.at_least(23, 1)
+    }
+
+    /// Whether multi-region databases and table localities are supported,
+    /// from v21.1.
+    pub fn supports_multi_region(&self) -> bool {
+        self.at_least(21, 1)
+    }
+
+    /// Whether bounded staleness reads, using the `with_max_staleness` and
+    /// `with_min_timestamp` functions, are supported, from v21.2.
+    pub fn supports_bounded_staleness(&self) -> bool {
+        self.at_least(21, 2)
+    }
+
+    /// Whether `ALTER DEFAULT PRIVILEGES` is supported, from v21.2.
+    pub fn supports_default_privileges(&self) -> bool {
+        self.at_least(21, 2)
+    }
+
+    /// Whether changefeeds can emit to webhook sinks, from v21.2.
+    pub fn supports_webhook_sink(&self) -> bool {
+        self.at_least(21, 2)
+    }
+
+    /// Whether changefeeds support the `end_time` option, from v22.1.
+    pub fn supports_changefeed_end_time(&self) -> bool {
+        self.at_least(22, 1)
+    }
+
+    /// Whether indexes can be made `NOT VISIBLE`, from v22.2.
+    pub fn supports_invisible_indexes(&self) -> bool {
+        self.at_least(22, 2)
+    }
+
+    /// Whether index recommendations are recorded in statement statistics,
+    /// from v22.2.
+    pub fn supports_index_recommendations(&self) -> bool {
+        self.at_least(22, 2)
+    }
+
+    /// Whether system privileges can be granted with `GRANT SYSTEM`, from
+    /// v22.2.
+    pub fn supports_system_privileges(&self) -> bool {
+        self.at_least(22, 2)
+    }
+}
+
+/// Statements which use features only supported by some versions of
+/// CockroachDB.
+///
+/// Checking a statement before running it gives an [`Unsupported`] error
+/// naming the feature, rather than a syntax error from the cluster.
+///
+/// ```
+/// # #[macro_use] extern crate diesel;
+/// # table! { books (id) { id -> Int8, title -> Text, } }
+/// # fn main() {
+/// use diesel_cockroach::{index::create_index, version::ClusterVersion};
+///
+/// let statement = create_index(books::table, books::title).not_visible();
+/// let error = ClusterVersion::new(22, 1, 0).check(&statement).unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "NOT VISIBLE indexes is not supported on CockroachDB v22.1.0, and requires v22.2 or later"
+/// );
+/// # }
+/// ```
+///
+/// [`Unsupported`]: struct.Unsupported.html
+pub trait VersionCheck {
+    /// Check that this statement is supported by `version`.
+    fn check_version(&self, version: &ClusterVersion) -> Result<(), Unsupported>;
+}
+
+impl fmt::Display for ClusterVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The error returned when a feature is not supported by the version of the
+/// cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The unsupported feature.
+    pub feature: &'static str,
+    /// The earliest version supporting the feature.
+    pub required: ClusterVersion,
+    /// The version of the cluster.
+    pub version: ClusterVersion,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is not supported on CockroachDB {}, and requires v{}.{} or later",
+            self.feature, self.version, self.required.major, self.required.minor
+        )
+    }
+}
+
+impl error::Error for Unsupported {}
+
+impl From<Unsupported> for Error {
+    fn from(err: Unsupported) -> Self {
+        Error::QueryBuilderError(Box::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            ClusterVersion::parse(
+                "CockroachDB CCL v22.2.3 (x86_64-pc-linux-gnu, built 2023/01/04 17:23:00, go1.19.1)"
+            ),
+            Some(ClusterVersion::new(22, 2, 3))
+        );
+        assert_eq!(
+            ClusterVersion::parse("CockroachDB OSS v23.1.0-beta.1 (aarch64-apple-darwin21.2)"),
+            Some(ClusterVersion::new(23, 1, 0))
+        );
+        assert_eq!(ClusterVersion::parse("PostgreSQL 13.4"), None);
+    }
+
+    #[test]
+    fn require() {
+        let version = ClusterVersion::new(22, 1, 9);
+        assert!(version.require("EXPORT INTO PARQUET", 21, 2).is_ok());
+        assert!(version.supports_changefeed_end_time());
+        assert!(!version.supports_invisible_indexes());
+        assert_eq!(
+            version
+                .require("EXPORT INTO PARQUET", 22, 2)
+                .unwrap_err()
+                .to_string(),
+            "EXPORT INTO PARQUET is not supported on CockroachDB v22.1.9, and requires v22.2 or later"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Support for reading replication zone configurations.
+//!
+//! See the [CockroachDB docs].
+//!
+//! [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/configure-replication-zones.html
+
+use diesel::{
+    pg::{Pg, PgConnection},
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::QuerySource,
+    result::QueryResult,
+    sql_types::Text,
+    Queryable,
+};
+use std::str::FromStr;
+
+/// Creates a `SHOW ZONE CONFIGURATION FOR TABLE` statement for the target table.
+///
+/// Load the result as a [`ZoneConfiguration`]:
+///
+/// ```no_run
+/// # #[macro_use] extern crate diesel;
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # table! { users (id) { id -> Int8, } }
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::zone_config::{show_zone_configuration_for_table, ZoneConfiguration};
+///
+/// let zone: ZoneConfiguration =
+///     show_zone_configuration_for_table(users::table).get_result(&connection)?;
+/// assert_eq!(zone.config().num_replicas, Some(5));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ZoneConfiguration`]: struct.ZoneConfiguration.html
+pub fn show_zone_configuration_for_table<T>(
+    target: T,
+) -> ShowZoneConfigurationStatement<TableZone<T>> {
+    ShowZoneConfigurationStatement(TableZone(target))
+}
+
+/// Creates a `SHOW ZONE CONFIGURATION FOR INDEX` statement for the named
+/// index on the target table.
+pub fn show_zone_configuration_for_index<T>(
+    target: T,
+    name: impl Into<String>,
+) -> ShowZoneConfigurationStatement<IndexZone<T>> {
+    ShowZoneConfigurationStatement(IndexZone(target, name.into()))
+}
+
+/// Creates a `SHOW ZONE CONFIGURATION FOR PARTITION` statement for the named
+/// partition of the target table.
+pub fn show_zone_configuration_for_partition<T>(
+    target: T,
+    name: impl Into<String>,
+) -> ShowZoneConfigurationStatement<PartitionZone<T>> {
+    ShowZoneConfigurationStatement(PartitionZone(target, name.into()))
+}
+
+/// Creates a `SHOW ZONE CONFIGURATION FOR DATABASE` statement for the named
+/// database.
+pub fn show_zone_configuration_for_database(
+    name: impl Into<String>,
+) -> ShowZoneConfigurationStatement<DatabaseZone> {
+    ShowZoneConfigurationStatement(DatabaseZone(name.into()))
+}
+
+/// Creates a `SHOW ALL ZONE CONFIGURATIONS` statement, listing the zone
+/// configuration of every zone in the cluster.
+///
+/// ```no_run
+/// # use diesel::{pg::PgConnection, Connection, RunQueryDsl};
+/// # fn main() -> diesel::QueryResult<()> {
+/// # let connection = PgConnection::establish("").unwrap();
+/// use diesel_cockroach::zone_config::{show_all_zone_configurations, ZoneConfiguration};
+///
+/// let zones: Vec<ZoneConfiguration> = show_all_zone_configurations().load(&connection)?;
+/// for zone in zones {
+///     println!("{}: {:?}", zone.target, zone.config().num_replicas);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_all_zone_configurations() -> ShowAllZoneConfigurationsStatement {
+    ShowAllZoneConfigurationsStatement
+}
+
+#[derive(Debug, Clone, Copy)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW ALL ZONE CONFIGURATIONS` statement.
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-zone-configurations.html
+pub struct ShowAllZoneConfigurationsStatement;
+
+impl QueryFragment<Pg> for ShowAllZoneConfigurationsStatement {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW ALL ZONE CONFIGURATIONS");
+        Ok(())
+    }
+}
+
+impl Query for ShowAllZoneConfigurationsStatement {
+    type SqlType = (Text, Text);
+}
+
+impl RunQueryDsl<PgConnection> for ShowAllZoneConfigurationsStatement {}
+
+impl QueryId for ShowAllZoneConfigurationsStatement {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+#[derive(Debug, Clone)]
+#[must_use = "Queries are only executed when calling `load`, `get_result` or similar."]
+/// A fully constructed `SHOW ZONE CONFIGURATION` statement.
+///
+/// The parameters of this struct represent:
+///
+/// - `Z`: The object whose zone configuration is shown
+///
+/// See the [CockroachDB docs].
+///
+/// [CockroachDB docs]: https://www.cockroachlabs.com/docs/stable/show-zone-configurations.html
+pub struct ShowZoneConfigurationStatement<Z>(Z);
+
+impl<Z> QueryFragment<Pg> for ShowZoneConfigurationStatement<Z>
+where
+    Z: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.unsafe_to_cache_prepared();
+
+        out.push_sql("SHOW ZONE CONFIGURATION FOR ");
+        self.0.walk_ast(out.reborrow())
+    }
+}
+
+impl<Z> Query for ShowZoneConfigurationStatement<Z> {
+    type SqlType = (Text, Text);
+}
+
+impl<Z> RunQueryDsl<PgConnection> for ShowZoneConfigurationStatement<Z> {}
+
+impl<Z> QueryId for ShowZoneConfigurationStatement<Z> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+/// A table's zone, created by [`show_zone_configuration_for_table`].
+///
+/// [`show_zone_configuration_for_table`]: fn.show_zone_configuration_for_table.html
+#[derive(Debug, Clone, Copy)]
+pub struct TableZone<T>(T);
+
+impl<T> QueryFragment<Pg> for TableZone<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("TABLE ");
+        self.0.from_clause().walk_ast(out.reborrow())
+    }
+}
+
+/// An index's zone, created by [`show_zone_configuration_for_index`].
+///
+/// [`show_zone_configuration_for_index`]: fn.show_zone_configuration_for_index.html
+#[derive(Debug, Clone)]
+pub struct IndexZone<T>(T, String);
+
+impl<T> QueryFragment<Pg> for IndexZone<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("INDEX ");
+        self.0.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql("@");
+        out.push_identifier(&self.1)
+    }
+}
+
+/// A partition's zone, created by [`show_zone_configuration_for_partition`].
+///
+/// [`show_zone_configuration_for_partition`]: fn.show_zone_configuration_for_partition.html
+#[derive(Debug, Clone)]
+pub struct PartitionZone<T>(T, String);
+
+impl<T> QueryFragment<Pg> for PartitionZone<T>
+where
+    T: QuerySource,
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("PARTITION ");
+        out.push_identifier(&self.1)?;
+        out.push_sql(" OF TABLE ");
+        self.0.from_clause().walk_ast(out.reborrow())
+    }
+}
+
+/// A database's zone, created by [`show_zone_configuration_for_database`].
+///
+/// [`show_zone_configuration_for_database`]: fn.show_zone_configuration_for_database.html
+#[derive(Debug, Clone)]
+pub struct DatabaseZone(String);
+
+impl QueryFragment<Pg> for DatabaseZone {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("DATABASE ");
+        out.push_identifier(&self.0)
+    }
+}
+
+/// A row returned by `SHOW ZONE CONFIGURATION` or
+/// `SHOW ALL ZONE CONFIGURATIONS`.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct ZoneConfiguration {
+    /// The zone the configuration is set on, such as `TABLE movr.users`, or
+    /// `RANGE default` if it is inherited from the default zone.
+    pub target: String,
+    /// The statement which would set the configuration.
+    pub raw_config_sql: String,
+}
+
+impl ZoneConfiguration {
+    /// Parse the effective configuration of the zone.
+    pub fn config(&self) -> ZoneConfig {
+        ZoneConfig::parse(&self.raw_config_sql)
+    }
+}
+
+/// The variables of a zone configuration, parsed from a `CONFIGURE ZONE`
+/// statement.
+///
+/// Variables which are not set are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ZoneConfig {
+    /// `range_min_bytes`
+    pub range_min_bytes: Option<i64>,
+    /// `range_max_bytes`
+    pub range_max_bytes: Option<i64>,
+    /// `gc.ttlseconds`
+    pub gc_ttlseconds: Option<i64>,
+    /// `global_reads`
+    pub global_reads: Option<bool>,
+    /// `num_replicas`
+    pub num_replicas: Option<i32>,
+    /// `num_voters`
+    pub num_voters: Option<i32>,
+    /// `constraints`, such as `{+region=us-east1: 1}`.
+    pub constraints: Option<String>,
+    /// `voter_constraints`
+    pub voter_constraints: Option<String>,
+    /// `lease_preferences`, such as `[[+region=us-east1]]`.
+    pub lease_preferences: Option<String>,
+    /// Any other variables, or variables whose values could not be parsed,
+    /// as raw SQL.
+    pub other: Vec<(String, String)>,
+}
+
+impl ZoneConfig {
+    /// Parse the variables set by a `CONFIGURE ZONE USING ...` statement.
+    pub fn parse(raw_config_sql: &str) -> Self {
+        let mut config = ZoneConfig::default();
+        let variables = match raw_config_sql.find(" USING") {
+            Some(index) => &raw_config_sql[index + " USING".len()..],
+            None => return config,
+        };
+        for variable in variables.split(",\n") {
+            let mut parts = variable.splitn(2, '=');
+            let (name, value) = match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => (name.trim(), value.trim()),
+                _ => continue,
+            };
+            if !config.set(name, value) {
+                config.other.push((name.to_owned(), value.to_owned()));
+            }
+        }
+        config
+    }
+
+    /// Sets a known variable from its raw SQL value, returning whether it
+    /// was recognised.
+    fn set(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "range_min_bytes" => parsed(&mut self.range_min_bytes, value),
+            "range_max_bytes" => parsed(&mut self.range_max_bytes, value),
+            "gc.ttlseconds" => parsed(&mut self.gc_ttlseconds, value),
+            "global_reads" => parsed(&mut self.global_reads, value),
+            "num_replicas" => parsed(&mut self.num_replicas, value),
+            "num_voters" => parsed(&mut self.num_voters, value),
+            "constraints" => string(&mut self.constraints, value),
+            "voter_constraints" => string(&mut self.voter_constraints, value),
+            "lease_preferences" => string(&mut self.lease_preferences, value),
+            _ => false,
+        }
+    }
+}
+
+/// Parses a number or boolean.
+fn parsed<V: FromStr>(field: &mut Option<V>, value: &str) -> bool {
+    *field = value.parse().ok();
+    field.is_some()
+}
+
+/// Parses a quoted string literal.
+fn string(field: &mut Option<String>, value: &str) -> bool {
+    *field = if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        Some(value[1..value.len() - 1].replace("''", "'"))
+    } else {
+        None
+    };
+    field.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    table! {
+        users (id) {
+            id -> Int8,
+        }
+    }
+
+    #[test]
+    fn show_zone_configuration() {
+        assert_eq!(
+            diesel::debug_query(&show_zone_configuration_for_table(users::table)).to_string(),
+            r#"SHOW ZONE CONFIGURATION FOR TABLE "users" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_zone_configuration_for_index(
+                users::table,
+                "users_pkey"
+            ))
+            .to_string(),
+            r#"SHOW ZONE CONFIGURATION FOR INDEX "users"@"users_pkey" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_zone_configuration_for_partition(
+                users::table,
+                "us_east"
+            ))
+            .to_string(),
+            r#"SHOW ZONE CONFIGURATION FOR PARTITION "us_east" OF TABLE "users" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_zone_configuration_for_database("movr")).to_string(),
+            r#"SHOW ZONE CONFIGURATION FOR DATABASE "movr" -- binds: []"#
+        );
+        assert_eq!(
+            diesel::debug_query(&show_all_zone_configurations()).to_string(),
+            r#"SHOW ALL ZONE CONFIGURATIONS -- binds: []"#
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let zone = ZoneConfiguration {
+            target: "TABLE movr.public.users".to_owned(),
+            raw_config_sql: "ALTER TABLE movr.public.users CONFIGURE ZONE USING\n\
+                \trange_min_bytes = 134217728,\n\
+                \trange_max_bytes = 536870912,\n\
+                \tgc.ttlseconds = 14400,\n\
+                \tglobal_reads = true,\n\
+                \tnum_replicas = 5,\n\
+                \tnum_voters = 3,\n\
+                \tconstraints = '{+region=us-east1: 1, +region=us-west1: 1}',\n\
+                \tvoter_constraints = '[+region=us-east1]',\n\
+                \tlease_preferences = '[[+region=us-east1]]',\n\
+                \texample = 'it''s'"
+                .to_owned(),
+        };
+        assert_eq!(
+            zone.config(),
+            ZoneConfig {
+                range_min_bytes: Some(134217728),
+                range_max_bytes: Some(536870912),
+                gc_ttlseconds: Some(14400),
+                global_reads: Some(true),
+                num_replicas: Some(5),
+                num_voters: Some(3),
+                constraints: Some("{+region=us-east1: 1, +region=us-west1: 1}".to_owned()),
+                voter_constraints: Some("[+region=us-east1]".to_owned()),
+                lease_preferences: Some("[[+region=us-east1]]".to_owned()),
+                other: vec![("example".to_owned(), "'it''s'".to_owned())],
+            }
+        );
+    }
+}